@@ -1,30 +1,58 @@
 // Criterion benchmarks for Lume Algo
 
-use criterion::{black_box, criterion_group, criterion_main, Criterion, BenchmarkId};
-use lume_algo::core::{Matcher, distance::{haversine_distance, calculate_bounding_box}};
-use lume_algo::models::{UserProfile, UserPreferences};
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion, BenchmarkId};
+use lume_algo::core::{Matcher, MatchOptions, calculate_match_score, cosine_similarity, distance::{haversine_distance, haversine_distance_batch, calculate_bounding_box, is_within_bounding_box, DistanceCache, DistanceCalculator, DistanceFormula}};
+use lume_algo::models::{ScoredMatch, ScoringWeights, UserProfile, UserPreferences, SCHEMA_VERSION};
 use chrono::Utc;
+use std::cmp::Ordering;
+use std::collections::HashSet;
 
 fn create_candidate(id: usize, lat: f64, lon: f64) -> UserProfile {
     UserProfile {
         user_id: id.to_string(),
         name: format!("User {}", id),
         age: 25 + (id % 10) as u8,
-        height_cm: 160 + (id % 30) as u16,
+        height_cm: Some(160 + (id % 30) as u16),
         hair_color: "brown".to_string(),
-        gender: if id % 2 == 0 { "female" } else { "male" }.to_string(),
+        gender: if id.is_multiple_of(2) { "female" } else { "male" }.to_string(),
         latitude: lat,
         longitude: lon,
-        is_verified: id % 3 == 0,
+        is_verified: Some(id.is_multiple_of(3)),
+        verification: None,
         is_active: true,
-        is_timeout: false,
+        is_timeout: Some(false),
         image_file_ids: vec![],
         description: None,
         sports_preferences: vec!["tennis".to_string()],
-        created_at: Utc::now(),
+        interests: vec![],
+        created_at: Some(Utc::now()),
+        timezone: None,
+        is_premium: None,
+        is_snoozed: None,
+        tenant_id: None,
+        market: None,
+        city: None,
+        tier: None,
+        embedding: None,
+        interested_in: vec![],
+        languages: vec![],
+        relationship_goal: None,
+        attributes: Default::default(),
+        updated_at: None,
+        schema_version: SCHEMA_VERSION,
     }
 }
 
+fn create_candidates(count: usize) -> Vec<UserProfile> {
+    (0..count)
+        .map(|i| {
+            let lat_offset = (i as f64 * 0.001) % 0.5;
+            let lon_offset = (i as f64 * 0.001) % 0.5;
+            create_candidate(i, 40.7128 + lat_offset, -74.0060 + lon_offset)
+        })
+        .collect()
+}
+
 fn create_preferences() -> UserPreferences {
     UserPreferences {
         user_id: "current_user".to_string(),
@@ -35,9 +63,19 @@ fn create_preferences() -> UserPreferences {
         max_height_cm: 180,
         preferred_hair_colors: vec![],
         preferred_sports: vec!["tennis".to_string()],
+        preferred_interests: vec![],
         max_distance_km: 50,
         latitude: 40.7128,
         longitude: -74.0060,
+        require_photos: None,
+        embedding: None,
+        preferred_languages: vec![],
+        require_shared_language: false,
+        verified_only: false,
+        relationship_goal: None,
+        attributes: Default::default(),
+        updated_at: None,
+        schema_version: SCHEMA_VERSION,
     }
 }
 
@@ -54,6 +92,80 @@ fn bench_haversine_distance(c: &mut Criterion) {
     });
 }
 
+fn bench_distance_calculator(c: &mut Criterion) {
+    let calculator = DistanceCalculator::new(DistanceFormula::Haversine, 40.7128, -74.0060);
+
+    c.bench_function("distance_calculator_haversine", |b| {
+        b.iter(|| calculator.distance_to(black_box(40.72), black_box(-74.01)));
+    });
+}
+
+fn bench_distance_batch(c: &mut Criterion) {
+    let calculator = DistanceCalculator::new(DistanceFormula::Haversine, 40.7128, -74.0060);
+    let points: Vec<(f64, f64)> = (0..1000)
+        .map(|i| (40.7128 + (i as f64 * 0.001) % 0.5, -74.0060 + (i as f64 * 0.001) % 0.5))
+        .collect();
+
+    let mut group = c.benchmark_group("distances_1000");
+
+    group.bench_function("scalar", |b| {
+        b.iter(|| {
+            points
+                .iter()
+                .map(|&(lat, lon)| calculator.distance_to(black_box(lat), black_box(lon)))
+                .collect::<Vec<f64>>()
+        });
+    });
+
+    group.bench_function("batch", |b| {
+        b.iter(|| haversine_distance_batch(black_box((40.7128, -74.0060)), black_box(&points)));
+    });
+
+    group.finish();
+}
+
+/// Reciprocal scoring: every user measured against every other, so each
+/// pair is computed once per direction
+fn bench_reciprocal_distances(c: &mut Criterion) {
+    let users: Vec<(f64, f64)> = (0..200)
+        .map(|i| (40.7128 + (i as f64 * 0.0013) % 0.3, -74.0060 + (i as f64 * 0.0017) % 0.3))
+        .collect();
+
+    let mut group = c.benchmark_group("reciprocal_distances_200");
+
+    for formula in [DistanceFormula::Haversine, DistanceFormula::Geodesic] {
+        let name = format!("{:?}", formula).to_lowercase();
+
+        group.bench_function(BenchmarkId::new("uncached", &name), |b| {
+            b.iter(|| {
+                let mut total = 0.0;
+                for &(lat1, lon1) in &users {
+                    for &(lat2, lon2) in &users {
+                        total += formula.distance(lat1, lon1, lat2, lon2);
+                    }
+                }
+                black_box(total)
+            });
+        });
+
+        // A fresh cache per run: the reverse direction of each pair hits
+        group.bench_function(BenchmarkId::new("cached", &name), |b| {
+            b.iter(|| {
+                let cache = DistanceCache::new(formula, 50_000);
+                let mut total = 0.0;
+                for &(lat1, lon1) in &users {
+                    for &(lat2, lon2) in &users {
+                        total += cache.distance(lat1, lon1, lat2, lon2);
+                    }
+                }
+                black_box(total)
+            });
+        });
+    }
+
+    group.finish();
+}
+
 fn bench_bounding_box(c: &mut Criterion) {
     c.bench_function("bounding_box_calculation", |b| {
         b.iter(|| {
@@ -73,13 +185,7 @@ fn bench_matching(c: &mut Criterion) {
     let mut group = c.benchmark_group("matching");
 
     for candidate_count in [10, 50, 100, 500, 1000].iter() {
-        let candidates: Vec<UserProfile> = (0..*candidate_count)
-            .map(|i| {
-                let lat_offset = (i as f64 * 0.001) % 0.5;
-                let lon_offset = (i as f64 * 0.001) % 0.5;
-                create_candidate(i, 40.7128 + lat_offset, -74.0060 + lon_offset)
-            })
-            .collect();
+        let candidates = create_candidates(*candidate_count);
 
         group.bench_with_input(
             BenchmarkId::new("find_matches", candidate_count),
@@ -101,13 +207,7 @@ fn bench_matching(c: &mut Criterion) {
 
 fn bench_filtering_pipeline(c: &mut Criterion) {
     let preferences = create_preferences();
-    let candidates: Vec<UserProfile> = (0..100)
-        .map(|i| {
-            let lat_offset = (i as f64 * 0.001) % 0.5;
-            let lon_offset = (i as f64 * 0.001) % 0.5;
-            create_candidate(i, 40.7128 + lat_offset, -74.0060 + lon_offset)
-        })
-        .collect();
+    let candidates = create_candidates(100);
 
     c.bench_function("filtering_pipeline_100_candidates", |b| {
         b.iter(|| {
@@ -119,6 +219,7 @@ fn bench_filtering_pipeline(c: &mut Criterion) {
 
             let filtered: Vec<_> = candidates
                 .iter()
+                .filter(|p| is_within_bounding_box(p.latitude, p.longitude, &bbox))
                 .filter(|p| {
                     haversine_distance(
                         preferences.latitude,
@@ -127,7 +228,7 @@ fn bench_filtering_pipeline(c: &mut Criterion) {
                         p.longitude,
                     ) < preferences.max_distance_km as f64
                 })
-                .filter(|p| p.is_active && !p.is_timeout)
+                .filter(|p| p.is_active && !p.timeout())
                 .filter(|p| preferences.preferred_genders.contains(&p.gender))
                 .filter(|p| p.age >= preferences.min_age && p.age <= preferences.max_age)
                 .collect();
@@ -137,12 +238,189 @@ fn bench_filtering_pipeline(c: &mut Criterion) {
     });
 }
 
+/// Seen-profile exclusion list sizes: a new user, a regular, a heavy swiper
+const EXCLUSION_SIZES: [usize; 3] = [0, 1_000, 10_000];
+
+/// Full pipeline over 2k candidates with seen lists of realistic sizes
+///
+/// Seen IDs are the even numbers, so up to half the pool is excluded and
+/// the rest of a large list matches nobody, like users seen long ago.
+fn bench_find_matches_excluding(c: &mut Criterion) {
+    let matcher = Matcher::with_default_weights();
+    let preferences = create_preferences();
+    let candidates = create_candidates(2000);
+
+    let mut group = c.benchmark_group("find_matches_excluding_2k");
+
+    for size in EXCLUSION_SIZES {
+        let exclude: HashSet<String> = (0..size).map(|i| (i * 2).to_string()).collect();
+
+        group.bench_with_input(BenchmarkId::from_parameter(size), &size, |b, _| {
+            b.iter_batched(
+                || candidates.clone(),
+                |candidates| matcher.find_matches_excluding(black_box(&preferences), candidates, black_box(20), black_box(&exclude)),
+                BatchSize::LargeInput,
+            );
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_exclusions(c: &mut Criterion) {
+    let matcher = Matcher::with_default_weights();
+    let preferences = create_preferences();
+    let candidates = create_candidates(1000);
+
+    let mut group = c.benchmark_group("exclusions");
+
+    for size in EXCLUSION_SIZES {
+        // Every other candidate among the seen profiles
+        let exclude_vec: Vec<String> = (0..size).map(|i| (i * 2).to_string()).collect();
+        let exclude_set: HashSet<String> = exclude_vec.iter().cloned().collect();
+
+        // Both arms get the same owned candidates, cloned outside the timing
+        group.bench_with_input(BenchmarkId::new("vec_contains", size), &size, |b, _| {
+            b.iter_batched(
+                || candidates.clone(),
+                |candidates| {
+                    let remaining: Vec<UserProfile> =
+                        candidates.into_iter().filter(|p| !exclude_vec.contains(&p.user_id)).collect();
+                    matcher.find_matches(black_box(&preferences), remaining, black_box(20))
+                },
+                BatchSize::LargeInput,
+            );
+        });
+
+        group.bench_with_input(BenchmarkId::new("hashset_contains", size), &size, |b, _| {
+            b.iter_batched(
+                || candidates.clone(),
+                |candidates| {
+                    let remaining: Vec<UserProfile> =
+                        candidates.into_iter().filter(|p| !exclude_set.contains(&p.user_id)).collect();
+                    matcher.find_matches(black_box(&preferences), remaining, black_box(20))
+                },
+                BatchSize::LargeInput,
+            );
+        });
+    }
+
+    group.finish();
+}
+
+/// Ranking step alone: sorting every match then truncating (what the
+/// matcher does) against selecting the top k first and sorting only those
+fn bench_top_k(c: &mut Criterion) {
+    let matcher = Matcher::with_default_weights();
+    let preferences = create_preferences();
+    let options = MatchOptions { limit: None, ..Default::default() };
+
+    let mut group = c.benchmark_group("top_20");
+
+    for candidate_count in [1_000, 10_000] {
+        let scored = matcher.find_matches_with(&preferences, create_candidates(candidate_count), &options).matches;
+        let by_rank = |a: &ScoredMatch, b: &ScoredMatch| {
+            b.match_score
+                .partial_cmp(&a.match_score)
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| a.distance_km.partial_cmp(&b.distance_km).unwrap_or(Ordering::Equal))
+        };
+
+        group.bench_with_input(BenchmarkId::new("sort_truncate", candidate_count), &candidate_count, |b, _| {
+            b.iter_batched(
+                || scored.clone(),
+                |mut matches| {
+                    matches.sort_by(by_rank);
+                    matches.truncate(20);
+                    matches
+                },
+                BatchSize::LargeInput,
+            );
+        });
+
+        group.bench_with_input(BenchmarkId::new("select_nth_then_sort", candidate_count), &candidate_count, |b, _| {
+            b.iter_batched(
+                || scored.clone(),
+                |mut matches| {
+                    if matches.len() > 20 {
+                        matches.select_nth_unstable_by(19, by_rank);
+                        matches.truncate(20);
+                    }
+                    matches.sort_by(by_rank);
+                    matches
+                },
+                BatchSize::LargeInput,
+            );
+        });
+    }
+
+    group.finish();
+}
+
+/// Scoring alone: one thread against the candidates split across all cores
+fn bench_parallel_scoring(c: &mut Criterion) {
+    let weights = ScoringWeights::default();
+    let preferences = create_preferences();
+    let threads = std::thread::available_parallelism().map_or(1, |threads| threads.get());
+
+    let mut group = c.benchmark_group("scoring");
+
+    for candidate_count in [1_000, 10_000] {
+        let candidates = create_candidates(candidate_count);
+        let score = |chunk: &[UserProfile]| -> Vec<f64> {
+            chunk.iter().map(|p| calculate_match_score(p, &preferences, &weights).0).collect()
+        };
+
+        group.bench_with_input(BenchmarkId::new("serial", candidate_count), &candidate_count, |b, _| {
+            b.iter(|| score(black_box(&candidates)));
+        });
+
+        group.bench_with_input(BenchmarkId::new(format!("parallel_{}_threads", threads), candidate_count), &candidate_count, |b, _| {
+            b.iter(|| {
+                let chunk_size = candidates.len().div_ceil(threads);
+                std::thread::scope(|scope| {
+                    let handles: Vec<_> = black_box(&candidates)
+                        .chunks(chunk_size)
+                        .map(|chunk| scope.spawn(move || score(chunk)))
+                        .collect();
+                    handles.into_iter().flat_map(|handle| handle.join().unwrap()).collect::<Vec<f64>>()
+                })
+            });
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_cosine_similarity(c: &mut Criterion) {
+    let mut group = c.benchmark_group("cosine_similarity");
+
+    for dims in [128usize, 384, 768] {
+        let a: Vec<f32> = (0..dims).map(|i| (i as f32 * 0.37).sin()).collect();
+        let b: Vec<f32> = (0..dims).map(|i| (i as f32 * 0.11).cos()).collect();
+
+        group.bench_with_input(BenchmarkId::from_parameter(dims), &dims, |bench, _| {
+            bench.iter(|| cosine_similarity(black_box(&a), black_box(&b)))
+        });
+    }
+
+    group.finish();
+}
+
 criterion_group!(
     benches,
     bench_haversine_distance,
+    bench_distance_calculator,
+    bench_distance_batch,
+    bench_reciprocal_distances,
     bench_bounding_box,
     bench_matching,
-    bench_filtering_pipeline
+    bench_filtering_pipeline,
+    bench_find_matches_excluding,
+    bench_exclusions,
+    bench_top_k,
+    bench_parallel_scoring,
+    bench_cosine_similarity
 );
 
 criterion_main!(benches);
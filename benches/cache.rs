@@ -0,0 +1,59 @@
+// Concurrent Redis round trips: one connection behind a mutex versus the
+// multiplexed connection CacheManager shares between callers
+//
+// Needs a Redis server: set LUME_BENCH_REDIS_URL (e.g. redis://127.0.0.1:6379).
+// Without it the benchmarks are skipped.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use futures_util::future::join_all;
+use lume_algo::services::CacheManager;
+use redis::aio::ConnectionManager;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Requests in flight at once
+const CONCURRENCY: [usize; 3] = [1, 16, 64];
+
+fn bench_concurrent_round_trips(c: &mut Criterion) {
+    let Ok(url) = std::env::var("LUME_BENCH_REDIS_URL") else {
+        eprintln!("LUME_BENCH_REDIS_URL is not set, skipping the Redis benchmarks");
+        return;
+    };
+
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let cache = rt.block_on(CacheManager::new(&url, 1000, 60)).expect("Failed to connect to Redis");
+    let client = redis::Client::open(url.as_str()).unwrap();
+    let locked = Arc::new(Mutex::new(rt.block_on(ConnectionManager::new(client)).unwrap()));
+
+    // PING skips the L1 cache, so every call is a Redis round trip
+    let mut group = c.benchmark_group("redis_round_trips");
+    for concurrency in CONCURRENCY {
+        group.throughput(Throughput::Elements(concurrency as u64));
+
+        group.bench_with_input(BenchmarkId::new("mutex", concurrency), &concurrency, |b, &n| {
+            b.iter(|| {
+                rt.block_on(join_all((0..n).map(|_| {
+                    let locked = locked.clone();
+                    async move {
+                        let mut conn = locked.lock().await;
+                        redis::cmd("PING").query_async::<()>(&mut *conn).await.unwrap();
+                    }
+                })))
+            })
+        });
+
+        group.bench_with_input(BenchmarkId::new("multiplexed", concurrency), &concurrency, |b, &n| {
+            b.iter(|| {
+                rt.block_on(async {
+                    for result in join_all((0..n).map(|_| cache.ping())).await {
+                        result.unwrap();
+                    }
+                })
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_concurrent_round_trips);
+criterion_main!(benches);
@@ -0,0 +1,46 @@
+// Appwrite profile documents: the parse (or the failure reason logged for
+// skipped documents), then filtering and scoring the parsed profile
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use lume_algo::core::{calculate_match_score, matches_demographics};
+use lume_algo::models::{ScoringWeights, UserPreferences, UserProfile};
+use lume_algo::services::appwrite::parse_failure_reason;
+use lume_algo::services::AppwriteDocument;
+use serde_json::Value;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(doc) = serde_json::from_slice::<Value>(data) else {
+        return;
+    };
+
+    let profile = match AppwriteDocument::<UserProfile>::from_value(&doc) {
+        Ok(parsed) => UserProfile::from(parsed),
+        Err(e) => {
+            parse_failure_reason::<UserProfile>("profiles", &doc, &e);
+            return;
+        }
+    };
+
+    let preferences = preferences();
+    profile.verification();
+    matches_demographics(&profile, &preferences);
+    calculate_match_score(&profile, &preferences, &ScoringWeights::default());
+});
+
+fn preferences() -> UserPreferences {
+    serde_json::from_value(serde_json::json!({
+        "userId": "viewer",
+        "preferredGenders": [],
+        "minAge": 18,
+        "maxAge": 99,
+        "minHeightCm": 140,
+        "maxHeightCm": 210,
+        "preferredHairColors": [],
+        "preferredSports": ["tennis"],
+        "maxDistanceKm": 100,
+        "latitude": 40.7128,
+        "longitude": -74.0060
+    }))
+    .expect("valid preferences")
+}
@@ -0,0 +1,26 @@
+// Body of POST /api/v1/matches/find: parse, validate, and check that an
+// accepted request survives a serialize/parse round trip
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use lume_algo::models::{FindMatchesRequest, ScoringWeights};
+use validator::Validate;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(request) = serde_json::from_slice::<FindMatchesRequest>(data) else {
+        return;
+    };
+    if request.validate().is_err() {
+        return;
+    }
+
+    if let Some(weights) = request.weights {
+        weights.apply(ScoringWeights::default());
+    }
+
+    let json = serde_json::to_vec(&request).expect("a parsed request serializes");
+    let reparsed: FindMatchesRequest = serde_json::from_slice(&json).expect("a serialized request parses");
+    assert_eq!(reparsed.user_id, request.user_id);
+    assert_eq!(reparsed.limit, request.limit);
+    assert_eq!(reparsed.exclude_user_ids, request.exclude_user_ids);
+});
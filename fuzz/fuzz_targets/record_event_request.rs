@@ -0,0 +1,23 @@
+// Body of POST /api/v1/matches/event: parse, validate, and check that an
+// accepted request survives a serialize/parse round trip
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use lume_algo::models::RecordEventRequest;
+use validator::Validate;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(request) = serde_json::from_slice::<RecordEventRequest>(data) else {
+        return;
+    };
+    if request.validate().is_err() {
+        return;
+    }
+
+    let json = serde_json::to_vec(&request).expect("a parsed request serializes");
+    let reparsed: RecordEventRequest = serde_json::from_slice(&json).expect("a serialized request parses");
+    assert_eq!(reparsed.user_id, request.user_id);
+    assert_eq!(reparsed.target_user_id, request.target_user_id);
+    assert_eq!(reparsed.event_type, request.event_type);
+    assert_eq!(reparsed.occurred_at, request.occurred_at);
+});
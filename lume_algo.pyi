@@ -0,0 +1,28 @@
+# Type hints for the `lume_algo` extension module (src/python.rs)
+#
+# Profiles and preferences are dicts in the API's JSON shape
+# (userId, heightCm, sportsPreferences, ...).
+
+from typing import Any, Optional
+
+Profile = dict[str, Any]
+Preferences = dict[str, Any]
+Weights = dict[str, float]
+
+def haversine_distance(lat1: float, lon1: float, lat2: float, lon2: float) -> float: ...
+def calculate_match_score(profile: Profile, preferences: Preferences, weights: Optional[Weights] = None) -> tuple[float, list[str]]: ...
+
+class Matcher:
+    def __init__(self, weights: Optional[Weights] = None, min_score: Optional[float] = None) -> None: ...
+    def find_matches(
+        self,
+        preferences: Preferences,
+        candidates: list[Profile],
+        limit: int = 20,
+        exclude_user_ids: Optional[set[str]] = None,
+        score_breakdown: bool = False,
+    ) -> list[dict[str, Any]]: ...
+    @property
+    def weights(self) -> Weights: ...
+    @property
+    def min_score(self) -> float: ...
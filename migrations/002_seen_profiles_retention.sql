@@ -0,0 +1,10 @@
+-- Support the seen_profiles retention job, which deletes old 'viewed' and
+-- 'passed' rows. A partial index keeps those deletes from scanning liked/matched
+-- history, which is kept indefinitely.
+--
+-- Note: the table is intentionally not range-partitioned by seen_at. Postgres
+-- requires unique constraints on partitioned tables to include the partition
+-- key, which would break the (user_id, target_user_id) upsert in record_seen.
+CREATE INDEX IF NOT EXISTS idx_seen_profiles_prunable
+    ON seen_profiles(seen_at)
+    WHERE event_type IN ('viewed', 'passed');
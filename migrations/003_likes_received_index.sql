@@ -0,0 +1,6 @@
+-- Incoming likes are read from the target's perspective ("who liked me"),
+-- newest first. This partial index serves that lookup without touching
+-- viewed/passed rows.
+CREATE INDEX IF NOT EXISTS idx_seen_profiles_likes_received
+    ON seen_profiles(target_user_id, seen_at DESC)
+    WHERE event_type = 'liked';
@@ -0,0 +1,11 @@
+-- Mutual matches with no follow-up event are marked inactive after
+-- maintenance.match_expiry_days by the match expiry job. A new event on the
+-- row (record_seen) clears expired_at again.
+ALTER TABLE seen_profiles ADD COLUMN IF NOT EXISTS expired_at TIMESTAMPTZ;
+
+-- The expiry job and the expiring-matches endpoint only look at active matches
+CREATE INDEX IF NOT EXISTS idx_seen_profiles_active_matches
+    ON seen_profiles(seen_at)
+    WHERE event_type = 'matched' AND expired_at IS NULL;
+
+COMMENT ON COLUMN seen_profiles.expired_at IS 'When a matched row was marked inactive for lack of follow-up; NULL while active';
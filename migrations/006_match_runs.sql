@@ -0,0 +1,18 @@
+-- One row per find pipeline run: how many candidates were asked for and
+-- how many matches came back, for tuning the candidate overfetch
+CREATE TABLE IF NOT EXISTS match_runs (
+    id BIGSERIAL PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    requested_limit INTEGER NOT NULL,
+    overfetch_multiplier INTEGER NOT NULL,
+    candidates INTEGER NOT NULL,
+    matches INTEGER NOT NULL,
+    partial BOOLEAN NOT NULL DEFAULT FALSE,
+    duration_ms INTEGER NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_match_runs_user_id ON match_runs(user_id, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_match_runs_created_at ON match_runs(created_at);
+
+COMMENT ON TABLE match_runs IS 'Find pipeline telemetry: candidate overfetch and results per run';
@@ -0,0 +1,6 @@
+-- Market and nearest city each find run was searched from, tagged by the
+-- embedded reverse geocoder, for per-market analytics
+ALTER TABLE match_runs ADD COLUMN IF NOT EXISTS market TEXT;
+ALTER TABLE match_runs ADD COLUMN IF NOT EXISTS city TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_match_runs_market ON match_runs(market, created_at DESC);
@@ -0,0 +1,10 @@
+-- Clients may send events late (swipes queued while offline) with the time
+-- they happened. seen_at holds that time and orders events; received_at is
+-- when the server recorded the event.
+ALTER TABLE seen_profiles ADD COLUMN IF NOT EXISTS received_at TIMESTAMPTZ;
+UPDATE seen_profiles SET received_at = seen_at WHERE received_at IS NULL;
+ALTER TABLE seen_profiles ALTER COLUMN received_at SET DEFAULT NOW();
+ALTER TABLE seen_profiles ALTER COLUMN received_at SET NOT NULL;
+
+COMMENT ON COLUMN seen_profiles.seen_at IS 'When the event occurred (client occurredAt, or server time when not sent)';
+COMMENT ON COLUMN seen_profiles.received_at IS 'When the server recorded the event';
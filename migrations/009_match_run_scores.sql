@@ -0,0 +1,6 @@
+-- Raw (uncalibrated) score of each match a find run returned, as a JSON
+-- object of target user ID to score. Joined with the swipes that followed,
+-- it gives the score -> like-rate data score calibration is fitted from.
+ALTER TABLE match_runs ADD COLUMN IF NOT EXISTS raw_scores TEXT;
+
+COMMENT ON COLUMN match_runs.raw_scores IS 'JSON object of returned target user ID to raw match score';
@@ -0,0 +1,36 @@
+-- Copy of the Appwrite profiles collection, kept current by the profile
+-- sync job. Deactivated and deleted profiles stay behind as tombstones
+-- (deleted_at set) until purged, so a late incremental sync can't bring
+-- them back.
+CREATE TABLE IF NOT EXISTS profile_mirror (
+    user_id TEXT PRIMARY KEY,
+    gender TEXT NOT NULL,
+    age INTEGER NOT NULL,
+    latitude DOUBLE PRECISION NOT NULL,
+    longitude DOUBLE PRECISION NOT NULL,
+    profile JSONB NOT NULL,
+    -- Appwrite $updatedAt of the document
+    updated_at TIMESTAMPTZ,
+    synced_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    deleted_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_profile_mirror_candidates
+    ON profile_mirror(gender, age, latitude, longitude) WHERE deleted_at IS NULL;
+CREATE INDEX IF NOT EXISTS idx_profile_mirror_deleted_at
+    ON profile_mirror(deleted_at) WHERE deleted_at IS NOT NULL;
+
+-- Progress of the sync job: the $updatedAt watermark incremental syncs
+-- resume from, and a lease so only one instance syncs at a time
+CREATE TABLE IF NOT EXISTS profile_sync_state (
+    name TEXT PRIMARY KEY,
+    watermark TIMESTAMPTZ,
+    last_sync_at TIMESTAMPTZ,
+    last_full_sync_at TIMESTAMPTZ,
+    leased_until TIMESTAMPTZ
+);
+
+INSERT INTO profile_sync_state (name) VALUES ('profiles') ON CONFLICT (name) DO NOTHING;
+
+COMMENT ON TABLE profile_mirror IS 'Appwrite profiles mirrored for candidate queries; deleted_at marks tombstones';
+COMMENT ON TABLE profile_sync_state IS 'Watermark and lease of the profile mirror sync';
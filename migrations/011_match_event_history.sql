@@ -0,0 +1,16 @@
+-- Every recorded match event, in the order received. seen_profiles keeps
+-- only the latest event per pair; this keeps all of them, plus the
+-- `matched` entries written when two likes make a mutual match.
+CREATE TABLE IF NOT EXISTS match_event_history (
+    id BIGSERIAL PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    target_user_id TEXT NOT NULL,
+    event_type event_type NOT NULL,
+    occurred_at TIMESTAMPTZ NOT NULL,
+    received_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_match_event_history_user ON match_event_history(user_id, occurred_at DESC);
+CREATE INDEX IF NOT EXISTS idx_match_event_history_target ON match_event_history(target_user_id, occurred_at DESC);
+
+COMMENT ON TABLE match_event_history IS 'Append-only history of match events, written with the seen_profiles upsert';
@@ -0,0 +1,24 @@
+-- One row per mutual match, written in the same transaction as the like
+-- that completed it (see PostgresClient::record_event). The pair is stored
+-- once, lowest user ID first; seen_profiles keeps the per-side `matched` rows.
+CREATE TABLE IF NOT EXISTS mutual_matches (
+    user_a TEXT NOT NULL,
+    user_b TEXT NOT NULL,
+    matched_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_a, user_b),
+    CHECK (user_a < user_b)
+);
+
+CREATE INDEX IF NOT EXISTS idx_mutual_matches_user_b ON mutual_matches(user_b, matched_at DESC);
+CREATE INDEX IF NOT EXISTS idx_mutual_matches_user_a ON mutual_matches(user_a, matched_at DESC);
+
+-- Existing matches: pairs where both sides have a `matched` row
+INSERT INTO mutual_matches (user_a, user_b, matched_at)
+SELECT a.user_id, a.target_user_id, GREATEST(a.seen_at, b.seen_at)
+FROM seen_profiles a
+JOIN seen_profiles b ON b.user_id = a.target_user_id AND b.target_user_id = a.user_id
+WHERE a.event_type = 'matched' AND b.event_type = 'matched' AND a.user_id < a.target_user_id
+ON CONFLICT DO NOTHING;
+
+COMMENT ON TABLE mutual_matches IS 'Mutual matches, one row per pair (user_a < user_b)';
@@ -0,0 +1,32 @@
+-- Incoming likes, one row per liker and target, written with the like.
+-- seen_profiles keeps only the liker's latest event, so a view or pass after
+-- the like used to drop it from the target's likes-received queue; rows here
+-- are only ever inserted, and the first like of a pair is kept.
+CREATE TABLE IF NOT EXISTS likes_received (
+    target_user_id TEXT NOT NULL,
+    liker_id TEXT NOT NULL,
+    liked_at TIMESTAMPTZ NOT NULL,
+    received_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (target_user_id, liker_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_likes_received_target ON likes_received(target_user_id, liked_at DESC);
+
+-- Existing likes: every recorded one, then current likes from before the history
+INSERT INTO likes_received (target_user_id, liker_id, liked_at)
+SELECT target_user_id, user_id, MIN(occurred_at)
+FROM match_event_history
+WHERE event_type = 'liked'
+GROUP BY target_user_id, user_id
+ON CONFLICT DO NOTHING;
+
+INSERT INTO likes_received (target_user_id, liker_id, liked_at)
+SELECT target_user_id, user_id, seen_at
+FROM seen_profiles
+WHERE event_type = 'liked'
+ON CONFLICT DO NOTHING;
+
+-- Likes received are no longer read from seen_profiles
+DROP INDEX IF EXISTS idx_seen_profiles_likes_received;
+
+COMMENT ON TABLE likes_received IS 'Append-only incoming likes, one row per pair (first like)';
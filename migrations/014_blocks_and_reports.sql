@@ -0,0 +1,27 @@
+-- Users blocking and reporting each other. A block hides both users from
+-- each other's feeds; a report is kept for moderation until resolved.
+CREATE TABLE IF NOT EXISTS user_blocks (
+    blocker_id TEXT NOT NULL,
+    blocked_id TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (blocker_id, blocked_id)
+);
+
+-- Feeds exclude users who blocked the viewer, too
+CREATE INDEX IF NOT EXISTS idx_user_blocks_blocked_id ON user_blocks(blocked_id);
+
+CREATE TABLE IF NOT EXISTS user_reports (
+    id BIGSERIAL PRIMARY KEY,
+    reporter_id TEXT NOT NULL,
+    reported_id TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    details TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    resolved_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_user_reports_reported_id ON user_reports(reported_id, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_user_reports_open ON user_reports(created_at) WHERE resolved_at IS NULL;
+
+COMMENT ON TABLE user_blocks IS 'Blocks between users; either direction hides the pair from each other';
+COMMENT ON TABLE user_reports IS 'User reports awaiting (resolved_at NULL) or past moderation';
@@ -0,0 +1,18 @@
+-- Transactional outbox for the event stream: messages written in the same
+-- transaction as the change they describe, then published and marked.
+CREATE TABLE IF NOT EXISTS event_outbox (
+    id BIGSERIAL PRIMARY KEY,
+    topic TEXT NOT NULL,
+    -- Partition key (user ID)
+    key TEXT NOT NULL,
+    payload JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    published_at TIMESTAMPTZ
+);
+
+-- The relay only reads unpublished messages, oldest first
+CREATE INDEX IF NOT EXISTS idx_event_outbox_pending ON event_outbox(id) WHERE published_at IS NULL;
+CREATE INDEX IF NOT EXISTS idx_event_outbox_published_at ON event_outbox(published_at) WHERE published_at IS NOT NULL;
+
+COMMENT ON TABLE event_outbox IS 'Event stream messages awaiting (published_at NULL) or past publishing';
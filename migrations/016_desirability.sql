@@ -0,0 +1,13 @@
+-- Per-user desirability computed from the swipes a profile receives, for
+-- ranking and for balancing who gets shown to whom.
+CREATE TABLE IF NOT EXISTS desirability_scores (
+    user_id TEXT PRIMARY KEY,
+    score DOUBLE PRECISION NOT NULL,
+    likes_received INTEGER NOT NULL DEFAULT 0,
+    views_received INTEGER NOT NULL DEFAULT 0,
+    computed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_desirability_scores_computed_at ON desirability_scores(computed_at);
+
+COMMENT ON TABLE desirability_scores IS 'Desirability per user from received swipes; score is 0-1';
@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS seen_profiles;
+DROP TYPE IF EXISTS event_type;
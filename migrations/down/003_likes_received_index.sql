@@ -0,0 +1 @@
+DROP INDEX IF EXISTS idx_seen_profiles_likes_received;
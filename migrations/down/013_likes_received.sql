@@ -0,0 +1,5 @@
+CREATE INDEX IF NOT EXISTS idx_seen_profiles_likes_received
+    ON seen_profiles(target_user_id, seen_at DESC)
+    WHERE event_type = 'liked';
+
+DROP TABLE IF EXISTS likes_received;
@@ -0,0 +1,357 @@
+//! `lume-admin` - operations tool for on-call engineers
+//!
+//! Talks to PostgreSQL, Redis and Appwrite directly with the service's own
+//! configuration (`config/default.toml` plus `LUME_` env vars), so it works
+//! when the HTTP API is down. Destructive commands are written to the audit
+//! log like their admin API counterparts.
+
+use clap::{Parser, Subcommand};
+use lume_algo::config::{DatabaseSettings, FeedSettings, Settings, TenantSettings};
+use lume_algo::core::ScoreCalibration;
+use lume_algo::models::{ScoringWeights, WeightsOverride};
+use lume_algo::services::{
+    AppwriteClient, AuditEntry, Cache, CacheKey, CacheManager, FeedGenerator, FeedOptions, FeedWorker, InvalidateOptions,
+    PostgresClient, ProfileSync, Seeder,
+};
+use std::sync::Arc;
+use validator::Validate;
+
+#[derive(Debug, Parser)]
+#[command(name = "lume-admin", about = "Operations tool for the Lume matching service")]
+struct Cli {
+    /// Act on a named tenant's schema, collections, cache prefix and weights
+    #[arg(long, global = true)]
+    tenant: Option<String>,
+
+    /// Operator recorded in the audit log (defaults to $USER)
+    #[arg(long, global = true)]
+    actor: Option<String>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Delete every seen record of a user and drop their stored feed
+    ClearSeen { user_id: String },
+
+    /// Print a user's swipe statistics
+    Stats { user_id: String },
+
+    /// Recompute a user's feed and print it
+    RecomputeFeed {
+        user_id: String,
+        /// Weight overrides as JSON, e.g. '{"distance": 0.0, "sports": 0.6}'
+        #[arg(long)]
+        weights: Option<String>,
+        /// Number of matches (defaults to `feed.feed_size`)
+        #[arg(long)]
+        limit: Option<usize>,
+        /// Store the feed under `matches:{userId}:v...` so it is served as pre-generated
+        #[arg(long)]
+        store: bool,
+    },
+
+    /// Delete cache entries matching a Redis pattern, e.g. 'matches:*'
+    Invalidate {
+        pattern: String,
+        /// Stop after deleting this many keys
+        #[arg(long, default_value_t = lume_algo::services::cache::DEFAULT_INVALIDATE_MAX_KEYS)]
+        max_keys: usize,
+        /// Count matching keys without deleting them
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Fit `[scoring.calibration]` from the swipes that followed recent find runs
+    FitCalibration {
+        /// Use find runs from the last N days
+        #[arg(long, default_value_t = 30)]
+        days: i64,
+        /// Number of piecewise points to fit
+        #[arg(long, default_value_t = 10)]
+        buckets: usize,
+        /// Most swipes to read
+        #[arg(long, default_value_t = 100_000)]
+        max_samples: i64,
+    },
+
+    /// Write load-test profiles and preferences to Appwrite (see `[seed]`)
+    Seed {
+        /// Number of users (defaults to `seed.count`)
+        #[arg(long)]
+        count: Option<usize>,
+        /// RNG seed for a reproducible data set (defaults to `seed.rng_seed`)
+        #[arg(long)]
+        rng_seed: Option<u64>,
+        /// Only scatter users around these configured cities, e.g. 'Berlin,Munich'
+        #[arg(long, value_delimiter = ',')]
+        cities: Vec<String>,
+    },
+
+    /// Delete every seeded profile and preferences document
+    PurgeSeed,
+
+    /// List the database migrations of this build and when each was applied
+    Migrations,
+
+    /// Undo the latest applied migration with its script in migrations/down/
+    MigrateDown {
+        /// Version of the latest applied migration, as a safety check
+        version: i64,
+    },
+
+    /// Sync Appwrite profiles into the PostgreSQL profile mirror now
+    SyncProfiles {
+        /// List every active profile and tombstone the ones gone from Appwrite
+        #[arg(long)]
+        full: bool,
+    },
+}
+
+type CliResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+/// Settings for the selected tenant, or the default app
+fn tenant_settings<'a>(settings: &'a Settings, tenant: Option<&str>) -> CliResult<Option<&'a TenantSettings>> {
+    let Some(tenant_id) = tenant else {
+        return Ok(None);
+    };
+
+    settings
+        .tenants
+        .get(tenant_id)
+        .map(Some)
+        .ok_or_else(|| format!("Unknown tenant {}", tenant_id).into())
+}
+
+async fn connect_postgres(settings: &Settings, tenant: Option<&TenantSettings>) -> CliResult<Arc<PostgresClient>> {
+    let database = DatabaseSettings {
+        schema: tenant
+            .and_then(|t| t.database_schema.clone())
+            .or_else(|| settings.database.schema.clone()),
+        ..settings.database.clone()
+    };
+
+    Ok(Arc::new(PostgresClient::from_settings(&database).await?))
+}
+
+async fn connect_cache(
+    settings: &Settings,
+    tenant_id: Option<&str>,
+    tenant: Option<&TenantSettings>,
+) -> CliResult<Arc<CacheManager>> {
+    let cache = CacheManager::new(
+        &settings.cache.redis_url,
+        settings.cache.l1_cache_size.unwrap_or(1000),
+        settings.cache.ttl_secs.unwrap_or(300),
+    )
+    .await?
+    .with_ttls(settings.cache.ttls.clone());
+
+    Ok(Arc::new(match (tenant_id, tenant) {
+        (Some(tenant_id), Some(tenant)) => cache.with_key_prefix(tenant.cache_prefix(tenant_id)),
+        _ => cache,
+    }))
+}
+
+fn connect_appwrite(settings: &Settings, tenant: Option<&TenantSettings>) -> Arc<AppwriteClient> {
+    let collections = tenant.and_then(|t| t.collection.as_ref()).unwrap_or(&settings.collection);
+    Arc::new(AppwriteClient::from_settings(&settings.appwrite, collections))
+}
+
+async fn audit(postgres: &PostgresClient, entry: AuditEntry) {
+    if let Err(e) = postgres.append_audit(&entry).await {
+        eprintln!("warning: failed to write audit entry for {}: {}", entry.action, e);
+    }
+}
+
+fn print_json(value: &impl serde::Serialize) -> CliResult<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+async fn run(cli: Cli) -> CliResult<()> {
+    let settings = Settings::load_with_secrets().await?;
+    settings.validate()?;
+    let tenant_id = cli.tenant.as_deref();
+    let tenant = tenant_settings(&settings, tenant_id)?;
+    let actor = cli
+        .actor
+        .or_else(|| std::env::var("USER").ok())
+        .map(|actor| format!("lume-admin:{}", actor))
+        .unwrap_or_else(|| "lume-admin".to_string());
+
+    match cli.command {
+        Command::ClearSeen { user_id } => {
+            let postgres = connect_postgres(&settings, tenant).await?;
+            let cache = connect_cache(&settings, tenant_id, tenant).await?;
+
+            let deleted = postgres.clear_seen_profiles(&user_id).await?;
+            cache.invalidate_pattern(&CacheKey::matches_pattern(&user_id)).await?;
+
+            audit(
+                &postgres,
+                AuditEntry::new(actor, "seen.clear")
+                    .with_target(user_id.as_str())
+                    .with_details(serde_json::json!({ "deleted": deleted, "tool": "lume-admin" })),
+            )
+            .await;
+
+            print_json(&serde_json::json!({ "userId": user_id, "deleted": deleted }))
+        }
+        Command::Stats { user_id } => {
+            let postgres = connect_postgres(&settings, tenant).await?;
+
+            let stats = postgres.get_seen_stats(&user_id).await?;
+            let pending_likes = postgres.count_likes_received(&user_id).await?;
+
+            print_json(&serde_json::json!({
+                "stats": stats,
+                "likeRate": stats.like_rate(),
+                "pendingLikes": pending_likes,
+            }))
+        }
+        Command::RecomputeFeed { user_id, weights, limit, store } => {
+            let overrides: WeightsOverride = match weights {
+                Some(json) => serde_json::from_str(&json)?,
+                None => WeightsOverride::default(),
+            };
+            overrides.validate()?;
+
+            let base = ScoringWeights::from(&settings.scoring.weights);
+            let base = tenant.map_or(base, |t| t.weights.apply(base));
+            let weights = overrides.apply(base);
+
+            let postgres = connect_postgres(&settings, tenant).await?;
+            let cache: Arc<dyn Cache> = connect_cache(&settings, tenant_id, tenant).await?;
+            let appwrite = connect_appwrite(&settings, tenant);
+
+            let feed = Arc::new(
+                FeedGenerator::new(appwrite, cache.clone(), postgres, settings.matcher(weights), settings.matching.clone())
+                    .with_tiers(settings.tiers.clone())
+                    .with_rules(settings.rules.clone())
+                    .with_markets(settings.markets.clone()),
+            );
+
+            let feed_settings = FeedSettings {
+                feed_size: limit.unwrap_or(settings.feed.feed_size),
+                ..settings.feed.clone()
+            };
+
+            if store {
+                let stored = FeedWorker::new(feed, cache, feed_settings).process(&user_id).await?;
+                return print_json(&stored);
+            }
+
+            let options = FeedOptions { limit: feed_settings.feed_size, ..Default::default() };
+            let result = feed.generate(&user_id, &options).await?;
+
+            print_json(&serde_json::json!({
+                "userId": user_id,
+                "weightOverrides": overrides,
+                "matches": result.result.matches,
+                "totalCandidates": result.result.total_candidates,
+                "warnings": result.warnings,
+            }))
+        }
+        Command::Invalidate { pattern, max_keys, dry_run } => {
+            let cache = connect_cache(&settings, tenant_id, tenant).await?;
+
+            let report = cache.invalidate_pattern_with(&pattern, InvalidateOptions { max_keys, dry_run }).await?;
+
+            if !dry_run {
+                let postgres = connect_postgres(&settings, tenant).await?;
+                audit(
+                    &postgres,
+                    AuditEntry::new(actor, "cache.invalidate").with_details(serde_json::json!({
+                        "pattern": pattern,
+                        "deleted": report.deleted,
+                        "truncated": report.truncated,
+                        "tool": "lume-admin",
+                    })),
+                )
+                .await;
+            }
+
+            print_json(&serde_json::json!({ "pattern": pattern, "report": report }))
+        }
+        Command::FitCalibration { days, buckets, max_samples } => {
+            let postgres = connect_postgres(&settings, tenant).await?;
+
+            let since = chrono::Utc::now() - chrono::Duration::days(days);
+            let samples = postgres.score_outcomes(since, max_samples).await?;
+            let calibration = ScoreCalibration::fit(&samples, buckets).ok_or_else(|| {
+                format!("{} swipes since {} are too few (or have no likes) to fit {} buckets", samples.len(), since, buckets)
+            })?;
+
+            print_json(&serde_json::json!({ "samples": samples.len(), "calibration": calibration }))
+        }
+        Command::Seed { count, rng_seed, cities } => {
+            let mut seed = settings.seed.clone();
+            seed.count = count.unwrap_or(seed.count);
+            seed.rng_seed = rng_seed.or(seed.rng_seed);
+            if !cities.is_empty() {
+                seed.cities.retain(|city| cities.iter().any(|name| name.eq_ignore_ascii_case(&city.name)));
+                if seed.cities.is_empty() {
+                    return Err(format!("None of {:?} are configured in seed.cities", cities).into());
+                }
+            }
+
+            let appwrite = connect_appwrite(&settings, tenant);
+
+            print_json(&Seeder::new(appwrite, seed).seed().await?)
+        }
+        Command::PurgeSeed => {
+            let appwrite = connect_appwrite(&settings, tenant);
+
+            print_json(&Seeder::new(appwrite, settings.seed.clone()).purge().await?)
+        }
+        Command::Migrations => {
+            let postgres = connect_postgres(&settings, tenant).await?;
+
+            print_json(&postgres.migration_status().await?)
+        }
+        Command::MigrateDown { version } => {
+            let postgres = connect_postgres(&settings, tenant).await?;
+
+            postgres.revert_migration(version).await?;
+            audit(
+                &postgres,
+                AuditEntry::new(actor, "migration.revert")
+                    .with_target(version.to_string())
+                    .with_details(serde_json::json!({ "tool": "lume-admin" })),
+            )
+            .await;
+
+            print_json(&serde_json::json!({ "reverted": version }))
+        }
+        Command::SyncProfiles { full } => {
+            let postgres = connect_postgres(&settings, tenant).await?;
+            let appwrite = connect_appwrite(&settings, tenant);
+
+            let sync = ProfileSync::new(appwrite, postgres, settings.profile_sync.clone());
+            match sync.run(full, true).await? {
+                Some(report) => print_json(&report),
+                None => Err("Another instance is syncing profiles; try again later".into()),
+            }
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    dotenv::dotenv().ok();
+
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::new(
+            std::env::var("LOG_LEVEL").unwrap_or_else(|_| "warn".to_string()),
+        ))
+        .with_writer(std::io::stderr)
+        .init();
+
+    if let Err(e) = run(Cli::parse()).await {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
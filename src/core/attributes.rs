@@ -0,0 +1,197 @@
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// How an attribute scorer compares the wanted and the candidate's value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AttributeMode {
+    /// The candidate's value equals the wanted value, or one of them if the
+    /// wanted value is a list (strings compare case-insensitively)
+    Exact,
+    /// Share of the wanted values the candidate has; either side may be a
+    /// list or a single value
+    Overlap,
+    /// The candidate's number lies within the wanted `[min, max]`, or within
+    /// `tolerance` of the wanted number
+    Range,
+}
+
+/// One configured attribute: where to read it, how to compare it and what it's worth
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct AttributeScorer {
+    /// Key in the profile's and preferences' `attributes`
+    pub name: String,
+    pub mode: AttributeMode,
+    /// Weight in the match score, like the `scoring.weights` factors
+    pub weight: f64,
+    /// Allowed distance from a single wanted number (range mode only)
+    #[serde(default)]
+    pub tolerance: f64,
+}
+
+impl AttributeScorer {
+    /// Match (0-1) of the candidate's value; None when either side is
+    /// missing or the values can't be compared
+    pub fn score(&self, wanted: Option<&Value>, candidate: Option<&Value>) -> Option<f64> {
+        let (wanted, candidate) = (present(wanted)?, present(candidate)?);
+        match self.mode {
+            AttributeMode::Exact => {
+                let matched = as_list(wanted).iter().any(|value| values_equal(value, candidate));
+                Some(if matched { 1.0 } else { 0.0 })
+            }
+            AttributeMode::Overlap => {
+                let wanted = as_list(wanted);
+                let candidate = as_list(candidate);
+                if wanted.is_empty() || candidate.is_empty() {
+                    return None;
+                }
+                let shared = wanted
+                    .iter()
+                    .filter(|value| candidate.iter().any(|other| values_equal(value, other)))
+                    .count();
+                Some(shared as f64 / wanted.len() as f64)
+            }
+            AttributeMode::Range => {
+                let (min, max) = match wanted {
+                    Value::Array(bounds) => match bounds.as_slice() {
+                        [min, max] => (min.as_f64()?, max.as_f64()?),
+                        _ => return None,
+                    },
+                    Value::Object(bounds) => (
+                        bounds.get("min").and_then(Value::as_f64).unwrap_or(f64::NEG_INFINITY),
+                        bounds.get("max").and_then(Value::as_f64).unwrap_or(f64::INFINITY),
+                    ),
+                    value => {
+                        let value = value.as_f64()?;
+                        (value - self.tolerance, value + self.tolerance)
+                    }
+                };
+                let value = candidate.as_f64()?;
+                Some(if (min..=max).contains(&value) { 1.0 } else { 0.0 })
+            }
+        }
+    }
+}
+
+/// Registry of the configured attribute scorers
+///
+/// New attributes (smoking, drinking, education, ...) are matched by adding
+/// a scorer to the `[[attributes]]` config; the values are read from the
+/// generic `attributes` maps on profiles and preferences. A candidate who
+/// lacks an attribute gets a neutral half score for it.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(transparent)]
+pub struct AttributeScorers(Vec<AttributeScorer>);
+
+impl AttributeScorers {
+    pub fn new(scorers: Vec<AttributeScorer>) -> Self {
+        Self(scorers)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn scorers(&self) -> &[AttributeScorer] {
+        &self.0
+    }
+
+    /// Weighted points (0-100 scale) the candidate's attributes add to the score
+    pub fn points(&self, wanted: &HashMap<String, Value>, candidate: &HashMap<String, Value>) -> f64 {
+        self.0
+            .iter()
+            .filter(|scorer| scorer.weight > 0.0)
+            .map(|scorer| {
+                let score = scorer.score(wanted.get(&scorer.name), candidate.get(&scorer.name));
+                score.unwrap_or(0.5) * scorer.weight * 100.0
+            })
+            .sum()
+    }
+}
+
+/// The value, unless it is absent or null
+fn present(value: Option<&Value>) -> Option<&Value> {
+    value.filter(|value| !value.is_null())
+}
+
+fn as_list(value: &Value) -> &[Value] {
+    match value {
+        Value::Array(values) => values,
+        value => std::slice::from_ref(value),
+    }
+}
+
+fn values_equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::String(a), Value::String(b)) => a.eq_ignore_ascii_case(b),
+        (a, b) => match (a.as_f64(), b.as_f64()) {
+            (Some(a), Some(b)) => a == b,
+            _ => a == b,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn scorer(mode: AttributeMode) -> AttributeScorer {
+        AttributeScorer { name: "attr".to_string(), mode, weight: 0.1, tolerance: 0.0 }
+    }
+
+    #[test]
+    fn test_exact_mode() {
+        let exact = scorer(AttributeMode::Exact);
+        assert_eq!(exact.score(Some(&json!("never")), Some(&json!("Never"))), Some(1.0));
+        assert_eq!(exact.score(Some(&json!("never")), Some(&json!("often"))), Some(0.0));
+        assert_eq!(exact.score(Some(&json!(["never", "rarely"])), Some(&json!("rarely"))), Some(1.0));
+        assert_eq!(exact.score(Some(&json!(3)), Some(&json!(3.0))), Some(1.0));
+        assert_eq!(exact.score(Some(&json!("never")), None), None);
+        assert_eq!(exact.score(Some(&json!(null)), Some(&json!("never"))), None);
+    }
+
+    #[test]
+    fn test_overlap_mode() {
+        let overlap = scorer(AttributeMode::Overlap);
+        let wanted = json!(["dogs", "cats"]);
+        assert_eq!(overlap.score(Some(&wanted), Some(&json!(["cats", "fish"]))), Some(0.5));
+        assert_eq!(overlap.score(Some(&wanted), Some(&json!("dogs"))), Some(0.5));
+        assert_eq!(overlap.score(Some(&wanted), Some(&json!(["Dogs", "cats"]))), Some(1.0));
+        assert_eq!(overlap.score(Some(&wanted), Some(&json!([]))), None);
+    }
+
+    #[test]
+    fn test_range_mode() {
+        let range = AttributeScorer { tolerance: 1.0, ..scorer(AttributeMode::Range) };
+        assert_eq!(range.score(Some(&json!([2, 4])), Some(&json!(3))), Some(1.0));
+        assert_eq!(range.score(Some(&json!([2, 4])), Some(&json!(5))), Some(0.0));
+        assert_eq!(range.score(Some(&json!({"min": 2})), Some(&json!(50))), Some(1.0));
+        assert_eq!(range.score(Some(&json!(3)), Some(&json!(4))), Some(1.0));
+        assert_eq!(range.score(Some(&json!(3)), Some(&json!(5))), Some(0.0));
+        assert_eq!(range.score(Some(&json!(3)), Some(&json!("high"))), None);
+    }
+
+    #[test]
+    fn test_points() {
+        let scorers: AttributeScorers = serde_json::from_str(
+            r#"[
+                {"name": "smoking", "mode": "exact", "weight": 0.1},
+                {"name": "education", "mode": "range", "weight": 0.2},
+                {"name": "pets", "mode": "overlap", "weight": 0.0}
+            ]"#,
+        )
+        .unwrap();
+        assert!(!scorers.is_empty());
+        assert!(AttributeScorers::default().is_empty());
+
+        let wanted: HashMap<String, Value> =
+            [("smoking".to_string(), json!("never")), ("education".to_string(), json!([3, 5]))].into();
+        let candidate: HashMap<String, Value> =
+            [("smoking".to_string(), json!("never")), ("pets".to_string(), json!(["dogs"]))].into();
+
+        // Full smoking points, half the education points for the missing value
+        assert!((scorers.points(&wanted, &candidate) - 20.0).abs() < 1e-9);
+    }
+}
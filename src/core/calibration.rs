@@ -0,0 +1,210 @@
+use serde::{Deserialize, Serialize};
+
+/// Samples needed per bucket before a fit is trusted
+const MIN_SAMPLES_PER_BUCKET: usize = 20;
+
+/// Maps raw match scores onto the 0-100 scale users see as "% match"
+///
+/// Raw scores are weighted sums of factor points and cluster in the middle
+/// of the range, so a 60 reads as mediocre even when it is one of the best
+/// matches a user gets. Calibration is monotonic: it never changes the
+/// ranking, only the numbers shown.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum ScoreCalibration {
+    /// Raw scores are returned unchanged
+    #[default]
+    None,
+    /// Linear interpolation between `[raw, calibrated]` points, sorted by
+    /// raw score; scores outside the first and last point are clamped
+    Piecewise { points: Vec<(f64, f64)> },
+    /// Logistic curve around `midpoint`, stretched so 0 and 100 stay fixed
+    Logistic { midpoint: f64, steepness: f64 },
+}
+
+impl ScoreCalibration {
+    pub fn is_none(&self) -> bool {
+        matches!(self, ScoreCalibration::None)
+    }
+
+    /// The calibrated score (0-100) for a raw score
+    pub fn apply(&self, raw: f64) -> f64 {
+        match self {
+            ScoreCalibration::None => raw,
+            ScoreCalibration::Piecewise { points } => interpolate(points, raw).clamp(0.0, 100.0),
+            ScoreCalibration::Logistic { midpoint, steepness } => {
+                let curve = |x: f64| 1.0 / (1.0 + (-steepness * (x - midpoint)).exp());
+                let (low, high) = (curve(0.0), curve(100.0));
+                if high - low <= f64::EPSILON {
+                    return raw;
+                }
+                (100.0 * (curve(raw) - low) / (high - low)).clamp(0.0, 100.0)
+            }
+        }
+    }
+
+    /// Problems that would make the mapping non-monotonic or leave 0-100
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        match self {
+            ScoreCalibration::None => {}
+            ScoreCalibration::Piecewise { points } => {
+                if points.len() < 2 {
+                    problems.push("piecewise calibration needs at least 2 points".to_string());
+                }
+                if points.iter().any(|(x, y)| !(0.0..=100.0).contains(x) || !(0.0..=100.0).contains(y)) {
+                    problems.push("points must lie within 0-100".to_string());
+                }
+                if points.windows(2).any(|w| w[1].0 <= w[0].0 || w[1].1 < w[0].1) {
+                    problems.push("points must have increasing raw and non-decreasing calibrated scores".to_string());
+                }
+            }
+            ScoreCalibration::Logistic { midpoint, steepness } => {
+                if !(0.0..=100.0).contains(midpoint) {
+                    problems.push(format!("midpoint {} is outside 0-100", midpoint));
+                }
+                if !(*steepness > 0.0 && steepness.is_finite()) {
+                    problems.push(format!("steepness {} must be positive", steepness));
+                }
+            }
+        }
+        problems
+    }
+
+    /// Fit a piecewise mapping from `(raw score, liked)` outcomes
+    ///
+    /// Samples are split into `buckets` of equal size by raw score. Each
+    /// bucket's like rate is made non-decreasing (pool adjacent violators)
+    /// and scaled so the best bucket maps to 100: a calibrated 50 is liked
+    /// half as often as the best matches. None without enough samples or
+    /// likes to fit.
+    pub fn fit(samples: &[(f64, bool)], buckets: usize) -> Option<ScoreCalibration> {
+        let buckets = buckets.max(2);
+        if samples.len() < buckets * MIN_SAMPLES_PER_BUCKET {
+            return None;
+        }
+
+        let mut sorted: Vec<(f64, bool)> = samples.iter().copied().filter(|(score, _)| score.is_finite()).collect();
+        sorted.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        // (mean raw score, like rate, weight) per bucket
+        let size = sorted.len().div_ceil(buckets);
+        let mut blocks: Vec<(f64, f64, f64)> = Vec::with_capacity(buckets);
+        for chunk in sorted.chunks(size) {
+            let n = chunk.len() as f64;
+            let mean = chunk.iter().map(|(score, _)| score).sum::<f64>() / n;
+            let rate = chunk.iter().filter(|(_, liked)| *liked).count() as f64 / n;
+            blocks.push((mean, rate, n));
+
+            // Pool adjacent violators so the rate never falls as scores rise
+            while blocks.len() > 1 && blocks[blocks.len() - 2].1 > blocks[blocks.len() - 1].1 {
+                let (x2, r2, n2) = blocks.pop().unwrap();
+                let (x1, r1, n1) = blocks.pop().unwrap();
+                let n = n1 + n2;
+                blocks.push(((x1 * n1 + x2 * n2) / n, (r1 * n1 + r2 * n2) / n, n));
+            }
+        }
+
+        let best = blocks.last().map(|(_, rate, _)| *rate).filter(|rate| *rate > 0.0)?;
+        let mut points: Vec<(f64, f64)> = Vec::with_capacity(blocks.len());
+        for (mean, rate, _) in blocks {
+            let point = (round2(mean.clamp(0.0, 100.0)), round2(100.0 * rate / best));
+            if points.last().is_none_or(|last| point.0 > last.0) {
+                points.push(point);
+            }
+        }
+
+        (points.len() >= 2).then_some(ScoreCalibration::Piecewise { points })
+    }
+}
+
+fn interpolate(points: &[(f64, f64)], raw: f64) -> f64 {
+    let (Some(first), Some(last)) = (points.first(), points.last()) else {
+        return raw;
+    };
+    if raw <= first.0 {
+        return first.1;
+    }
+    if raw >= last.0 {
+        return last.1;
+    }
+
+    points
+        .windows(2)
+        .find(|w| raw <= w[1].0)
+        .map(|w| {
+            let ((x0, y0), (x1, y1)) = (w[0], w[1]);
+            y0 + (y1 - y0) * (raw - x0) / (x1 - x0)
+        })
+        .unwrap_or(last.1)
+}
+
+fn round2(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_piecewise() {
+        let calibration = ScoreCalibration::Piecewise { points: vec![(40.0, 10.0), (55.0, 50.0), (70.0, 95.0)] };
+        assert!(calibration.problems().is_empty());
+
+        assert_eq!(calibration.apply(20.0), 10.0);
+        assert_eq!(calibration.apply(47.5), 30.0);
+        assert_eq!(calibration.apply(55.0), 50.0);
+        assert_eq!(calibration.apply(90.0), 95.0);
+        assert_eq!(ScoreCalibration::None.apply(47.5), 47.5);
+
+        let unsorted = ScoreCalibration::Piecewise { points: vec![(60.0, 50.0), (40.0, 60.0)] };
+        assert_eq!(unsorted.problems().len(), 1);
+        assert_eq!(ScoreCalibration::Piecewise { points: vec![(50.0, 120.0)] }.problems().len(), 2);
+    }
+
+    #[test]
+    fn test_logistic_spreads_the_middle() {
+        let calibration = ScoreCalibration::Logistic { midpoint: 55.0, steepness: 0.15 };
+        assert!(calibration.problems().is_empty());
+
+        assert!(calibration.apply(0.0).abs() < 1e-9);
+        assert!((calibration.apply(100.0) - 100.0).abs() < 1e-9);
+        assert!((calibration.apply(55.0) - 50.0).abs() < 1.0);
+        // 40-70 covers most of the scale
+        assert!(calibration.apply(70.0) - calibration.apply(40.0) > 75.0);
+
+        let mut last = 0.0;
+        for raw in 0..=100 {
+            let score = calibration.apply(raw as f64);
+            assert!(score >= last);
+            last = score;
+        }
+
+        assert!(!ScoreCalibration::Logistic { midpoint: 55.0, steepness: 0.0 }.problems().is_empty());
+    }
+
+    #[test]
+    fn test_fit_from_outcomes() {
+        // Like rate rises with score, with one noisy dip around 50
+        let samples: Vec<(f64, bool)> = (0..1000)
+            .map(|i| {
+                let score = 40.0 + (i % 30) as f64;
+                let liked = match score as u32 {
+                    48..=51 => i % 10 == 0,
+                    s => i % 100 < (s - 35) as usize * 3,
+                };
+                (score, liked)
+            })
+            .collect();
+
+        let calibration = ScoreCalibration::fit(&samples, 5).expect("enough samples");
+        assert!(calibration.problems().is_empty(), "{:?}", calibration.problems());
+        assert_eq!(calibration.apply(100.0), 100.0);
+        assert!(calibration.apply(40.0) < calibration.apply(69.0));
+
+        assert_eq!(ScoreCalibration::fit(&samples[..50], 5), None);
+        let no_likes: Vec<(f64, bool)> = samples.iter().map(|(score, _)| (*score, false)).collect();
+        assert_eq!(ScoreCalibration::fit(&no_likes, 5), None);
+    }
+}
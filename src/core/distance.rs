@@ -1,8 +1,229 @@
 use crate::models::BoundingBox;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
 
 /// Earth's radius in kilometers
 const EARTH_RADIUS_KM: f64 = 6371.0;
 
+/// WGS-84 semi-major axis in meters
+const WGS84_A: f64 = 6_378_137.0;
+
+/// WGS-84 flattening
+const WGS84_F: f64 = 1.0 / 298.257_223_563;
+
+/// Which formula the scoring pipeline uses for candidate distances
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DistanceFormula {
+    /// Spherical great-circle distance (fast, ~0.5% error)
+    #[default]
+    Haversine,
+    /// Ellipsoidal WGS-84 distance via Vincenty (accurate to millimeters)
+    Geodesic,
+}
+
+impl DistanceFormula {
+    /// Distance between two points in kilometers using this formula
+    #[inline]
+    pub fn distance(self, lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+        match self {
+            DistanceFormula::Haversine => haversine_distance(lat1, lon1, lat2, lon2),
+            DistanceFormula::Geodesic => geodesic_distance(lat1, lon1, lat2, lon2),
+        }
+    }
+}
+
+/// Distance from a fixed origin to many points
+///
+/// The matcher measures every candidate against the same user location, so
+/// the origin's radians and cosine are computed once here instead of per call.
+#[derive(Debug, Clone, Copy)]
+pub struct DistanceCalculator {
+    formula: DistanceFormula,
+    lat: f64,
+    lon: f64,
+    lat_rad: f64,
+    cos_lat: f64,
+}
+
+impl DistanceCalculator {
+    pub fn new(formula: DistanceFormula, lat: f64, lon: f64) -> Self {
+        let lat_rad = lat.to_radians();
+
+        Self {
+            formula,
+            lat,
+            lon,
+            lat_rad,
+            cos_lat: lat_rad.cos(),
+        }
+    }
+
+    /// Distance from the origin to the given point in kilometers
+    #[inline]
+    pub fn distance_to(&self, lat: f64, lon: f64) -> f64 {
+        match self.formula {
+            DistanceFormula::Haversine => {
+                let lat_rad = lat.to_radians();
+                let delta_lat = lat_rad - self.lat_rad;
+                let delta_lon = (lon - self.lon).to_radians();
+
+                let a = (delta_lat / 2.0).sin().powi(2)
+                    + self.cos_lat * lat_rad.cos() * (delta_lon / 2.0).sin().powi(2);
+                let c = 2.0 * a.sqrt().atan2((1.0 - a).sqrt());
+
+                EARTH_RADIUS_KM * c
+            }
+            DistanceFormula::Geodesic => geodesic_distance(self.lat, self.lon, lat, lon),
+        }
+    }
+
+    /// Distances from the origin to each `(lat, lon)` point, in order
+    pub fn distances_to(&self, points: &[(f64, f64)]) -> Vec<f64> {
+        match self.formula {
+            DistanceFormula::Haversine => haversine_batch(self.lat_rad, self.cos_lat, self.lon, points),
+            DistanceFormula::Geodesic => points
+                .iter()
+                .map(|&(lat, lon)| geodesic_distance(self.lat, self.lon, lat, lon))
+                .collect(),
+        }
+    }
+}
+
+/// Points processed together by [`haversine_distance_batch`]
+const BATCH_LANES: usize = 4;
+
+/// Haversine distances in kilometers from `origin` to each `(lat, lon)`
+/// point, in order
+///
+/// Works through the points in fixed-width lanes, one formula step at a
+/// time across the lane, so the arithmetic is laid out for the compiler to
+/// vectorize. Results match [`haversine_distance`] to within rounding.
+pub fn haversine_distance_batch(origin: (f64, f64), points: &[(f64, f64)]) -> Vec<f64> {
+    let lat_rad = origin.0.to_radians();
+    haversine_batch(lat_rad, lat_rad.cos(), origin.1, points)
+}
+
+fn haversine_batch(lat_rad: f64, cos_lat: f64, lon: f64, points: &[(f64, f64)]) -> Vec<f64> {
+    let mut distances = Vec::with_capacity(points.len());
+
+    for chunk in points.chunks(BATCH_LANES) {
+        // A short last chunk is padded and its padding dropped again
+        let mut lanes = [(0.0, 0.0); BATCH_LANES];
+        lanes[..chunk.len()].copy_from_slice(chunk);
+
+        let lane_lat_rad = lanes.map(|(lat, _)| lat.to_radians());
+        let sin_delta_lat = lane_lat_rad.map(|lane_lat| ((lane_lat - lat_rad) / 2.0).sin());
+        let sin_delta_lon = lanes.map(|(_, lane_lon)| ((lane_lon - lon).to_radians() / 2.0).sin());
+        let lane_cos_lat = lane_lat_rad.map(f64::cos);
+
+        let mut a = [0.0; BATCH_LANES];
+        for (((a, sin_delta_lat), sin_delta_lon), lane_cos_lat) in
+            a.iter_mut().zip(sin_delta_lat).zip(sin_delta_lon).zip(lane_cos_lat)
+        {
+            *a = sin_delta_lat * sin_delta_lat + cos_lat * lane_cos_lat * sin_delta_lon * sin_delta_lon;
+        }
+
+        let lane_distances = a.map(|a| EARTH_RADIUS_KM * 2.0 * a.sqrt().atan2((1.0 - a).sqrt()));
+        distances.extend_from_slice(&lane_distances[..chunk.len()]);
+    }
+
+    distances
+}
+
+/// Coordinates are cached at this many steps per degree (~11 m at the equator)
+const CACHE_STEPS_PER_DEGREE: f64 = 10_000.0;
+
+/// A point snapped to the cache grid
+type GridPoint = (i32, i32);
+
+fn to_grid(lat: f64, lon: f64) -> GridPoint {
+    (
+        (lat * CACHE_STEPS_PER_DEGREE).round() as i32,
+        (lon * CACHE_STEPS_PER_DEGREE).round() as i32,
+    )
+}
+
+fn from_grid((lat, lon): GridPoint) -> (f64, f64) {
+    (lat as f64 / CACHE_STEPS_PER_DEGREE, lon as f64 / CACHE_STEPS_PER_DEGREE)
+}
+
+/// Memoized distances between point pairs, for workloads that measure the
+/// same pairs repeatedly (e.g. scoring both directions of every pair)
+///
+/// Points are snapped to a ~11 m grid and distances are computed between
+/// the snapped points, so a pair gives the same result whichever way round
+/// or however precisely it's asked for. When `capacity` pairs are cached
+/// the cache is cleared and starts over, which keeps the bound without
+/// per-entry bookkeeping on the hot path.
+#[derive(Debug)]
+pub struct DistanceCache {
+    formula: DistanceFormula,
+    capacity: usize,
+    entries: Mutex<HashMap<(GridPoint, GridPoint), f64>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl DistanceCache {
+    pub fn new(formula: DistanceFormula, capacity: usize) -> Self {
+        Self {
+            formula,
+            capacity: capacity.max(1),
+            entries: Mutex::new(HashMap::new()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Distance between two points in kilometers, from the cache when the
+    /// pair was measured before
+    pub fn distance(&self, lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+        let (a, b) = (to_grid(lat1, lon1), to_grid(lat2, lon2));
+        let key = if a <= b { (a, b) } else { (b, a) };
+
+        if let Some(&distance) = self.entries.lock().unwrap().get(&key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return distance;
+        }
+
+        // Computed outside the lock; a concurrent miss on the same pair
+        // computes the same value
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let ((lat1, lon1), (lat2, lon2)) = (from_grid(key.0), from_grid(key.1));
+        let distance = self.formula.distance(lat1, lon1, lat2, lon2);
+
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= self.capacity {
+            entries.clear();
+        }
+        entries.insert(key, distance);
+
+        distance
+    }
+
+    /// Cached pairs
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Lookups answered from the cache
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Lookups that computed the distance
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+}
+
 /// Calculate the Haversine distance between two points in kilometers
 ///
 /// # Arguments
@@ -27,6 +248,74 @@ pub fn haversine_distance(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
     EARTH_RADIUS_KM * c
 }
 
+/// Calculate the geodesic distance between two points on the WGS-84 ellipsoid
+///
+/// Uses Vincenty's inverse formula. For nearly antipodal points where the
+/// iteration doesn't converge, falls back to Haversine.
+///
+/// # Returns
+/// Distance in kilometers
+pub fn geodesic_distance(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let b = (1.0 - WGS84_F) * WGS84_A;
+    let l = (lon2 - lon1).to_radians();
+    let u1 = ((1.0 - WGS84_F) * lat1.to_radians().tan()).atan();
+    let u2 = ((1.0 - WGS84_F) * lat2.to_radians().tan()).atan();
+    let (sin_u1, cos_u1) = u1.sin_cos();
+    let (sin_u2, cos_u2) = u2.sin_cos();
+
+    let mut lambda = l;
+    for _ in 0..200 {
+        let (sin_lambda, cos_lambda) = lambda.sin_cos();
+        let sin_sigma = ((cos_u2 * sin_lambda).powi(2)
+            + (cos_u1 * sin_u2 - sin_u1 * cos_u2 * cos_lambda).powi(2))
+        .sqrt();
+
+        if sin_sigma == 0.0 {
+            return 0.0; // Coincident points
+        }
+
+        let cos_sigma = sin_u1 * sin_u2 + cos_u1 * cos_u2 * cos_lambda;
+        let sigma = sin_sigma.atan2(cos_sigma);
+        let sin_alpha = cos_u1 * cos_u2 * sin_lambda / sin_sigma;
+        let cos_sq_alpha = 1.0 - sin_alpha * sin_alpha;
+        // Equatorial line: cos_sq_alpha = 0
+        let cos_2sigma_m = if cos_sq_alpha != 0.0 {
+            cos_sigma - 2.0 * sin_u1 * sin_u2 / cos_sq_alpha
+        } else {
+            0.0
+        };
+        let c = WGS84_F / 16.0 * cos_sq_alpha * (4.0 + WGS84_F * (4.0 - 3.0 * cos_sq_alpha));
+
+        let lambda_prev = lambda;
+        lambda = l
+            + (1.0 - c)
+                * WGS84_F
+                * sin_alpha
+                * (sigma
+                    + c * sin_sigma
+                        * (cos_2sigma_m + c * cos_sigma * (-1.0 + 2.0 * cos_2sigma_m * cos_2sigma_m)));
+
+        if (lambda - lambda_prev).abs() < 1e-12 {
+            let u_sq = cos_sq_alpha * (WGS84_A * WGS84_A - b * b) / (b * b);
+            let big_a = 1.0 + u_sq / 16384.0 * (4096.0 + u_sq * (-768.0 + u_sq * (320.0 - 175.0 * u_sq)));
+            let big_b = u_sq / 1024.0 * (256.0 + u_sq * (-128.0 + u_sq * (74.0 - 47.0 * u_sq)));
+            let delta_sigma = big_b
+                * sin_sigma
+                * (cos_2sigma_m
+                    + big_b / 4.0
+                        * (cos_sigma * (-1.0 + 2.0 * cos_2sigma_m * cos_2sigma_m)
+                            - big_b / 6.0
+                                * cos_2sigma_m
+                                * (-3.0 + 4.0 * sin_sigma * sin_sigma)
+                                * (-3.0 + 4.0 * cos_2sigma_m * cos_2sigma_m)));
+
+            return b * big_a * (sigma - delta_sigma) / 1000.0;
+        }
+    }
+
+    haversine_distance(lat1, lon1, lat2, lon2)
+}
+
 /// Calculate a bounding box around a center point
 ///
 /// This is much faster than Haversine for pre-filtering.
@@ -83,6 +372,111 @@ mod tests {
         assert!((distance - 344.0).abs() < 10.0, "Distance should be ~344km, got {}", distance);
     }
 
+    #[test]
+    fn test_geodesic_vincenty_reference() {
+        // Flinders Peak to Buninyong, Vincenty's (1975) reference: 54972.271 m
+        let flinders = (-(37.0 + 57.0 / 60.0 + 3.72030 / 3600.0), 144.0 + 25.0 / 60.0 + 29.52440 / 3600.0);
+        let buninyong = (-(37.0 + 39.0 / 60.0 + 10.15610 / 3600.0), 143.0 + 55.0 / 60.0 + 35.38390 / 3600.0);
+
+        let distance = geodesic_distance(flinders.0, flinders.1, buninyong.0, buninyong.1);
+        assert!((distance - 54.972271).abs() < 0.001, "Expected 54.972271km, got {}", distance);
+    }
+
+    #[test]
+    fn test_geodesic_quarter_meridian() {
+        // Equator to pole along a meridian is 10001.966 km on WGS-84
+        let geodesic = geodesic_distance(0.0, 0.0, 90.0, 0.0);
+        assert!((geodesic - 10_001.966).abs() < 0.01, "Got {}", geodesic);
+
+        // Haversine is ~5.5 km off here
+        let haversine = haversine_distance(0.0, 0.0, 90.0, 0.0);
+        assert!((haversine - geodesic).abs() > 5.0);
+    }
+
+    #[test]
+    fn test_geodesic_city_pairs() {
+        // London to Paris: ~343.9 km
+        let london_paris = geodesic_distance(51.5074, -0.1278, 48.8566, 2.3522);
+        assert!((london_paris - 343.9).abs() < 1.0, "Got {}", london_paris);
+
+        // New York to Los Angeles: ~3944 km
+        let nyc_la = geodesic_distance(40.7128, -74.0060, 34.0522, -118.2437);
+        assert!((nyc_la - 3944.0).abs() < 10.0, "Got {}", nyc_la);
+
+        // Same point
+        assert_eq!(geodesic_distance(40.7128, -74.0060, 40.7128, -74.0060), 0.0);
+    }
+
+    #[test]
+    fn test_geodesic_antipodal_fallback() {
+        let distance = geodesic_distance(0.0, 0.0, 0.5, 179.7);
+        assert!(distance.is_finite() && distance > 19_000.0);
+    }
+
+    #[test]
+    fn test_calculator_matches_free_functions() {
+        let haversine = DistanceCalculator::new(DistanceFormula::Haversine, 40.7128, -74.0060);
+        let geodesic = DistanceCalculator::new(DistanceFormula::Geodesic, 40.7128, -74.0060);
+
+        for (lat, lon) in [(40.72, -74.01), (34.0522, -118.2437), (-33.8688, 151.2093), (40.7128, -74.0060)] {
+            let expected = haversine_distance(40.7128, -74.0060, lat, lon);
+            assert!((haversine.distance_to(lat, lon) - expected).abs() < 1e-9);
+
+            let expected = geodesic_distance(40.7128, -74.0060, lat, lon);
+            assert!((geodesic.distance_to(lat, lon) - expected).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_batch_matches_scalar() {
+        // Not a multiple of the lane width, so the padded tail is covered
+        let points: Vec<(f64, f64)> = (0..11)
+            .map(|i| (40.7128 + i as f64 * 0.37 - 2.0, -74.0060 + i as f64 * 1.3))
+            .chain([(-33.8688, 151.2093), (40.7128, -74.0060)])
+            .collect();
+
+        let batch = haversine_distance_batch((40.7128, -74.0060), &points);
+        assert_eq!(batch.len(), points.len());
+        for (&(lat, lon), distance) in points.iter().zip(&batch) {
+            assert!((distance - haversine_distance(40.7128, -74.0060, lat, lon)).abs() < 1e-9);
+        }
+
+        for formula in [DistanceFormula::Haversine, DistanceFormula::Geodesic] {
+            let calculator = DistanceCalculator::new(formula, 40.7128, -74.0060);
+            for (&(lat, lon), distance) in points.iter().zip(calculator.distances_to(&points)) {
+                assert!((distance - calculator.distance_to(lat, lon)).abs() < 1e-9);
+            }
+        }
+
+        assert!(haversine_distance_batch((0.0, 0.0), &[]).is_empty());
+    }
+
+    #[test]
+    fn test_distance_cache() {
+        let cache = DistanceCache::new(DistanceFormula::Haversine, 100);
+
+        let there = cache.distance(51.5074, -0.1278, 48.8566, 2.3522);
+        assert!((there - haversine_distance(51.5074, -0.1278, 48.8566, 2.3522)).abs() < 0.01);
+        assert_eq!((cache.hits(), cache.misses()), (0, 1));
+
+        // The reverse direction and points within the same grid cell hit
+        assert_eq!(cache.distance(48.8566, 2.3522, 51.5074, -0.1278), there);
+        assert_eq!(cache.distance(51.507401, -0.127801, 48.856599, 2.352201), there);
+        assert_eq!((cache.hits(), cache.misses()), (2, 1));
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn test_distance_cache_is_bounded() {
+        let cache = DistanceCache::new(DistanceFormula::Haversine, 10);
+
+        for i in 0..25 {
+            cache.distance(40.0, -74.0, 40.0 + i as f64 * 0.01, -74.0);
+            assert!(cache.len() <= 10);
+        }
+        assert_eq!(cache.misses(), 25);
+    }
+
     #[test]
     fn test_bounding_box() {
         let bbox = calculate_bounding_box(40.7128, -74.0060, 10.0);
@@ -0,0 +1,100 @@
+/// Lanes accumulated side by side so the loops compile to SIMD
+const LANES: usize = 8;
+
+/// Cosine similarity (-1 to 1) of two embeddings
+///
+/// Returns None when the lengths differ, either is empty or either has
+/// zero norm (or isn't finite), since no meaningful similarity exists.
+///
+/// The dot product and norms are summed over fixed-width lane arrays, which
+/// LLVM auto-vectorizes without any `unsafe` or target-specific code.
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> Option<f32> {
+    if a.len() != b.len() || a.is_empty() {
+        return None;
+    }
+
+    let mut dot = [0.0f32; LANES];
+    let mut norm_a = [0.0f32; LANES];
+    let mut norm_b = [0.0f32; LANES];
+
+    let chunks_a = a.chunks_exact(LANES);
+    let chunks_b = b.chunks_exact(LANES);
+    let (rest_a, rest_b) = (chunks_a.remainder(), chunks_b.remainder());
+
+    for (x, y) in chunks_a.zip(chunks_b) {
+        for lane in 0..LANES {
+            dot[lane] += x[lane] * y[lane];
+            norm_a[lane] += x[lane] * x[lane];
+            norm_b[lane] += y[lane] * y[lane];
+        }
+    }
+
+    let mut dot: f32 = dot.iter().sum();
+    let mut norm_a: f32 = norm_a.iter().sum();
+    let mut norm_b: f32 = norm_b.iter().sum();
+    for (x, y) in rest_a.iter().zip(rest_b) {
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+
+    let denominator = (norm_a * norm_b).sqrt();
+    if denominator == 0.0 || !denominator.is_finite() {
+        return None;
+    }
+
+    Some((dot / denominator).clamp(-1.0, 1.0))
+}
+
+/// Calculate embedding score (0-1)
+/// Only positive similarity counts; unrelated or opposite profiles score 0.
+/// Profiles without comparable embeddings get a neutral score.
+#[inline]
+pub fn embedding_score(a: Option<&[f32]>, b: Option<&[f32]>) -> f64 {
+    match a.zip(b).and_then(|(a, b)| cosine_similarity(a, b)) {
+        Some(similarity) => similarity.max(0.0) as f64,
+        None => 0.5,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reference implementation without lanes
+    fn naive_cosine(a: &[f32], b: &[f32]) -> f32 {
+        let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+        let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+        let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+        dot / (norm_a * norm_b)
+    }
+
+    #[test]
+    fn test_cosine_similarity() {
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[1.0, 0.0]), Some(1.0));
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]), Some(0.0));
+        assert_eq!(cosine_similarity(&[1.0, 2.0], &[-1.0, -2.0]), Some(-1.0));
+
+        // Lengths that exercise both the lanes and the remainder
+        let a: Vec<f32> = (0..37).map(|i| (i as f32 * 0.37).sin()).collect();
+        let b: Vec<f32> = (0..37).map(|i| (i as f32 * 0.11).cos()).collect();
+        let similarity = cosine_similarity(&a, &b).unwrap();
+        assert!((similarity - naive_cosine(&a, &b)).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_cosine_similarity_invalid() {
+        assert_eq!(cosine_similarity(&[], &[]), None);
+        assert_eq!(cosine_similarity(&[1.0, 2.0], &[1.0]), None);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 1.0]), None);
+    }
+
+    #[test]
+    fn test_embedding_score() {
+        let a = [1.0, 0.0];
+        assert_eq!(embedding_score(Some(&a), Some(&[1.0, 0.0])), 1.0);
+        assert_eq!(embedding_score(Some(&a), Some(&[-1.0, 0.0])), 0.0);
+        assert_eq!(embedding_score(Some(&a), None), 0.5);
+        assert_eq!(embedding_score(Some(&a), Some(&[1.0, 0.0, 0.0])), 0.5);
+    }
+}
@@ -1,4 +1,48 @@
 use crate::models::{UserProfile, UserPreferences, CandidateQuery};
+use std::borrow::Cow;
+
+/// Minimum age of any candidate the service will return
+pub const MIN_AGE: u8 = 18;
+
+/// Reject profiles under the minimum age, regardless of preferences or weights
+///
+/// An underage profile should never exist in the data, so every hit is
+/// written to the audit log.
+#[inline]
+pub fn is_of_age(profile: &UserProfile) -> bool {
+    if profile.age >= MIN_AGE {
+        return true;
+    }
+
+    tracing::warn!(
+        target: "audit",
+        event = "underage_profile",
+        user_id = %profile.user_id,
+        age = profile.age,
+        "Profile below minimum age excluded from matching"
+    );
+    false
+}
+
+/// Clamp preferences so the age range never starts below the minimum age
+pub fn clamp_min_age(preferences: &UserPreferences) -> Cow<'_, UserPreferences> {
+    if preferences.min_age >= MIN_AGE {
+        return Cow::Borrowed(preferences);
+    }
+
+    tracing::info!(
+        target: "audit",
+        event = "min_age_clamped",
+        user_id = %preferences.user_id,
+        requested_min_age = preferences.min_age,
+        "Preference min_age raised to {}", MIN_AGE
+    );
+
+    let mut clamped = preferences.clone();
+    clamped.min_age = MIN_AGE;
+    clamped.max_age = clamped.max_age.max(MIN_AGE);
+    Cow::Owned(clamped)
+}
 
 /// Check if a profile matches the user's demographic preferences
 ///
@@ -8,8 +52,23 @@ pub fn matches_demographics(
     profile: &UserProfile,
     preferences: &UserPreferences,
 ) -> bool {
-    // Skip if not active or is timed out
-    if !profile.is_active || profile.timeout() {
+    // Hard floor, checked before anything preference-driven
+    if !is_of_age(profile) {
+        return false;
+    }
+
+    // Skip if not active, timed out or snoozed
+    if !profile.is_active || profile.timeout() || profile.snoozed() {
+        return false;
+    }
+
+    // Skip blank profiles if the user asked for photos
+    if preferences.require_photos.unwrap_or(false) && profile.image_file_ids.is_empty() {
+        return false;
+    }
+
+    // Skip unverified profiles if the user only wants verified ones
+    if preferences.verified_only && !profile.verified() {
         return false;
     }
 
@@ -24,9 +83,15 @@ pub fn matches_demographics(
         return false;
     }
 
-    // Check height range
-    if profile.height_cm < preferences.min_height_cm
-        || profile.height_cm > preferences.max_height_cm {
+    // Check height range (profiles without a height pass)
+    if profile.height_cm.is_some_and(|height| height < preferences.min_height_cm || height > preferences.max_height_cm) {
+        return false;
+    }
+
+    // Check for a shared language, if required
+    if preferences.require_shared_language
+        && !preferences.preferred_languages.is_empty()
+        && !profile.speaks_any(&preferences.preferred_languages) {
         return false;
     }
 
@@ -95,19 +160,13 @@ pub fn matches_query_constraints(
         return false;
     }
 
-    // Check excluded users
-    if query.exclude_user_ids.contains(&profile.user_id) {
-        return false;
-    }
-
     // Check age range
     if profile.age < query.min_age || profile.age > query.max_age {
         return false;
     }
 
-    // Check height range
-    if profile.height_cm < query.min_height_cm
-        || profile.height_cm > query.max_height_cm {
+    // Check height range (profiles without a height pass)
+    if profile.height_cm.is_some_and(|height| height < query.min_height_cm || height > query.max_height_cm) {
         return false;
     }
 
@@ -124,24 +183,41 @@ pub fn matches_query_constraints(
 mod tests {
     use super::*;
     use chrono::Utc;
+    use crate::models::{Verification, SCHEMA_VERSION};
 
     fn create_test_profile(age: u8, gender: &str, height_cm: u16) -> UserProfile {
         UserProfile {
             user_id: "test_user".to_string(),
             name: "Test User".to_string(),
             age,
-            height_cm,
+            height_cm: Some(height_cm),
             hair_color: "brown".to_string(),
             gender: gender.to_string(),
             latitude: 40.7128,
             longitude: -74.0060,
             is_verified: Some(true),
+            verification: None,
             is_active: true,
             is_timeout: Some(false),
             image_file_ids: vec![],
             description: None,
             sports_preferences: vec!["tennis".to_string(), "swimming".to_string()],
+            interests: vec![],
             created_at: Some(Utc::now()),
+            timezone: None,
+            is_premium: None,
+            is_snoozed: None,
+            tenant_id: None,
+            market: None,
+            city: None,
+            tier: None,
+            embedding: None,
+            interested_in: vec![],
+            languages: vec![],
+            relationship_goal: None,
+            attributes: Default::default(),
+            updated_at: None,
+            schema_version: SCHEMA_VERSION,
         }
     }
 
@@ -155,9 +231,19 @@ mod tests {
             max_height_cm: 180,
             preferred_hair_colors: vec![],
             preferred_sports: vec!["tennis".to_string()],
+            preferred_interests: vec![],
             max_distance_km: 50,
             latitude: 40.7128,
             longitude: -74.0060,
+            require_photos: None,
+            embedding: None,
+            preferred_languages: vec![],
+            require_shared_language: false,
+            verified_only: false,
+            relationship_goal: None,
+            attributes: Default::default(),
+            updated_at: None,
+            schema_version: SCHEMA_VERSION,
         }
     }
 
@@ -185,6 +271,17 @@ mod tests {
         assert!(!matches_demographics(&profile, &preferences));
     }
 
+    #[test]
+    fn test_demographics_missing_height() {
+        let preferences = create_test_preferences();
+
+        assert!(!matches_demographics(&create_test_profile(25, "female", 190), &preferences));
+
+        let mut profile = create_test_profile(25, "female", 190);
+        profile.height_cm = None;
+        assert!(matches_demographics(&profile, &preferences));
+    }
+
     #[test]
     fn test_inactive_user_filtered() {
         let mut profile = create_test_profile(25, "female", 170);
@@ -194,6 +291,86 @@ mod tests {
         assert!(!matches_demographics(&profile, &preferences));
     }
 
+    #[test]
+    fn test_underage_never_matches() {
+        let mut preferences = create_test_preferences();
+        preferences.min_age = 16;
+        let profile = create_test_profile(17, "female", 170);
+
+        assert!(!matches_demographics(&profile, &preferences));
+        assert!(matches_demographics(&create_test_profile(18, "female", 170), &preferences));
+    }
+
+    #[test]
+    fn test_clamp_min_age() {
+        let mut preferences = create_test_preferences();
+        assert!(matches!(clamp_min_age(&preferences), Cow::Borrowed(_)));
+
+        preferences.min_age = 13;
+        preferences.max_age = 16;
+        let clamped = clamp_min_age(&preferences);
+        assert_eq!(clamped.min_age, MIN_AGE);
+        assert_eq!(clamped.max_age, MIN_AGE);
+    }
+
+    #[test]
+    fn test_snoozed_user_filtered() {
+        let preferences = create_test_preferences();
+        let mut profile = create_test_profile(25, "female", 170);
+        profile.is_snoozed = Some(true);
+
+        assert!(!matches_demographics(&profile, &preferences));
+    }
+
+    #[test]
+    fn test_require_photos() {
+        let mut preferences = create_test_preferences();
+        let mut profile = create_test_profile(25, "female", 170);
+        assert!(profile.image_file_ids.is_empty());
+        assert!(matches_demographics(&profile, &preferences));
+
+        preferences.require_photos = Some(true);
+        assert!(!matches_demographics(&profile, &preferences));
+
+        profile.image_file_ids.push("file_1".to_string());
+        assert!(matches_demographics(&profile, &preferences));
+    }
+
+    #[test]
+    fn test_verified_only() {
+        let mut preferences = create_test_preferences();
+        let mut profile = create_test_profile(25, "female", 170);
+        profile.is_verified = None;
+        preferences.verified_only = true;
+        assert!(!matches_demographics(&profile, &preferences));
+
+        profile.verification = Some(Verification::Id);
+        assert!(matches_demographics(&profile, &preferences));
+
+        profile.verification = None;
+        profile.is_verified = Some(true);
+        assert!(matches_demographics(&profile, &preferences));
+    }
+
+    #[test]
+    fn test_require_shared_language() {
+        let mut preferences = create_test_preferences();
+        let mut profile = create_test_profile(25, "female", 170);
+        preferences.preferred_languages = vec!["de".to_string(), "fr".to_string()];
+        assert!(matches_demographics(&profile, &preferences));
+
+        preferences.require_shared_language = true;
+        assert!(!matches_demographics(&profile, &preferences));
+
+        profile.languages = vec!["en".to_string(), "FR".to_string()];
+        assert!(matches_demographics(&profile, &preferences));
+
+        // Nothing to share when no languages are preferred
+        profile.languages.clear();
+        preferences.preferred_languages.clear();
+        assert!(matches_demographics(&profile, &preferences));
+    }
+
     #[test]
     fn test_preference_score() {
         let profile = create_test_profile(25, "female", 170);
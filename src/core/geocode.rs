@@ -0,0 +1,143 @@
+use crate::core::distance::haversine_distance;
+use crate::models::UserProfile;
+use std::sync::LazyLock;
+
+/// Embedded city centroids (`id,name,market,latitude,longitude`)
+const CITIES_CSV: &str = include_str!("../../data/cities.csv");
+
+static CITIES: LazyLock<Vec<City>> = LazyLock::new(|| parse_cities(CITIES_CSV));
+
+/// A city centroid from the embedded dataset
+#[derive(Debug, Clone, PartialEq)]
+pub struct City {
+    /// Stable identifier, e.g. "de-ber"
+    pub id: String,
+    pub name: String,
+    /// Market the city belongs to (lowercase ISO 3166-1 country code)
+    pub market: String,
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+/// Offline reverse geocoder over the embedded city dataset
+///
+/// Maps a location to the nearest city centroid within `radius_km`;
+/// locations farther from every known city get no city and no market.
+#[derive(Debug, Clone, Copy)]
+pub struct ReverseGeocoder {
+    cities: &'static [City],
+    radius_km: f64,
+}
+
+impl ReverseGeocoder {
+    pub fn new(radius_km: f64) -> Self {
+        Self {
+            cities: &CITIES,
+            radius_km,
+        }
+    }
+
+    /// Nearest known city within the radius
+    pub fn locate(&self, lat: f64, lon: f64) -> Option<&'static City> {
+        // A degree of latitude is ~111 km: skip cities that can't be in range
+        let max_lat_delta = self.radius_km / 111.0;
+        self.cities
+            .iter()
+            .filter(|city| (city.latitude - lat).abs() <= max_lat_delta)
+            .map(|city| (city, haversine_distance(lat, lon, city.latitude, city.longitude)))
+            .filter(|(_, distance)| *distance <= self.radius_km)
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(city, _)| city)
+    }
+
+    /// Known cities within `distance_km` of a location
+    pub fn cities_within(&self, lat: f64, lon: f64, distance_km: f64) -> impl Iterator<Item = &'static City> {
+        let max_lat_delta = distance_km / 111.0;
+        self.cities
+            .iter()
+            .filter(move |city| (city.latitude - lat).abs() <= max_lat_delta)
+            .filter(move |city| haversine_distance(lat, lon, city.latitude, city.longitude) <= distance_km)
+    }
+
+    /// The radius a location must be within to get a city
+    pub fn radius_km(&self) -> f64 {
+        self.radius_km
+    }
+
+    /// Fill in a profile's city, and its market if the profile has none
+    pub fn tag(&self, profile: &mut UserProfile) {
+        if let Some(city) = self.locate(profile.latitude, profile.longitude) {
+            profile.city = Some(city.id.clone());
+            profile.market.get_or_insert_with(|| city.market.clone());
+        }
+    }
+}
+
+fn parse_cities(csv: &str) -> Vec<City> {
+    csv.lines()
+        .skip(1)
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let fields: Vec<&str> = line.split(',').collect();
+            let [id, name, market, latitude, longitude] = fields[..] else {
+                panic!("invalid city row: {}", line);
+            };
+            City {
+                id: id.to_string(),
+                name: name.to_string(),
+                market: market.to_string(),
+                latitude: latitude.parse().expect("city latitude"),
+                longitude: longitude.parse().expect("city longitude"),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_dataset() {
+        assert!(CITIES.len() >= 100);
+        let ids: HashSet<&str> = CITIES.iter().map(|city| city.id.as_str()).collect();
+        assert_eq!(ids.len(), CITIES.len());
+        for city in CITIES.iter() {
+            assert!(city.id.starts_with(&format!("{}-", city.market)), "{}", city.id);
+            assert!((-90.0..=90.0).contains(&city.latitude) && (-180.0..=180.0).contains(&city.longitude));
+        }
+    }
+
+    #[test]
+    fn test_locate() {
+        let geocoder = ReverseGeocoder::new(50.0);
+        let city = geocoder.locate(52.49, 13.35).unwrap();
+        assert_eq!((city.id.as_str(), city.market.as_str()), ("de-ber", "de"));
+
+        // Nearest centroid wins: Rotterdam, not Amsterdam
+        assert_eq!(geocoder.locate(51.95, 4.50).unwrap().id, "nl-rtm");
+
+        // Far from every known city
+        assert!(geocoder.locate(0.0, 0.0).is_none());
+        assert!(ReverseGeocoder::new(5.0).locate(52.30, 13.40).is_none());
+    }
+
+    #[test]
+    fn test_tag_keeps_profile_market() {
+        let geocoder = ReverseGeocoder::new(50.0);
+        let mut profile: UserProfile = serde_json::from_value(serde_json::json!({
+            "userId": "a", "name": "a", "age": 30, "hairColor": "brown", "gender": "female",
+            "latitude": 48.86, "longitude": 2.35, "imageFileIds": [], "market": "eu"
+        }))
+        .unwrap();
+
+        geocoder.tag(&mut profile);
+        assert_eq!(profile.city.as_deref(), Some("fr-par"));
+        assert_eq!(profile.market.as_deref(), Some("eu"));
+
+        profile.market = None;
+        geocoder.tag(&mut profile);
+        assert_eq!(profile.market.as_deref(), Some("fr"));
+    }
+}
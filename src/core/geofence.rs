@@ -0,0 +1,135 @@
+use crate::models::BoundingBox;
+use serde::Deserialize;
+
+/// What happens inside a geofence
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GeofenceAction {
+    /// Candidates located inside are dropped in the geo stage
+    #[default]
+    Exclude,
+    /// As `exclude`, and users located or searching inside get no feed
+    Refuse,
+}
+
+/// A market the service must not match in, as a bounding box or polygon
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Geofence {
+    /// Shown in refusals and logs, e.g. a country code
+    pub name: String,
+    #[serde(default)]
+    pub action: GeofenceAction,
+    /// Box covering the market; `min_lon` above `max_lon` wraps around the
+    /// antimeridian
+    #[serde(default)]
+    pub bounding_box: Option<BoundingBox>,
+    /// Polygon vertices as `[lat, lon]` pairs, closed implicitly
+    #[serde(default)]
+    pub polygon: Vec<[f64; 2]>,
+}
+
+impl Geofence {
+    /// Whether the point lies inside the box or the polygon
+    pub fn contains(&self, lat: f64, lon: f64) -> bool {
+        let in_box = self.bounding_box.is_some_and(|bbox| {
+            let in_lon = if bbox.min_lon <= bbox.max_lon {
+                (bbox.min_lon..=bbox.max_lon).contains(&lon)
+            } else {
+                lon >= bbox.min_lon || lon <= bbox.max_lon
+            };
+            (bbox.min_lat..=bbox.max_lat).contains(&lat) && in_lon
+        });
+        in_box || (self.polygon.len() >= 3 && in_polygon(&self.polygon, lat, lon))
+    }
+}
+
+/// Deployment-wide geofences (none by default)
+///
+/// Legal can hard-exclude markets before launch: candidates inside any
+/// geofence are dropped in the geo stage, and `refuse` geofences also
+/// reject feed requests from users located or searching inside them.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(transparent)]
+pub struct Geofences(Vec<Geofence>);
+
+impl Geofences {
+    pub fn new(geofences: Vec<Geofence>) -> Self {
+        Self(geofences)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Check whether a candidate at the point may be matched
+    #[inline]
+    pub fn allows(&self, lat: f64, lon: f64) -> bool {
+        !self.0.iter().any(|fence| fence.contains(lat, lon))
+    }
+
+    /// The `refuse` geofence containing the point, if any
+    pub fn refusing(&self, lat: f64, lon: f64) -> Option<&Geofence> {
+        self.0
+            .iter()
+            .find(|fence| fence.action == GeofenceAction::Refuse && fence.contains(lat, lon))
+    }
+}
+
+/// Ray casting, with longitude as x and latitude as y
+fn in_polygon(vertices: &[[f64; 2]], lat: f64, lon: f64) -> bool {
+    let mut inside = false;
+    let mut previous = vertices[vertices.len() - 1];
+    for &vertex in vertices {
+        let ([lat_a, lon_a], [lat_b, lon_b]) = (vertex, previous);
+        if (lat_a > lat) != (lat_b > lat) && lon < (lon_b - lon_a) * (lat - lat_a) / (lat_b - lat_a) + lon_a {
+            inside = !inside;
+        }
+        previous = vertex;
+    }
+    inside
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn geofences() -> Geofences {
+        serde_json::from_str(
+            r#"[
+                {"name": "xa", "bounding_box": {"min_lat": 10, "max_lat": 20, "min_lon": 30, "max_lon": 40}},
+                {"name": "xb", "action": "refuse", "polygon": [[0, 0], [0, 10], [10, 0]]},
+                {"name": "xc", "bounding_box": {"min_lat": -20, "max_lat": -10, "min_lon": 170, "max_lon": -170}}
+            ]"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_bounding_box() {
+        let fences = geofences();
+        assert!(!fences.allows(15.0, 35.0));
+        assert!(fences.allows(15.0, 41.0));
+        assert!(fences.allows(21.0, 35.0));
+
+        // Wraps around the antimeridian
+        assert!(!fences.allows(-15.0, 175.0));
+        assert!(!fences.allows(-15.0, -175.0));
+        assert!(fences.allows(-15.0, 0.0));
+    }
+
+    #[test]
+    fn test_polygon() {
+        let fences = geofences();
+        assert!(!fences.allows(2.0, 2.0));
+        assert!(fences.allows(6.0, 6.0));
+        assert!(fences.allows(-1.0, 2.0));
+    }
+
+    #[test]
+    fn test_refusing() {
+        let fences = geofences();
+        assert_eq!(fences.refusing(2.0, 2.0).map(|fence| fence.name.as_str()), Some("xb"));
+        assert!(fences.refusing(15.0, 35.0).is_none());
+        assert!(Geofences::default().allows(2.0, 2.0));
+    }
+}
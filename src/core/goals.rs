@@ -0,0 +1,104 @@
+use crate::models::RelationshipGoal;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Relationship-goal compatibility between a searcher and a candidate
+///
+/// Scores range from 0 (incompatible) to 1 (the same goal). The default
+/// matrix can be overridden per pair; pairs are looked up searcher goal
+/// first, so asymmetric scores are possible. Profiles without a goal are
+/// never filtered and score a neutral 0.5.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct GoalCompatibility {
+    /// Drop candidates whose goal is incompatible (scores 0) instead of
+    /// just ranking them lower
+    #[serde(default)]
+    pub hard_filter: bool,
+    /// Overrides of the default matrix, by searcher goal, then candidate goal
+    #[serde(default)]
+    pub matrix: HashMap<RelationshipGoal, HashMap<RelationshipGoal, f64>>,
+}
+
+impl GoalCompatibility {
+    /// Compatibility (0-1) of the two goals; None when either is unknown
+    pub fn score(&self, searcher: Option<RelationshipGoal>, candidate: Option<RelationshipGoal>) -> Option<f64> {
+        let (searcher, candidate) = (searcher?, candidate?);
+        let score = self
+            .matrix
+            .get(&searcher)
+            .and_then(|row| row.get(&candidate))
+            .copied()
+            .unwrap_or_else(|| default_compatibility(searcher, candidate));
+        Some(score.clamp(0.0, 1.0))
+    }
+
+    /// Check whether the hard filter lets the candidate through
+    #[inline]
+    pub fn allows(&self, searcher: Option<RelationshipGoal>, candidate: Option<RelationshipGoal>) -> bool {
+        !self.hard_filter || self.score(searcher, candidate).is_none_or(|score| score > 0.0)
+    }
+}
+
+/// Built-in compatibility matrix (symmetric)
+fn default_compatibility(a: RelationshipGoal, b: RelationshipGoal) -> f64 {
+    use RelationshipGoal::*;
+
+    match (a, b) {
+        _ if a == b => 1.0,
+        (Casual, LongTerm) | (LongTerm, Casual) => 0.0,
+        (Casual, Friends) | (Friends, Casual) => 0.3,
+        (LongTerm, Friends) | (Friends, LongTerm) => 0.2,
+        (Friends, Unsure) | (Unsure, Friends) => 0.5,
+        (Unsure, _) | (_, Unsure) => 0.6,
+        _ => 0.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use RelationshipGoal::*;
+
+    #[test]
+    fn test_default_matrix() {
+        let goals = GoalCompatibility::default();
+        assert_eq!(goals.score(Some(LongTerm), Some(LongTerm)), Some(1.0));
+        assert_eq!(goals.score(Some(Casual), Some(LongTerm)), Some(0.0));
+        assert_eq!(goals.score(Some(Unsure), Some(Casual)), Some(0.6));
+        assert_eq!(goals.score(Some(Friends), Some(Unsure)), Some(0.5));
+        assert_eq!(goals.score(None, Some(Casual)), None);
+
+        // Every pair is symmetric and an exact match scores highest
+        let all = [Casual, LongTerm, Friends, Unsure];
+        for a in all {
+            for b in all {
+                assert_eq!(goals.score(Some(a), Some(b)), goals.score(Some(b), Some(a)));
+                assert!(goals.score(Some(a), Some(b)) <= goals.score(Some(a), Some(a)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_hard_filter() {
+        let mut goals = GoalCompatibility::default();
+        assert!(goals.allows(Some(Casual), Some(LongTerm)));
+
+        goals.hard_filter = true;
+        assert!(!goals.allows(Some(Casual), Some(LongTerm)));
+        assert!(goals.allows(Some(Casual), Some(Friends)));
+        assert!(goals.allows(Some(Casual), None));
+    }
+
+    #[test]
+    fn test_matrix_overrides() {
+        let goals: GoalCompatibility = serde_json::from_str(
+            r#"{"hard_filter": true, "matrix": {"friends": {"casual": 0.0, "long_term": 0.8}}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(goals.score(Some(Friends), Some(LongTerm)), Some(0.8));
+        assert_eq!(goals.score(Some(LongTerm), Some(Friends)), Some(0.2));
+        assert!(!goals.allows(Some(Friends), Some(Casual)));
+        assert!(goals.allows(Some(Casual), Some(Friends)));
+    }
+}
@@ -1,15 +1,27 @@
-use crate::models::{UserProfile, UserPreferences, ScoredMatch, ScoringWeights, CandidateQuery};
+use crate::models::{UserProfile, UserPreferences, ScoredMatch, ScoringWeights, CandidateQuery, BoundingBox, MatchDiagnostics, Tier};
 use crate::core::{
-    distance::{calculate_bounding_box, haversine_distance},
-    filters::{matches_demographics, matches_query_constraints},
-    scoring::calculate_match_score,
+    attributes::AttributeScorers,
+    calibration::ScoreCalibration,
+    distance::{calculate_bounding_box, is_within_bounding_box, DistanceCalculator, DistanceFormula},
+    filters::{clamp_min_age, matches_demographics, matches_query_constraints},
+    geofence::Geofences,
+    goals::GoalCompatibility,
+    scoring::calculate_score_breakdown,
 };
+use crate::util::rng;
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use std::collections::{HashMap, HashSet};
 
 /// Result of the matching process
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct MatchResult {
     pub matches: Vec<ScoredMatch>,
     pub total_candidates: usize,
+    /// The user had no location, so the geo filter and distance weight were skipped
+    pub global_mode: bool,
+    /// Candidates eliminated at each pipeline stage
+    pub diagnostics: MatchDiagnostics,
 }
 
 /// Main matching orchestrator - implements the multi-stage filtering pipeline
@@ -19,20 +31,117 @@ pub struct MatchResult {
 /// 2. Demographic filtering
 /// 3. Preference matching
 /// 4. Scoring and ranking
+/// 5. Freshness mixing and exploration
 #[derive(Debug, Clone)]
 pub struct Matcher {
     weights: ScoringWeights,
+    distance_formula: DistanceFormula,
+    min_score: f64,
+    max_results: Option<usize>,
+    dedupe: bool,
+    exploration: f64,
+    tier_boosts: TierBoosts,
+    freshness: f64,
+    new_profile_days: u32,
+    goals: GoalCompatibility,
+    attributes: AttributeScorers,
+    shuffle_band_width: f64,
+    geofences: Geofences,
+    calibration: ScoreCalibration,
+    rng_seed: Option<u64>,
 }
 
+/// Profiles scoring below this are dropped unless configured otherwise
+pub const DEFAULT_MIN_SCORE: f64 = 5.0;
+
 impl Matcher {
     pub fn new(weights: ScoringWeights) -> Self {
-        Self { weights }
+        Self::builder().weights(weights).build()
     }
 
     pub fn with_default_weights() -> Self {
-        Self {
-            weights: ScoringWeights::default(),
-        }
+        Self::new(ScoringWeights::default())
+    }
+
+    /// Start building a matcher; unset options keep the service defaults
+    ///
+    /// ```
+    /// use lume_algo::core::Matcher;
+    ///
+    /// let matcher = Matcher::builder().min_score(20.0).max_results(50).dedupe(true).build();
+    /// assert_eq!(matcher.min_score(), 20.0);
+    /// ```
+    pub fn builder() -> MatcherBuilder {
+        MatcherBuilder::default()
+    }
+
+    /// Replace the scoring weights (e.g. for a request-scoped override)
+    pub fn with_weights(mut self, weights: ScoringWeights) -> Self {
+        self.weights = weights;
+        self
+    }
+
+    pub fn weights(&self) -> ScoringWeights {
+        self.weights
+    }
+
+    pub fn min_score(&self) -> f64 {
+        self.min_score
+    }
+
+    pub fn distance_formula(&self) -> DistanceFormula {
+        self.distance_formula
+    }
+
+    pub fn max_results(&self) -> Option<usize> {
+        self.max_results
+    }
+
+    pub fn dedupe(&self) -> bool {
+        self.dedupe
+    }
+
+    pub fn exploration(&self) -> f64 {
+        self.exploration
+    }
+
+    /// Points added to the score of candidates on the given tier
+    pub fn tier_boost(&self, tier: Tier) -> f64 {
+        self.tier_boosts.get(tier)
+    }
+
+    pub fn freshness(&self) -> f64 {
+        self.freshness
+    }
+
+    pub fn new_profile_days(&self) -> u32 {
+        self.new_profile_days
+    }
+
+    pub fn relationship_goals(&self) -> &GoalCompatibility {
+        &self.goals
+    }
+
+    pub fn attribute_scorers(&self) -> &AttributeScorers {
+        &self.attributes
+    }
+
+    pub fn shuffle_band_width(&self) -> f64 {
+        self.shuffle_band_width
+    }
+
+    pub fn geofences(&self) -> &Geofences {
+        &self.geofences
+    }
+
+    pub fn calibration(&self) -> &ScoreCalibration {
+        &self.calibration
+    }
+
+    /// Use the given formula for candidate distances (Haversine by default)
+    pub fn with_distance_formula(mut self, distance_formula: DistanceFormula) -> Self {
+        self.distance_formula = distance_formula;
+        self
     }
 
     /// Find matches for a user based on their preferences
@@ -51,15 +160,91 @@ impl Matcher {
         preferences: &UserPreferences,
         candidates: Vec<UserProfile>,
         limit: usize,
+    ) -> MatchResult {
+        self.find_matches_excluding(preferences, candidates, limit, &HashSet::new())
+    }
+
+    /// Find matches while skipping the given user IDs (e.g. already seen profiles)
+    ///
+    /// Exclusions are checked with a set lookup, so large seen lists stay cheap.
+    ///
+    /// If the preferences carry no location, matching runs in global mode:
+    /// the bounding box is skipped and the distance weight is redistributed
+    /// over the other factors.
+    pub fn find_matches_excluding(
+        &self,
+        preferences: &UserPreferences,
+        candidates: Vec<UserProfile>,
+        limit: usize,
+        exclude_user_ids: &HashSet<String>,
+    ) -> MatchResult {
+        let options = MatchOptions {
+            limit: Some(limit),
+            ..Default::default()
+        };
+
+        self.rank(preferences, candidates, &options, exclude_user_ids)
+    }
+
+    /// Find matches with per-call options layered over the matcher's own
+    ///
+    /// The result size is the smaller of `options.limit` and the matcher's
+    /// `max_results`; with neither set, every match is returned.
+    pub fn find_matches_with(
+        &self,
+        preferences: &UserPreferences,
+        candidates: Vec<UserProfile>,
+        options: &MatchOptions,
+    ) -> MatchResult {
+        self.rank(preferences, candidates, options, &options.exclude_user_ids)
+    }
+
+    /// [`find_matches_with`](Self::find_matches_with), skipping
+    /// `exclude_user_ids` instead of `options.exclude_user_ids`
+    ///
+    /// The set is only borrowed, so large seen lists aren't copied per call.
+    fn rank(
+        &self,
+        preferences: &UserPreferences,
+        mut candidates: Vec<UserProfile>,
+        options: &MatchOptions,
+        exclude_user_ids: &HashSet<String>,
     ) -> MatchResult {
         let total_candidates = candidates.len();
+        let preferences = clamp_min_age(preferences);
+        let preferences = preferences.as_ref();
+
+        let limit = match (options.limit, self.max_results) {
+            (Some(limit), Some(max)) => limit.min(max),
+            (limit, max) => limit.or(max).unwrap_or(usize::MAX),
+        };
+        let min_score = options.min_score.unwrap_or(self.min_score);
+
+        let mut diagnostics = MatchDiagnostics::default();
+
+        if self.dedupe {
+            let mut seen = HashSet::with_capacity(candidates.len());
+            candidates.retain(|profile| seen.insert(profile.user_id.clone()));
+            diagnostics.duplicates = total_candidates - candidates.len();
+        }
+
+        let global_mode = !preferences.has_location();
+        let weights = if global_mode {
+            self.weights.without_distance()
+        } else {
+            self.weights
+        };
 
         // Build candidate query
-        let bounding_box = calculate_bounding_box(
-            preferences.latitude,
-            preferences.longitude,
-            preferences.max_distance_km as f64,
-        );
+        let bounding_box = if global_mode {
+            BoundingBox::world()
+        } else {
+            calculate_bounding_box(
+                preferences.latitude,
+                preferences.longitude,
+                preferences.max_distance_km as f64,
+            )
+        };
 
         let query = CandidateQuery {
             bounding_box,
@@ -68,35 +253,94 @@ impl Matcher {
             max_age: preferences.max_age,
             min_height_cm: preferences.min_height_cm,
             max_height_cm: preferences.max_height_cm,
-            exclude_user_ids: vec![preferences.user_id.clone()], // Exclude self
             limit,
         };
 
+        let calculator = DistanceCalculator::new(
+            self.distance_formula,
+            preferences.latitude,
+            preferences.longitude,
+        );
+
+        // With exploration on, candidates below the minimum score are kept as
+        // an exploration pool: they passed every hard filter
+        let exploring = self.exploration > 0.0;
+
+        // Join dates of recently joined candidates, for freshness mixing
+        let joined_after = Utc::now() - chrono::Duration::days(self.new_profile_days as i64);
+        let mut joined: HashMap<String, DateTime<Utc>> = HashMap::new();
+
         // Multi-stage filtering pipeline
-        let mut scored_matches: Vec<ScoredMatch> = candidates
+        let candidates = candidates
             .into_iter()
+            .filter(|profile| {
+                // Exclude self
+                let excluded = profile.user_id == preferences.user_id || exclude_user_ids.contains(&profile.user_id);
+                diagnostics.excluded += excluded as usize;
+                !excluded
+            })
+            .filter(|profile| {
+                let inside = is_within_bounding_box(profile.latitude, profile.longitude, &query.bounding_box)
+                    && self.geofences.allows(profile.latitude, profile.longitude);
+                diagnostics.geo += !inside as usize;
+                inside
+            })
             // Stage 1: Geospatial + basic query pre-filter
-            .filter(|profile| matches_query_constraints(profile, &query))
             // Stage 2: Demographic filtering
-            .filter(|profile| matches_demographics(profile, preferences))
+            .filter(|profile| {
+                let matches = matches_query_constraints(profile, &query)
+                    && matches_demographics(profile, preferences)
+                    && self.goals.allows(preferences.relationship_goal, profile.relationship_goal);
+                diagnostics.demographics += !matches as usize;
+                matches
+            })
+            .collect::<Vec<UserProfile>>();
+
+        // Computed once per candidate, in one batch, and shared with scoring.
+        // Distance is meaningless without a user location.
+        let distances = if global_mode {
+            vec![0.0; candidates.len()]
+        } else {
+            let points: Vec<(f64, f64)> = candidates.iter().map(|p| (p.latitude, p.longitude)).collect();
+            calculator.distances_to(&points)
+        };
+
+        let scored: Vec<ScoredMatch> = candidates
+            .into_iter()
+            .zip(distances)
             // Stage 3 & 4: Calculate scores
-            .filter_map(|profile| {
-                let (score, shared_sports) = calculate_match_score(
+            .filter_map(|(profile, distance_km)| {
+                let (mut breakdown, shared_sports) = calculate_score_breakdown(
                     &profile,
                     preferences,
-                    &self.weights,
+                    &weights,
+                    distance_km,
                 );
+                if weights.relationship_goal > 0.0 {
+                    let compatibility = self.goals.score(preferences.relationship_goal, profile.relationship_goal);
+                    breakdown.relationship_goal =
+                        compatibility.unwrap_or(0.5) * weights.for_profile(&profile, preferences).relationship_goal * 100.0;
+                }
+                if !self.attributes.is_empty() {
+                    breakdown.attributes = self.attributes.points(&preferences.attributes, &profile.attributes);
+                }
+                breakdown.tier_boost = self.tier_boosts.get(profile.tier());
+                let raw_score = breakdown.total();
+                let score = self.calibration.apply(raw_score);
+                if score >= min_score {
+                    diagnostics.ranked += 1;
+                } else {
+                    diagnostics.below_min_score += 1;
+                }
 
                 // Only include profiles with a minimum score
-                if score >= 5.0 {
-                    let distance_km = haversine_distance(
-                        preferences.latitude,
-                        preferences.longitude,
-                        profile.latitude,
-                        profile.longitude,
-                    );
-
+                if score >= min_score || exploring {
                     let is_verified = profile.verified();
+                    let verification = profile.verification();
+
+                    if let Some(created_at) = profile.created_at.filter(|at| self.freshness > 0.0 && *at >= joined_after) {
+                        joined.insert(profile.user_id.clone(), created_at);
+                    }
 
                     Some(ScoredMatch {
                         user_id: profile.user_id,
@@ -107,10 +351,17 @@ impl Matcher {
                         gender: profile.gender,
                         distance_km,
                         match_score: score,
+                        raw_score,
                         shared_sports,
                         is_verified,
+                        verification,
                         image_file_ids: profile.image_file_ids,
                         description: profile.description,
+                        market: profile.market,
+                        city: profile.city,
+                        exploratory: false,
+                        fresh: false,
+                        score_breakdown: options.score_breakdown.then_some(breakdown),
                     })
                 } else {
                     None
@@ -118,6 +369,9 @@ impl Matcher {
             })
             .collect();
 
+        let (mut scored_matches, mut pool): (Vec<ScoredMatch>, Vec<ScoredMatch>) =
+            scored.into_iter().partition(|m| m.match_score >= min_score);
+
         // Sort by score (descending) and then by distance (ascending)
         scored_matches.sort_by(|a, b| {
             b.match_score
@@ -130,12 +384,29 @@ impl Matcher {
                 })
         });
 
-        // Limit results
-        scored_matches.truncate(limit);
+        if self.shuffle_band_width > 0.0 {
+            let seed = rng::daily_seed(&preferences.user_id, Utc::now().date_naive());
+            shuffle_bands(&mut scored_matches, self.shuffle_band_width, &mut rng::seeded(seed));
+        }
+
+        if self.freshness > 0.0 {
+            scored_matches = mix_fresh(scored_matches, &joined, self.freshness);
+        }
+
+        // Limit results; whatever is cut off joins the exploration pool
+        if exploring {
+            pool.extend(scored_matches.drain(limit.min(scored_matches.len())..));
+            let seed = self.rng_seed.map(|seed| rng::derive_seed([seed.to_le_bytes().as_slice(), preferences.user_id.as_bytes()]));
+            explore(&mut scored_matches, pool, self.exploration, &mut rng::seeded_or_entropy(seed));
+        } else {
+            scored_matches.truncate(limit);
+        }
 
         MatchResult {
             matches: scored_matches,
             total_candidates,
+            global_mode,
+            diagnostics,
         }
     }
 }
@@ -146,9 +417,276 @@ impl Default for Matcher {
     }
 }
 
+/// Freshness mixing: a `fraction` of the slots, spread evenly, go to the
+/// newest recently joined profiles (by `joined`); the others keep score order
+///
+/// Mixed-in profiles are tagged `fresh`. Once the recently joined profiles
+/// run out, the remaining slots keep score order too.
+fn mix_fresh(ranked: Vec<ScoredMatch>, joined: &HashMap<String, DateTime<Utc>>, fraction: f64) -> Vec<ScoredMatch> {
+    let mut newest: Vec<usize> = (0..ranked.len()).filter(|&i| joined.contains_key(&ranked[i].user_id)).collect();
+    newest.sort_by_key(|&i| std::cmp::Reverse(joined[&ranked[i].user_id]));
+    let mut newest = newest.into_iter();
+
+    let mut slots: Vec<Option<ScoredMatch>> = ranked.into_iter().map(Some).collect();
+    let mut mixed = Vec::with_capacity(slots.len());
+    let mut next_ranked = 0;
+
+    for slot in 0..slots.len() {
+        let fresh_slot = ((slot + 1) as f64 * fraction).floor() > (slot as f64 * fraction).floor();
+        if fresh_slot {
+            if let Some(pick) = newest.by_ref().find_map(|i| slots[i].take()) {
+                mixed.push(ScoredMatch { fresh: true, ..pick });
+                continue;
+            }
+        }
+
+        while let Some(slot) = slots.get_mut(next_ranked) {
+            next_ranked += 1;
+            if let Some(pick) = slot.take() {
+                mixed.push(pick);
+                break;
+            }
+        }
+    }
+
+    mixed
+}
+
+/// Score-band shuffling: splits score-sorted matches into bands `width`
+/// points wide and shuffles each band, weighted by score
+///
+/// Higher scores tend to stay near the top of their band, and no match
+/// moves past one from a better band.
+fn shuffle_bands(ranked: &mut [ScoredMatch], width: f64, rng: &mut impl Rng) {
+    for band in ranked.chunk_by_mut(|a, b| (a.match_score / width).floor() == (b.match_score / width).floor()) {
+        // Weighted random order (Efraimidis-Spirakis): sort by u^(1/weight)
+        let mut keyed: Vec<(f64, ScoredMatch)> = band
+            .iter()
+            .map(|m| (rng.gen::<f64>().powf(1.0 / m.match_score.max(f64::EPSILON)), m.clone()))
+            .collect();
+        keyed.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+        for (slot, (_, m)) in band.iter_mut().zip(keyed) {
+            *slot = m;
+        }
+    }
+}
+
+/// Epsilon-greedy exploration: each ranked slot is, with probability
+/// `epsilon`, given to a random candidate from the pool instead
+///
+/// Explored matches keep their own score and are tagged `exploratory`, so
+/// feedback on them can be told apart from feedback on ranked matches.
+fn explore(ranked: &mut [ScoredMatch], mut pool: Vec<ScoredMatch>, epsilon: f64, rng: &mut impl Rng) {
+    for slot in ranked.iter_mut() {
+        if pool.is_empty() {
+            break;
+        }
+
+        if rng.gen_bool(epsilon) {
+            let pick = pool.swap_remove(rng.gen_range(0..pool.len()));
+            *slot = ScoredMatch { exploratory: true, ..pick };
+        }
+    }
+}
+
+/// Builder for [`Matcher`], for embedding the algorithm as a library
+#[derive(Debug, Clone)]
+pub struct MatcherBuilder {
+    weights: ScoringWeights,
+    distance_formula: DistanceFormula,
+    min_score: f64,
+    max_results: Option<usize>,
+    dedupe: bool,
+    exploration: f64,
+    tier_boosts: TierBoosts,
+    freshness: f64,
+    new_profile_days: u32,
+    goals: GoalCompatibility,
+    attributes: AttributeScorers,
+    shuffle_band_width: f64,
+    geofences: Geofences,
+    calibration: ScoreCalibration,
+    rng_seed: Option<u64>,
+}
+
+impl Default for MatcherBuilder {
+    fn default() -> Self {
+        Self {
+            weights: ScoringWeights::default(),
+            distance_formula: DistanceFormula::default(),
+            min_score: DEFAULT_MIN_SCORE,
+            max_results: None,
+            dedupe: false,
+            exploration: 0.0,
+            tier_boosts: TierBoosts::default(),
+            freshness: 0.0,
+            new_profile_days: 7,
+            goals: GoalCompatibility::default(),
+            attributes: AttributeScorers::default(),
+            shuffle_band_width: 0.0,
+            geofences: Geofences::default(),
+            calibration: ScoreCalibration::None,
+            rng_seed: None,
+        }
+    }
+}
+
+impl MatcherBuilder {
+    pub fn weights(mut self, weights: ScoringWeights) -> Self {
+        self.weights = weights;
+        self
+    }
+
+    pub fn distance_formula(mut self, distance_formula: DistanceFormula) -> Self {
+        self.distance_formula = distance_formula;
+        self
+    }
+
+    /// Drop matches scoring below this (0-100)
+    pub fn min_score(mut self, min_score: f64) -> Self {
+        self.min_score = min_score;
+        self
+    }
+
+    /// Never return more than this many matches, whatever the call asks for
+    pub fn max_results(mut self, max_results: usize) -> Self {
+        self.max_results = Some(max_results);
+        self
+    }
+
+    /// Score each user ID only once when candidate lists contain duplicates
+    pub fn dedupe(mut self, dedupe: bool) -> Self {
+        self.dedupe = dedupe;
+        self
+    }
+
+    /// Chance (0-1) that each result slot goes to a random candidate that
+    /// passed the hard filters instead of the ranked match (0 = off)
+    pub fn exploration(mut self, epsilon: f64) -> Self {
+        self.exploration = epsilon.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Add `points` (0-100 scale) to the score of candidates on `tier`
+    pub fn tier_boost(mut self, tier: Tier, points: f64) -> Self {
+        self.tier_boosts.set(tier, points);
+        self
+    }
+
+    /// Interleave a `fraction` (0-1) of the results with the newest profiles
+    /// created within `new_profile_days` (0 = off)
+    pub fn freshness(mut self, fraction: f64, new_profile_days: u32) -> Self {
+        self.freshness = fraction.clamp(0.0, 1.0);
+        self.new_profile_days = new_profile_days;
+        self
+    }
+
+    /// Score (and optionally hard-filter) candidates by relationship goal
+    pub fn relationship_goals(mut self, goals: GoalCompatibility) -> Self {
+        self.goals = goals;
+        self
+    }
+
+    /// Score the configured optional attributes
+    pub fn attribute_scorers(mut self, attributes: AttributeScorers) -> Self {
+        self.attributes = attributes;
+        self
+    }
+
+    /// Shuffle matches within score bands this many points wide, in an
+    /// order that is stable per user and day (0 = off)
+    pub fn shuffle_bands(mut self, width: f64) -> Self {
+        self.shuffle_band_width = width.max(0.0);
+        self
+    }
+
+    /// Seed exploration per user, so the same candidates explore the same
+    /// way (None = fresh randomness on every call)
+    pub fn rng_seed(mut self, seed: Option<u64>) -> Self {
+        self.rng_seed = seed;
+        self
+    }
+
+    /// Drop candidates located inside any of these geofences
+    pub fn geofences(mut self, geofences: Geofences) -> Self {
+        self.geofences = geofences;
+        self
+    }
+
+    /// Map raw scores onto the calibrated 0-100 scale before filtering by
+    /// minimum score and returning them
+    pub fn calibration(mut self, calibration: ScoreCalibration) -> Self {
+        self.calibration = calibration;
+        self
+    }
+
+    pub fn build(self) -> Matcher {
+        Matcher {
+            weights: self.weights,
+            distance_formula: self.distance_formula,
+            min_score: self.min_score,
+            max_results: self.max_results,
+            dedupe: self.dedupe,
+            exploration: self.exploration,
+            tier_boosts: self.tier_boosts,
+            freshness: self.freshness,
+            new_profile_days: self.new_profile_days,
+            goals: self.goals,
+            attributes: self.attributes,
+            shuffle_band_width: self.shuffle_band_width,
+            geofences: self.geofences,
+            calibration: self.calibration,
+            rng_seed: self.rng_seed,
+        }
+    }
+}
+
+/// Ranking boost points per candidate tier (all zero by default)
+#[derive(Debug, Clone, Copy, Default)]
+struct TierBoosts {
+    free: f64,
+    plus: f64,
+    premium: f64,
+}
+
+impl TierBoosts {
+    fn get(&self, tier: Tier) -> f64 {
+        match tier {
+            Tier::Free => self.free,
+            Tier::Plus => self.plus,
+            Tier::Premium => self.premium,
+        }
+    }
+
+    fn set(&mut self, tier: Tier, points: f64) {
+        let slot = match tier {
+            Tier::Free => &mut self.free,
+            Tier::Plus => &mut self.plus,
+            Tier::Premium => &mut self.premium,
+        };
+        *slot = points;
+    }
+}
+
+/// Per-call options for [`Matcher::find_matches_with`]
+#[derive(Debug, Clone, Default)]
+pub struct MatchOptions {
+    /// Maximum matches to return (further capped by the matcher's `max_results`)
+    pub limit: Option<usize>,
+    /// Overrides the matcher's minimum score for this call
+    pub min_score: Option<f64>,
+    /// User IDs to skip (e.g. already seen profiles)
+    pub exclude_user_ids: HashSet<String>,
+    /// Attach each match's per-factor score breakdown
+    pub score_breakdown: bool,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::attributes::{AttributeMode, AttributeScorer};
+    use crate::core::geofence::{Geofence, GeofenceAction};
+    use crate::models::{RelationshipGoal, SCHEMA_VERSION};
     use chrono::Utc;
 
     fn create_candidate(
@@ -163,18 +701,34 @@ mod tests {
             user_id: id.to_string(),
             name: format!("User {}", id),
             age,
-            height_cm: 170,
+            height_cm: Some(170),
             hair_color: "brown".to_string(),
             gender: gender.to_string(),
             latitude: lat,
             longitude: lon,
             is_verified: Some(is_verified),
+            verification: None,
             is_active: true,
             is_timeout: Some(false),
             image_file_ids: vec![],
             description: None,
             sports_preferences: vec!["tennis".to_string()],
+            interests: vec![],
             created_at: Some(Utc::now()),
+            timezone: None,
+            is_premium: None,
+            is_snoozed: None,
+            tenant_id: None,
+            market: None,
+            city: None,
+            tier: None,
+            embedding: None,
+            interested_in: vec![],
+            languages: vec![],
+            relationship_goal: None,
+            attributes: Default::default(),
+            updated_at: None,
+            schema_version: SCHEMA_VERSION,
         }
     }
 
@@ -188,9 +742,19 @@ mod tests {
             max_height_cm: 180,
             preferred_hair_colors: vec![],
             preferred_sports: vec!["tennis".to_string()],
+            preferred_interests: vec![],
             max_distance_km: 50,
             latitude: 40.7128,  // New York
             longitude: -74.0060,
+            require_photos: None,
+            embedding: None,
+            preferred_languages: vec![],
+            require_shared_language: false,
+            verified_only: false,
+            relationship_goal: None,
+            attributes: Default::default(),
+            updated_at: None,
+            schema_version: SCHEMA_VERSION,
         }
     }
 
@@ -229,6 +793,32 @@ mod tests {
         assert!(result.matches[0].match_score >= result.matches[1].match_score);
     }
 
+    #[test]
+    fn test_calibrated_scores_keep_order() {
+        let preferences = create_preferences();
+        let candidates = || {
+            (0..10)
+                .map(|i| create_candidate(&i.to_string(), 22 + i as u8, "female", 40.72, -74.01, i % 2 == 0))
+                .collect::<Vec<_>>()
+        };
+
+        let calibration = ScoreCalibration::Logistic { midpoint: 55.0, steepness: 0.15 };
+        let raw = Matcher::builder().min_score(0.0).build().find_matches(&preferences, candidates(), 10);
+        let calibrated = Matcher::builder()
+            .min_score(0.0)
+            .calibration(calibration.clone())
+            .build()
+            .find_matches(&preferences, candidates(), 10);
+
+        let ids = |result: &MatchResult| result.matches.iter().map(|m| m.user_id.clone()).collect::<Vec<_>>();
+        assert_eq!(ids(&raw), ids(&calibrated));
+        for (raw, calibrated) in raw.matches.iter().zip(&calibrated.matches) {
+            assert_eq!(raw.match_score, raw.raw_score);
+            assert_eq!(calibrated.raw_score, raw.match_score);
+            assert_eq!(calibrated.match_score, calibration.apply(raw.match_score));
+        }
+    }
+
     #[test]
     fn test_respects_limit() {
         let matcher = Matcher::with_default_weights();
@@ -252,6 +842,353 @@ mod tests {
         assert!(result.matches.len() <= 5);
     }
 
+    #[test]
+    fn test_find_matches_excluding() {
+        let matcher = Matcher::with_default_weights();
+        let preferences = create_preferences();
+
+        let candidates = vec![
+            create_candidate("1", 25, "female", 40.72, -74.01, true),
+            create_candidate("2", 26, "female", 40.72, -74.01, true),
+            create_candidate("current_user", 26, "female", 40.72, -74.01, true),
+        ];
+        let exclude: HashSet<String> = ["1".to_string()].into_iter().collect();
+
+        let result = matcher.find_matches_excluding(&preferences, candidates, 10, &exclude);
+
+        assert_eq!(result.matches.len(), 1);
+        assert_eq!(result.matches[0].user_id, "2");
+    }
+
+    #[test]
+    fn test_stage_diagnostics() {
+        let matcher = Matcher::builder().dedupe(true).build();
+        let preferences = create_preferences();
+
+        let candidates = vec![
+            create_candidate("1", 25, "female", 40.72, -74.01, true),   // Ranked
+            create_candidate("1", 25, "female", 40.72, -74.01, true),   // Duplicate
+            create_candidate("2", 25, "female", 40.72, -74.01, true),   // Excluded
+            create_candidate("3", 25, "female", 51.51, -0.13, true),    // London
+            create_candidate("4", 40, "female", 40.72, -74.01, true),   // Too old
+            create_candidate("5", 25, "male", 40.72, -74.01, true),     // Wrong gender
+        ];
+        let options = MatchOptions {
+            exclude_user_ids: ["2".to_string()].into_iter().collect(),
+            ..Default::default()
+        };
+
+        let result = matcher.find_matches_with(&preferences, candidates.clone(), &options);
+        assert_eq!(
+            result.diagnostics,
+            MatchDiagnostics { duplicates: 1, excluded: 1, geo: 1, demographics: 2, below_min_score: 0, ranked: 1 }
+        );
+
+        let options = MatchOptions { min_score: Some(101.0), ..options };
+        let result = matcher.find_matches_with(&preferences, candidates, &options);
+        assert!(result.matches.is_empty());
+        assert_eq!(result.diagnostics.below_min_score, 1);
+        assert_eq!(result.diagnostics.ranked, 0);
+    }
+
+    #[test]
+    fn test_global_mode_without_location() {
+        let matcher = Matcher::with_default_weights();
+        let mut preferences = create_preferences();
+        preferences.latitude = 0.0;
+        preferences.longitude = 0.0;
+
+        let candidates = vec![
+            create_candidate("1", 25, "female", 40.72, -74.01, true),  // New York
+            create_candidate("2", 25, "female", 35.68, 139.69, true),  // Tokyo
+        ];
+
+        let result = matcher.find_matches(&preferences, candidates, 10);
+
+        assert!(result.global_mode);
+        assert_eq!(result.matches.len(), 2);
+        assert_eq!(result.matches[0].match_score, result.matches[1].match_score);
+        assert!(result.matches.iter().all(|m| m.distance_km == 0.0));
+    }
+
+    #[test]
+    fn test_weights_without_distance() {
+        let weights = ScoringWeights::default().without_distance();
+        let total = weights.age + weights.sports + weights.verified + weights.height + weights.activity;
+
+        assert_eq!(weights.distance, 0.0);
+        assert!((total - 1.0).abs() < 1e-9);
+        assert!((weights.age / weights.sports - 0.20 / 0.25).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_underage_candidates_excluded() {
+        let matcher = Matcher::with_default_weights();
+        let mut preferences = create_preferences();
+        preferences.min_age = 14;
+
+        let candidates = vec![
+            create_candidate("1", 17, "female", 40.72, -74.01, true),
+            create_candidate("2", 19, "female", 40.72, -74.01, true),
+        ];
+
+        let result = matcher.find_matches(&preferences, candidates, 10);
+
+        assert_eq!(result.matches.len(), 1);
+        assert_eq!(result.matches[0].user_id, "2");
+    }
+
+    #[test]
+    fn test_geodesic_formula_matches() {
+        let matcher = Matcher::with_default_weights().with_distance_formula(DistanceFormula::Geodesic);
+        let preferences = create_preferences();
+
+        let candidates = vec![create_candidate("1", 25, "female", 40.72, -74.01, true)];
+
+        let result = matcher.find_matches(&preferences, candidates, 10);
+
+        assert_eq!(result.matches.len(), 1);
+        assert!(result.matches[0].distance_km > 0.5 && result.matches[0].distance_km < 2.0);
+    }
+
+    #[test]
+    fn test_builder_options() {
+        let preferences = create_preferences();
+        let candidates = || {
+            vec![
+                create_candidate("1", 25, "female", 40.72, -74.01, true),
+                create_candidate("1", 25, "female", 40.72, -74.01, true),
+                create_candidate("2", 28, "female", 40.72, -74.01, false),
+            ]
+        };
+
+        let matcher = Matcher::builder().build();
+        assert_eq!(matcher.min_score(), DEFAULT_MIN_SCORE);
+        assert_eq!(matcher.find_matches(&preferences, candidates(), 10).matches.len(), 3);
+
+        let matcher = Matcher::builder().dedupe(true).build();
+        assert_eq!(matcher.find_matches(&preferences, candidates(), 10).matches.len(), 2);
+
+        let matcher = Matcher::builder().dedupe(true).max_results(1).build();
+        let result = matcher.find_matches_with(&preferences, candidates(), &MatchOptions::default());
+        assert_eq!(result.matches.len(), 1);
+        assert_eq!(result.matches[0].user_id, "1");
+
+        let matcher = Matcher::builder().min_score(101.0).build();
+        assert!(matcher.find_matches(&preferences, candidates(), 10).matches.is_empty());
+
+        let options = MatchOptions { min_score: Some(0.0), ..Default::default() };
+        assert_eq!(matcher.find_matches_with(&preferences, candidates(), &options).matches.len(), 3);
+    }
+
+    #[test]
+    fn test_exploration() {
+        let preferences = create_preferences();
+        let candidates: Vec<UserProfile> = (0..10)
+            .map(|i| create_candidate(&i.to_string(), 25, "female", 40.72, -74.01, i % 2 == 0))
+            .collect();
+
+        let matcher = Matcher::builder().build();
+        let result = matcher.find_matches(&preferences, candidates.clone(), 3);
+        assert!(result.matches.iter().all(|m| !m.exploratory));
+
+        // Every slot is explored, drawing from the seven matches cut off by the limit
+        let matcher = Matcher::builder().exploration(1.0).build();
+        let ranked: HashSet<String> = result.matches.iter().map(|m| m.user_id.clone()).collect();
+        let explored = matcher.find_matches(&preferences, candidates.clone(), 3);
+
+        assert_eq!(explored.matches.len(), 3);
+        assert!(explored.matches.iter().all(|m| m.exploratory && !ranked.contains(&m.user_id)));
+
+        // A fixed seed explores the same way every time
+        let matcher = Matcher::builder().exploration(0.5).rng_seed(Some(7)).build();
+        let ids = |result: MatchResult| result.matches.into_iter().map(|m| m.user_id).collect::<Vec<_>>();
+        let first = ids(matcher.find_matches(&preferences, candidates.clone(), 3));
+        assert!((0..5).all(|_| ids(matcher.find_matches(&preferences, candidates.clone(), 3)) == first));
+    }
+
+    #[test]
+    fn test_tier_boost_and_breakdown() {
+        let preferences = create_preferences();
+        let mut premium = create_candidate("premium", 28, "female", 40.72, -74.01, false);
+        premium.tier = Some(Tier::Premium);
+        let candidates = vec![create_candidate("free", 28, "female", 40.72, -74.01, true), premium];
+
+        let matcher = Matcher::builder().build();
+        let result = matcher.find_matches(&preferences, candidates.clone(), 10);
+        assert_eq!(result.matches[0].user_id, "free");
+        assert!(result.matches.iter().all(|m| m.score_breakdown.is_none()));
+
+        // A boost larger than the verified bonus lifts the premium candidate
+        let matcher = Matcher::builder().tier_boost(Tier::Premium, 15.0).build();
+        let options = MatchOptions { score_breakdown: true, ..Default::default() };
+        let result = matcher.find_matches_with(&preferences, candidates, &options);
+        assert_eq!(result.matches[0].user_id, "premium");
+
+        let breakdown = result.matches[0].score_breakdown.expect("breakdown requested");
+        assert_eq!(breakdown.tier_boost, 15.0);
+        assert_eq!(breakdown.total(), result.matches[0].match_score);
+        assert_eq!(result.matches[1].score_breakdown.unwrap().tier_boost, 0.0);
+    }
+
+    #[test]
+    fn test_relationship_goals() {
+        let mut preferences = create_preferences();
+        preferences.relationship_goal = Some(RelationshipGoal::LongTerm);
+        let mut casual = create_candidate("casual", 28, "female", 40.72, -74.01, true);
+        casual.relationship_goal = Some(RelationshipGoal::Casual);
+        let mut long_term = create_candidate("long_term", 28, "female", 40.72, -74.01, false);
+        long_term.relationship_goal = Some(RelationshipGoal::LongTerm);
+        let unknown = create_candidate("unknown", 28, "female", 40.72, -74.01, false);
+        let candidates = vec![casual, long_term, unknown];
+
+        // Off by default: the verified casual candidate ranks first
+        let matcher = Matcher::builder().build();
+        let result = matcher.find_matches(&preferences, candidates.clone(), 10);
+        assert_eq!(result.matches[0].user_id, "casual");
+
+        let weights = ScoringWeights { relationship_goal: 0.3, ..Default::default() };
+        let matcher = Matcher::builder().weights(weights).build();
+        let options = MatchOptions { score_breakdown: true, ..Default::default() };
+        let result = matcher.find_matches_with(&preferences, candidates.clone(), &options);
+        let ids: Vec<&str> = result.matches.iter().map(|m| m.user_id.as_str()).collect();
+        assert_eq!(ids, vec!["long_term", "unknown", "casual"]);
+        let breakdown = result.matches[0].score_breakdown.unwrap();
+        assert_eq!(breakdown.relationship_goal, 30.0);
+        assert_eq!(breakdown.total(), result.matches[0].match_score);
+        assert_eq!(result.matches[1].score_breakdown.unwrap().relationship_goal, 15.0);
+
+        // The hard filter drops incompatible goals but keeps unknown ones
+        let goals = GoalCompatibility { hard_filter: true, ..Default::default() };
+        let matcher = Matcher::builder().relationship_goals(goals).build();
+        let result = matcher.find_matches(&preferences, candidates, 10);
+        assert_eq!(result.matches.len(), 2);
+        assert!(result.matches.iter().all(|m| m.user_id != "casual"));
+        assert_eq!(result.diagnostics.demographics, 1);
+    }
+
+    #[test]
+    fn test_attribute_scorers() {
+        let mut preferences = create_preferences();
+        preferences.attributes.insert("smoking".to_string(), serde_json::json!("never"));
+        let mut smoker = create_candidate("smoker", 28, "female", 40.72, -74.01, true);
+        smoker.attributes.insert("smoking".to_string(), serde_json::json!("often"));
+        let mut non_smoker = create_candidate("non_smoker", 28, "female", 40.72, -74.01, false);
+        non_smoker.attributes.insert("smoking".to_string(), serde_json::json!("never"));
+        let candidates = vec![smoker, non_smoker];
+
+        let matcher = Matcher::builder().build();
+        let result = matcher.find_matches(&preferences, candidates.clone(), 10);
+        assert_eq!(result.matches[0].user_id, "smoker");
+
+        let scorers = AttributeScorers::new(vec![AttributeScorer {
+            name: "smoking".to_string(),
+            mode: AttributeMode::Exact,
+            weight: 0.2,
+            tolerance: 0.0,
+        }]);
+        let matcher = Matcher::builder().attribute_scorers(scorers).build();
+        let options = MatchOptions { score_breakdown: true, ..Default::default() };
+        let result = matcher.find_matches_with(&preferences, candidates, &options);
+        assert_eq!(result.matches[0].user_id, "non_smoker");
+
+        let breakdown = result.matches[0].score_breakdown.unwrap();
+        assert_eq!(breakdown.attributes, 20.0);
+        assert_eq!(breakdown.total(), result.matches[0].match_score);
+        assert_eq!(result.matches[1].score_breakdown.unwrap().attributes, 0.0);
+    }
+
+    #[test]
+    fn test_score_band_shuffle() {
+        let preferences = create_preferences();
+        let candidates: Vec<UserProfile> =
+            (0..9).map(|i| create_candidate(&i.to_string(), 25, "female", 40.72, -74.01, true)).collect();
+        let mut ranked = Matcher::builder().build().find_matches(&preferences, candidates, 9).matches;
+        ranked.sort_by_key(|m| m.user_id.clone());
+        for (m, score) in ranked.iter_mut().zip([95.0, 94.0, 93.0, 92.0, 91.0, 85.0, 84.0, 83.0, 70.0]) {
+            m.match_score = score;
+        }
+        let ids = |matches: &[ScoredMatch]| matches.iter().map(|m| m.user_id.clone()).collect::<Vec<_>>();
+
+        // Bands keep their order; members stay within their band
+        let mut orders = HashSet::new();
+        for seed in 0..20 {
+            let mut shuffled = ranked.clone();
+            shuffle_bands(&mut shuffled, 10.0, &mut rng::seeded(seed));
+            let mut top: Vec<String> = ids(&shuffled[..5]);
+            top.sort();
+            assert_eq!(top, vec!["0", "1", "2", "3", "4"]);
+            assert!(shuffled[5..8].iter().all(|m| (80.0..90.0).contains(&m.match_score)));
+            assert_eq!(shuffled[8].user_id, "8");
+            orders.insert(ids(&shuffled));
+        }
+        assert!(orders.len() > 1);
+
+        // Matching with the same user and day gives the same order
+        let candidates: Vec<UserProfile> =
+            (0..12).map(|i| create_candidate(&i.to_string(), 25, "female", 40.72, -74.01, i % 2 == 0)).collect();
+        let matcher = Matcher::builder().shuffle_bands(20.0).build();
+        let first = matcher.find_matches(&preferences, candidates.clone(), 12);
+        let second = matcher.find_matches(&preferences, candidates, 12);
+        assert_eq!(ids(&first.matches), ids(&second.matches));
+    }
+
+    #[test]
+    fn test_geofences_in_geo_stage() {
+        let preferences = create_preferences();
+        let candidates = vec![
+            create_candidate("inside", 25, "female", 40.72, -74.01, true),
+            create_candidate("outside", 25, "female", 40.80, -73.95, true),
+        ];
+        let geofences = Geofences::new(vec![Geofence {
+            name: "test".to_string(),
+            action: GeofenceAction::Exclude,
+            bounding_box: None,
+            polygon: vec![[40.70, -74.02], [40.74, -74.02], [40.74, -74.00], [40.70, -74.00]],
+        }]);
+
+        let matcher = Matcher::builder().geofences(geofences).build();
+        let result = matcher.find_matches(&preferences, candidates, 10);
+        assert_eq!(result.matches.len(), 1);
+        assert_eq!(result.matches[0].user_id, "outside");
+        assert_eq!(result.diagnostics.geo, 1);
+    }
+
+    #[test]
+    fn test_freshness_mixing() {
+        let preferences = create_preferences();
+        let candidates: Vec<UserProfile> = (0..8)
+            .map(|i| {
+                // 0-5 are established and verified; 6 and 7 joined recently and rank last
+                let mut candidate = create_candidate(&i.to_string(), 25, "female", 40.72, -74.01, i < 6);
+                candidate.created_at = Some(Utc::now() - chrono::Duration::days(if i < 6 { 90 } else { 8 - i }));
+                candidate
+            })
+            .collect();
+
+        let matcher = Matcher::builder().build();
+        let ranked = matcher.find_matches(&preferences, candidates.clone(), 8);
+        assert!(ranked.matches[..6].iter().all(|m| m.user_id.parse::<usize>().unwrap() < 6));
+
+        // Every fourth slot goes to the newest recently joined profile left
+        let matcher = Matcher::builder().freshness(0.25, 7).build();
+        let mixed = matcher.find_matches(&preferences, candidates.clone(), 8);
+        let fresh: Vec<(usize, &str)> = mixed
+            .matches
+            .iter()
+            .enumerate()
+            .filter(|(_, m)| m.fresh)
+            .map(|(slot, m)| (slot, m.user_id.as_str()))
+            .collect();
+        assert_eq!(fresh, vec![(3, "7"), (7, "6")]);
+        assert_eq!(mixed.matches.len(), 8);
+
+        // Profiles older than the window are never mixed in
+        let matcher = Matcher::builder().freshness(0.25, 1).build();
+        let mixed = matcher.find_matches(&preferences, candidates, 8);
+        assert!(mixed.matches.iter().all(|m| !m.fresh));
+    }
+
     #[test]
     fn test_distance_filtering() {
         let matcher = Matcher::with_default_weights();
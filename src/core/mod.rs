@@ -1,10 +1,29 @@
 // Core algorithm exports
 pub mod distance;
+pub mod embedding;
+pub mod attributes;
+pub mod calibration;
 pub mod filters;
+pub mod geocode;
+pub mod geofence;
+pub mod goals;
 pub mod matcher;
+pub mod rules;
 pub mod scoring;
+pub mod shadow;
 
-pub use distance::{haversine_distance, calculate_bounding_box, is_within_bounding_box};
-pub use filters::{matches_demographics, calculate_preference_score, matches_query_constraints};
-pub use matcher::{Matcher, MatchResult};
-pub use scoring::calculate_match_score;
+pub use distance::{haversine_distance, haversine_distance_batch, geodesic_distance, calculate_bounding_box, is_within_bounding_box, DistanceCache, DistanceCalculator, DistanceFormula};
+pub use embedding::{cosine_similarity, embedding_score};
+pub use filters::{matches_demographics, calculate_preference_score, matches_query_constraints, clamp_min_age, MIN_AGE};
+pub use attributes::{AttributeMode, AttributeScorer, AttributeScorers};
+pub use calibration::ScoreCalibration;
+pub use geocode::{City, ReverseGeocoder};
+pub use geofence::{Geofence, GeofenceAction, Geofences};
+pub use goals::GoalCompatibility;
+pub use matcher::{Matcher, MatcherBuilder, MatchOptions, MatchResult, DEFAULT_MIN_SCORE};
+pub use rules::MatchRules;
+pub use shadow::{compare_rankings, kendall_tau, top_k_overlap, RankingDiff};
+pub use scoring::{
+    calculate_activity_score, calculate_age_score, calculate_distance_score, calculate_height_score, calculate_language_score,
+    calculate_match_score, calculate_match_score_at_distance, calculate_score_breakdown,
+};
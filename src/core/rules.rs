@@ -0,0 +1,134 @@
+use crate::models::UserProfile;
+use serde::Deserialize;
+
+/// Deployment-wide hard-block rules
+///
+/// Enforced as an extra filter stage on feed candidates, after retrieval and
+/// before scoring, for white-label deployments sharing one profile store.
+/// Every rule is symmetric and off by default.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct MatchRules {
+    /// Only match profiles with the same `tenantId` (profiles without one
+    /// form their own tenant)
+    #[serde(default)]
+    pub tenant_isolation: bool,
+    /// Only match profiles in the same `market`, or in markets listed
+    /// together in `market_groups` (compared case-insensitively)
+    #[serde(default)]
+    pub market_segregation: bool,
+    #[serde(default)]
+    pub market_groups: Vec<Vec<String>>,
+    /// Never match user IDs sharing the prefix before this delimiter
+    /// (e.g. "_" keeps `acme_1` and `acme_2` apart); IDs without it are exempt
+    #[serde(default)]
+    pub user_id_prefix_delimiter: Option<String>,
+}
+
+impl MatchRules {
+    /// True when no rule is enabled
+    pub fn is_empty(&self) -> bool {
+        !self.tenant_isolation
+            && !self.market_segregation
+            && self.user_id_prefix_delimiter.as_deref().is_none_or(str::is_empty)
+    }
+
+    /// Check whether the user may be matched with the candidate
+    #[inline]
+    pub fn allows(&self, user: &UserProfile, candidate: &UserProfile) -> bool {
+        if self.tenant_isolation && user.tenant_id != candidate.tenant_id {
+            return false;
+        }
+
+        if self.market_segregation && !self.same_market(user.market.as_deref(), candidate.market.as_deref()) {
+            return false;
+        }
+
+        if let Some(delimiter) = self.user_id_prefix_delimiter.as_deref().filter(|d| !d.is_empty()) {
+            let user_prefix = user.user_id.split_once(delimiter).map(|(prefix, _)| prefix);
+            let candidate_prefix = candidate.user_id.split_once(delimiter).map(|(prefix, _)| prefix);
+            if user_prefix.is_some() && user_prefix == candidate_prefix {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    fn same_market(&self, a: Option<&str>, b: Option<&str>) -> bool {
+        let (a, b) = match (a, b) {
+            (Some(a), Some(b)) => (a, b),
+            (a, b) => return a.is_none() && b.is_none(),
+        };
+
+        a.eq_ignore_ascii_case(b)
+            || self.market_groups.iter().any(|group| {
+                group.iter().any(|m| m.eq_ignore_ascii_case(a)) && group.iter().any(|m| m.eq_ignore_ascii_case(b))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(user_id: &str, tenant_id: Option<&str>, market: Option<&str>) -> UserProfile {
+        serde_json::from_value(serde_json::json!({
+            "userId": user_id,
+            "name": user_id,
+            "age": 30,
+            "heightCm": 175,
+            "hairColor": "brown",
+            "gender": "female",
+            "latitude": 52.52,
+            "longitude": 13.405,
+            "tenantId": tenant_id,
+            "market": market,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_no_rules_allow_everything() {
+        let rules = MatchRules::default();
+        assert!(rules.is_empty());
+        assert!(rules.allows(&profile("acme_1", Some("a"), Some("us")), &profile("acme_2", Some("b"), Some("de"))));
+    }
+
+    #[test]
+    fn test_tenant_isolation() {
+        let rules = MatchRules { tenant_isolation: true, ..Default::default() };
+        let user = profile("u1", Some("acme"), None);
+
+        assert!(rules.allows(&user, &profile("u2", Some("acme"), None)));
+        assert!(!rules.allows(&user, &profile("u3", Some("globex"), None)));
+        assert!(!rules.allows(&user, &profile("u4", None, None)));
+        assert!(rules.allows(&profile("u5", None, None), &profile("u4", None, None)));
+    }
+
+    #[test]
+    fn test_market_segregation() {
+        let rules = MatchRules {
+            market_segregation: true,
+            market_groups: vec![vec!["US".to_string(), "CA".to_string()]],
+            ..Default::default()
+        };
+        let user = profile("u1", None, Some("us"));
+
+        assert!(rules.allows(&user, &profile("u2", None, Some("US"))));
+        assert!(rules.allows(&user, &profile("u3", None, Some("ca"))));
+        assert!(!rules.allows(&user, &profile("u4", None, Some("de"))));
+        assert!(!rules.allows(&user, &profile("u5", None, None)));
+        assert!(!rules.allows(&profile("u6", None, Some("de")), &profile("u7", None, Some("ca"))));
+    }
+
+    #[test]
+    fn test_user_id_prefix() {
+        let rules = MatchRules { user_id_prefix_delimiter: Some("_".to_string()), ..Default::default() };
+        let user = profile("acme_1", None, None);
+
+        assert!(!rules.allows(&user, &profile("acme_2", None, None)));
+        assert!(rules.allows(&user, &profile("globex_1", None, None)));
+        assert!(rules.allows(&user, &profile("acme", None, None)));
+        assert!(rules.allows(&profile("plain", None, None), &profile("plain2", None, None)));
+    }
+}
@@ -1,5 +1,6 @@
-use crate::models::{UserProfile, UserPreferences, ScoringWeights};
-use crate::core::{distance::haversine_distance, filters::calculate_preference_score};
+use crate::models::{UserProfile, UserPreferences, ScoreBreakdown, ScoringWeights};
+use crate::core::{distance::haversine_distance, embedding::embedding_score, filters::calculate_preference_score};
+use chrono::{DateTime, Timelike, Utc};
 
 /// Calculate a match score (0-100) for a profile based on user preferences
 ///
@@ -8,15 +9,16 @@ use crate::core::{distance::haversine_distance, filters::calculate_preference_sc
 ///     distance_score * 0.35 +      # Closer = higher score
 ///     age_score * 0.20 +           # Within preferred range = higher
 ///     sports_score * 0.25 +        # More shared sports = higher
-///     verified_bonus * 0.10 +      # isVerified = true
-///     height_score * 0.10          # Within preferred height range
+///     verified_bonus * 0.10 +      # ID-verified 1.0, photo-verified 0.6
+///     height_score * 0.10 +        # Within preferred height range
+///     activity_score * 0.0 +       # Likely awake now (off by default)
+///     embedding_score * 0.0        # Similar bio/photo embeddings (off by default)
 /// )
 pub fn calculate_match_score(
     profile: &UserProfile,
     preferences: &UserPreferences,
     weights: &ScoringWeights,
 ) -> (f64, Vec<String>) {
-    // Stage 4a: Distance score (closer is better)
     let distance_km = haversine_distance(
         preferences.latitude,
         preferences.longitude,
@@ -24,6 +26,38 @@ pub fn calculate_match_score(
         profile.longitude,
     );
 
+    calculate_match_score_at_distance(profile, preferences, weights, distance_km)
+}
+
+/// Calculate a match score (0-100) given an already computed distance
+///
+/// Lets the caller choose the distance formula and reuse the distance
+/// for the response instead of computing it twice.
+pub fn calculate_match_score_at_distance(
+    profile: &UserProfile,
+    preferences: &UserPreferences,
+    weights: &ScoringWeights,
+    distance_km: f64,
+) -> (f64, Vec<String>) {
+    let (breakdown, shared_sports) = calculate_score_breakdown(profile, preferences, weights, distance_km);
+    (breakdown.total(), shared_sports)
+}
+
+/// Calculate the weighted points (0-100 scale) each factor contributes
+///
+/// The tier boost is left at zero; it is a ranking policy applied by the
+/// matcher, not part of the compatibility score. A profile without a height,
+/// or preferences without a maximum height, are scored on the other factors,
+/// scaled up to the same total (see [`ScoringWeights::for_profile`]).
+pub fn calculate_score_breakdown(
+    profile: &UserProfile,
+    preferences: &UserPreferences,
+    weights: &ScoringWeights,
+    distance_km: f64,
+) -> (ScoreBreakdown, Vec<String>) {
+    let weights = &weights.for_profile(profile, preferences);
+
+    // Stage 4a: Distance score (closer is better)
     let distance_score = calculate_distance_score(distance_km, preferences.max_distance_km);
 
     // Stage 4b: Age score (closer to middle of preferred range is better)
@@ -32,31 +66,58 @@ pub fn calculate_match_score(
     // Stage 4c: Sports/preference score
     let (pref_score, shared_sports) = calculate_preference_score(profile, preferences);
 
-    // Stage 4d: Verified bonus
-    let verified_score = if profile.verified() { 1.0 } else { 0.0 };
-
-    // Stage 4e: Height score (within preferred range)
-    let height_score = calculate_height_score(
-        profile.height_cm,
-        preferences.min_height_cm,
-        preferences.max_height_cm,
-    );
+    // Stage 4d: Verified bonus, by verification tier
+    let verified_score = profile.verification().score();
+
+    // Stage 4e: Height score (within preferred range; carries no weight when
+    // the profile has no height or the range has no upper bound)
+    let height_score = profile.height_cm.filter(|_| preferences.has_max_height()).map_or(0.0, |height_cm| {
+        calculate_height_score(height_cm, preferences.min_height_cm, preferences.max_height_cm)
+    });
+
+    // Stage 4f: Activity window score (only evaluated when enabled)
+    let activity_score = if weights.activity > 0.0 {
+        calculate_activity_score(profile.timezone.as_deref(), Utc::now())
+    } else {
+        0.0
+    };
+
+    // Stage 4g: Embedding similarity (only evaluated when enabled)
+    let embedding_score = if weights.embedding > 0.0 {
+        embedding_score(preferences.embedding.as_deref(), profile.embedding.as_deref())
+    } else {
+        0.0
+    };
+
+    // Stage 4h: Shared language (only evaluated when enabled)
+    let language_score = if weights.language > 0.0 {
+        calculate_language_score(profile, preferences)
+    } else {
+        0.0
+    };
 
     // Weighted combination
-    let total_score = (distance_score * weights.distance
-        + age_score * weights.age
-        + pref_score * weights.sports
-        + verified_score * weights.verified
-        + height_score * weights.height)
-        * 100.0;
-
-    (total_score.min(100.0).max(0.0), shared_sports)
+    let breakdown = ScoreBreakdown {
+        distance: distance_score * weights.distance * 100.0,
+        age: age_score * weights.age * 100.0,
+        sports: pref_score * weights.sports * 100.0,
+        verified: verified_score * weights.verified * 100.0,
+        height: height_score * weights.height * 100.0,
+        activity: activity_score * weights.activity * 100.0,
+        embedding: embedding_score * weights.embedding * 100.0,
+        language: language_score * weights.language * 100.0,
+        relationship_goal: 0.0,
+        attributes: 0.0,
+        tier_boost: 0.0,
+    };
+
+    (breakdown, shared_sports)
 }
 
 /// Calculate distance score (0-1)
 /// Closer distance = higher score, exponentially decaying
 #[inline]
-fn calculate_distance_score(distance_km: f64, max_distance_km: u16) -> f64 {
+pub fn calculate_distance_score(distance_km: f64, max_distance_km: u16) -> f64 {
     let max = max_distance_km as f64;
     if distance_km >= max {
         return 0.0;
@@ -68,11 +129,12 @@ fn calculate_distance_score(distance_km: f64, max_distance_km: u16) -> f64 {
 }
 
 /// Calculate age score (0-1)
-/// Users closer to the middle of the preferred range score higher
+/// Users closer to the middle of the preferred range score higher.
+/// An empty or inverted range scores every age 1.0.
 #[inline]
-fn calculate_age_score(age: u8, min_age: u8, max_age: u8) -> f64 {
-    let mid = (min_age + max_age) as f64 / 2.0;
-    let range = (max_age - min_age) as f64;
+pub fn calculate_age_score(age: u8, min_age: u8, max_age: u8) -> f64 {
+    let mid = (min_age as f64 + max_age as f64) / 2.0;
+    let range = max_age as f64 - min_age as f64;
     let age_f = age as f64;
 
     if range <= 0.0 {
@@ -89,9 +151,9 @@ fn calculate_age_score(age: u8, min_age: u8, max_age: u8) -> f64 {
 /// Calculate height score (0-1)
 /// Users closer to the middle of the preferred height range score higher
 #[inline]
-fn calculate_height_score(height_cm: u16, min_height_cm: u16, max_height_cm: u16) -> f64 {
-    let mid = (min_height_cm + max_height_cm) as f64 / 2.0;
-    let range = (max_height_cm - min_height_cm) as f64;
+pub fn calculate_height_score(height_cm: u16, min_height_cm: u16, max_height_cm: u16) -> f64 {
+    let mid = (min_height_cm as f64 + max_height_cm as f64) / 2.0;
+    let range = max_height_cm as f64 - min_height_cm as f64;
     let height_f = height_cm as f64;
 
     if range <= 0.0 {
@@ -105,28 +167,78 @@ fn calculate_height_score(height_cm: u16, min_height_cm: u16, max_height_cm: u16
     1.0 - normalized_deviation.min(1.0)
 }
 
+/// Calculate language score (0-1)
+/// Full marks for speaking a preferred language, none otherwise.
+/// Without preferred languages or listed languages the score is neutral.
+#[inline]
+pub fn calculate_language_score(profile: &UserProfile, preferences: &UserPreferences) -> f64 {
+    if preferences.preferred_languages.is_empty() || profile.languages.is_empty() {
+        return 0.5;
+    }
+
+    if profile.speaks_any(&preferences.preferred_languages) {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+/// Calculate activity score (0-1) from the candidate's local hour
+/// Daytime and evening score highest, the small hours lowest.
+/// Unknown or invalid time zones get a neutral score.
+#[inline]
+pub fn calculate_activity_score(timezone: Option<&str>, now: DateTime<Utc>) -> f64 {
+    let Some(tz) = timezone.and_then(|tz| tz.parse::<chrono_tz::Tz>().ok()) else {
+        return 0.5;
+    };
+
+    match now.with_timezone(&tz).hour() {
+        8..=22 => 1.0,
+        7 | 23 => 0.7,
+        0 | 1 | 6 => 0.4,
+        _ => 0.1,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use chrono::Utc;
+    use crate::models::{Verification, SCHEMA_VERSION};
 
     fn create_test_profile(age: u8, height_cm: u16, is_verified: bool) -> UserProfile {
         UserProfile {
             user_id: "test_user".to_string(),
             name: "Test User".to_string(),
             age,
-            height_cm,
+            height_cm: Some(height_cm),
             hair_color: "brown".to_string(),
             gender: "female".to_string(),
             latitude: 40.7128,
             longitude: -74.0060,
             is_verified: Some(is_verified),
+            verification: None,
             is_active: true,
             is_timeout: Some(false),
             image_file_ids: vec![],
             description: None,
             sports_preferences: vec!["tennis".to_string()],
+            interests: vec![],
             created_at: Some(Utc::now()),
+            timezone: None,
+            is_premium: None,
+            is_snoozed: None,
+            tenant_id: None,
+            market: None,
+            city: None,
+            tier: None,
+            embedding: None,
+            interested_in: vec![],
+            languages: vec![],
+            relationship_goal: None,
+            attributes: Default::default(),
+            updated_at: None,
+            schema_version: SCHEMA_VERSION,
         }
     }
 
@@ -140,9 +252,19 @@ mod tests {
             max_height_cm: 180,
             preferred_hair_colors: vec![],
             preferred_sports: vec!["tennis".to_string()],
+            preferred_interests: vec![],
             max_distance_km: 50,
             latitude: 40.7128,
             longitude: -74.0060,
+            require_photos: None,
+            embedding: None,
+            preferred_languages: vec![],
+            require_shared_language: false,
+            verified_only: false,
+            relationship_goal: None,
+            attributes: Default::default(),
+            updated_at: None,
+            schema_version: SCHEMA_VERSION,
         }
     }
 
@@ -154,7 +276,7 @@ mod tests {
 
         let (score, shared) = calculate_match_score(&profile, &preferences, &weights);
 
-        assert!(score >= 0.0 && score <= 100.0);
+        assert!((0.0..=100.0).contains(&score));
         assert_eq!(shared, vec!["tennis"]);
     }
 
@@ -195,6 +317,72 @@ mod tests {
         assert!(edge < 0.5);
     }
 
+    #[test]
+    fn test_missing_height_is_left_out() {
+        let mut profile = create_test_profile(25, 170, true);
+        let preferences = create_test_preferences();
+        let weights = ScoringWeights::default();
+
+        let (with_height, _) = calculate_score_breakdown(&profile, &preferences, &weights, 5.0);
+        profile.height_cm = Some(preferences.min_height_cm);
+        let (edge_height, _) = calculate_score_breakdown(&profile, &preferences, &weights, 5.0);
+        profile.height_cm = None;
+        let (without_height, _) = calculate_score_breakdown(&profile, &preferences, &weights, 5.0);
+
+        // The other factors are scaled up to fill the height weight
+        assert!(with_height.height > 0.0);
+        assert_eq!(without_height.height, 0.0);
+        let others = with_height.total() - with_height.height;
+        assert!((without_height.total() - others / (1.0 - weights.height)).abs() < 1e-9);
+        // ...so a missing height isn't scored like the worst height in range
+        assert_eq!(edge_height.height, 0.0);
+        assert!(without_height.total() > edge_height.total());
+    }
+
+    #[test]
+    fn test_open_height_range_is_left_out() {
+        // No upper bound (v1 `maxHeightCm: 0`, or unset in v2): nothing to score against
+        let preferences = UserPreferences { max_height_cm: u16::MAX, ..create_test_preferences() };
+        let weights = ScoringWeights::default();
+        let mut profile = create_test_profile(25, 170, true);
+
+        let (with_height, _) = calculate_score_breakdown(&profile, &preferences, &weights, 5.0);
+        profile.height_cm = None;
+        let (without_height, _) = calculate_score_breakdown(&profile, &preferences, &weights, 5.0);
+
+        assert_eq!(with_height.height, 0.0);
+        assert!((with_height.total() - without_height.total()).abs() < 1e-9);
+        assert!(with_height.total() > 0.0);
+    }
+
+    #[test]
+    fn test_activity_score() {
+        let noon_utc = "2024-06-01T12:00:00Z".parse::<DateTime<Utc>>().unwrap();
+
+        // 14:00 in Berlin = awake
+        assert_eq!(calculate_activity_score(Some("Europe/Berlin"), noon_utc), 1.0);
+
+        // 02:00 in Honolulu (UTC-10) = asleep
+        assert!(calculate_activity_score(Some("Pacific/Honolulu"), noon_utc) < 0.2);
+
+        // Missing or unknown time zone = neutral
+        assert_eq!(calculate_activity_score(None, noon_utc), 0.5);
+        assert_eq!(calculate_activity_score(Some("Mars/Olympus"), noon_utc), 0.5);
+    }
+
+    #[test]
+    fn test_activity_weight_off_by_default() {
+        let mut profile = create_test_profile(25, 170, true);
+        let preferences = create_test_preferences();
+        let weights = ScoringWeights::default();
+
+        let (without_tz, _) = calculate_match_score(&profile, &preferences, &weights);
+        profile.timezone = Some("Europe/Berlin".to_string());
+        let (with_tz, _) = calculate_match_score(&profile, &preferences, &weights);
+
+        assert_eq!(without_tz, with_tz);
+    }
+
     #[test]
     fn test_verified_bonus() {
         let verified_profile = create_test_profile(25, 170, true);
@@ -207,4 +395,87 @@ mod tests {
 
         assert!(verified_score > unverified_score);
     }
+
+    #[test]
+    fn test_verification_tiers() {
+        let mut profile = create_test_profile(25, 170, false);
+        let preferences = create_test_preferences();
+        let weights = ScoringWeights::default();
+        let verified_points = |profile: &UserProfile| calculate_score_breakdown(profile, &preferences, &weights, 5.0).0.verified;
+
+        assert_eq!(verified_points(&profile), 0.0);
+
+        // Legacy isVerified counts as photo verification
+        profile.is_verified = Some(true);
+        let photo = verified_points(&profile);
+        assert!((photo - 6.0).abs() < 1e-9);
+
+        profile.verification = Some(Verification::Id);
+        assert!((verified_points(&profile) - 10.0).abs() < 1e-9);
+
+        profile.verification = Some(Verification::Unverified);
+        assert_eq!(verified_points(&profile), 0.0);
+    }
+
+    #[test]
+    fn test_language_weight() {
+        let mut profile = create_test_profile(25, 170, true);
+        let mut preferences = create_test_preferences();
+        let weights = ScoringWeights { language: 0.2, ..ScoringWeights::default() };
+
+        // Unknown languages are neutral
+        let (breakdown, _) = calculate_score_breakdown(&profile, &preferences, &weights, 5.0);
+        assert!((breakdown.language - 10.0).abs() < 1e-9);
+
+        preferences.preferred_languages = vec!["de".to_string()];
+        profile.languages = vec!["en".to_string()];
+        let (breakdown, _) = calculate_score_breakdown(&profile, &preferences, &weights, 5.0);
+        assert_eq!(breakdown.language, 0.0);
+
+        profile.languages.push("de".to_string());
+        let (breakdown, _) = calculate_score_breakdown(&profile, &preferences, &weights, 5.0);
+        assert!((breakdown.language - 20.0).abs() < 1e-9);
+
+        // Off by default
+        let (breakdown, _) = calculate_score_breakdown(&profile, &preferences, &ScoringWeights::default(), 5.0);
+        assert_eq!(breakdown.language, 0.0);
+    }
+
+    #[test]
+    fn test_embedding_weight() {
+        let mut profile = create_test_profile(25, 170, true);
+        let mut preferences = create_test_preferences();
+        let weights = ScoringWeights { embedding: 0.2, ..ScoringWeights::default() };
+
+        // Missing embeddings are neutral
+        let (breakdown, _) = calculate_score_breakdown(&profile, &preferences, &weights, 5.0);
+        assert!((breakdown.embedding - 10.0).abs() < 1e-9);
+
+        preferences.embedding = Some(vec![0.6, 0.8]);
+        profile.embedding = Some(vec![0.6, 0.8]);
+        let (similar, _) = calculate_match_score_at_distance(&profile, &preferences, &weights, 5.0);
+        profile.embedding = Some(vec![0.8, -0.6]);
+        let (unrelated, _) = calculate_match_score_at_distance(&profile, &preferences, &weights, 5.0);
+        assert!((similar - unrelated - 20.0).abs() < 1e-4);
+
+        // Off by default, so embeddings don't change the score
+        let (off, _) = calculate_match_score_at_distance(&profile, &preferences, &ScoringWeights::default(), 5.0);
+        profile.embedding = None;
+        let (none, _) = calculate_match_score_at_distance(&profile, &preferences, &ScoringWeights::default(), 5.0);
+        assert_eq!(off, none);
+    }
+
+    #[test]
+    fn test_score_breakdown_sums_to_score() {
+        let profile = create_test_profile(25, 170, true);
+        let preferences = create_test_preferences();
+        let weights = ScoringWeights::default();
+
+        let (breakdown, _) = calculate_score_breakdown(&profile, &preferences, &weights, 5.0);
+        let (score, _) = calculate_match_score_at_distance(&profile, &preferences, &weights, 5.0);
+
+        assert_eq!(breakdown.verified, 10.0);
+        assert_eq!(breakdown.tier_boost, 0.0);
+        assert!((breakdown.total() - score).abs() < 1e-9);
+    }
 }
@@ -0,0 +1,100 @@
+use crate::models::ScoredMatch;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+/// How many leading matches `top10_overlap` compares
+pub const TOP_K: usize = 10;
+
+/// Ranking agreement between the served result and a shadow result
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct RankingDiff {
+    pub served: usize,
+    pub shadow: usize,
+    /// Profiles ranked by both
+    pub common: usize,
+    /// Kendall rank correlation over the common profiles (-1 to 1);
+    /// None with fewer than two in common
+    pub kendall_tau: Option<f64>,
+    /// Share of the top 10 both rankings agree on (0 to 1)
+    pub top10_overlap: f64,
+}
+
+/// Compare two ranked match lists by user ID
+pub fn compare_rankings(served: &[ScoredMatch], shadow: &[ScoredMatch]) -> RankingDiff {
+    let served_ids: Vec<&str> = served.iter().map(|m| m.user_id.as_str()).collect();
+    let shadow_ids: Vec<&str> = shadow.iter().map(|m| m.user_id.as_str()).collect();
+    let shadow_ranks: HashMap<&str, usize> = shadow_ids.iter().enumerate().map(|(rank, id)| (*id, rank)).collect();
+
+    // Shadow ranks of the common profiles, in served order
+    let ranks: Vec<usize> = served_ids.iter().filter_map(|id| shadow_ranks.get(id).copied()).collect();
+
+    RankingDiff {
+        served: served.len(),
+        shadow: shadow.len(),
+        common: ranks.len(),
+        kendall_tau: kendall_tau(&ranks),
+        top10_overlap: top_k_overlap(&served_ids, &shadow_ids, TOP_K),
+    }
+}
+
+/// Kendall tau-a of a permutation against its sorted order
+///
+/// `ranks[i]` is the position in the second ranking of the item at position
+/// `i` in the first. O(n²), which is fine for feed-sized lists.
+pub fn kendall_tau(ranks: &[usize]) -> Option<f64> {
+    let n = ranks.len();
+    if n < 2 {
+        return None;
+    }
+
+    let mut concordant = 0i64;
+    let mut discordant = 0i64;
+    for i in 0..n {
+        for j in (i + 1)..n {
+            if ranks[i] < ranks[j] {
+                concordant += 1;
+            } else {
+                discordant += 1;
+            }
+        }
+    }
+
+    let pairs = (n * (n - 1) / 2) as f64;
+    Some((concordant - discordant) as f64 / pairs)
+}
+
+/// Fraction of the first `k` items shared by both rankings
+///
+/// Two empty rankings agree completely.
+pub fn top_k_overlap(a: &[&str], b: &[&str], k: usize) -> f64 {
+    let depth = k.min(a.len().max(b.len()));
+    if depth == 0 {
+        return 1.0;
+    }
+
+    let top_a: HashSet<&str> = a.iter().take(k).copied().collect();
+    let shared = b.iter().take(k).filter(|id| top_a.contains(*id)).count();
+    shared as f64 / depth as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kendall_tau() {
+        assert_eq!(kendall_tau(&[0, 1, 2, 3]), Some(1.0));
+        assert_eq!(kendall_tau(&[3, 2, 1, 0]), Some(-1.0));
+        // One swapped adjacent pair out of six
+        assert!((kendall_tau(&[1, 0, 2, 3]).unwrap() - 4.0 / 6.0).abs() < 1e-9);
+        assert_eq!(kendall_tau(&[0]), None);
+    }
+
+    #[test]
+    fn test_top_k_overlap() {
+        assert_eq!(top_k_overlap(&["a", "b", "c"], &["c", "b", "a"], 10), 1.0);
+        assert_eq!(top_k_overlap(&["a", "b"], &["c", "d"], 10), 0.0);
+        assert_eq!(top_k_overlap(&["a", "b", "c", "d"], &["a", "x", "b", "y"], 2), 0.5);
+        assert_eq!(top_k_overlap(&[], &[], 10), 1.0);
+    }
+}
@@ -0,0 +1,448 @@
+use crate::models::{ErrorResponse, FieldError};
+use crate::routes::case::to_camel_case;
+use crate::services::{AppwriteError, CacheError, FeedError, PostgresError, ProfilingError, ReconcileError};
+use actix_web::{http::{header, StatusCode}, HttpResponse, ResponseError};
+use thiserror::Error;
+use validator::{ValidationErrors, ValidationErrorsKind};
+
+/// Application-wide error type returned by handlers
+///
+/// Every variant maps to an HTTP status and a stable `error_code` string, so
+/// clients can branch on the code instead of parsing messages. Service errors
+/// convert into it with `?` or, to keep context, `map_err(AppError::upstream(..))`.
+#[derive(Debug, Error)]
+pub enum AppError {
+    #[error("{}", summarize(.0))]
+    Validation(Vec<FieldError>),
+
+    #[error("{message}")]
+    InvalidJson { message: String, errors: Vec<FieldError> },
+
+    #[error("{message}")]
+    BadRequest { code: &'static str, message: String },
+
+    #[error("{0}")]
+    Unauthorized(String),
+
+    #[error("{message}")]
+    Forbidden { code: &'static str, message: String },
+
+    #[error("{message}")]
+    NotFound { code: &'static str, message: String },
+
+    #[error("{message}")]
+    Conflict { code: &'static str, message: String },
+
+    #[error("{0}")]
+    PayloadTooLarge(String),
+
+    #[error("{0}")]
+    RateLimited(String),
+
+    #[error("{message}")]
+    Overloaded { message: String, retry_after_secs: u64 },
+
+    #[error("{0}")]
+    Timeout(String),
+
+    #[error("{0}")]
+    DeadlineExceeded(String),
+
+    #[error("{context}: {source}")]
+    Upstream {
+        context: &'static str,
+        #[source]
+        source: AppwriteError,
+    },
+
+    #[error("{context}: {source}")]
+    Database {
+        context: &'static str,
+        #[source]
+        source: PostgresError,
+    },
+
+    #[error("Cache error: {0}")]
+    Cache(#[from] CacheError),
+
+    #[error("{0}")]
+    Internal(String),
+}
+
+pub type AppResult<T> = Result<T, AppError>;
+
+impl AppError {
+    /// Stable machine-readable code for this error
+    pub fn error_code(&self) -> &'static str {
+        match self {
+            AppError::Validation(_) => "validation_failed",
+            AppError::InvalidJson { .. } => "invalid_json",
+            AppError::BadRequest { code, .. }
+            | AppError::Forbidden { code, .. }
+            | AppError::NotFound { code, .. }
+            | AppError::Conflict { code, .. } => code,
+            AppError::Unauthorized(_) => "unauthorized",
+            AppError::PayloadTooLarge(_) => "payload_too_large",
+            AppError::RateLimited(_) => "rate_limited",
+            AppError::Overloaded { .. } => "overloaded",
+            AppError::Timeout(_) => "upstream_timeout",
+            AppError::DeadlineExceeded(_) => "deadline_exceeded",
+            AppError::Upstream { .. } => "upstream_error",
+            AppError::Database { .. } => "database_error",
+            AppError::Cache(_) => "cache_error",
+            AppError::Internal(_) => "internal_error",
+        }
+    }
+
+    /// Short human-readable summary (the `error` field of the response)
+    fn title(&self) -> &'static str {
+        match self {
+            AppError::Validation(_) => "Validation failed",
+            AppError::InvalidJson { .. } | AppError::BadRequest { .. } => "Bad request",
+            AppError::Unauthorized(_) => "Unauthorized",
+            AppError::Forbidden { .. } => "Forbidden",
+            AppError::NotFound { .. } => "Not found",
+            AppError::Conflict { .. } => "Conflict",
+            AppError::PayloadTooLarge(_) => "Payload too large",
+            AppError::RateLimited(_) => "Too many requests",
+            AppError::Overloaded { .. } => "Service unavailable",
+            AppError::Timeout(_) => "Gateway timeout",
+            AppError::DeadlineExceeded(_) => "Deadline exceeded",
+            AppError::Upstream { context, .. } | AppError::Database { context, .. } => context,
+            AppError::Cache(_) => "Cache error",
+            AppError::Internal(_) => "Internal error",
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            AppError::Upstream { source, .. } => source.to_string(),
+            AppError::Database { source, .. } => source.to_string(),
+            other => other.to_string(),
+        }
+    }
+
+    /// Per-field details, for validation and JSON body errors
+    pub fn field_errors(&self) -> &[FieldError] {
+        match self {
+            AppError::Validation(errors) | AppError::InvalidJson { errors, .. } => errors,
+            _ => &[],
+        }
+    }
+
+    /// Wrap an Appwrite error with a description of what failed
+    pub fn upstream(context: &'static str) -> impl FnOnce(AppwriteError) -> Self {
+        move |source| AppError::Upstream { context, source }
+    }
+
+    /// Wrap a PostgreSQL error with a description of what failed
+    pub fn database(context: &'static str) -> impl FnOnce(PostgresError) -> Self {
+        move |source| AppError::Database { context, source }
+    }
+}
+
+impl ResponseError for AppError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            AppError::Validation(_) | AppError::InvalidJson { .. } | AppError::BadRequest { .. } => {
+                StatusCode::BAD_REQUEST
+            }
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden { .. } => StatusCode::FORBIDDEN,
+            AppError::NotFound { .. } => StatusCode::NOT_FOUND,
+            AppError::Conflict { .. } => StatusCode::CONFLICT,
+            AppError::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            AppError::RateLimited(_) => StatusCode::TOO_MANY_REQUESTS,
+            AppError::Overloaded { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            AppError::Timeout(_) | AppError::DeadlineExceeded(_) => StatusCode::GATEWAY_TIMEOUT,
+            AppError::Upstream { .. } => StatusCode::BAD_GATEWAY,
+            AppError::Database { .. } | AppError::Cache(_) | AppError::Internal(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let status = self.status_code();
+        if status.is_server_error() {
+            tracing::error!(error_code = self.error_code(), "{}", self);
+        } else {
+            tracing::debug!(error_code = self.error_code(), "{}", self);
+        }
+
+        let mut response = HttpResponse::build(status);
+        if let AppError::Overloaded { retry_after_secs, .. } = self {
+            response.insert_header((header::RETRY_AFTER, retry_after_secs.to_string()));
+        }
+
+        response.json(ErrorResponse {
+            error_code: self.error_code().to_string(),
+            error: self.title().to_string(),
+            message: self.message(),
+            status_code: status.as_u16(),
+            validation_errors: self.field_errors().to_vec(),
+        })
+    }
+}
+
+impl From<AppwriteError> for AppError {
+    fn from(source: AppwriteError) -> Self {
+        AppError::Upstream { context: "Appwrite request failed", source }
+    }
+}
+
+impl From<PostgresError> for AppError {
+    fn from(source: PostgresError) -> Self {
+        AppError::Database { context: "Database query failed", source }
+    }
+}
+
+impl From<ValidationErrors> for AppError {
+    fn from(errors: ValidationErrors) -> Self {
+        AppError::Validation(validation_field_errors(&errors))
+    }
+}
+
+/// Flatten validator errors into one entry per failed check
+///
+/// Paths use the client's camelCase names, with nested structs joined by
+/// "." and list items indexed, e.g. "candidates[3].userId".
+pub fn validation_field_errors(errors: &ValidationErrors) -> Vec<FieldError> {
+    let mut fields = Vec::new();
+    collect_field_errors(errors, "", &mut fields);
+    fields.sort_by(|a, b| a.field.cmp(&b.field));
+    fields
+}
+
+fn collect_field_errors(errors: &ValidationErrors, prefix: &str, out: &mut Vec<FieldError>) {
+    for (field, kind) in errors.errors() {
+        let path = format!("{}{}", prefix, to_camel_case(field));
+        match kind {
+            ValidationErrorsKind::Field(failures) => {
+                out.extend(failures.iter().map(|failure| FieldError {
+                    field: path.clone(),
+                    code: failure.code.to_string(),
+                    message: failure
+                        .message
+                        .as_ref()
+                        .map(|m| m.to_string())
+                        .unwrap_or_else(|| describe(failure)),
+                }));
+            }
+            ValidationErrorsKind::Struct(nested) => collect_field_errors(nested, &format!("{}.", path), out),
+            ValidationErrorsKind::List(items) => {
+                for (index, nested) in items {
+                    collect_field_errors(nested, &format!("{}[{}].", path, index), out);
+                }
+            }
+        }
+    }
+}
+
+/// Default message for a failed validator check
+fn describe(failure: &validator::ValidationError) -> String {
+    let param = |name: &str| failure.params.get(name).map(|v| v.to_string());
+    let bounds = match (param("min"), param("max")) {
+        (Some(min), Some(max)) => format!("between {} and {}", min, max),
+        (Some(min), None) => format!("at least {}", min),
+        (None, Some(max)) => format!("at most {}", max),
+        (None, None) => String::new(),
+    };
+
+    match failure.code.as_ref() {
+        "length" if !bounds.is_empty() => format!("length must be {}", bounds),
+        "range" if !bounds.is_empty() => format!("must be {}", bounds),
+        code => format!("failed {} validation", code),
+    }
+}
+
+fn summarize(errors: &[FieldError]) -> String {
+    errors
+        .iter()
+        .map(|e| format!("{}: {}", e.field, e.message))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Describe a JSON body deserialization error as a field error
+///
+/// serde_json only names the field for missing fields; other errors are
+/// attributed to the body with the parser's message.
+pub fn json_field_error(err: &serde_json::Error) -> FieldError {
+    let message = err.to_string();
+    let (field, code) = match err.classify() {
+        serde_json::error::Category::Data => match message.strip_prefix("missing field `") {
+            Some(rest) => (rest.split('`').next().unwrap_or("body").to_string(), "missing_field"),
+            None => ("body".to_string(), "invalid_value"),
+        },
+        serde_json::error::Category::Syntax => ("body".to_string(), "syntax"),
+        serde_json::error::Category::Eof => ("body".to_string(), "eof"),
+        serde_json::error::Category::Io => ("body".to_string(), "io"),
+    };
+
+    FieldError {
+        field,
+        code: code.to_string(),
+        message,
+    }
+}
+
+impl From<FeedError> for AppError {
+    fn from(err: FeedError) -> Self {
+        match err {
+            FeedError::Snoozed => AppError::Conflict {
+                code: "user_snoozed",
+                message: "Profile is snoozed; unsnooze to see matches".to_string(),
+            },
+            FeedError::PassportRequiresPremium => AppError::Forbidden {
+                code: "premium_required",
+                message: "overrideLocation is not available on your subscription tier".to_string(),
+            },
+            FeedError::Geofenced(_) => AppError::Forbidden {
+                code: "market_unavailable",
+                message: "Matching is not available in this region".to_string(),
+            },
+            FeedError::Profile(AppwriteError::NotFound(message)) => AppError::NotFound {
+                code: "profile_not_found",
+                message,
+            },
+            FeedError::Preferences(AppwriteError::NotFound(message)) => AppError::NotFound {
+                code: "preferences_not_found",
+                message,
+            },
+            FeedError::Profile(source) => AppError::Upstream { context: "Failed to fetch user profile", source },
+            FeedError::Preferences(source) => AppError::Upstream { context: "Failed to fetch preferences", source },
+            FeedError::Candidates(source) => AppError::Upstream { context: "Failed to query candidates", source },
+            FeedError::TimedOut(_) => AppError::Timeout(err.to_string()),
+            FeedError::Overloaded { retry_after_secs } => AppError::Overloaded {
+                message: "Too many matching requests in flight, retry shortly".to_string(),
+                retry_after_secs,
+            },
+        }
+    }
+}
+
+impl From<ReconcileError> for AppError {
+    fn from(err: ReconcileError) -> Self {
+        match err {
+            ReconcileError::Postgres(source) => AppError::Database { context: "Failed to reconcile events", source },
+            ReconcileError::Appwrite(source) => AppError::Upstream { context: "Failed to reconcile events", source },
+        }
+    }
+}
+
+impl From<ProfilingError> for AppError {
+    fn from(err: ProfilingError) -> Self {
+        match err {
+            ProfilingError::Disabled => AppError::NotFound {
+                code: "profiling_disabled",
+                message: "This build has no profiler; rebuild with --features profiling".to_string(),
+            },
+            ProfilingError::Busy => AppError::Conflict {
+                code: "profile_in_progress",
+                message: "Another profile is being captured; retry when it finishes".to_string(),
+            },
+            ProfilingError::Profiler(message) => AppError::Internal(message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_and_codes() {
+        let cases = [
+            (AppError::Validation(vec![]), StatusCode::BAD_REQUEST, "validation_failed"),
+            (AppError::Unauthorized("no".into()), StatusCode::UNAUTHORIZED, "unauthorized"),
+            (
+                AppError::NotFound { code: "profile_not_found", message: "missing".into() },
+                StatusCode::NOT_FOUND,
+                "profile_not_found",
+            ),
+            (AppError::RateLimited("slow down".into()), StatusCode::TOO_MANY_REQUESTS, "rate_limited"),
+            (
+                AppError::Overloaded { message: "busy".into(), retry_after_secs: 1 },
+                StatusCode::SERVICE_UNAVAILABLE,
+                "overloaded",
+            ),
+            (AppError::DeadlineExceeded("late".into()), StatusCode::GATEWAY_TIMEOUT, "deadline_exceeded"),
+            (
+                AppError::from(AppwriteError::ApiError("boom".into())),
+                StatusCode::BAD_GATEWAY,
+                "upstream_error",
+            ),
+            (
+                AppError::from(PostgresError::InvalidInput("x".into())),
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "database_error",
+            ),
+        ];
+
+        for (err, status, code) in cases {
+            assert_eq!(err.status_code(), status);
+            assert_eq!(err.error_code(), code);
+        }
+    }
+
+    #[test]
+    fn test_overloaded_retry_after() {
+        let response = AppError::Overloaded { message: "busy".into(), retry_after_secs: 2 }.error_response();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers().get(header::RETRY_AFTER).unwrap(), "2");
+    }
+
+    #[test]
+    fn test_validation_field_errors() {
+        use crate::models::FindMatchesRequest;
+        use validator::Validate;
+
+        let req: FindMatchesRequest =
+            serde_json::from_str(r#"{"userId": "", "weights": {"age": 2.0}}"#).unwrap();
+        let err = AppError::from(req.validate().unwrap_err());
+        let fields = err.field_errors();
+
+        assert_eq!(fields.len(), 2);
+        assert_eq!(fields[0].field, "userId");
+        assert_eq!(fields[0].code, "length");
+        assert_eq!(fields[0].message, "length must be at least 1");
+        assert_eq!(fields[1].field, "weights.age");
+        assert_eq!(fields[1].code, "range");
+        assert_eq!(fields[1].message, "must be between 0.0 and 1.0");
+        assert_eq!(err.to_string(), "userId: length must be at least 1; weights.age: must be between 0.0 and 1.0");
+    }
+
+    #[test]
+    fn test_json_field_error() {
+        let err = serde_json::from_str::<crate::models::FindMatchesRequest>(r#"{"limit": 5}"#).unwrap_err();
+        let field = json_field_error(&err);
+        assert_eq!(field.field, "userId");
+        assert_eq!(field.code, "missing_field");
+
+        let err = serde_json::from_str::<crate::models::FindMatchesRequest>(r#"{"userId": 5"#).unwrap_err();
+        assert_eq!(json_field_error(&err).field, "body");
+    }
+
+    #[test]
+    fn test_feed_error_mapping() {
+        let err = AppError::from(FeedError::Snoozed);
+        assert_eq!(err.status_code(), StatusCode::CONFLICT);
+        assert_eq!(err.error_code(), "user_snoozed");
+
+        let err = AppError::from(FeedError::Profile(AppwriteError::NotFound("user_1".into())));
+        assert_eq!(err.status_code(), StatusCode::NOT_FOUND);
+        assert_eq!(err.error_code(), "profile_not_found");
+
+        let err = AppError::from(FeedError::Preferences(AppwriteError::NotFound("user_1".into())));
+        assert_eq!(err.error_code(), "preferences_not_found");
+
+        let err = AppError::from(FeedError::TimedOut("user profile"));
+        assert_eq!(err.status_code(), StatusCode::GATEWAY_TIMEOUT);
+        assert_eq!(err.error_code(), "upstream_timeout");
+
+        let err = AppError::from(FeedError::Preferences(AppwriteError::ApiError("500".into())));
+        assert_eq!(err.title(), "Failed to fetch preferences");
+        assert_eq!(err.message(), "API returned error: 500");
+    }
+}
@@ -2,15 +2,30 @@
 //!
 //! This library provides the core matching algorithm used by the Lume dating app.
 //! It implements a multi-stage filtering pipeline for efficient user matching.
+//!
+//! The HTTP service (`config`, `error`, `routes`, `services`) is behind the
+//! default `server` feature. With `default-features = false` only the
+//! matching core (`core`, `models`, `util`) is built, which also compiles
+//! to wasm32 for on-device pre-ranking (see `wasm/`).
 
+#[cfg(feature = "server")]
 pub mod config;
 pub mod core;
+#[cfg(feature = "server")]
+pub mod error;
 pub mod models;
+#[cfg(feature = "python")]
+mod python;
+#[cfg(feature = "server")]
 pub mod routes;
+#[cfg(feature = "server")]
 pub mod services;
+#[cfg(all(feature = "server", any(test, feature = "testing")))]
+pub mod testing;
+pub mod util;
 
 // Re-export commonly used types
-pub use core::{Matcher, distance::{haversine_distance, calculate_bounding_box}};
+pub use core::{Matcher, MatcherBuilder, MatchOptions, distance::{haversine_distance, calculate_bounding_box}};
 pub use models::{UserProfile, UserPreferences, ScoredMatch, ScoringWeights, FindMatchesRequest, FindMatchesResponse};
 
 #[cfg(test)]
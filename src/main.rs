@@ -1,62 +1,154 @@
-mod config;
-mod core;
-mod models;
-mod routes;
-mod services;
-
 use actix_cors::Cors;
-use actix_web::{web, App, HttpServer, HttpResponse, middleware, error, http::StatusCode};
-use config::Settings;
-use routes::matches::AppState;
-use services::{AppwriteClient, AppwriteCollections, CacheManager, PostgresClient};
-use core::Matcher;
-use models::ScoringWeights;
+use actix_web::{web, App, HttpServer, middleware, dev::Service, http::KeepAlive};
+use actix_web::http::header::{self, HeaderMap, HeaderValue};
+use lume_algo::config::{CollectionSettings, DatabaseSettings, FeedSettings, Settings, TenantSettings, VectorSettings};
+use lume_algo::routes::{self, matches::AppState, errors::{handle_json_payload_error, handle_query_payload_error}, ConcurrencyLimiter, SignatureVerifier, TenantRegistry};
+use lume_algo::services::{AppwriteClient, Cache, CacheManager, EventPublisher, EventReconciler, MatchExpirer, FeedGenerator, FeedWorker, InMemoryProfileStore, MirroredProfileStore, PostgresClient, ProfileMirror, ProfileStore, ProfileSync, RealtimeSubscriber, SeenPruner, SyncedProfileStore, SeenStore, ShadowEvaluator, SqliteSeenStore, connect_vector_index, warm_up};
+use lume_algo::models::ScoringWeights;
 use std::sync::Arc;
-use tracing::{info, error};
-
-/// JSON error response for JSON payload errors
-#[derive(Debug, serde::Serialize)]
-pub struct JsonError {
-    pub error: String,
-    pub message: String,
-    pub status_code: u16,
-}
+use std::time::Duration;
+use tracing::{info, error, warn};
+
+/// Remove `br` from Accept-Encoding so the Compress middleware falls back to gzip/zstd
+fn strip_brotli(headers: &mut HeaderMap) {
+    let Some(value) = headers
+        .get(header::ACCEPT_ENCODING)
+        .and_then(|v| v.to_str().ok())
+    else {
+        return;
+    };
+
+    let filtered = value
+        .split(',')
+        .map(str::trim)
+        .filter(|coding| {
+            !coding
+                .split(';')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .eq_ignore_ascii_case("br")
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
 
-impl std::fmt::Display for JsonError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}: {}", self.error, self.message)
+    match HeaderValue::from_str(&filtered) {
+        Ok(v) if !filtered.is_empty() => {
+            headers.insert(header::ACCEPT_ENCODING, v);
+        }
+        _ => {
+            headers.remove(header::ACCEPT_ENCODING);
+        }
     }
 }
 
-impl std::error::Error for JsonError {}
+/// Appwrite client for `collections`, with its failover health check running
+fn connect_appwrite(settings: &Settings, collections: &CollectionSettings) -> Arc<AppwriteClient> {
+    let appwrite = Arc::new(AppwriteClient::from_settings(&settings.appwrite, collections));
+    appwrite
+        .clone()
+        .spawn_health_check(Duration::from_secs(settings.appwrite.health_check_interval_secs.max(1)));
 
-impl error::ResponseError for JsonError {
-    fn error_response(&self) -> HttpResponse {
-        HttpResponse::build(StatusCode::from_u16(self.status_code).unwrap_or(StatusCode::BAD_REQUEST))
-            .content_type("application/json")
-            .body(serde_json::to_string(self).unwrap())
-    }
+    appwrite
 }
 
-/// Handle JSON payload errors
-pub fn handle_json_payload_error(err: error::JsonPayloadError, req: &actix_web::HttpRequest) -> actix_web::Error {
-    tracing::info!("JSON payload error on {}: {}", req.path(), err);
-    JsonError {
-        error: "invalid_json".to_string(),
-        message: format!("Invalid JSON: {}", err),
-        status_code: 400,
-    }
-    .into()
+/// Client set for a named tenant
+///
+/// Connects its own Appwrite collections, PostgreSQL schema, prefixed cache
+/// and vector index, starts its maintenance jobs, and gets its own admin
+/// token, find limiter and event publisher (tagging events with the tenant,
+/// over `events`' connection).
+async fn build_tenant(
+    settings: &Settings,
+    tenant_id: &str,
+    tenant: &TenantSettings,
+    events: &EventPublisher,
+    weights: ScoringWeights,
+) -> std::io::Result<AppState> {
+    let fail = |what: &str, e: String| {
+        error!("Failed to connect {} for tenant {}: {}", what, tenant_id, e);
+        std::io::Error::other(e)
+    };
+
+    let appwrite: Arc<dyn ProfileStore> =
+        connect_appwrite(settings, tenant.collection.as_ref().unwrap_or(&settings.collection));
+
+    let cache: Arc<dyn Cache> = Arc::new(
+        CacheManager::new(
+            &settings.cache.redis_url,
+            settings.cache.l1_cache_size.unwrap_or(1000),
+            settings.cache.ttl_secs.unwrap_or(300),
+        )
+        .await
+        .map_err(|e| fail("Redis", e.to_string()))?
+        .with_ttls(settings.cache.ttls.clone())
+        .with_key_prefix(tenant.cache_prefix(tenant_id)),
+    );
+
+    let database = DatabaseSettings {
+        schema: tenant.database_schema.clone().or_else(|| settings.database.schema.clone()),
+        ..settings.database.clone()
+    };
+    let postgres: Arc<dyn SeenStore> = Arc::new(
+        PostgresClient::from_settings(&database)
+            .await
+            .map_err(|e| fail("PostgreSQL", e.to_string()))?,
+    );
+
+    let pruner = Arc::new(SeenPruner::new(postgres.clone(), settings.maintenance.clone()));
+    pruner.clone().spawn();
+    let reconciler = Arc::new(EventReconciler::new(postgres.clone(), appwrite.clone(), settings.maintenance.clone()));
+    reconciler.clone().spawn();
+    let expirer = Arc::new(MatchExpirer::new(postgres.clone(), settings.maintenance.clone()));
+    expirer.clone().spawn();
+
+    let vector_settings = VectorSettings {
+        collection: tenant.vector_collection.clone().unwrap_or_else(|| settings.vector.collection.clone()),
+        ..settings.vector.clone()
+    };
+    let vector = connect_vector_index(&vector_settings, &settings.database.url)
+        .await
+        .map_err(|e| fail("vector index", e.to_string()))?;
+
+    let matcher = settings.matcher(tenant.weights.apply(weights));
+    let feed = Arc::new(
+        FeedGenerator::new(appwrite.clone(), cache.clone(), postgres.clone(), matcher, settings.matching.clone())
+            .with_tiers(settings.tiers.clone())
+            .with_vector_index(vector, settings.vector.top_n)
+            .with_rules(settings.rules.clone())
+            .with_markets(settings.markets.clone())
+            .with_read_through(settings.cache.read_through.clone()),
+    );
+
+    Ok(AppState {
+        appwrite,
+        cache,
+        postgres,
+        pruner,
+        reconciler,
+        expirer,
+        admin_token: tenant.admin_token.clone().or_else(|| settings.admin.token.clone()),
+        feed,
+        events: Arc::new(events.for_tenant(tenant_id)),
+        serve_pregenerated: settings.feed.serve_pregenerated,
+        tiers: settings.tiers.clone(),
+        viewers: settings.viewers.clone(),
+        event_time: settings.event_time.clone(),
+        event_dedup: settings.event_dedup.clone(),
+        find_limiter: Arc::new(ConcurrencyLimiter::new(
+            tenant.find_concurrency.as_ref().unwrap_or(&settings.server.find_concurrency),
+        )),
+        profile: settings.profile,
+    })
 }
 
-/// Handle query payload errors
-pub fn handle_query_payload_error(err: error::QueryPayloadError, _req: &actix_web::HttpRequest) -> actix_web::Error {
-    JsonError {
-        error: "invalid_query".to_string(),
-        message: format!("Invalid query: {}", err),
-        status_code: 400,
+/// Feed settings of a named tenant's worker: its queue is the top-level
+/// one under the tenant's cache prefix (e.g. `acme:feed:queue`)
+fn tenant_feed_settings(settings: &Settings, tenant_id: &str, tenant: &TenantSettings) -> FeedSettings {
+    FeedSettings {
+        queue_key: format!("{}{}", tenant.cache_prefix(tenant_id), settings.feed.queue_key),
+        ..settings.feed.clone()
     }
-    .into()
 }
 
 #[actix_web::main]
@@ -69,6 +161,7 @@ async fn main() -> std::io::Result<()> {
     let log_format = std::env::var("LOG_FORMAT").unwrap_or_else(|_| "json".to_string());
 
     let subscriber = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::new(&log_level))
         .with_target(false)
         .with_level(true);
 
@@ -81,115 +174,320 @@ async fn main() -> std::io::Result<()> {
     info!("Starting Lume Algo matching service...");
 
     // Load configuration
-    let settings = Settings::load().unwrap_or_else(|e| {
+    let mut settings = Settings::load_with_secrets().await.map_err(|e| {
         error!("Failed to load configuration: {}", e);
-        panic!("Configuration error: {}", e);
-    });
-
-    info!("Configuration loaded successfully");
-
-    // Initialize Appwrite client
-    let appwrite_collections = AppwriteCollections {
-        user_profiles: settings.collection.user_profiles,
-        user_preferences: settings.collection.user_preferences,
-        match_events: settings.collection.match_events,
-        user_matches: settings.collection.user_matches,
-    };
+        std::io::Error::other(e.to_string())
+    })?;
+    settings.dev.enabled |= std::env::args().any(|arg| arg == "--dev");
 
-    let appwrite = Arc::new(AppwriteClient::new(
-        settings.appwrite.endpoint,
-        settings.appwrite.api_key,
-        settings.appwrite.project_id,
-        settings.appwrite.database_id,
-        appwrite_collections,
-    ));
+    // Report every invalid setting at once rather than failing on first use
+    settings.validate().map_err(|e| {
+        error!("Invalid configuration, {}", e);
+        std::io::Error::other(e.to_string())
+    })?;
 
-    info!("Appwrite client initialized");
+    match settings.profile {
+        Some(profile) => info!("Configuration loaded successfully (profile: {})", profile),
+        None => info!("Configuration loaded successfully"),
+    }
 
-    // Initialize cache manager (optional - app can work without it)
+    // Cache sizing, shared by the Redis-backed and in-memory caches
     let cache_ttl = settings.cache.ttl_secs.unwrap_or(300);
     let l1_cache_size = settings.cache.l1_cache_size.unwrap_or(1000);
 
-    let cache = match CacheManager::new(
-        &settings.cache.redis_url,
-        l1_cache_size,
-        cache_ttl,
-    ).await {
-        Ok(c) => {
-            info!("Cache manager initialized (L1: {} entries, TTL: {}s)", l1_cache_size, cache_ttl);
-            Arc::new(c)
-        }
-        Err(e) => {
-            error!("Failed to connect to Redis ({}), running without cache", e);
-            // Create a dummy cache manager that fails gracefully
-            // For now, we'll continue without cache - seen profiles still work via PostgreSQL
-            error!("Caching disabled - seen profiles will still be tracked via PostgreSQL");
-            return Err(std::io::Error::new(std::io::ErrorKind::Other, "Redis connection required"));
-        }
-    };
+    // Dev profile: SQLite, in-memory cache and CSV-seeded profiles, no external services
+    let dev_mode = settings.dev.enabled;
 
-    // Initialize PostgreSQL client
-    let db_max_conn = settings.database.max_connections.unwrap_or(10);
-    let db_min_conn = settings.database.min_connections.unwrap_or(1);
-
-    let postgres = Arc::new(
-        PostgresClient::from_settings(
-            &settings.database.url,
-            Some(db_max_conn),
-            Some(db_min_conn),
-            settings.database.acquire_timeout_secs,
-            settings.database.idle_timeout_secs,
+    let (appwrite, cache, postgres): (Arc<dyn ProfileStore>, Arc<dyn Cache>, Arc<dyn SeenStore>) = if dev_mode {
+        info!("Running with the dev profile (SQLite: {})", settings.dev.sqlite_url);
+
+        let profiles = InMemoryProfileStore::from_csv(&settings.dev.profiles_csv, &settings.dev.preferences_csv)
+            .map_err(|e| {
+                error!("Failed to load dev seed data (run generate-test-data first): {}", e);
+                std::io::Error::other(e.to_string())
+            })?;
+        info!("Loaded {} dev profiles", profiles.profile_count());
+
+        let seen = SqliteSeenStore::connect(&settings.dev.sqlite_url).await.map_err(|e| {
+            error!("Failed to open SQLite database: {}", e);
+            std::io::Error::other(e.to_string())
+        })?;
+
+        (
+            Arc::new(profiles),
+            Arc::new(CacheManager::in_memory(l1_cache_size, cache_ttl).with_ttls(settings.cache.ttls.clone())),
+            Arc::new(seen),
         )
-        .await
-        .unwrap_or_else(|e| {
-            error!("Failed to connect to PostgreSQL: {}", e);
-            panic!("PostgreSQL connection error: {}", e);
-        }),
-    );
+    } else {
+        // Initialize Appwrite client
+        let client = connect_appwrite(&settings, &settings.collection);
+        let appwrite: Arc<dyn ProfileStore> = client.clone();
+
+        info!(
+            "Appwrite client initialized ({} fallback endpoints)",
+            settings.appwrite.fallback_endpoints.len()
+        );
+
+        let appwrite: Arc<dyn ProfileStore> = if settings.realtime.enabled {
+            let mirror = Arc::new(ProfileMirror::new(&settings.realtime.markets, settings.markets.city_radius_km));
+            if RealtimeSubscriber::new(client.clone(), mirror.clone(), settings.realtime.clone()).spawn().is_some() {
+                info!("Profile mirror enabled for markets {:?}", settings.realtime.markets);
+            } else {
+                warn!("realtime.enabled is set but this build lacks the `realtime` feature; reading Appwrite directly");
+            }
+            Arc::new(MirroredProfileStore::new(appwrite, mirror))
+        } else {
+            appwrite
+        };
+
+        let cache = match CacheManager::new(
+            &settings.cache.redis_url,
+            l1_cache_size,
+            cache_ttl,
+        ).await {
+            Ok(c) => {
+                info!("Cache manager initialized (L1: {} entries, TTL: {}s)", l1_cache_size, cache_ttl);
+                Arc::new(c.with_ttls(settings.cache.ttls.clone()))
+            }
+            Err(e) => {
+                error!("Failed to connect to Redis ({}), running without cache", e);
+                // Create a dummy cache manager that fails gracefully
+                // For now, we'll continue without cache - seen profiles still work via PostgreSQL
+                error!("Caching disabled - seen profiles will still be tracked via PostgreSQL");
+                return Err(std::io::Error::other("Redis connection required"));
+            }
+        };
+
+        // Initialize PostgreSQL client
+        let db_max_conn = settings.database.max_connections.unwrap_or(10);
+
+        let postgres = Arc::new(
+            PostgresClient::from_settings(&settings.database)
+                .await
+                .unwrap_or_else(|e| {
+                    error!("Failed to connect to PostgreSQL: {}", e);
+                    panic!("PostgreSQL connection error: {}", e);
+                }),
+        );
+
+        info!("PostgreSQL client initialized (max: {} connections)", db_max_conn);
+
+        Arc::new(ProfileSync::new(client, postgres.clone(), settings.profile_sync.clone())).spawn();
+        let appwrite: Arc<dyn ProfileStore> = if settings.profile_sync.read_from_mirror {
+            info!("Candidate queries read the PostgreSQL profile mirror (max lag: {}s)", settings.profile_sync.max_lag_secs);
+            Arc::new(SyncedProfileStore::new(appwrite, postgres.clone(), &settings.profile_sync))
+        } else {
+            appwrite
+        };
+
+        (appwrite, cache, postgres)
+    };
 
-    info!("PostgreSQL client initialized (max: {} connections)", db_max_conn);
+    // Start seen-profiles retention job
+    let pruner = Arc::new(SeenPruner::new(postgres.clone(), settings.maintenance.clone()));
+    pruner.clone().spawn();
+
+    // Start PostgreSQL/Appwrite event reconciliation job
+    let reconciler = Arc::new(EventReconciler::new(postgres.clone(), appwrite.clone(), settings.maintenance.clone()));
+    reconciler.clone().spawn();
+
+    // Start stale match expiry job (only when match_expiry_days is set)
+    let expirer = Arc::new(MatchExpirer::new(postgres.clone(), settings.maintenance.clone()));
+    expirer.clone().spawn();
 
     // Initialize matcher with configured weights
-    let weights = ScoringWeights {
-        distance: settings.scoring.weights.distance,
-        age: settings.scoring.weights.age,
-        sports: settings.scoring.weights.sports,
-        verified: settings.scoring.weights.verified,
-        height: settings.scoring.weights.height,
+    let weights = ScoringWeights::from(&settings.scoring.weights);
+    let matcher = settings.matcher(weights);
+
+    info!(
+        "Matcher initialized with weights: {:?}, distance formula: {:?}, exploration: {}, freshness: {}",
+        weights,
+        settings.scoring.distance_formula,
+        settings.matching.exploration_epsilon,
+        settings.matching.freshness_fraction
+    );
+
+    let shadow = ShadowEvaluator::from_settings(&matcher, &settings.scoring.shadow);
+
+    // Semantic retrieval needs an external index, so the dev profile skips it
+    let vector = if dev_mode {
+        None
+    } else {
+        connect_vector_index(&settings.vector, &settings.database.url).await.map_err(|e| {
+            error!("Failed to connect vector index: {}", e);
+            std::io::Error::other(e.to_string())
+        })?
     };
 
-    let matcher = Matcher::new(weights);
+    let feed = Arc::new(
+        FeedGenerator::new(appwrite.clone(), cache.clone(), postgres.clone(), matcher, settings.matching.clone())
+            .with_shadow(shadow)
+            .with_tiers(settings.tiers.clone())
+            .with_vector_index(vector, settings.vector.top_n)
+            .with_rules(settings.rules.clone())
+            .with_markets(settings.markets.clone())
+            .with_read_through(settings.cache.read_through.clone()),
+    );
+
+    // Warm up dependencies before taking any traffic
+    if settings.warmup.enabled {
+        let min_connections = settings.database.min_connections.unwrap_or(1);
+        let report = warm_up(postgres.as_ref(), cache.as_ref(), appwrite.as_ref(), &settings.warmup, min_connections)
+            .await
+            .map_err(|e| {
+                error!("Warm-up failed, not accepting traffic: {}", e);
+                std::io::Error::other(e.to_string())
+            })?;
+
+        for (hub, bbox) in &report.hub_boxes {
+            info!("Hub {} search box: {:?}", hub, bbox);
+        }
+        info!(
+            "Warm-up finished in {}ms ({} PostgreSQL connections, Appwrite checked: {}, {} hubs)",
+            report.duration_ms,
+            report.postgres_connections,
+            report.appwrite_checked,
+            report.hub_boxes.len()
+        );
+    }
+
+    // Worker mode pre-generates feeds from the Redis queues instead of serving HTTP
+    let worker_mode = settings.feed.worker_enabled || std::env::args().any(|arg| arg == "--worker");
 
-    info!("Matcher initialized with weights: {:?}", weights);
+    // Workers record no events, so they don't need the event stream
+    let events = if worker_mode {
+        Arc::new(EventPublisher::disabled())
+    } else {
+        Arc::new(EventPublisher::connect(&settings.events).await.map_err(|e| {
+            error!("Failed to start event publisher: {}", e);
+            std::io::Error::other(e.to_string())
+        })?)
+    };
 
     // Build application state
     let app_state = AppState {
         appwrite,
         cache,
         postgres,
-        matcher,
+        pruner,
+        reconciler,
+        expirer,
+        admin_token: settings.admin.token.clone(),
+        feed,
+        events,
+        serve_pregenerated: settings.feed.serve_pregenerated,
+        tiers: settings.tiers.clone(),
+        viewers: settings.viewers.clone(),
+        event_time: settings.event_time.clone(),
+        event_dedup: settings.event_dedup.clone(),
+        find_limiter: Arc::new(ConcurrencyLimiter::new(&settings.server.find_concurrency)),
+        profile: settings.profile,
+    };
+
+    // Named tenants get their own collections, schema, cache prefix and weights
+    let mut tenants = TenantRegistry::new(app_state.clone());
+    let mut workers = vec![FeedWorker::new(app_state.feed.clone(), app_state.cache.clone(), settings.feed.clone())];
+    if dev_mode && !settings.tenants.is_empty() {
+        warn!("Ignoring {} configured tenants in the dev profile", settings.tenants.len());
+    } else {
+        for (tenant_id, tenant) in &settings.tenants {
+            let state = build_tenant(&settings, tenant_id, tenant, &events, weights).await?;
+            workers.push(FeedWorker::new(
+                state.feed.clone(),
+                state.cache.clone(),
+                tenant_feed_settings(&settings, tenant_id, tenant),
+            ));
+            tenants = tenants.with_tenant(tenant_id.clone(), state);
+            info!("Tenant {} initialized", tenant_id);
+        }
+    }
+
+    if worker_mode {
+        for worker in workers {
+            Arc::new(worker)
+                .spawn(&settings.cache.redis_url)
+                .await
+                .map_err(|e| std::io::Error::other(format!("Failed to start feed worker: {}", e)))?;
+        }
+
+        tokio::signal::ctrl_c().await?;
+        info!("Feed workers shutting down");
+        return Ok(());
+    }
+
+    // HMAC signatures for server-to-server calls
+    let signing = match SignatureVerifier::from_settings(&settings.signing) {
+        Some(verifier) => {
+            let verifier = verifier
+                .with_nonce_store(app_state.cache.clone())
+                .with_body_limit(settings.server.payload_limits.largest());
+            info!("Request signing required on {:?}", settings.signing.routes);
+            Some(web::Data::new(verifier))
+        }
+        None if settings.signing.enabled => {
+            error!("signing.enabled is set but signing.secret is empty");
+            return Err(std::io::Error::other("Request signing requires a secret"));
+        }
+        None => None,
     };
 
     // Configure HTTP server
     let host = settings.server.host.clone();
     let port = settings.server.port;
     let workers = settings.server.workers.unwrap_or(4);
+    let compression = settings.server.compression.clone();
+    let payload_limits = settings.server.payload_limits;
+    let server = settings.server.clone();
+    let keep_alive = match server.keep_alive_secs {
+        0 => KeepAlive::Disabled,
+        secs => KeepAlive::Timeout(Duration::from_secs(secs)),
+    };
 
     info!("Starting HTTP server on {}:{}", host, port);
+    info!(
+        "Server tuning: keep-alive {}s, request timeout {}ms, {} connections/worker, backlog {}, shutdown timeout {}s",
+        server.keep_alive_secs,
+        server.client_request_timeout_ms,
+        server.max_connections,
+        server.backlog,
+        server.shutdown_timeout_secs
+    );
 
     HttpServer::new(move || {
         let cors = Cors::permissive();
 
-        App::new()
-            .app_data(web::Data::new(app_state.clone()))
-            .app_data(web::JsonConfig::default().error_handler(handle_json_payload_error))
+        let app = App::new().app_data(web::Data::new(tenants.clone()));
+        let app = match &signing {
+            Some(verifier) => app.app_data(verifier.clone()),
+            None => app,
+        };
+
+        app
+            .app_data(
+                web::JsonConfig::default()
+                    .limit(payload_limits.default_bytes)
+                    .error_handler(handle_json_payload_error),
+            )
             .app_data(web::QueryConfig::default().error_handler(handle_query_payload_error))
             .wrap(cors)
             .wrap(middleware::Logger::default())
-            .wrap(middleware::Compress::default())
-            .configure(routes::configure_routes)
+            .wrap(middleware::Condition::new(compression.enabled, middleware::Compress::default()))
+            .wrap_fn(move |mut req, srv| {
+                if !compression.brotli {
+                    strip_brotli(req.headers_mut());
+                }
+                srv.call(req)
+            })
+            .configure(|cfg| routes::configure_routes(cfg, payload_limits))
     })
     .workers(workers)
+    .keep_alive(keep_alive)
+    .client_request_timeout(Duration::from_millis(server.client_request_timeout_ms))
+    .client_disconnect_timeout(Duration::from_millis(server.client_disconnect_timeout_ms))
+    .max_connections(server.max_connections)
+    .max_connection_rate(server.max_connection_rate)
+    .backlog(server.backlog)
+    .shutdown_timeout(server.shutdown_timeout_secs)
     .bind((host, port))?
     .run()
     .await
@@ -1,21 +1,43 @@
-use serde::{Deserialize, Serialize};
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
+
+/// Current schema version of profile and preferences documents
+///
+/// - v1: `heightCm`, `minHeightCm` and `maxHeightCm` required, 0 when
+///   unknown; no interests
+/// - v2: heights optional, `interests` and `preferredInterests` added
+///
+/// Documents without `schemaVersion` are v1. Older versions are upgraded
+/// while deserializing, so a parsed value is always the current version;
+/// newer ones are rejected rather than misread.
+pub const SCHEMA_VERSION: u8 = 2;
+
+fn legacy_schema_version() -> u8 { 1 }
 
 /// User profile with demographic and location data
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(remote = "Self")]
 pub struct UserProfile {
     #[serde(rename = "userId")]
     pub user_id: String,
     pub name: String,
     pub age: u8,
-    #[serde(rename = "heightCm")]
-    pub height_cm: u16,
+    /// Not every profile has a height; missing ones pass height filters and
+    /// are scored without the height weight
+    #[serde(rename = "heightCm", default)]
+    pub height_cm: Option<u16>,
     #[serde(rename = "hairColor")]
     pub hair_color: String,
     pub gender: String,
     pub latitude: f64,
     pub longitude: f64,
+    /// Legacy verified flag; `verification` takes precedence when set
     #[serde(rename = "isVerified", default)]
     pub is_verified: Option<bool>,
+    /// How the profile was verified (None falls back to `isVerified`)
+    #[serde(default)]
+    pub verification: Option<Verification>,
     #[serde(rename = "isActive", default = "default_true")]
     pub is_active: bool,
     #[serde(rename = "isTimeout", default)]
@@ -26,26 +48,174 @@ pub struct UserProfile {
     pub description: Option<String>,
     #[serde(rename = "sportsPreferences", default)]
     pub sports_preferences: Vec<String>,
+    /// Free-form interests (schema v2)
+    #[serde(default)]
+    pub interests: Vec<String>,
     #[serde(default)]
     pub created_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// IANA time zone name (e.g. "Europe/Berlin") used for activity-window scoring
+    #[serde(default)]
+    pub timezone: Option<String>,
+    #[serde(rename = "isPremium", default)]
+    pub is_premium: Option<bool>,
+    /// Subscription tier (None falls back to `isPremium`)
+    #[serde(default)]
+    pub tier: Option<Tier>,
+    /// Bio/photo embedding from the external profile model
+    #[serde(default)]
+    pub embedding: Option<Vec<f32>>,
+    /// White-label deployment the profile belongs to
+    #[serde(rename = "tenantId", default)]
+    pub tenant_id: Option<String>,
+    /// Market (e.g. country or region code) the profile signed up in;
+    /// tagged by the reverse geocoder when missing
+    #[serde(default)]
+    pub market: Option<String>,
+    /// Nearest known city (e.g. "de-ber"), tagged by the reverse geocoder
+    #[serde(default)]
+    pub city: Option<String>,
+    /// Snoozed (incognito) users are hidden from everyone's candidates
+    #[serde(rename = "isSnoozed", default)]
+    pub is_snoozed: Option<bool>,
+    /// Genders the user said they're interested in during onboarding
+    #[serde(rename = "interestedIn", default)]
+    pub interested_in: Vec<String>,
+    /// Spoken languages as ISO 639-1 codes (e.g. "de", "en")
+    #[serde(default)]
+    pub languages: Vec<String>,
+    #[serde(rename = "relationshipGoal", default)]
+    pub relationship_goal: Option<RelationshipGoal>,
+    /// Optional attributes (e.g. "smoking", "education") matched by the
+    /// configured attribute scorers
+    #[serde(default)]
+    pub attributes: HashMap<String, serde_json::Value>,
+    /// Appwrite `$updatedAt` of the profile document, versioning cache keys
+    #[serde(default)]
+    pub updated_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Always [`SCHEMA_VERSION`] once parsed
+    #[serde(rename = "schemaVersion", default = "legacy_schema_version")]
+    pub schema_version: u8,
+}
+
+impl<'de> Deserialize<'de> for UserProfile {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        UserProfile::deserialize(deserializer)?.upgrade().map_err(D::Error::custom)
+    }
+}
+
+impl Serialize for UserProfile {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        UserProfile::serialize(self, serializer)
+    }
 }
 
 impl UserProfile {
-    /// Helper to get is_verified as a bool, defaulting to false
+    /// Bring a document of an older schema version up to [`SCHEMA_VERSION`]
+    fn upgrade(mut self) -> Result<Self, String> {
+        match self.schema_version {
+            // v1 stored an unknown height as 0
+            1 => self.height_cm = self.height_cm.filter(|&height| height > 0),
+            SCHEMA_VERSION => {}
+            version => return Err(format!("unsupported schemaVersion {}", version)),
+        }
+        self.schema_version = SCHEMA_VERSION;
+        Ok(self)
+    }
+
+    /// Whether the profile lists any of `languages` (case-insensitive)
+    pub fn speaks_any(&self, languages: &[String]) -> bool {
+        self.languages
+            .iter()
+            .any(|spoken| languages.iter().any(|language| spoken.eq_ignore_ascii_case(language)))
+    }
+
+    /// Verification tier; a legacy `isVerified` profile counts as photo-verified
+    pub fn verification(&self) -> Verification {
+        match self.verification {
+            Some(verification) => verification,
+            None if self.is_verified.unwrap_or(false) => Verification::Photo,
+            None => Verification::Unverified,
+        }
+    }
+
+    /// Verified by any method
     pub fn verified(&self) -> bool {
-        self.is_verified.unwrap_or(false)
+        self.verification() != Verification::Unverified
     }
 
     /// Helper to get is_timeout as a bool, defaulting to false
     pub fn timeout(&self) -> bool {
         self.is_timeout.unwrap_or(false)
     }
+
+    /// Helper to get is_premium as a bool, defaulting to false
+    pub fn premium(&self) -> bool {
+        self.is_premium.unwrap_or(false)
+    }
+
+    /// Helper to get is_snoozed as a bool, defaulting to false
+    pub fn snoozed(&self) -> bool {
+        self.is_snoozed.unwrap_or(false)
+    }
+
+    /// Subscription tier, falling back to the legacy `isPremium` flag
+    pub fn tier(&self) -> Tier {
+        match self.tier {
+            Some(tier) => tier,
+            None if self.premium() => Tier::Premium,
+            None => Tier::Free,
+        }
+    }
+}
+
+/// Subscription tier
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Tier {
+    #[default]
+    Free,
+    Plus,
+    Premium,
+}
+
+/// How a profile was verified, weakest first
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Verification {
+    #[default]
+    Unverified,
+    /// Selfie matched against the profile photos
+    Photo,
+    /// Government ID checked
+    Id,
+}
+
+impl Verification {
+    /// Share (0-1) of the verified weight this tier earns
+    pub fn score(self) -> f64 {
+        match self {
+            Verification::Unverified => 0.0,
+            Verification::Photo => 0.6,
+            Verification::Id => 1.0,
+        }
+    }
+}
+
+/// What a user is looking for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RelationshipGoal {
+    Casual,
+    LongTerm,
+    Friends,
+    Unsure,
 }
 
 fn default_true() -> bool { true }
 
 /// User matching preferences
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(remote = "Self")]
 pub struct UserPreferences {
     #[serde(rename = "userId")]
     pub user_id: String,
@@ -55,20 +225,131 @@ pub struct UserPreferences {
     pub min_age: u8,
     #[serde(rename = "maxAge")]
     pub max_age: u8,
-    #[serde(rename = "minHeightCm")]
+    /// Optional since schema v2 (unset: no lower bound)
+    #[serde(rename = "minHeightCm", default)]
     pub min_height_cm: u16,
-    #[serde(rename = "maxHeightCm")]
+    /// Optional since schema v2 (unset: no upper bound)
+    #[serde(rename = "maxHeightCm", default = "open_max_height")]
     pub max_height_cm: u16,
     #[serde(rename = "preferredHairColors")]
     pub preferred_hair_colors: Vec<String>,
     #[serde(rename = "preferredSports")]
     pub preferred_sports: Vec<String>,
+    /// Interests to favor (schema v2)
+    #[serde(rename = "preferredInterests", default)]
+    pub preferred_interests: Vec<String>,
     #[serde(rename = "maxDistanceKm")]
     pub max_distance_km: u16,
     #[serde(default)]
     pub latitude: f64,
     #[serde(default)]
     pub longitude: f64,
+    /// Skip candidates without photos (None falls back to matching.require_photos)
+    #[serde(rename = "requirePhotos", default)]
+    pub require_photos: Option<bool>,
+    /// The searcher's profile embedding, filled in from the profile like the search location
+    #[serde(default)]
+    pub embedding: Option<Vec<f32>>,
+    /// Languages a match should speak (empty: the searcher's own languages)
+    #[serde(rename = "preferredLanguages", default)]
+    pub preferred_languages: Vec<String>,
+    /// Skip candidates who speak none of the preferred languages
+    #[serde(rename = "requireSharedLanguage", default)]
+    pub require_shared_language: bool,
+    /// Skip candidates who aren't photo- or ID-verified
+    #[serde(rename = "verifiedOnly", default)]
+    pub verified_only: bool,
+    /// The goal candidates are matched against (None: the searcher's own)
+    #[serde(rename = "relationshipGoal", default)]
+    pub relationship_goal: Option<RelationshipGoal>,
+    /// Wanted attribute values by name; attributes not set here use the
+    /// searcher's own value
+    #[serde(default)]
+    pub attributes: HashMap<String, serde_json::Value>,
+    /// Appwrite `$updatedAt` of the preferences document (None for defaults)
+    #[serde(default)]
+    pub updated_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Always [`SCHEMA_VERSION`] once parsed
+    #[serde(rename = "schemaVersion", default = "legacy_schema_version")]
+    pub schema_version: u8,
+}
+
+fn open_max_height() -> u16 { u16::MAX }
+
+impl<'de> Deserialize<'de> for UserPreferences {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        UserPreferences::deserialize(deserializer)?.upgrade().map_err(D::Error::custom)
+    }
+}
+
+impl Serialize for UserPreferences {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        UserPreferences::serialize(self, serializer)
+    }
+}
+
+impl UserPreferences {
+    /// Bring a document of an older schema version up to [`SCHEMA_VERSION`]
+    fn upgrade(mut self) -> Result<Self, String> {
+        match self.schema_version {
+            // v1 stored "no maximum height" as 0
+            1 if self.max_height_cm == 0 => self.max_height_cm = open_max_height(),
+            1 | SCHEMA_VERSION => {}
+            version => return Err(format!("unsupported schemaVersion {}", version)),
+        }
+        self.schema_version = SCHEMA_VERSION;
+        Ok(self)
+    }
+
+    /// Synthesized preferences for a user without a preferences document
+    ///
+    /// Genders come from the onboarding answer (any if unanswered), ages are
+    /// within `age_spread` years of the user (never below 18), any height or
+    /// hair color within `max_distance_km` is accepted, and the user's own
+    /// languages and attributes are preferred.
+    pub fn defaults_for(profile: &UserProfile, age_spread: u8, max_distance_km: u16) -> Self {
+        Self {
+            user_id: profile.user_id.clone(),
+            preferred_genders: profile.interested_in.clone(),
+            min_age: profile.age.saturating_sub(age_spread).max(18),
+            max_age: profile.age.saturating_add(age_spread).max(18),
+            min_height_cm: 0,
+            max_height_cm: u16::MAX,
+            preferred_hair_colors: vec![],
+            preferred_sports: profile.sports_preferences.clone(),
+            preferred_interests: profile.interests.clone(),
+            max_distance_km,
+            latitude: profile.latitude,
+            longitude: profile.longitude,
+            require_photos: None,
+            embedding: profile.embedding.clone(),
+            preferred_languages: profile.languages.clone(),
+            require_shared_language: false,
+            verified_only: false,
+            relationship_goal: profile.relationship_goal,
+            attributes: profile.attributes.clone(),
+            updated_at: None,
+            schema_version: SCHEMA_VERSION,
+        }
+    }
+
+    /// Whether a usable search location is set
+    ///
+    /// Missing coordinates deserialize to 0.0/0.0 (Null Island), which is
+    /// treated as "no location".
+    pub fn has_location(&self) -> bool {
+        self.latitude.is_finite()
+            && self.longitude.is_finite()
+            && !(self.latitude == 0.0 && self.longitude == 0.0)
+    }
+
+    /// Whether the height range has an upper bound to score against
+    ///
+    /// Open ranges come from defaults, v1 `maxHeightCm: 0` and unset v2
+    /// preferences.
+    pub fn has_max_height(&self) -> bool {
+        self.max_height_cm != open_max_height()
+    }
 }
 
 /// Match event for tracking user interactions
@@ -105,8 +386,8 @@ pub struct ScoredMatch {
     pub user_id: String,
     pub name: String,
     pub age: u8,
-    #[serde(rename = "heightCm")]
-    pub height_cm: u16,
+    #[serde(rename = "heightCm", default)]
+    pub height_cm: Option<u16>,
     #[serde(rename = "hairColor")]
     pub hair_color: String,
     pub gender: String,
@@ -114,17 +395,93 @@ pub struct ScoredMatch {
     pub distance_km: f64,
     #[serde(rename = "matchScore")]
     pub match_score: f64,
+    /// `match_score` before score calibration, for fitting the calibration
+    #[serde(skip)]
+    pub raw_score: f64,
     #[serde(rename = "sharedSports")]
     pub shared_sports: Vec<String>,
     #[serde(rename = "isVerified")]
     pub is_verified: bool,
+    #[serde(default)]
+    pub verification: Verification,
     #[serde(rename = "imageFileIds")]
     pub image_file_ids: Vec<String>,
     pub description: Option<String>,
+    /// The candidate's market and nearest known city
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub market: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub city: Option<String>,
+    /// Randomly sampled for exploration instead of ranked by score
+    #[serde(default)]
+    pub exploratory: bool,
+    /// A recently joined profile mixed in by the freshness policy
+    #[serde(default)]
+    pub fresh: bool,
+    /// Per-factor points behind `matchScore` (only for tiers that may see it)
+    #[serde(rename = "scoreBreakdown", default, skip_serializing_if = "Option::is_none")]
+    pub score_breakdown: Option<ScoreBreakdown>,
+}
+
+/// A user's top pick of the day, pinned until `expires_at`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TopPick {
+    #[serde(rename = "match")]
+    pub candidate: ScoredMatch,
+    /// Geometric mean of the user's score for the candidate and the
+    /// candidate's score for the user
+    #[serde(rename = "reciprocalScore")]
+    pub reciprocal_score: f64,
+    /// Day in the user's time zone the pick is for
+    pub day: chrono::NaiveDate,
+    /// Next local midnight
+    #[serde(rename = "expiresAt")]
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Weighted points each factor contributed to a match score
+///
+/// The factors sum to the score before clamping to 0-100.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ScoreBreakdown {
+    pub distance: f64,
+    pub age: f64,
+    pub sports: f64,
+    pub verified: f64,
+    pub height: f64,
+    pub activity: f64,
+    pub embedding: f64,
+    #[serde(default)]
+    pub language: f64,
+    #[serde(rename = "relationshipGoal", default)]
+    pub relationship_goal: f64,
+    /// Sum of the configured attribute scorers
+    #[serde(default)]
+    pub attributes: f64,
+    /// Ranking boost for the candidate's subscription tier
+    #[serde(rename = "tierBoost")]
+    pub tier_boost: f64,
+}
+
+impl ScoreBreakdown {
+    /// The match score (0-100) these points add up to
+    pub fn total(&self) -> f64 {
+        let factors = self.distance
+            + self.age
+            + self.sports
+            + self.verified
+            + self.height
+            + self.activity
+            + self.embedding
+            + self.language
+            + self.relationship_goal
+            + self.attributes;
+        (factors + self.tier_boost).clamp(0.0, 100.0)
+    }
 }
 
 /// Geospatial bounding box
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
 pub struct BoundingBox {
     pub min_lat: f64,
     pub max_lat: f64,
@@ -132,6 +489,18 @@ pub struct BoundingBox {
     pub max_lon: f64,
 }
 
+impl BoundingBox {
+    /// A box covering the whole globe (no geo pre-filter)
+    pub fn world() -> Self {
+        Self {
+            min_lat: -90.0,
+            max_lat: 90.0,
+            min_lon: -180.0,
+            max_lon: 180.0,
+        }
+    }
+}
+
 /// Candidate query parameters
 #[derive(Debug, Clone)]
 pub struct CandidateQuery {
@@ -141,18 +510,78 @@ pub struct CandidateQuery {
     pub max_age: u8,
     pub min_height_cm: u16,
     pub max_height_cm: u16,
-    pub exclude_user_ids: Vec<String>,
     pub limit: usize,
 }
 
 /// Scoring weights
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize)]
 pub struct ScoringWeights {
     pub distance: f64,
     pub age: f64,
     pub sports: f64,
     pub verified: f64,
     pub height: f64,
+    /// Likely-awake-now bonus based on the candidate's local hour (off by default)
+    pub activity: f64,
+    /// Cosine similarity of the profile embeddings (off by default)
+    pub embedding: f64,
+    /// Speaks one of the preferred languages (off by default)
+    pub language: f64,
+    /// Relationship-goal compatibility, applied by the matcher (off by default)
+    pub relationship_goal: f64,
+}
+
+impl ScoringWeights {
+    /// Weights for location-less (global) matching
+    ///
+    /// Zeroes the distance weight and scales the others up so they keep
+    /// the same total.
+    pub fn without_distance(&self) -> Self {
+        self.redistribute(Self { distance: 0.0, ..*self })
+    }
+
+    /// Weights for scoring `profile` against `preferences`
+    ///
+    /// Height is only scored for a profile with a height against a range
+    /// with an upper bound. Otherwise the height weight is zeroed and the
+    /// others scaled up the same way, so neither a missing height nor an
+    /// open range is a penalty or a bonus.
+    pub fn for_profile(&self, profile: &UserProfile, preferences: &UserPreferences) -> Self {
+        if self.height == 0.0 || (profile.height_cm.is_some() && preferences.has_max_height()) {
+            return *self;
+        }
+        self.redistribute(Self { height: 0.0, ..*self })
+    }
+
+    fn sum(&self) -> f64 {
+        self.distance
+            + self.age
+            + self.sports
+            + self.verified
+            + self.height
+            + self.activity
+            + self.embedding
+            + self.language
+            + self.relationship_goal
+    }
+
+    /// `kept` scaled up to this set's total
+    fn redistribute(&self, kept: Self) -> Self {
+        let remaining = kept.sum();
+        let scale = if remaining > 0.0 { self.sum() / remaining } else { 0.0 };
+
+        Self {
+            distance: kept.distance * scale,
+            age: kept.age * scale,
+            sports: kept.sports * scale,
+            verified: kept.verified * scale,
+            height: kept.height * scale,
+            activity: kept.activity * scale,
+            embedding: kept.embedding * scale,
+            language: kept.language * scale,
+            relationship_goal: kept.relationship_goal * scale,
+        }
+    }
 }
 
 impl Default for ScoringWeights {
@@ -163,6 +592,10 @@ impl Default for ScoringWeights {
             sports: 0.25,
             verified: 0.10,
             height: 0.10,
+            activity: 0.0,
+            embedding: 0.0,
+            language: 0.0,
+            relationship_goal: 0.0,
         }
     }
 }
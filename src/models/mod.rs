@@ -3,6 +3,6 @@ pub mod domain;
 pub mod requests;
 pub mod responses;
 
-pub use domain::{UserProfile, UserPreferences, MatchEvent, MatchEventType, UserMatch, ScoredMatch, BoundingBox, CandidateQuery, ScoringWeights};
-pub use requests::{FindMatchesRequest, RecordEventRequest};
-pub use responses::{FindMatchesResponse, HealthResponse, ErrorResponse, RecordEventResponse};
+pub use domain::{UserProfile, UserPreferences, SCHEMA_VERSION, MatchEvent, MatchEventType, UserMatch, ScoredMatch, ScoreBreakdown, TopPick, Tier, Verification, RelationshipGoal, BoundingBox, CandidateQuery, ScoringWeights};
+pub use requests::{FindMatchesRequest, RecordEventRequest, LikesReceivedQuery, ViewersQuery, ExpiringMatchesQuery, TopPickQuery, UserStatsQuery, GeoPoint, WeightsOverride, ScoreCandidatesRequest, MAX_SCORE_CANDIDATES};
+pub use responses::{FindMatchesResponse, FeedDiagnostics, MatchDiagnostics, HealthResponse, AppwriteEndpointStatus, ErrorResponse, FieldError, RecordEventResponse, LikeReceived, LikesReceivedResponse, ProfileViewer, ViewersResponse, ExpiringMatch, ExpiringMatchesResponse, ScoreCandidatesResponse, TopPickResponse, UserStatsResponse};
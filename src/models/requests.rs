@@ -1,3 +1,4 @@
+use crate::models::{ScoringWeights, UserPreferences, UserProfile};
 use serde::{Deserialize, Serialize};
 use validator::Validate;
 
@@ -15,6 +16,111 @@ pub struct FindMatchesRequest {
     pub exclude_user_ids: Vec<String>,
     #[serde(alias = "cursor", rename = "cursor")]
     pub cursor: Option<String>,
+    /// Passport mode: search from this location instead of the stored profile location
+    #[validate(nested)]
+    #[serde(default, alias = "override_location", rename = "overrideLocation")]
+    pub override_location: Option<GeoPoint>,
+    /// Scoring weights for this request only; unset fields keep the server defaults
+    #[validate(nested)]
+    #[serde(default)]
+    pub weights: Option<WeightsOverride>,
+    /// Report per-stage candidate counts (always computed live)
+    #[serde(default)]
+    pub diagnostics: bool,
+    /// Leave out matches scoring below this (0-100); unset keeps the server minimum
+    #[validate(range(min = 0.0, max = 100.0))]
+    #[serde(default, rename = "minScore")]
+    pub min_score: Option<f64>,
+    /// Relax the candidate query (overfetch, then radius) within bounded
+    /// retries until at least this many matches are found
+    #[validate(range(min = 1, max = 100))]
+    #[serde(default, rename = "minResults")]
+    pub min_results: Option<u16>,
+    /// Also return the user's top pick of the day (see `/matches/top-pick`)
+    #[serde(default, rename = "topPick")]
+    pub top_pick: bool,
+}
+
+/// Maximum candidates accepted by the bulk scoring endpoint
+pub const MAX_SCORE_CANDIDATES: u64 = 1000;
+
+/// Request to score caller-supplied candidates against one set of preferences
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct ScoreCandidatesRequest {
+    pub preferences: UserPreferences,
+    #[validate(length(min = 1, max = MAX_SCORE_CANDIDATES))]
+    pub candidates: Vec<UserProfile>,
+    /// Scoring weights for this request only; unset fields keep the server defaults
+    #[validate(nested)]
+    #[serde(default)]
+    pub weights: Option<WeightsOverride>,
+}
+
+/// Per-request scoring weight overrides (each between 0 and 1)
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, Validate)]
+pub struct WeightsOverride {
+    #[validate(range(min = 0.0, max = 1.0))]
+    pub distance: Option<f64>,
+    #[validate(range(min = 0.0, max = 1.0))]
+    pub age: Option<f64>,
+    #[validate(range(min = 0.0, max = 1.0))]
+    pub sports: Option<f64>,
+    #[validate(range(min = 0.0, max = 1.0))]
+    pub verified: Option<f64>,
+    #[validate(range(min = 0.0, max = 1.0))]
+    pub height: Option<f64>,
+    #[validate(range(min = 0.0, max = 1.0))]
+    pub activity: Option<f64>,
+    #[validate(range(min = 0.0, max = 1.0))]
+    pub embedding: Option<f64>,
+    #[validate(range(min = 0.0, max = 1.0))]
+    pub language: Option<f64>,
+    #[validate(range(min = 0.0, max = 1.0))]
+    #[serde(alias = "relationshipGoal")]
+    pub relationship_goal: Option<f64>,
+}
+
+impl WeightsOverride {
+    /// Apply the overrides on top of the given weights
+    pub fn apply(&self, base: ScoringWeights) -> ScoringWeights {
+        ScoringWeights {
+            distance: self.distance.unwrap_or(base.distance),
+            age: self.age.unwrap_or(base.age),
+            sports: self.sports.unwrap_or(base.sports),
+            verified: self.verified.unwrap_or(base.verified),
+            height: self.height.unwrap_or(base.height),
+            activity: self.activity.unwrap_or(base.activity),
+            embedding: self.embedding.unwrap_or(base.embedding),
+            language: self.language.unwrap_or(base.language),
+            relationship_goal: self.relationship_goal.unwrap_or(base.relationship_goal),
+        }
+    }
+
+    /// These overrides, falling back to `base` for the weights they leave unset
+    pub fn or(self, base: WeightsOverride) -> WeightsOverride {
+        WeightsOverride {
+            distance: self.distance.or(base.distance),
+            age: self.age.or(base.age),
+            sports: self.sports.or(base.sports),
+            verified: self.verified.or(base.verified),
+            height: self.height.or(base.height),
+            activity: self.activity.or(base.activity),
+            embedding: self.embedding.or(base.embedding),
+            language: self.language.or(base.language),
+            relationship_goal: self.relationship_goal.or(base.relationship_goal),
+        }
+    }
+}
+
+/// A latitude/longitude pair supplied by the client
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Validate)]
+pub struct GeoPoint {
+    #[validate(range(min = -90.0, max = 90.0))]
+    #[serde(alias = "latitude")]
+    pub lat: f64,
+    #[validate(range(min = -180.0, max = 180.0))]
+    #[serde(alias = "longitude")]
+    pub lon: f64,
 }
 
 fn default_limit() -> u16 {
@@ -36,4 +142,57 @@ pub struct RecordEventRequest {
     pub target_user_id: String,
     #[serde(alias = "eventType", rename = "eventType")]
     pub event_type: String,
+    /// When the event happened on the client, for events queued offline
+    /// (defaults to when the server receives it)
+    #[serde(default, alias = "occurred_at", rename = "occurredAt")]
+    pub occurred_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Query parameters for the stats endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserStatsQuery {
+    #[serde(rename = "userId")]
+    pub user_id: String,
+}
+
+/// Query parameters for the top-pick endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TopPickQuery {
+    #[serde(rename = "userId")]
+    pub user_id: String,
+}
+
+/// Query parameters for the expiring-matches endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExpiringMatchesQuery {
+    #[serde(rename = "userId")]
+    pub user_id: String,
+    /// List matches lapsing within this many days (defaults to `match_expiry_warning_days`)
+    #[serde(rename = "withinDays")]
+    pub within_days: Option<u32>,
+}
+
+/// Query parameters for the who-viewed-me endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ViewersQuery {
+    #[serde(rename = "userId")]
+    pub user_id: String,
+    #[serde(default = "default_limit")]
+    pub limit: u16,
+    #[serde(default)]
+    pub offset: u32,
+}
+
+/// Query parameters for the likes-received endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LikesReceivedQuery {
+    #[serde(rename = "userId")]
+    pub user_id: String,
+    /// Only return the pending like count (for badges)
+    #[serde(rename = "countOnly", default)]
+    pub count_only: bool,
+    #[serde(default = "default_limit")]
+    pub limit: u16,
+    #[serde(default)]
+    pub offset: u32,
 }
@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use crate::models::domain::ScoredMatch;
+use crate::models::domain::{MatchEventType, ScoredMatch, TopPick};
 
 /// Response for find matches endpoint
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -7,6 +7,91 @@ pub struct FindMatchesResponse {
     pub matches: Vec<ScoredMatch>,
     pub next_cursor: Option<String>,
     pub total_results: usize,
+    /// No user location was available: results are not distance-filtered or distance-ranked
+    #[serde(default)]
+    pub global_mode: bool,
+    /// The user has no preferences yet and was matched with synthesized defaults
+    #[serde(default)]
+    pub default_preferences: bool,
+    /// A pipeline stage ran out of time and was skipped; see `warnings`
+    #[serde(default)]
+    pub partial: bool,
+    /// Skipped stages, e.g. "seen_profiles_timeout" or "candidates_timeout"
+    #[serde(default)]
+    pub warnings: Vec<String>,
+    /// Per-stage candidate counts, when the request set `diagnostics`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diagnostics: Option<FeedDiagnostics>,
+    /// Market and nearest known city searched from, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub market: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub city: Option<String>,
+    /// Relaxations applied to reach `minResults`, in order, e.g.
+    /// "overfetch:10x" or "radius:75km"
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub relaxations: Vec<String>,
+    /// The user's top pick of the day (see `/matches/top-pick`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_pick: Option<TopPick>,
+}
+
+/// Top pick of the day, for the home-screen widget
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TopPickResponse {
+    #[serde(rename = "userId")]
+    pub user_id: String,
+    /// None when nobody qualifies today
+    #[serde(rename = "topPick")]
+    pub top_pick: Option<TopPick>,
+}
+
+/// Where a feed's candidates went, for explaining short or empty feeds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeedDiagnostics {
+    /// Already seen (or client-excluded) profiles left out of the candidate query
+    pub seen_excluded: usize,
+    /// Candidates returned by the geo query
+    pub geo_candidates: usize,
+    /// Candidate documents dropped because they failed to parse
+    pub unparseable: usize,
+    /// Candidates added by vector retrieval
+    pub vector_candidates: usize,
+    /// Candidates removed by hard-block rules
+    pub blocked_by_rules: usize,
+    /// What the matching pipeline did with the rest
+    #[serde(flatten)]
+    pub matching: MatchDiagnostics,
+}
+
+/// How many candidates each pipeline stage eliminated
+///
+/// Stages run in field order; each candidate is counted once, at the first
+/// stage it fails or as `ranked`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatchDiagnostics {
+    /// Duplicate candidates (with dedupe on)
+    pub duplicates: usize,
+    /// The user themselves and explicitly excluded IDs
+    pub excluded: usize,
+    /// Outside the search radius's bounding box, or inside a geofence
+    pub geo: usize,
+    /// Failed age, gender, height, activity, photo, language or relationship-goal requirements
+    pub demographics: usize,
+    /// Scored below the minimum score
+    pub below_min_score: usize,
+    /// Passed every stage
+    pub ranked: usize,
+}
+
+/// Response for the bulk scoring endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScoreCandidatesResponse {
+    /// Candidates that passed the filters, best score first
+    pub matches: Vec<ScoredMatch>,
+    pub total_candidates: usize,
+    #[serde(default)]
+    pub global_mode: bool,
 }
 
 /// Health check response
@@ -15,14 +100,47 @@ pub struct HealthResponse {
     pub status: String,
     pub version: String,
     pub timestamp: chrono::DateTime<chrono::Utc>,
+    /// Which Appwrite endpoint is serving requests
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub appwrite: Option<AppwriteEndpointStatus>,
+    /// Configuration profile selected with `LUME_ENV`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+}
+
+/// Active Appwrite endpoint and failover counters
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppwriteEndpointStatus {
+    pub active: String,
+    /// The active endpoint is the first configured one
+    pub primary: bool,
+    pub endpoints: usize,
+    pub consecutive_failures: u32,
+    pub failovers: u64,
 }
 
 /// Error response
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ErrorResponse {
+    /// Stable machine-readable code (e.g. "validation_failed", "user_snoozed")
+    pub error_code: String,
     pub error: String,
     pub message: String,
     pub status_code: u16,
+    /// Per-field details for validation and JSON body errors
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub validation_errors: Vec<FieldError>,
+}
+
+/// One invalid request field
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldError {
+    /// Field path as sent by the client (e.g. "weights.age", "candidates[3].userId");
+    /// "body" when the error can't be tied to a field
+    pub field: String,
+    /// Validator or parse code (e.g. "length", "range", "missing_field")
+    pub code: String,
+    pub message: String,
 }
 
 /// Record event response
@@ -30,4 +148,105 @@ pub struct ErrorResponse {
 pub struct RecordEventResponse {
     pub success: bool,
     pub event_id: String,
+    /// Repeat of an event recorded within the dedup window; nothing was written
+    #[serde(default)]
+    pub duplicate: bool,
+    /// The like answered the target's like; the two users are now matched
+    #[serde(default)]
+    pub mutual_match: bool,
+}
+
+/// A mutual match that will expire without a follow-up event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExpiringMatch {
+    #[serde(rename = "userId")]
+    pub user_id: String,
+    #[serde(rename = "lastActivityAt")]
+    pub last_activity_at: chrono::DateTime<chrono::Utc>,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Response for the expiring-matches endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExpiringMatchesResponse {
+    #[serde(rename = "userId")]
+    pub user_id: String,
+    #[serde(rename = "expiryDays")]
+    pub expiry_days: u32,
+    /// Soonest to expire first
+    pub matches: Vec<ExpiringMatch>,
+}
+
+/// A user who recently viewed or liked the requester's profile
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileViewer {
+    #[serde(rename = "userId")]
+    pub user_id: String,
+    pub name: String,
+    pub age: u8,
+    #[serde(rename = "isVerified")]
+    pub is_verified: bool,
+    #[serde(rename = "imageFileIds")]
+    pub image_file_ids: Vec<String>,
+    /// `viewed` or `liked`
+    #[serde(rename = "eventType")]
+    pub event_type: MatchEventType,
+    #[serde(rename = "viewedAt")]
+    pub viewed_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Response for the who-viewed-me endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ViewersResponse {
+    #[serde(rename = "userId")]
+    pub user_id: String,
+    /// Newest first
+    pub viewers: Vec<ProfileViewer>,
+    /// Viewers within the window, across all pages
+    pub count: i64,
+    #[serde(rename = "windowDays")]
+    pub window_days: u32,
+}
+
+/// A pending like shown in the "who liked me" queue
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LikeReceived {
+    #[serde(rename = "userId")]
+    pub user_id: String,
+    pub name: String,
+    pub age: u8,
+    #[serde(rename = "isVerified")]
+    pub is_verified: bool,
+    #[serde(rename = "imageFileIds")]
+    pub image_file_ids: Vec<String>,
+    #[serde(rename = "likedAt")]
+    pub liked_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Response for the likes-received endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LikesReceivedResponse {
+    #[serde(rename = "userId")]
+    pub user_id: String,
+    pub likes: Vec<LikeReceived>,
+    pub count: i64,
+}
+
+/// Swipe activity summary for profile insights
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserStatsResponse {
+    #[serde(rename = "userId")]
+    pub user_id: String,
+    #[serde(rename = "profilesSeen")]
+    pub profiles_seen: i64,
+    #[serde(rename = "likesGiven")]
+    pub likes_given: i64,
+    pub passes: i64,
+    pub matches: i64,
+    /// Likes (including those that became matches) per like or pass; null before any
+    #[serde(rename = "likeRate")]
+    pub like_rate: Option<f64>,
+    #[serde(rename = "lastActivityAt")]
+    pub last_activity_at: Option<chrono::DateTime<chrono::Utc>>,
 }
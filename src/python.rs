@@ -0,0 +1,188 @@
+//! Python bindings for the scoring core (`python` feature)
+//!
+//! Built as the `lume_algo` extension module with maturin (see
+//! pyproject.toml). Profiles and preferences are plain dicts in the API's
+//! JSON shape (`userId`, `heightCm`, ...), so rows exported from Appwrite or
+//! the match logs can be scored as they are.
+//!
+//! Only the weights and minimum score are configurable. Every other matcher
+//! setting the server reads from its config (distance formula, exploration,
+//! tier boosts, freshness, relationship goals, attribute scorers, shuffle
+//! bands, geofences, calibration) keeps the library default, so results
+//! match production only where the server runs those defaults too.
+
+use crate::core::{calculate_match_score, Matcher, MatchOptions};
+use crate::models::{ScoringWeights, UserPreferences, UserProfile, WeightsOverride};
+use pyo3::prelude::*;
+use pythonize::{depythonize, pythonize};
+use std::collections::HashSet;
+
+/// Default weights with the given overrides (`{"distance": 0.4, ...}`)
+fn weights(overrides: Option<&Bound<'_, PyAny>>) -> PyResult<ScoringWeights> {
+    let overrides: WeightsOverride = match overrides {
+        Some(overrides) => depythonize(overrides)?,
+        None => WeightsOverride::default(),
+    };
+    Ok(overrides.apply(ScoringWeights::default()))
+}
+
+/// Great-circle distance in kilometers
+#[pyfunction]
+#[pyo3(name = "haversine_distance")]
+fn py_haversine_distance(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    crate::core::haversine_distance(lat1, lon1, lat2, lon2)
+}
+
+/// Match score (0-100) of `profile` for `preferences`, and the shared sports
+#[pyfunction]
+#[pyo3(name = "calculate_match_score", signature = (profile, preferences, weights=None))]
+fn py_calculate_match_score(
+    profile: &Bound<'_, PyAny>,
+    preferences: &Bound<'_, PyAny>,
+    weights: Option<&Bound<'_, PyAny>>,
+) -> PyResult<(f64, Vec<String>)> {
+    let profile: UserProfile = depythonize(profile)?;
+    let preferences: UserPreferences = depythonize(preferences)?;
+    Ok(calculate_match_score(&profile, &preferences, &self::weights(weights)?))
+}
+
+/// The matching pipeline with the given weights and minimum score, and
+/// library defaults for every other matcher setting
+#[pyclass(name = "Matcher", module = "lume_algo")]
+struct PyMatcher {
+    matcher: Matcher,
+}
+
+#[pymethods]
+impl PyMatcher {
+    #[new]
+    #[pyo3(signature = (weights=None, min_score=None))]
+    fn new(weights: Option<&Bound<'_, PyAny>>, min_score: Option<f64>) -> PyResult<Self> {
+        let mut builder = Matcher::builder().weights(self::weights(weights)?);
+        if let Some(min_score) = min_score {
+            builder = builder.min_score(min_score);
+        }
+        Ok(Self { matcher: builder.build() })
+    }
+
+    /// Ranked matches (dicts shaped like the API's) for `preferences`
+    #[pyo3(signature = (preferences, candidates, limit=20, exclude_user_ids=None, score_breakdown=false))]
+    fn find_matches<'py>(
+        &self,
+        py: Python<'py>,
+        preferences: &Bound<'py, PyAny>,
+        candidates: &Bound<'py, PyAny>,
+        limit: usize,
+        exclude_user_ids: Option<HashSet<String>>,
+        score_breakdown: bool,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let preferences: UserPreferences = depythonize(preferences)?;
+        let candidates: Vec<UserProfile> = depythonize(candidates)?;
+        let options = MatchOptions {
+            limit: Some(limit),
+            exclude_user_ids: exclude_user_ids.unwrap_or_default(),
+            score_breakdown,
+            ..Default::default()
+        };
+
+        let result = py.allow_threads(|| self.matcher.find_matches_with(&preferences, candidates, &options));
+        Ok(pythonize(py, &result.matches)?)
+    }
+
+    /// The weights in use, as a dict
+    #[getter]
+    fn weights<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        Ok(pythonize(py, &self.matcher.weights())?)
+    }
+
+    #[getter]
+    fn min_score(&self) -> f64 {
+        self.matcher.min_score()
+    }
+}
+
+#[pymodule]
+fn lume_algo(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(py_haversine_distance, m)?)?;
+    m.add_function(wrap_pyfunction!(py_calculate_match_score, m)?)?;
+    m.add_class::<PyMatcher>()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn with_py<T>(f: impl for<'py> FnOnce(Python<'py>) -> T) -> T {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(f)
+    }
+
+    fn profile(user_id: &str, gender: &str, age: u8) -> Value {
+        json!({
+            "userId": user_id,
+            "name": user_id,
+            "age": age,
+            "heightCm": 170,
+            "hairColor": "brown",
+            "gender": gender,
+            "latitude": 52.52,
+            "longitude": 13.405,
+            "sportsPreferences": ["tennis"],
+        })
+    }
+
+    fn preferences() -> Value {
+        json!({
+            "userId": "alice",
+            "preferredGenders": ["female"],
+            "minAge": 25,
+            "maxAge": 35,
+            "preferredHairColors": [],
+            "preferredSports": ["tennis"],
+            "maxDistanceKm": 50,
+            "latitude": 52.52,
+            "longitude": 13.405,
+        })
+    }
+
+    #[test]
+    fn test_calculate_match_score() {
+        with_py(|py| {
+            let profile = pythonize(py, &profile("beth", "female", 28)).unwrap();
+            let preferences = pythonize(py, &preferences()).unwrap();
+
+            let (score, shared) = py_calculate_match_score(&profile, &preferences, None).unwrap();
+            assert!(score > 0.0 && score <= 100.0);
+            assert_eq!(shared, vec!["tennis"]);
+
+            let bad_weights = pythonize(py, &json!({ "distance": "far" })).unwrap();
+            assert!(py_calculate_match_score(&profile, &preferences, Some(&bad_weights)).is_err());
+        });
+    }
+
+    #[test]
+    fn test_matcher() {
+        with_py(|py| {
+            let weights = pythonize(py, &json!({ "sports": 0.3 })).unwrap();
+            let matcher = PyMatcher::new(Some(&weights), Some(5.0)).unwrap();
+            assert_eq!(matcher.min_score(), 5.0);
+            let weights: Value = depythonize(&matcher.weights(py).unwrap()).unwrap();
+            assert_eq!(weights["sports"], 0.3);
+
+            let preferences = pythonize(py, &preferences()).unwrap();
+            let candidates = pythonize(
+                py,
+                &json!([profile("beth", "female", 28), profile("cara", "female", 31), profile("dave", "male", 30)]),
+            )
+            .unwrap();
+            let exclude = Some(HashSet::from(["cara".to_string()]));
+
+            let matches = matcher.find_matches(py, &preferences, &candidates, 20, exclude, false).unwrap();
+            let matches: Vec<Value> = depythonize(&matches).unwrap();
+            let ids: Vec<&str> = matches.iter().map(|m| m["userId"].as_str().unwrap()).collect();
+            assert_eq!(ids, vec!["beth"]);
+        });
+    }
+}
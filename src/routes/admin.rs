@@ -0,0 +1,355 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+use subtle::ConstantTimeEq;
+use crate::config::MIN_SEEN_RETENTION_DAYS;
+use crate::error::{AppError, AppResult};
+use crate::routes::matches::AppState;
+use crate::routes::Tenant;
+use crate::services::{
+    capture_profile, AuditEntry, AuditFilter, ProfileFormat, DEFAULT_PROFILE_FREQUENCY, DEFAULT_PROFILE_SECS,
+    MAX_PROFILE_SECS,
+};
+use std::time::Duration;
+
+/// Configure admin routes
+///
+/// All admin routes require the `X-Admin-Token` header to match
+/// `admin.token`; they are disabled when no token is configured. Actions that
+/// change or delete data are written to the audit log.
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/admin")
+            .route("/audit", web::get().to(get_audit_log))
+            .route("/users/{user_id}/seen", web::delete().to(clear_seen_profiles))
+            .route("/seen/prune", web::post().to(prune_seen))
+            .route("/seen/prune", web::get().to(prune_status))
+            .route("/events/reconcile", web::post().to(reconcile_events))
+            .route("/events/reconcile", web::get().to(reconcile_status))
+            .route("/matches/expire", web::post().to(expire_matches))
+            .route("/matches/expire", web::get().to(expiry_status))
+            .route("/cache/stats", web::get().to(cache_stats))
+            .route("/profile", web::get().to(cpu_profile)),
+    );
+}
+
+/// Check the admin token
+fn authorize(state: &AppState, req: &HttpRequest) -> AppResult<()> {
+    if state.admin_token.is_none() {
+        return Err(AppError::Forbidden {
+            code: "admin_disabled",
+            message: "No admin token is configured".to_string(),
+        });
+    }
+
+    if !has_admin_token(state, req) {
+        return Err(AppError::Unauthorized(
+            "Missing or invalid X-Admin-Token header".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Whether the request carries the configured admin token
+///
+/// Compared in constant time, so response timing doesn't reveal how much of
+/// a guessed token matched. Always false without a configured token.
+pub(crate) fn has_admin_token(state: &AppState, req: &HttpRequest) -> bool {
+    let Some(expected) = state.admin_token.as_deref() else {
+        return false;
+    };
+
+    req.headers()
+        .get("X-Admin-Token")
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|provided| bool::from(provided.as_bytes().ct_eq(expected.as_bytes())))
+}
+
+/// Operator named in the `X-Admin-Actor` header, for the audit log
+///
+/// The admin token is shared, so the header is how operators tell their
+/// actions apart; without it the actor is `admin`.
+fn actor(req: &HttpRequest) -> String {
+    req.headers()
+        .get("X-Admin-Actor")
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|actor| !actor.is_empty())
+        .unwrap_or("admin")
+        .to_string()
+}
+
+/// Append an entry to the audit log
+///
+/// The action has already happened by the time it is audited, so a failed
+/// write is logged rather than failing the request.
+async fn audit(state: &AppState, entry: AuditEntry) {
+    tracing::info!(
+        target: "audit",
+        event = %entry.action,
+        actor = %entry.actor,
+        target_id = entry.target.as_deref().unwrap_or(""),
+        "Admin action"
+    );
+
+    if let Err(e) = state.postgres.append_audit(&entry).await {
+        tracing::error!(target: "audit", "Failed to write audit entry for {}: {}", entry.action, e);
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct AuditQuery {
+    actor: Option<String>,
+    action: Option<String>,
+    target: Option<String>,
+    limit: Option<usize>,
+    offset: Option<usize>,
+}
+
+/// List audit log entries, newest first
+///
+/// GET /api/v1/admin/audit?actor={actor}&action={action}&target={userId}&limit={n}&offset={n}
+async fn get_audit_log(
+    state: Tenant,
+    req: HttpRequest,
+    query: web::Query<AuditQuery>,
+) -> AppResult<HttpResponse> {
+    authorize(&state, &req)?;
+
+    let query = query.into_inner();
+    let filter = AuditFilter {
+        actor: query.actor,
+        action: query.action,
+        target: query.target,
+    };
+    let limit = query.limit.unwrap_or(50).min(500);
+    let offset = query.offset.unwrap_or(0);
+    let entries = state
+        .postgres
+        .get_audit_log(&filter, limit, offset)
+        .await
+        .map_err(AppError::database("Failed to read audit log"))?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "entries": entries,
+        "limit": limit,
+        "offset": offset,
+    })))
+}
+
+/// Delete every seen record of a user, so all profiles can reappear in their feed
+///
+/// DELETE /api/v1/admin/users/{userId}/seen
+async fn clear_seen_profiles(
+    state: Tenant,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> AppResult<HttpResponse> {
+    authorize(&state, &req)?;
+
+    let user_id = path.into_inner();
+    let deleted = state
+        .postgres
+        .clear_seen_profiles(&user_id)
+        .await
+        .map_err(AppError::database("Failed to clear seen profiles"))?;
+    state.cache.invalidate_seen_set(&user_id).await;
+
+    audit(
+        &state,
+        AuditEntry::new(actor(&req), "seen.clear")
+            .with_target(user_id.as_str())
+            .with_details(serde_json::json!({ "deleted": deleted })),
+    )
+    .await;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "userId": user_id,
+        "deleted": deleted,
+    })))
+}
+
+#[derive(Debug, Deserialize)]
+struct PruneQuery {
+    #[serde(rename = "retentionDays")]
+    retention_days: Option<u32>,
+}
+
+/// Run the seen-profiles prune job now
+///
+/// POST /api/v1/admin/seen/prune?retentionDays={days}
+///
+/// `retentionDays` must be at least [`MIN_SEEN_RETENTION_DAYS`].
+async fn prune_seen(
+    state: Tenant,
+    req: HttpRequest,
+    query: web::Query<PruneQuery>,
+) -> AppResult<HttpResponse> {
+    authorize(&state, &req)?;
+
+    if query.retention_days.is_some_and(|days| days < MIN_SEEN_RETENTION_DAYS) {
+        return Err(AppError::BadRequest {
+            code: "invalid_retention",
+            message: format!("retentionDays must be at least {}", MIN_SEEN_RETENTION_DAYS),
+        });
+    }
+
+    let report = state
+        .pruner
+        .run(query.retention_days, true)
+        .await
+        .map_err(AppError::database("Failed to prune seen profiles"))?;
+
+    audit(&state, AuditEntry::new(actor(&req), "seen.prune").with_details(serde_json::json!(report))).await;
+
+    Ok(HttpResponse::Ok().json(report))
+}
+
+/// Show the most recent prune run
+///
+/// GET /api/v1/admin/seen/prune
+async fn prune_status(state: Tenant, req: HttpRequest) -> AppResult<HttpResponse> {
+    authorize(&state, &req)?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "retentionDays": state.pruner.retention_days(),
+        "lastRun": state.pruner.last_run().await,
+    })))
+}
+
+#[derive(Debug, Deserialize)]
+struct ReconcileQuery {
+    #[serde(rename = "windowHours")]
+    window_hours: Option<u32>,
+}
+
+/// Compare recent events in PostgreSQL and Appwrite and re-sync missing ones now
+///
+/// POST /api/v1/admin/events/reconcile?windowHours={hours}
+async fn reconcile_events(
+    state: Tenant,
+    req: HttpRequest,
+    query: web::Query<ReconcileQuery>,
+) -> AppResult<HttpResponse> {
+    authorize(&state, &req)?;
+
+    let report = state.reconciler.run(query.window_hours, true).await?;
+
+    audit(&state, AuditEntry::new(actor(&req), "events.reconcile").with_details(serde_json::json!(report))).await;
+
+    Ok(HttpResponse::Ok().json(report))
+}
+
+/// Show reconciliation totals and the most recent run
+///
+/// GET /api/v1/admin/events/reconcile
+async fn reconcile_status(state: Tenant, req: HttpRequest) -> AppResult<HttpResponse> {
+    authorize(&state, &req)?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "windowHours": state.reconciler.window_hours(),
+        "stats": state.reconciler.stats().await,
+    })))
+}
+
+#[derive(Debug, Deserialize)]
+struct ExpireQuery {
+    #[serde(rename = "expiryDays")]
+    expiry_days: Option<u32>,
+}
+
+/// Run the match expiry job now
+///
+/// POST /api/v1/admin/matches/expire?expiryDays={days}
+///
+/// `expiryDays` is required when `match_expiry_days` is not configured.
+async fn expire_matches(
+    state: Tenant,
+    req: HttpRequest,
+    query: web::Query<ExpireQuery>,
+) -> AppResult<HttpResponse> {
+    authorize(&state, &req)?;
+
+    let expiry_days = query.expiry_days.or(state.expirer.expiry_days()).ok_or_else(|| AppError::BadRequest {
+        code: "match_expiry_disabled",
+        message: "Match expiry is not configured; pass expiryDays".to_string(),
+    })?;
+
+    let report = state
+        .expirer
+        .run(expiry_days, true)
+        .await
+        .map_err(AppError::database("Failed to expire matches"))?;
+
+    audit(&state, AuditEntry::new(actor(&req), "matches.expire").with_details(serde_json::json!(report))).await;
+
+    Ok(HttpResponse::Ok().json(report))
+}
+
+/// Show the most recent match expiry run
+///
+/// GET /api/v1/admin/matches/expire
+async fn expiry_status(state: Tenant, req: HttpRequest) -> AppResult<HttpResponse> {
+    authorize(&state, &req)?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "expiryDays": state.expirer.expiry_days(),
+        "lastRun": state.expirer.last_run().await,
+    })))
+}
+
+/// Cache hit and miss counts of this instance, per tier and key prefix, and
+/// its read-through recomputes
+///
+/// GET /api/v1/admin/cache/stats
+async fn cache_stats(state: Tenant, req: HttpRequest) -> AppResult<HttpResponse> {
+    authorize(&state, &req)?;
+
+    let recomputes = state.cache.recomputes();
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "stats": state.cache.stats(),
+        "recomputes": {
+            "inFlight": recomputes.in_flight(),
+            "coalesced": recomputes.coalesced(),
+        },
+    })))
+}
+
+#[derive(Debug, Deserialize)]
+struct ProfileQuery {
+    seconds: Option<u64>,
+    frequency: Option<i32>,
+    format: Option<String>,
+}
+
+/// Capture a CPU profile of this instance (requires the `profiling` feature)
+///
+/// GET /api/v1/admin/profile?seconds={1-120}&frequency={Hz}&format={flamegraph|pprof}
+///
+/// Blocks for the whole capture and returns an SVG flamegraph or a pprof
+/// protobuf. Only one capture runs at a time.
+async fn cpu_profile(
+    state: Tenant,
+    req: HttpRequest,
+    query: web::Query<ProfileQuery>,
+) -> AppResult<HttpResponse> {
+    authorize(&state, &req)?;
+
+    let format = match query.format.as_deref() {
+        None | Some("flamegraph") | Some("svg") => ProfileFormat::Flamegraph,
+        Some("pprof") => ProfileFormat::Pprof,
+        Some(other) => {
+            return Err(AppError::BadRequest {
+                code: "invalid_profile_format",
+                message: format!("Unknown profile format {}; use flamegraph or pprof", other),
+            })
+        }
+    };
+    let seconds = query.seconds.unwrap_or(DEFAULT_PROFILE_SECS).clamp(1, MAX_PROFILE_SECS);
+    let frequency = query.frequency.unwrap_or(DEFAULT_PROFILE_FREQUENCY);
+
+    tracing::info!("Capturing a {}s CPU profile at {} Hz for {}", seconds, frequency, actor(&req));
+    let body = capture_profile(Duration::from_secs(seconds), frequency, format).await?;
+
+    Ok(HttpResponse::Ok().content_type(format.content_type()).body(body))
+}
@@ -0,0 +1,199 @@
+use crate::error::{AppError, AppResult};
+use crate::routes::etag;
+use actix_web::http::header::{self, ContentType};
+use actix_web::{HttpRequest, HttpResponse, ResponseError};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// Header alternative to the `case` query parameter
+pub const RESPONSE_CASE_HEADER: &str = "X-Response-Case";
+
+/// Key casing for response bodies, chosen by the client
+///
+/// Response types mix camelCase (`userId`) and snake_case (`total_results`)
+/// keys. `?case=snake` or `?case=camel` (or the `X-Response-Case` header)
+/// rewrites every key of the body to one convention; the default leaves
+/// bodies as they are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResponseCase {
+    #[default]
+    AsIs,
+    Snake,
+    Camel,
+}
+
+impl ResponseCase {
+    /// Read the requested casing; the query parameter wins over the header
+    pub fn from_request(req: &HttpRequest) -> AppResult<Self> {
+        let from_query = req
+            .query_string()
+            .split('&')
+            .find_map(|pair| pair.strip_prefix("case="));
+        let from_header = || {
+            req.headers()
+                .get(RESPONSE_CASE_HEADER)
+                .and_then(|value| value.to_str().ok())
+        };
+
+        match from_query.or_else(from_header).map(str::to_ascii_lowercase).as_deref() {
+            None => Ok(ResponseCase::AsIs),
+            Some("snake") | Some("snake_case") => Ok(ResponseCase::Snake),
+            Some("camel") | Some("camelcase") => Ok(ResponseCase::Camel),
+            Some(other) => Err(AppError::BadRequest {
+                code: "invalid_case",
+                message: format!("Unsupported response case '{}': use snake or camel", other),
+            }),
+        }
+    }
+
+    /// 200 response with the body's keys in this casing
+    pub fn json<T: Serialize>(self, body: &T) -> HttpResponse {
+        match self {
+            ResponseCase::AsIs => HttpResponse::Ok().json(body),
+            case => match serde_json::to_value(body) {
+                Ok(value) => HttpResponse::Ok().json(case.apply(value)),
+                Err(e) => AppError::Internal(format!("Failed to serialize response: {}", e)).error_response(),
+            },
+        }
+    }
+
+    /// [`json`](Self::json) with an `ETag` of the body, or an empty 304
+    /// when the client's `If-None-Match` already has it
+    ///
+    /// Lets polling clients skip downloading a body that hasn't changed.
+    pub fn json_with_etag<T: Serialize>(self, req: &HttpRequest, body: &T) -> HttpResponse {
+        let body = match self.to_vec(body) {
+            Ok(body) => body,
+            Err(e) => return AppError::Internal(format!("Failed to serialize response: {}", e)).error_response(),
+        };
+        let etag = etag::content_etag(&body);
+
+        let not_modified = etag::not_modified(req, &etag);
+
+        let mut response = if not_modified { HttpResponse::NotModified() } else { HttpResponse::Ok() };
+        // Responses are per user; caches may keep them but must revalidate
+        response
+            .insert_header(header::ETag(etag))
+            .insert_header((header::CACHE_CONTROL, "private, no-cache"));
+
+        if not_modified {
+            response.finish()
+        } else {
+            response.content_type(ContentType::json()).body(body)
+        }
+    }
+
+    /// Serialize one value as a newline-terminated JSON line
+    pub fn to_line<T: Serialize>(self, body: &T) -> serde_json::Result<Vec<u8>> {
+        let mut line = self.to_vec(body)?;
+        line.push(b'\n');
+        Ok(line)
+    }
+
+    fn to_vec<T: Serialize>(self, body: &T) -> serde_json::Result<Vec<u8>> {
+        match self {
+            ResponseCase::AsIs => serde_json::to_vec(body),
+            case => serde_json::to_vec(&case.apply(serde_json::to_value(body)?)),
+        }
+    }
+
+    /// Rewrite object keys recursively
+    pub fn apply(self, value: Value) -> Value {
+        let convert: fn(&str) -> String = match self {
+            ResponseCase::AsIs => return value,
+            ResponseCase::Snake => to_snake_case,
+            ResponseCase::Camel => to_camel_case,
+        };
+        rename_keys(value, convert)
+    }
+}
+
+fn rename_keys(value: Value, convert: fn(&str) -> String) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(key, value)| (convert(&key), rename_keys(value, convert)))
+                .collect::<Map<String, Value>>(),
+        ),
+        Value::Array(items) => Value::Array(items.into_iter().map(|v| rename_keys(v, convert)).collect()),
+        other => other,
+    }
+}
+
+/// `heightCm` -> `height_cm`
+pub fn to_snake_case(key: &str) -> String {
+    let mut out = String::with_capacity(key.len() + 4);
+    for c in key.chars() {
+        if c.is_ascii_uppercase() {
+            if !out.is_empty() {
+                out.push('_');
+            }
+            out.push(c.to_ascii_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// `height_cm` -> `heightCm`
+pub fn to_camel_case(key: &str) -> String {
+    let mut out = String::with_capacity(key.len());
+    let mut upper = false;
+    for c in key.chars() {
+        if c == '_' {
+            upper = true;
+        } else if upper {
+            out.extend(c.to_uppercase());
+            upper = false;
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    #[test]
+    fn test_key_conversion() {
+        assert_eq!(to_snake_case("heightCm"), "height_cm");
+        assert_eq!(to_snake_case("total_results"), "total_results");
+        assert_eq!(to_camel_case("total_results"), "totalResults");
+        assert_eq!(to_camel_case("userId"), "userId");
+    }
+
+    #[test]
+    fn test_apply_recurses() {
+        let body = serde_json::json!({
+            "total_results": 1,
+            "matches": [{ "userId": "a", "sharedSports": ["tennis"] }],
+        });
+
+        let snake = ResponseCase::Snake.apply(body.clone());
+        assert_eq!(snake["matches"][0]["user_id"], "a");
+        assert_eq!(snake["matches"][0]["shared_sports"][0], "tennis");
+
+        let camel = ResponseCase::Camel.apply(body);
+        assert_eq!(camel["totalResults"], 1);
+        assert_eq!(camel["matches"][0]["userId"], "a");
+    }
+
+    #[test]
+    fn test_from_request() {
+        let req = TestRequest::with_uri("/matches/stats?userId=a&case=snake").to_http_request();
+        assert_eq!(ResponseCase::from_request(&req).unwrap(), ResponseCase::Snake);
+
+        let req = TestRequest::default().insert_header((RESPONSE_CASE_HEADER, "camel")).to_http_request();
+        assert_eq!(ResponseCase::from_request(&req).unwrap(), ResponseCase::Camel);
+
+        let req = TestRequest::default().to_http_request();
+        assert_eq!(ResponseCase::from_request(&req).unwrap(), ResponseCase::AsIs);
+
+        let req = TestRequest::with_uri("/?case=kebab").to_http_request();
+        assert!(ResponseCase::from_request(&req).is_err());
+    }
+}
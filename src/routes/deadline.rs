@@ -0,0 +1,197 @@
+use crate::error::{AppError, AppResult};
+use actix_web::HttpRequest;
+use chrono::{DateTime, Utc};
+use std::future::Future;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Absolute deadline set by the gateway: Unix epoch milliseconds or RFC 3339
+pub const DEADLINE_HEADER: &str = "X-Request-Deadline";
+
+/// Remaining time in gRPC's format: up to 8 digits and a unit
+/// (`H`, `M`, `S`, `m`, `u`, `n`), e.g. `250m` for 250 ms
+pub const GRPC_TIMEOUT_HEADER: &str = "grpc-timeout";
+
+/// How long the gateway waits for this request
+///
+/// Work still running after the deadline is wasted: the gateway has given up
+/// and answered the client. Futures run with [`run`](Self::run) are dropped
+/// once it passes, which cancels their in-flight Appwrite, PostgreSQL and
+/// Redis calls, and the request fails with 504 `deadline_exceeded`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RequestDeadline(Option<Instant>);
+
+impl RequestDeadline {
+    /// Deadline `timeout` from now
+    pub fn after(timeout: Duration) -> Self {
+        Self(Some(Instant::now() + timeout))
+    }
+
+    /// Read the deadline headers; with both, the earlier one wins
+    ///
+    /// Absolute deadlines are compared with this instance's clock, so they
+    /// are only as accurate as the clocks are in sync.
+    pub fn from_request(req: &HttpRequest) -> AppResult<Self> {
+        let header = |name: &str| {
+            req.headers()
+                .get(name)
+                .map(|value| value.to_str().map_err(|_| invalid(format!("{} must be visible ASCII", name))))
+                .transpose()
+        };
+
+        let absolute = header(DEADLINE_HEADER)?.map(parse_deadline).transpose()?;
+        let relative = header(GRPC_TIMEOUT_HEADER)?.map(parse_grpc_timeout).transpose()?;
+
+        let now = Instant::now();
+        Ok(Self(absolute.into_iter().chain(relative).min().map(|remaining| now + remaining)))
+    }
+
+    /// Time left, None without a deadline
+    pub fn remaining(&self) -> Option<Duration> {
+        self.0.map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    /// A deadline `share` (0-1) of the way from now to this one; none without
+    /// a deadline
+    pub fn share(&self, share: f64) -> Self {
+        Self(self.remaining().map(|remaining| Instant::now() + remaining.mul_f64(share)))
+    }
+
+    /// Run `work` until the deadline, dropping it when time runs out
+    pub async fn run<T>(self, work: impl Future<Output = T>) -> AppResult<T> {
+        let Some(deadline) = self.0 else {
+            return Ok(work.await);
+        };
+
+        // Don't start anything for a request the gateway already gave up on
+        if deadline <= Instant::now() {
+            return Err(exceeded());
+        }
+
+        tokio::time::timeout_at(deadline, work).await.map_err(|_| exceeded())
+    }
+}
+
+fn exceeded() -> AppError {
+    AppError::DeadlineExceeded("The gateway's deadline for this request passed".to_string())
+}
+
+fn invalid(message: String) -> AppError {
+    AppError::BadRequest { code: "invalid_deadline", message }
+}
+
+/// Time left until an absolute deadline (zero when it passed)
+fn parse_deadline(value: &str) -> AppResult<Duration> {
+    let deadline = match value.parse::<i64>() {
+        Ok(millis) => DateTime::from_timestamp_millis(millis),
+        Err(_) => DateTime::parse_from_rfc3339(value).ok().map(|deadline| deadline.with_timezone(&Utc)),
+    }
+    .ok_or_else(|| invalid(format!("{} must be Unix epoch milliseconds or RFC 3339, got '{}'", DEADLINE_HEADER, value)))?;
+
+    Ok((deadline - Utc::now()).to_std().unwrap_or(Duration::ZERO))
+}
+
+fn parse_grpc_timeout(value: &str) -> AppResult<Duration> {
+    let malformed = || {
+        invalid(format!("{} must be up to 8 digits and a unit (H, M, S, m, u, n), got '{}'", GRPC_TIMEOUT_HEADER, value))
+    };
+
+    if !value.is_ascii() {
+        return Err(malformed());
+    }
+    let (amount, unit) = value.split_at(value.len().saturating_sub(1));
+    if amount.is_empty() || amount.len() > 8 || !amount.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(malformed());
+    }
+    let amount: u64 = amount.parse().map_err(|_| malformed())?;
+
+    match unit {
+        "H" => Ok(Duration::from_secs(amount * 3600)),
+        "M" => Ok(Duration::from_secs(amount * 60)),
+        "S" => Ok(Duration::from_secs(amount)),
+        "m" => Ok(Duration::from_millis(amount)),
+        "u" => Ok(Duration::from_micros(amount)),
+        "n" => Ok(Duration::from_nanos(amount)),
+        _ => Err(malformed()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    fn deadline(headers: &[(&str, &str)]) -> AppResult<RequestDeadline> {
+        let req = headers.iter().fold(TestRequest::default(), |req, &header| req.insert_header(header));
+        RequestDeadline::from_request(&req.to_http_request())
+    }
+
+    #[test]
+    fn test_grpc_timeout() {
+        assert_eq!(parse_grpc_timeout("250m").unwrap(), Duration::from_millis(250));
+        assert_eq!(parse_grpc_timeout("2S").unwrap(), Duration::from_secs(2));
+        assert_eq!(parse_grpc_timeout("1H").unwrap(), Duration::from_secs(3600));
+        assert_eq!(parse_grpc_timeout("99999999u").unwrap(), Duration::from_micros(99_999_999));
+
+        for value in ["", "m", "250", "250ms", "123456789m", "-5m", "2.5S"] {
+            assert!(parse_grpc_timeout(value).is_err(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn test_absolute_deadline() {
+        let in_a_second = Utc::now() + chrono::Duration::seconds(1);
+        let remaining = parse_deadline(&in_a_second.timestamp_millis().to_string()).unwrap();
+        assert!(remaining > Duration::from_millis(900) && remaining <= Duration::from_secs(1));
+        assert!(parse_deadline(&in_a_second.to_rfc3339()).unwrap() > Duration::from_millis(900));
+
+        // Already passed
+        assert_eq!(parse_deadline("1700000000000").unwrap(), Duration::ZERO);
+        assert!(parse_deadline("tomorrow").is_err());
+    }
+
+    #[test]
+    fn test_from_request() {
+        assert_eq!(deadline(&[]).unwrap(), RequestDeadline::default());
+        assert!(deadline(&[]).unwrap().remaining().is_none());
+
+        let later = (Utc::now() + chrono::Duration::seconds(30)).timestamp_millis().to_string();
+        let both = deadline(&[(DEADLINE_HEADER, later.as_str()), (GRPC_TIMEOUT_HEADER, "100m")]).unwrap();
+        assert!(both.remaining().unwrap() <= Duration::from_millis(100));
+
+        let err = deadline(&[(GRPC_TIMEOUT_HEADER, "soon")]).unwrap_err();
+        assert_eq!(err.error_code(), "invalid_deadline");
+    }
+
+    #[tokio::test]
+    async fn test_run_cancels_work_past_the_deadline() {
+        assert_eq!(RequestDeadline::default().run(async { 7 }).await.unwrap(), 7);
+        assert_eq!(RequestDeadline::after(Duration::from_secs(5)).run(async { 7 }).await.unwrap(), 7);
+
+        // Dropped mid-flight: the rest of the work never runs
+        let finished = Arc::new(AtomicBool::new(false));
+        let work = {
+            let finished = finished.clone();
+            async move {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                finished.store(true, Ordering::SeqCst);
+            }
+        };
+        let err = RequestDeadline::after(Duration::from_millis(20)).run(work).await.unwrap_err();
+        assert_eq!(err.error_code(), "deadline_exceeded");
+        assert!(!finished.load(Ordering::SeqCst));
+
+        // A share of the deadline runs out first
+        let deadline = RequestDeadline::after(Duration::from_secs(5));
+        let err = deadline.share(0.01).run(tokio::time::sleep(Duration::from_secs(1))).await.unwrap_err();
+        assert_eq!(err.error_code(), "deadline_exceeded");
+        assert!(RequestDeadline::default().share(0.5).remaining().is_none());
+
+        let started = Arc::new(AtomicBool::new(false));
+        let expired = deadline(&[(DEADLINE_HEADER, "1700000000000")]).unwrap();
+        let result = expired.run(async { started.store(true, Ordering::SeqCst) }).await;
+        assert!(result.is_err() && !started.load(Ordering::SeqCst));
+    }
+}
@@ -0,0 +1,83 @@
+use crate::error::{json_field_error, AppError};
+use actix_web::{error, web};
+
+/// Handle JSON payload errors
+pub fn handle_json_payload_error(err: error::JsonPayloadError, req: &actix_web::HttpRequest) -> actix_web::Error {
+    tracing::info!("JSON payload error on {}: {}", req.path(), err);
+
+    match err {
+        error::JsonPayloadError::OverflowKnownLength { .. } | error::JsonPayloadError::Overflow { .. } => {
+            AppError::PayloadTooLarge(err.to_string())
+        }
+        error::JsonPayloadError::Deserialize(ref source) => AppError::InvalidJson {
+            message: format!("Invalid JSON: {}", err),
+            errors: vec![json_field_error(source)],
+        },
+        _ => AppError::InvalidJson {
+            message: format!("Invalid JSON: {}", err),
+            errors: vec![],
+        },
+    }
+    .into()
+}
+
+/// Handle query payload errors
+pub fn handle_query_payload_error(err: error::QueryPayloadError, _req: &actix_web::HttpRequest) -> actix_web::Error {
+    AppError::BadRequest {
+        code: "invalid_query",
+        message: format!("Invalid query: {}", err),
+    }
+    .into()
+}
+
+/// Build a JSON extractor config with the given body size limit
+///
+/// Resource-level configs replace the app-level one, so the error
+/// handler has to be attached here as well.
+pub fn json_config(limit_bytes: usize) -> web::JsonConfig {
+    web::JsonConfig::default()
+        .limit(limit_bytes)
+        .error_handler(handle_json_payload_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::StatusCode;
+    use actix_web::test::TestRequest;
+
+    #[test]
+    fn test_overflow_maps_to_413() {
+        let req = TestRequest::default().to_http_request();
+        let err = handle_json_payload_error(
+            error::JsonPayloadError::OverflowKnownLength { length: 2048, limit: 1024 },
+            &req,
+        );
+
+        assert_eq!(err.as_response_error().status_code(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[test]
+    fn test_deserialize_error_has_field_details() {
+        let req = TestRequest::default().to_http_request();
+        let source = serde_json::from_str::<crate::models::RecordEventRequest>(r#"{"userId": "a"}"#).unwrap_err();
+        let err = handle_json_payload_error(error::JsonPayloadError::Deserialize(source), &req);
+
+        let response = err.as_response_error().error_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let body = actix_web::body::to_bytes(response.into_body());
+        let body = futures_util::FutureExt::now_or_never(body).unwrap().unwrap();
+        let body: crate::models::ErrorResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body.error_code, "invalid_json");
+        assert_eq!(body.validation_errors[0].field, "targetUserId");
+    }
+
+    #[test]
+    fn test_invalid_json_maps_to_400() {
+        let req = TestRequest::default().to_http_request();
+        let err = handle_json_payload_error(error::JsonPayloadError::ContentType, &req);
+
+        assert_eq!(err.as_response_error().status_code(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,59 @@
+use actix_web::http::header::{EntityTag, Header, IfNoneMatch};
+use actix_web::HttpRequest;
+use sha2::{Digest, Sha256};
+
+/// Strong entity tag of a response body (first 128 bits of its SHA-256)
+pub fn content_etag(body: &[u8]) -> EntityTag {
+    let digest = Sha256::digest(body);
+    EntityTag::new_strong(hex::encode(&digest[..16]))
+}
+
+/// Whether the client's `If-None-Match` already covers `etag`
+///
+/// Uses the weak comparison RFC 9110 prescribes for `If-None-Match`, so a
+/// `W/` prefix added by a proxy still matches.
+pub fn not_modified(req: &HttpRequest, etag: &EntityTag) -> bool {
+    match IfNoneMatch::parse(req) {
+        Ok(IfNoneMatch::Any) => true,
+        Ok(IfNoneMatch::Items(tags)) => tags.iter().any(|tag| tag.weak_eq(etag)),
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::header;
+    use actix_web::test::TestRequest;
+
+    #[test]
+    fn test_content_etag() {
+        let etag = content_etag(b"{\"count\":1}");
+        assert!(!etag.weak);
+        assert_eq!(etag.tag().len(), 32);
+        assert_eq!(etag, content_etag(b"{\"count\":1}"));
+        assert_ne!(etag, content_etag(b"{\"count\":2}"));
+    }
+
+    #[test]
+    fn test_not_modified() {
+        let etag = content_etag(b"body");
+        let quoted = etag.to_string();
+
+        let req = TestRequest::default().to_http_request();
+        assert!(!not_modified(&req, &etag));
+
+        let req = TestRequest::default().insert_header((header::IF_NONE_MATCH, quoted.as_str())).to_http_request();
+        assert!(not_modified(&req, &etag));
+
+        let listed = format!("\"other\", W/{}", quoted);
+        let req = TestRequest::default().insert_header((header::IF_NONE_MATCH, listed)).to_http_request();
+        assert!(not_modified(&req, &etag));
+
+        let req = TestRequest::default().insert_header((header::IF_NONE_MATCH, "*")).to_http_request();
+        assert!(not_modified(&req, &etag));
+
+        let req = TestRequest::default().insert_header((header::IF_NONE_MATCH, "\"other\"")).to_http_request();
+        assert!(!not_modified(&req, &etag));
+    }
+}
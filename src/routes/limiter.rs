@@ -0,0 +1,145 @@
+use crate::config::ConcurrencySettings;
+use crate::error::{AppError, AppResult};
+use crate::services::FeedError;
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Per-instance cap on concurrent matching pipelines
+///
+/// Each tenant has its own, so a cache flush can't send more than
+/// `max_concurrent` profile and candidate queries at its Appwrite and
+/// PostgreSQL at once, nor starve other tenants of slots. Requests over the
+/// cap wait briefly for a slot and are then shed with 503 `overloaded`.
+pub struct ConcurrencyLimiter {
+    semaphore: Option<Arc<Semaphore>>,
+    max_concurrent: usize,
+    queue_timeout: Duration,
+    retry_after_secs: u64,
+    rejected: AtomicU64,
+}
+
+/// Limiter counters for /metrics
+#[derive(Debug, Clone, Serialize)]
+pub struct LimiterStats {
+    /// 0 when unlimited
+    pub max_concurrent: usize,
+    pub in_flight: usize,
+    pub rejected: u64,
+}
+
+impl ConcurrencyLimiter {
+    pub fn new(settings: &ConcurrencySettings) -> Self {
+        Self {
+            semaphore: (settings.max_concurrent > 0).then(|| Arc::new(Semaphore::new(settings.max_concurrent))),
+            max_concurrent: settings.max_concurrent,
+            queue_timeout: Duration::from_millis(settings.queue_timeout_ms),
+            retry_after_secs: settings.retry_after_secs,
+            rejected: AtomicU64::new(0),
+        }
+    }
+
+    /// A limiter that never rejects
+    pub fn unlimited() -> Self {
+        Self::new(&ConcurrencySettings { max_concurrent: 0, ..ConcurrencySettings::default() })
+    }
+
+    /// Wait for a slot, held until the returned permit is dropped
+    ///
+    /// Returns None when unlimited, and `AppError::Overloaded` when no slot
+    /// frees up within the queue timeout.
+    pub async fn acquire(&self) -> AppResult<Option<OwnedSemaphorePermit>> {
+        let Some(semaphore) = &self.semaphore else {
+            return Ok(None);
+        };
+
+        if let Ok(permit) = semaphore.clone().try_acquire_owned() {
+            return Ok(Some(permit));
+        }
+
+        match tokio::time::timeout(self.queue_timeout, semaphore.clone().acquire_owned()).await {
+            Ok(Ok(permit)) => Ok(Some(permit)),
+            _ => {
+                self.rejected.fetch_add(1, Ordering::Relaxed);
+                tracing::warn!("Shedding find request: {} matching pipelines in flight", self.max_concurrent);
+                Err(AppError::Overloaded {
+                    message: "Too many matching requests in flight, retry shortly".to_string(),
+                    retry_after_secs: self.retry_after_secs,
+                })
+            }
+        }
+    }
+
+    /// [`acquire`](Self::acquire) for a feed pipeline, shed as
+    /// `FeedError::Overloaded` so coalesced requests share the rejection
+    pub async fn admit(&self) -> Result<Option<OwnedSemaphorePermit>, FeedError> {
+        self.acquire().await.map_err(|_| FeedError::Overloaded { retry_after_secs: self.retry_after_secs })
+    }
+
+    pub fn stats(&self) -> LimiterStats {
+        LimiterStats {
+            max_concurrent: self.max_concurrent,
+            in_flight: self
+                .semaphore
+                .as_ref()
+                .map_or(0, |semaphore| self.max_concurrent - semaphore.available_permits()),
+            rejected: self.rejected.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(max_concurrent: usize) -> ConcurrencyLimiter {
+        ConcurrencyLimiter::new(&ConcurrencySettings { max_concurrent, queue_timeout_ms: 20, retry_after_secs: 3 })
+    }
+
+    #[tokio::test]
+    async fn test_sheds_when_saturated() {
+        let limiter = limiter(2);
+        let first = limiter.acquire().await.unwrap();
+        let _second = limiter.acquire().await.unwrap();
+        assert_eq!(limiter.stats().in_flight, 2);
+
+        match limiter.acquire().await {
+            Err(AppError::Overloaded { retry_after_secs, .. }) => assert_eq!(retry_after_secs, 3),
+            other => panic!("expected Overloaded, got {:?}", other.map(|p| p.is_some())),
+        }
+        assert_eq!(limiter.stats().rejected, 1);
+
+        drop(first);
+        assert!(limiter.acquire().await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_waits_for_slot() {
+        let limiter = Arc::new(ConcurrencyLimiter::new(&ConcurrencySettings {
+            max_concurrent: 1,
+            queue_timeout_ms: 1000,
+            retry_after_secs: 1,
+        }));
+        let permit = limiter.acquire().await.unwrap();
+
+        let waiter = tokio::spawn({
+            let limiter = limiter.clone();
+            async move { limiter.acquire().await.map(|p| p.is_some()) }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        drop(permit);
+
+        assert!(waiter.await.unwrap().unwrap());
+        assert_eq!(limiter.stats().rejected, 0);
+    }
+
+    #[tokio::test]
+    async fn test_unlimited() {
+        let limiter = ConcurrencyLimiter::unlimited();
+        assert!(limiter.acquire().await.unwrap().is_none());
+        assert_eq!(limiter.stats().max_concurrent, 0);
+        assert_eq!(limiter.stats().in_flight, 0);
+    }
+}
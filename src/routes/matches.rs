@@ -1,31 +1,80 @@
-use actix_web::{web, HttpResponse, Responder};
+use actix_web::{http::header, web, HttpRequest, HttpResponse, Responder};
 use validator::Validate;
-use crate::models::{FindMatchesRequest, RecordEventRequest, FindMatchesResponse, HealthResponse, RecordEventResponse, ErrorResponse, MatchEvent, MatchEventType};
-use crate::services::{AppwriteClient, CacheManager, CacheKey, PostgresClient, EventType};
-use crate::core::Matcher;
+use crate::models::{FindMatchesRequest, RecordEventRequest, FindMatchesResponse, HealthResponse, RecordEventResponse, MatchEvent, MatchEventType, LikesReceivedQuery, LikesReceivedResponse, LikeReceived, ViewersQuery, ViewersResponse, ProfileViewer, ExpiringMatchesQuery, ExpiringMatchesResponse, ExpiringMatch, ScoreCandidatesRequest, ScoreCandidatesResponse, TopPick, TopPickQuery, TopPickResponse, UserStatsQuery, UserStatsResponse};
+use crate::services::{ProfileStore, SeenStore, Cache, CacheKey, EventType, SeenPruner, EventReconciler, MatchExpirer, Feed, FeedError, FeedGenerator, FeedOptions, EventPublisher};
+use crate::config::{ConfigProfile, EventDedupSettings, EventTimeSettings, PayloadLimitSettings, TierSettings, ViewerSettings};
+use crate::error::{AppError, AppResult};
+use crate::routes::admin;
+use crate::routes::case::ResponseCase;
+use crate::routes::errors::json_config;
+use crate::routes::{ConcurrencyLimiter, RequestDeadline, Tenant};
 use std::sync::Arc;
 
 /// Application state shared across all handlers
 #[derive(Clone)]
 pub struct AppState {
-    pub appwrite: Arc<AppwriteClient>,
-    pub cache: Arc<CacheManager>,
-    pub postgres: Arc<PostgresClient>,
-    pub matcher: Matcher,
+    pub appwrite: Arc<dyn ProfileStore>,
+    pub cache: Arc<dyn Cache>,
+    pub postgres: Arc<dyn SeenStore>,
+    pub pruner: Arc<SeenPruner>,
+    pub reconciler: Arc<EventReconciler>,
+    pub expirer: Arc<MatchExpirer>,
+    pub admin_token: Option<String>,
+    pub feed: Arc<FeedGenerator>,
+    /// Streams match events to Kafka/NATS (no-op when disabled)
+    pub events: Arc<EventPublisher>,
+    /// Serve plain find requests from worker-generated feeds
+    pub serve_pregenerated: bool,
+    /// Per-tier limits (daily likes, who viewed me)
+    pub tiers: TierSettings,
+    pub viewers: ViewerSettings,
+    /// Accepted range of client-supplied event timestamps
+    pub event_time: EventTimeSettings,
+    pub event_dedup: EventDedupSettings,
+    /// Caps matching pipelines in flight on this instance (one per tenant)
+    pub find_limiter: Arc<ConcurrencyLimiter>,
+    /// Configuration profile selected with `LUME_ENV`, reported by `/health`
+    pub profile: Option<ConfigProfile>,
 }
 
 /// Configure all match-related routes
-pub fn configure(cfg: &mut web::ServiceConfig) {
+pub fn configure(cfg: &mut web::ServiceConfig, limits: PayloadLimitSettings) {
     cfg
         .route("/health", web::get().to(health_check))
-        .route("/matches/find", web::post().to(find_matches))
-        .route("/matches/event", web::post().to(record_event))
+        .service(
+            web::resource("/matches/find")
+                .app_data(json_config(limits.find_bytes))
+                .route(web::post().to(find_matches)),
+        )
+        .service(
+            web::resource("/matches/score")
+                .app_data(json_config(limits.score_bytes))
+                .route(web::post().to(score_candidates)),
+        )
+        .service(
+            web::resource("/matches/event")
+                .app_data(json_config(limits.event_bytes))
+                .route(web::post().to(record_event)),
+        )
         .route("/matches/seen", web::get().to(get_seen_profiles))
-        .route("/debug/echo", web::post().to(debug_echo));
+        .route("/matches/likes-received", web::get().to(get_likes_received))
+        .route("/matches/stats", web::get().to(get_user_stats))
+        .route("/matches/viewers", web::get().to(get_viewers))
+        .route("/matches/expiring", web::get().to(get_expiring_matches))
+        .route("/matches/top-pick", web::get().to(get_top_pick))
+        .route("/metrics", web::get().to(metrics))
+        .service(
+            web::resource("/debug/echo")
+                .app_data(web::PayloadConfig::new(limits.default_bytes))
+                .route(web::post().to(debug_echo)),
+        );
 }
 
 /// Health check endpoint
-async fn health_check(state: web::Data<AppState>) -> impl Responder {
+///
+/// Reports PostgreSQL health, which Appwrite endpoint is active and the
+/// configuration profile the instance was started with.
+async fn health_check(state: Tenant) -> impl Responder {
     // Check PostgreSQL health
     let pg_healthy = state.postgres.health_check().await.unwrap_or(false);
 
@@ -35,9 +84,33 @@ async fn health_check(state: web::Data<AppState>) -> impl Responder {
         status: status.to_string(),
         version: env!("CARGO_PKG_VERSION").to_string(),
         timestamp: chrono::Utc::now(),
+        appwrite: state.appwrite.endpoint_status(),
+        profile: state.profile.map(|profile| profile.to_string()),
     })
 }
 
+/// Service metrics endpoint
+///
+/// GET /api/v1/metrics
+///
+/// Returns PostgreSQL pool statistics (size, idle, acquire wait times,
+/// slow query count), cache statistics, PostgreSQL/Appwrite event
+/// divergence from the reconciliation job, shadow ranking agreement
+/// (null unless shadow scoring is enabled) and Appwrite documents skipped
+/// because they failed to parse.
+async fn metrics(state: Tenant) -> impl Responder {
+    HttpResponse::Ok().json(serde_json::json!({
+        "postgres": state.postgres.pool_stats(),
+        "cache": state.cache.stats(),
+        "dual_write": state.reconciler.stats().await,
+        "shadow": state.feed.shadow_stats(),
+        "events": state.events.stats(),
+        "find_concurrency": state.find_limiter.stats(),
+        "find_coalesced": state.feed.coalesced_requests(),
+        "appwrite_parse_failures": state.appwrite.parse_failures(),
+    }))
+}
+
 /// Debug endpoint to echo raw JSON for debugging
 async fn debug_echo(
     body: web::Bytes,
@@ -62,108 +135,122 @@ async fn debug_echo(
 ///   "userId": "string",
 ///   "limit": 20,
 ///   "excludeUserIds": ["string"],
-///   "cursor": "string"
+///   "cursor": "string",
+///   "overrideLocation": { "lat": 48.85, "lon": 2.35 },
+///   "minScore": 40,
+///   "minResults": 10
 /// }
 /// ```
+///
+/// `weights` overrides the scoring weights, and only with the admin token
+/// (`X-Admin-Token`); apps get 403 `weights_not_allowed`.
+///
+/// JSON responses carry an `ETag`; a request whose `If-None-Match` has it
+/// gets an empty 304. With `Accept: application/x-ndjson` the matches are
+/// streamed one JSON object per line instead, with the totals in response
+/// headers.
+///
+/// An `X-Request-Deadline` or `grpc-timeout` header from the gateway bounds
+/// the pipeline: past it, the pipeline is cancelled and the request fails
+/// with 504 `deadline_exceeded`.
 async fn find_matches(
-    state: web::Data<AppState>,
+    state: Tenant,
     req: web::Json<FindMatchesRequest>,
-    http_req: actix_web::HttpRequest,
-) -> impl Responder {
+    http_req: HttpRequest,
+) -> AppResult<HttpResponse> {
     // Validate request
     if let Err(errors) = req.validate() {
         tracing::info!("Validation failed for find_matches request: field_errors={:?}", errors);
         tracing::info!("Request data: userId={:?}, limit={:?}, excludeUserIds={:?}",
             req.user_id, req.limit, req.exclude_user_ids);
         tracing::info!("Request path: {}, method: {}", http_req.path(), http_req.method());
-        return HttpResponse::BadRequest().json(ErrorResponse {
-            error: "Validation failed".to_string(),
-            message: errors.to_string(),
-            status_code: 400,
+        return Err(errors.into());
+    }
+
+    // Weight overrides are for operators tuning the ranking, not for apps
+    if req.weights.is_some() && !admin::has_admin_token(&state, &http_req) {
+        return Err(AppError::Forbidden {
+            code: "weights_not_allowed",
+            message: "weights overrides require a valid X-Admin-Token header".to_string(),
         });
     }
 
+    let case = ResponseCase::from_request(&http_req)?;
+    let deadline = RequestDeadline::from_request(&http_req)?;
     let user_id = &req.user_id;
     // Cap limit at 100 to prevent excessive queries
     let limit = req.limit.min(100) as usize;
 
     tracing::info!("Finding matches for user: {}, limit: {}", user_id, limit);
 
-    // Note: Caching disabled for matches endpoint to ensure seen profiles are always up-to-date
-
-    // Fetch already seen profiles from PostgreSQL to prevent repeats
-    let mut seen_profile_ids = match state.postgres.get_seen_profiles(user_id).await {
-        Ok(ids) => ids,
-        Err(e) => {
-            tracing::warn!("Failed to fetch seen profiles for {}, proceeding without filtering: {}", user_id, e);
-            vec![]
-        }
-    };
-
-    // Add client-provided exclude IDs (if any)
-    seen_profile_ids.extend(req.exclude_user_ids.clone());
-
-    tracing::debug!("Excluding {} seen profiles for user {}", seen_profile_ids.len(), user_id);
-
-    // Fetch user profile to get location data
-    let user_profile = match state.appwrite.get_profile(user_id).await {
-        Ok(profile) => profile,
-        Err(e) => {
-            tracing::error!("Failed to fetch profile for {}: {}", user_id, e);
-            return HttpResponse::InternalServerError().json(ErrorResponse {
-                error: "Failed to fetch user profile".to_string(),
-                message: e.to_string(),
-                status_code: 500,
-            });
-        }
+    let options = FeedOptions {
+        limit,
+        exclude_user_ids: req.exclude_user_ids.clone(),
+        override_location: req.override_location,
+        weights: req.weights,
+        min_score: req.min_score,
+        min_results: req.min_results.map(usize::from),
     };
 
-    // Fetch user preferences from Appwrite
-    let mut preferences = match state.appwrite.get_preferences(user_id).await {
-        Ok(prefs) => prefs,
-        Err(e) => {
-            tracing::error!("Failed to fetch preferences for {}: {}", user_id, e);
-            return HttpResponse::InternalServerError().json(ErrorResponse {
-                error: "Failed to fetch preferences".to_string(),
-                message: e.to_string(),
-                status_code: 500,
-            });
-        }
-    };
+    // Plain requests are served from the pre-generated feed when one exists.
+    // Its version check reads Appwrite uncached, so it gets half the time
+    // left; if that runs out, the feed is generated live in the rest
+    let try_pregenerated = state.serve_pregenerated && options.is_default() && !req.diagnostics;
+    let feed = deadline
+        .run(async {
+            if try_pregenerated {
+                match deadline.share(0.5).run(state.feed.pregenerated(user_id, limit)).await {
+                    Ok(Some(feed)) => {
+                        tracing::debug!("Serving pre-generated feed for {}", user_id);
+                        return Ok(feed);
+                    }
+                    Ok(None) => {}
+                    Err(_) => tracing::warn!("Pre-generated feed lookup for {} timed out, regenerating", user_id),
+                }
+            }
+            state.feed.generate_coalesced_with(user_id, &options, state.find_limiter.admit()).await
+        })
+        .await??;
 
-    // Update preferences with location from user profile
-    preferences.latitude = user_profile.latitude;
-    preferences.longitude = user_profile.longitude;
+    if wants_ndjson(&http_req) {
+        tracing::info!("Streaming {} matches for user {} as NDJSON", feed.result.matches.len(), user_id);
+        return Ok(ndjson_response(feed, case));
+    }
 
-    // Query candidates from Appwrite
-    let candidates = match state
-        .appwrite
-        .query_candidates(user_id, &preferences, &seen_profile_ids, limit * 5)
-        .await
-    {
-        Ok(candidates) => candidates,
-        Err(e) => {
-            tracing::error!("Failed to query candidates for {}: {}", user_id, e);
-            return HttpResponse::InternalServerError().json(ErrorResponse {
-                error: "Failed to query candidates".to_string(),
-                message: e.to_string(),
-                status_code: 500,
-            });
+    // The top pick is an extra, only computed when asked for; failing to
+    // compute it (or running out of time for it) doesn't fail the feed
+    let top_pick = if req.top_pick {
+        match deadline.run(limited_top_pick(&state, user_id)).await {
+            Ok(Ok(top_pick)) => top_pick,
+            Ok(Err(e)) => {
+                tracing::warn!("Failed to compute top pick for {}: {}", user_id, e);
+                None
+            }
+            Err(_) => {
+                tracing::debug!("Skipping the top pick for {}: deadline passed", user_id);
+                None
+            }
         }
+    } else {
+        None
     };
 
-    tracing::debug!("Found {} candidates for {}", candidates.len(), user_id);
-
-    // Run matching algorithm
-    let result = state
-        .matcher
-        .find_matches(&preferences, candidates, limit);
-
     // Build response
+    let partial = feed.partial();
+    let Feed { result, default_preferences, warnings, diagnostics, market, city, relaxations, .. } = feed;
     let response = FindMatchesResponse {
         matches: result.matches,
         next_cursor: None,  // TODO: implement cursor-based pagination
         total_results: result.total_candidates,
+        global_mode: result.global_mode,
+        default_preferences,
+        partial,
+        warnings: warnings.into_iter().map(str::to_string).collect(),
+        diagnostics: req.diagnostics.then_some(diagnostics),
+        market,
+        city,
+        relaxations,
+        top_pick,
     };
 
     tracing::info!(
@@ -173,7 +260,97 @@ async fn find_matches(
         result.total_candidates
     );
 
-    HttpResponse::Ok().json(response)
+    Ok(case.json_with_etag(&http_req, &response))
+}
+
+/// NDJSON media type for streamed match lists
+const NDJSON: &str = "application/x-ndjson";
+
+/// Whether the client asked for a streamed NDJSON response
+fn wants_ndjson(req: &HttpRequest) -> bool {
+    req.headers()
+        .get(header::ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(|accept| accept.split(',').any(|media| media.trim().starts_with(NDJSON)))
+}
+
+/// Stream matches one per line, serializing each only as the client reads it
+fn ndjson_response(feed: Feed, case: ResponseCase) -> HttpResponse {
+    let partial = feed.partial();
+    let Feed { result, default_preferences, warnings, .. } = feed;
+    let lines = futures_util::stream::iter(
+        result
+            .matches
+            .into_iter()
+            .map(move |scored| case.to_line(&scored).map(web::Bytes::from)),
+    );
+
+    HttpResponse::Ok()
+        .content_type(NDJSON)
+        .insert_header(("X-Total-Results", result.total_candidates))
+        .insert_header(("X-Global-Mode", result.global_mode.to_string()))
+        .insert_header(("X-Default-Preferences", default_preferences.to_string()))
+        .insert_header(("X-Partial", partial.to_string()))
+        .insert_header(("X-Warnings", warnings.join(",")))
+        .streaming(lines)
+}
+
+/// Bulk candidate scoring endpoint
+///
+/// POST /api/v1/matches/score
+///
+/// Scores up to 1000 caller-supplied candidates against one preferences
+/// object with the same filters and scoring as find. Nothing is read from
+/// Appwrite or PostgreSQL; candidates that fail the filters are omitted.
+///
+/// Request body:
+/// ```json
+/// {
+///   "preferences": { "userId": "string", "minAge": 21, ... },
+///   "candidates": [{ "userId": "string", "age": 27, ... }],
+///   "weights": { "distance": 0.5 }
+/// }
+/// ```
+///
+/// `weights` needs the admin token, as on find; apps get 403
+/// `weights_not_allowed`.
+async fn score_candidates(
+    state: Tenant,
+    req: web::Json<ScoreCandidatesRequest>,
+    http_req: HttpRequest,
+) -> AppResult<HttpResponse> {
+    req.validate()?;
+
+    if req.weights.is_some() && !admin::has_admin_token(&state, &http_req) {
+        return Err(AppError::Forbidden {
+            code: "weights_not_allowed",
+            message: "weights overrides require a valid X-Admin-Token header".to_string(),
+        });
+    }
+
+    let case = ResponseCase::from_request(&http_req)?;
+
+    let ScoreCandidatesRequest { preferences, candidates, weights } = req.into_inner();
+    let limit = candidates.len();
+
+    if preferences.has_location() {
+        state.feed.check_geofences(preferences.latitude, preferences.longitude)?;
+    }
+
+    let result = state.feed.score(&preferences, candidates, limit, weights.as_ref());
+
+    tracing::debug!(
+        "Scored {} candidates for {}: {} passed filters",
+        result.total_candidates,
+        preferences.user_id,
+        result.matches.len()
+    );
+
+    Ok(case.json(&ScoreCandidatesResponse {
+        matches: result.matches,
+        total_candidates: result.total_candidates,
+        global_mode: result.global_mode,
+    }))
 }
 
 /// Record match event endpoint
@@ -185,21 +362,23 @@ async fn find_matches(
 /// {
 ///   "userId": "string",
 ///   "targetUserId": "string",
-///   "eventType": "viewed|liked|passed|matched"
+///   "eventType": "viewed|liked|passed|matched",
+///   "occurredAt": "2024-05-01T18:30:00Z"
 /// }
 /// ```
+///
+/// `occurredAt` is optional and must fall within `event_time`; an event
+/// older than the one already recorded for the pair doesn't replace it.
+/// A repeat of the same event within `event_dedup.window_secs` gets a
+/// success response with `"duplicate": true` and is not recorded again.
 async fn record_event(
-    state: web::Data<AppState>,
+    state: Tenant,
     req: web::Json<RecordEventRequest>,
-) -> impl Responder {
+    http_req: HttpRequest,
+) -> AppResult<HttpResponse> {
     // Validate request
-    if let Err(errors) = req.validate() {
-        return HttpResponse::BadRequest().json(ErrorResponse {
-            error: "Validation failed".to_string(),
-            message: errors.to_string(),
-            status_code: 400,
-        });
-    }
+    req.validate()?;
+    let case = ResponseCase::from_request(&http_req)?;
 
     // Parse event type
     let event_type = match req.event_type.to_lowercase().as_str() {
@@ -208,27 +387,60 @@ async fn record_event(
         "passed" => MatchEventType::Passed,
         "matched" => MatchEventType::Matched,
         _ => {
-            return HttpResponse::BadRequest().json(ErrorResponse {
-                error: "Invalid event type".to_string(),
+            return Err(AppError::BadRequest {
+                code: "invalid_event_type",
                 message: "Event type must be one of: viewed, liked, passed, matched".to_string(),
-                status_code: 400,
             });
         }
     };
 
+    let occurred_at = occurred_at(&state.event_time, req.occurred_at, chrono::Utc::now())?;
+
+    // A repeat within the dedup window (e.g. a double-tapped like) is
+    // acknowledged without writing it again
+    let dedup_key = state
+        .event_dedup
+        .enabled
+        .then(|| CacheKey::event_dedup(&req.user_id, &req.target_user_id, &req.event_type.to_lowercase()));
+    if let Some(key) = &dedup_key {
+        match state.cache.set_if_absent(key, state.event_dedup.window_secs).await {
+            Ok(true) => {}
+            Ok(false) => {
+                tracing::debug!("Duplicate event {} -> {} ({}) within the dedup window", req.user_id, req.target_user_id, req.event_type);
+                return Ok(case.json(&RecordEventResponse {
+                    success: true,
+                    event_id: uuid::Uuid::new_v4().to_string(),
+                    duplicate: true,
+                    mutual_match: false,
+                }));
+            }
+            // Fail open: a cache outage must not drop events
+            Err(e) => tracing::warn!("Event dedup check failed, recording anyway: {}", e),
+        }
+    }
+
+    if matches!(event_type, MatchEventType::Liked | MatchEventType::Matched) {
+        if let Err(e) = check_daily_like_limit(&state, &req.user_id).await {
+            release_dedup_claim(&state, dedup_key.as_deref()).await;
+            return Err(e);
+        }
+    }
+
     let event = MatchEvent {
         user_id: req.user_id.clone(),
         target_user_id: req.target_user_id.clone(),
         event_type,
-        created_at: chrono::Utc::now(),
+        created_at: occurred_at,
     };
 
-    // Record event in PostgreSQL for seen profile tracking (primary source)
-    let pg_event_type = EventType::from(event.event_type.clone());
-    let postgres_result = state.postgres.record_seen(
+    // Record event in PostgreSQL for seen profile tracking (primary source),
+    // together with its history entry and any mutual match it completes
+    let pg_event_type = EventType::from(event.event_type);
+    let postgres_result = state.postgres.record_event(
         &req.user_id,
         &req.target_user_id,
         pg_event_type,
+        occurred_at,
     ).await;
 
     // Record event in Appwrite (best-effort, for analytics/backup)
@@ -236,7 +448,7 @@ async fn record_event(
 
     // Handle results - PostgreSQL is the critical one
     match postgres_result {
-        Ok(_) => {
+        Ok(recorded) => {
             // PostgreSQL succeeded - this is what matters for seen profile tracking
             if let Err(e) = &appwrite_result {
                 // Log Appwrite failure but don't fail the request
@@ -249,69 +461,382 @@ async fn record_event(
                 );
             }
 
-            // Invalidate cache for this user
-            let cache_key = CacheKey::matches(&req.user_id);
-            if let Err(e) = state.cache.delete(&cache_key).await {
-                tracing::warn!("Failed to invalidate cache: {}", e);
+            // Invalidate the seen set; a pre-generated feed is kept since it
+            // is filtered against the seen set when served
+            state.cache.invalidate_seen_set(&req.user_id).await;
+
+            state.events.publish_match_event(&event);
+            if recorded.mutual_match {
+                state.events.publish_match_event(&MatchEvent { event_type: MatchEventType::Matched, ..event });
             }
 
-            HttpResponse::Ok().json(RecordEventResponse {
+            Ok(case.json(&RecordEventResponse {
                 success: true,
                 event_id: uuid::Uuid::new_v4().to_string(),
-            })
+                duplicate: false,
+                mutual_match: recorded.mutual_match,
+            }))
         }
+        // PostgreSQL failed - this is the critical failure
         Err(e) => {
-            // PostgreSQL failed - this is the critical failure
-            tracing::error!("Failed to record event in PostgreSQL: {}", e);
-            HttpResponse::InternalServerError().json(ErrorResponse {
-                error: "Failed to record event".to_string(),
-                message: e.to_string(),
-                status_code: 500,
-            })
+            // Let the client's retry through instead of acknowledging it as a duplicate
+            release_dedup_claim(&state, dedup_key.as_deref()).await;
+            Err(AppError::database("Failed to record event")(e))
         }
     }
 }
 
-/// Get seen profiles for a user
+/// Drop an event's dedup claim after the event was not recorded
+async fn release_dedup_claim(state: &AppState, key: Option<&str>) {
+    if let Some(key) = key {
+        if let Err(e) = state.cache.delete(key).await {
+            tracing::warn!("Failed to release event dedup claim {}: {}", key, e);
+        }
+    }
+}
+
+/// When an event happened: the client's `occurredAt` if plausible, else now
 ///
-/// GET /api/v1/matches/seen?userId={userId}
+/// Small clock skew into the future is clamped to `received_at`.
+fn occurred_at(
+    settings: &EventTimeSettings,
+    claimed: Option<chrono::DateTime<chrono::Utc>>,
+    received_at: chrono::DateTime<chrono::Utc>,
+) -> AppResult<chrono::DateTime<chrono::Utc>> {
+    let Some(claimed) = claimed else {
+        return Ok(received_at);
+    };
+
+    let max_skew = chrono::Duration::seconds(settings.max_skew_secs as i64);
+    let max_age = chrono::Duration::seconds(settings.max_age_secs as i64);
+    if claimed > received_at + max_skew {
+        return Err(AppError::BadRequest {
+            code: "invalid_occurred_at",
+            message: format!("occurredAt is more than {}s ahead of the server clock", settings.max_skew_secs),
+        });
+    }
+    if claimed < received_at - max_age {
+        return Err(AppError::BadRequest {
+            code: "invalid_occurred_at",
+            message: format!("occurredAt is more than {}s in the past", settings.max_age_secs),
+        });
+    }
+
+    Ok(claimed.min(received_at))
+}
+
+/// Reject a like once the user's tier has used up today's (UTC) likes
 ///
-/// Returns a list of profile IDs the user has already seen, for client-side
-/// synchronization and debugging purposes.
-async fn get_seen_profiles(
-    state: web::Data<AppState>,
-    query: web::Query<std::collections::HashMap<String, String>>,
-) -> impl Responder {
-    let user_id = match query.get("userId") {
-        Some(id) => id,
-        None => {
-            return HttpResponse::BadRequest().json(ErrorResponse {
-                error: "Missing userId parameter".to_string(),
-                message: "userId query parameter is required".to_string(),
-                status_code: 400,
-            });
+/// Fails open when the profile or the like count can't be read, so an
+/// Appwrite or PostgreSQL hiccup never blocks swiping.
+async fn check_daily_like_limit(state: &AppState, user_id: &str) -> AppResult<()> {
+    let tier = match state.appwrite.get_profile(user_id).await {
+        Ok(profile) => profile.tier(),
+        Err(e) => {
+            tracing::warn!("Failed to fetch profile of {} for the like limit, allowing: {}", user_id, e);
+            return Ok(());
         }
     };
 
-    match state.postgres.get_seen_profiles(user_id).await {
-        Ok(seen_ids) => {
-            HttpResponse::Ok().json(serde_json::json!({
-                "userId": user_id,
-                "seenProfiles": seen_ids,
-                "count": seen_ids.len(),
-            }))
+    let Some(limit) = state.tiers.limits(tier).daily_like_limit else {
+        return Ok(());
+    };
+
+    let start_of_day = chrono::Utc::now().date_naive().and_time(chrono::NaiveTime::MIN).and_utc();
+    match state.postgres.count_likes_since(user_id, start_of_day).await {
+        Ok(count) if count >= i64::from(limit) => {
+            Err(AppError::RateLimited(format!("Daily like limit of {} reached", limit)))
         }
+        Ok(_) => Ok(()),
         Err(e) => {
-            tracing::error!("Failed to fetch seen profiles for {}: {}", user_id, e);
-            HttpResponse::InternalServerError().json(ErrorResponse {
-                error: "Failed to fetch seen profiles".to_string(),
-                message: e.to_string(),
-                status_code: 500,
-            })
+            tracing::warn!("Failed to count today's likes for {}, allowing: {}", user_id, e);
+            Ok(())
         }
     }
 }
 
+/// Get seen profiles for a user
+///
+/// GET /api/v1/matches/seen?userId={userId}
+///
+/// Returns a list of profile IDs the user has already seen, for client-side
+/// synchronization and debugging purposes. Supports `If-None-Match` like
+/// find.
+async fn get_seen_profiles(
+    state: Tenant,
+    query: web::Query<std::collections::HashMap<String, String>>,
+    http_req: HttpRequest,
+) -> AppResult<HttpResponse> {
+    let case = ResponseCase::from_request(&http_req)?;
+    let user_id = query.get("userId").ok_or_else(|| AppError::BadRequest {
+        code: "missing_user_id",
+        message: "userId query parameter is required".to_string(),
+    })?;
+
+    let seen_ids = state
+        .postgres
+        .get_seen_profiles(user_id)
+        .await
+        .map_err(AppError::database("Failed to fetch seen profiles"))?;
+
+    Ok(case.json_with_etag(&http_req, &serde_json::json!({
+        "userId": user_id,
+        "seenProfiles": seen_ids,
+        "count": seen_ids.len(),
+    })))
+}
+
+/// Get swipe statistics for a user
+///
+/// GET /api/v1/matches/stats?userId={userId}
+///
+/// Summarizes the user's seen profiles (likes given, passes, matches, last
+/// activity) with the derived like rate, for profile insights.
+async fn get_user_stats(
+    state: Tenant,
+    query: web::Query<UserStatsQuery>,
+    http_req: HttpRequest,
+) -> AppResult<HttpResponse> {
+    let case = ResponseCase::from_request(&http_req)?;
+    let stats = state
+        .postgres
+        .get_seen_stats(&query.user_id)
+        .await
+        .map_err(AppError::database("Failed to fetch stats"))?;
+
+    Ok(case.json(&UserStatsResponse {
+        like_rate: stats.like_rate(),
+        user_id: stats.user_id,
+        profiles_seen: stats.total_seen,
+        likes_given: stats.liked + stats.matched,
+        passes: stats.passed,
+        matches: stats.matched,
+        last_activity_at: stats.last_seen_at,
+    }))
+}
+
+/// Get the user's top pick of the day
+///
+/// GET /api/v1/matches/top-pick?userId={userId}
+///
+/// Just the pick returned with find, for the home-screen widget. Computed on
+/// the first request of the day and pinned until the user's local midnight.
+/// Bounded by the gateway's deadline headers like find.
+async fn get_top_pick(
+    state: Tenant,
+    query: web::Query<TopPickQuery>,
+    http_req: HttpRequest,
+) -> AppResult<HttpResponse> {
+    let case = ResponseCase::from_request(&http_req)?;
+    let deadline = RequestDeadline::from_request(&http_req)?;
+    let top_pick = deadline.run(limited_top_pick(&state, &query.user_id)).await??;
+
+    Ok(case.json(&TopPickResponse {
+        user_id: query.user_id.clone(),
+        top_pick,
+    }))
+}
+
+/// The top pick; on a miss its matching pipeline runs under the find limiter
+async fn limited_top_pick(state: &AppState, user_id: &str) -> AppResult<Option<TopPick>> {
+    Ok(state.feed.top_pick_with(user_id, state.find_limiter.admit()).await?)
+}
+
+/// Get users who recently viewed or liked the user ("who viewed me")
+///
+/// GET /api/v1/matches/viewers?userId={userId}&limit=20&offset=0
+///
+/// Lists viewers within `viewers.window_days`, newest first. Only tiers with
+/// `see_viewers` may call it, and `viewers.enabled = false` turns it off for
+/// everyone. Viewers who passed, and snoozed viewers, are never listed.
+async fn get_viewers(
+    state: Tenant,
+    query: web::Query<ViewersQuery>,
+    http_req: HttpRequest,
+) -> AppResult<HttpResponse> {
+    let case = ResponseCase::from_request(&http_req)?;
+    let user_id = &query.user_id;
+
+    if !state.viewers.enabled {
+        return Err(AppError::Forbidden {
+            code: "viewers_disabled",
+            message: "Who viewed me is not enabled".to_string(),
+        });
+    }
+
+    let profile = state
+        .appwrite
+        .get_profile(user_id)
+        .await
+        .map_err(|e| AppError::from(FeedError::Profile(e)))?;
+    if !state.tiers.limits(profile.tier()).see_viewers {
+        return Err(AppError::Forbidden {
+            code: "premium_required",
+            message: "Who viewed me is not available on your subscription tier".to_string(),
+        });
+    }
+
+    let since = chrono::Utc::now() - chrono::Duration::days(state.viewers.window_days as i64);
+    let count = state
+        .postgres
+        .count_viewers(user_id, since)
+        .await
+        .map_err(AppError::database("Failed to fetch viewers"))?;
+
+    let limit = query.limit.min(100) as usize;
+    let views = state
+        .postgres
+        .get_viewers(user_id, since, limit, query.offset as usize)
+        .await
+        .map_err(AppError::database("Failed to fetch viewers"))?;
+
+    let viewer_ids: Vec<String> = views.iter().map(|view| view.user_id.clone()).collect();
+    let mut profiles: std::collections::HashMap<String, _> = state
+        .appwrite
+        .get_profiles(&viewer_ids)
+        .await
+        .map_err(AppError::upstream("Failed to fetch viewer profiles"))?
+        .into_iter()
+        .map(|p| (p.user_id.clone(), p))
+        .collect();
+
+    // Keep recency order; deleted and snoozed viewers are dropped
+    let viewers = views
+        .into_iter()
+        .filter_map(|view| {
+            profiles
+                .remove(&view.user_id)
+                .filter(|profile| !profile.snoozed())
+                .map(|profile| ProfileViewer {
+                    is_verified: profile.verified(),
+                    user_id: profile.user_id,
+                    name: profile.name,
+                    age: profile.age,
+                    image_file_ids: profile.image_file_ids,
+                    event_type: view.event_type.into(),
+                    viewed_at: view.viewed_at,
+                })
+        })
+        .collect();
+
+    Ok(case.json(&ViewersResponse {
+        user_id: user_id.clone(),
+        viewers,
+        count,
+        window_days: state.viewers.window_days,
+    }))
+}
+
+/// Get pending likes for a user ("who liked me")
+///
+/// GET /api/v1/matches/likes-received?userId={userId}&limit=20&offset=0
+/// GET /api/v1/matches/likes-received?userId={userId}&countOnly=true
+///
+/// Returns profiles that liked the user but haven't been liked, passed or
+/// matched back, newest first. `countOnly` skips the profile lookup and
+/// returns just the badge count.
+async fn get_likes_received(
+    state: Tenant,
+    query: web::Query<LikesReceivedQuery>,
+    http_req: HttpRequest,
+) -> AppResult<HttpResponse> {
+    let case = ResponseCase::from_request(&http_req)?;
+    let user_id = &query.user_id;
+
+    let count = state
+        .postgres
+        .count_likes_received(user_id)
+        .await
+        .map_err(AppError::database("Failed to fetch likes"))?;
+
+    if query.count_only {
+        return Ok(case.json(&LikesReceivedResponse {
+            user_id: user_id.clone(),
+            likes: vec![],
+            count,
+        }));
+    }
+
+    let limit = query.limit.min(100) as usize;
+    let received = state
+        .postgres
+        .get_likes_received(user_id, limit, query.offset as usize)
+        .await
+        .map_err(AppError::database("Failed to fetch likes"))?;
+
+    let liker_ids: Vec<String> = received.iter().map(|like| like.user_id.clone()).collect();
+    let mut profiles: std::collections::HashMap<String, _> = state
+        .appwrite
+        .get_profiles(&liker_ids)
+        .await
+        .map_err(AppError::upstream("Failed to fetch liker profiles"))?
+        .into_iter()
+        .map(|p| (p.user_id.clone(), p))
+        .collect();
+
+    // Keep recency order; likers whose profile no longer exists are dropped
+    let likes = received
+        .into_iter()
+        .filter_map(|like| {
+            profiles.remove(&like.user_id).map(|profile| LikeReceived {
+                is_verified: profile.verified(),
+                user_id: profile.user_id,
+                name: profile.name,
+                age: profile.age,
+                image_file_ids: profile.image_file_ids,
+                liked_at: like.liked_at,
+            })
+        })
+        .collect();
+
+    Ok(case.json(&LikesReceivedResponse {
+        user_id: user_id.clone(),
+        likes,
+        count,
+    }))
+}
+
+/// Get mutual matches about to expire for lack of follow-up
+///
+/// GET /api/v1/matches/expiring?userId={userId}&withinDays=3
+///
+/// Lists the user's active matches with no event from either side for
+/// long enough that they expire within `withinDays`, soonest first, so the
+/// app can nudge the user. Forbidden when match expiry is disabled.
+async fn get_expiring_matches(
+    state: Tenant,
+    query: web::Query<ExpiringMatchesQuery>,
+    http_req: HttpRequest,
+) -> AppResult<HttpResponse> {
+    let case = ResponseCase::from_request(&http_req)?;
+    let expiry_days = state.expirer.expiry_days().ok_or_else(|| AppError::Forbidden {
+        code: "match_expiry_disabled",
+        message: "Match expiry is not enabled".to_string(),
+    })?;
+    let within_days = query.within_days.unwrap_or_else(|| state.expirer.warning_days());
+
+    let expiry = chrono::Duration::days(expiry_days as i64);
+    let inactive_since = chrono::Utc::now() - expiry + chrono::Duration::days(within_days as i64);
+    let stale = state
+        .postgres
+        .get_stale_matches(&query.user_id, inactive_since)
+        .await
+        .map_err(AppError::database("Failed to fetch expiring matches"))?;
+
+    Ok(case.json(&ExpiringMatchesResponse {
+        user_id: query.user_id.clone(),
+        expiry_days,
+        matches: stale
+            .into_iter()
+            .map(|activity| ExpiringMatch {
+                expires_at: activity.last_activity_at + expiry,
+                user_id: activity.user_id,
+                last_activity_at: activity.last_activity_at,
+            })
+            .collect(),
+    }))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -322,8 +847,72 @@ mod tests {
             status: "healthy".to_string(),
             version: "0.1.0".to_string(),
             timestamp: chrono::Utc::now(),
+            appwrite: None,
+            profile: Some("staging".to_string()),
         };
 
         assert_eq!(response.status, "healthy");
     }
+
+    #[test]
+    fn test_occurred_at() {
+        let settings = EventTimeSettings { max_skew_secs: 300, max_age_secs: 3600 };
+        let now = chrono::Utc::now();
+        let minutes = chrono::Duration::minutes;
+
+        assert_eq!(occurred_at(&settings, None, now).unwrap(), now);
+        assert_eq!(occurred_at(&settings, Some(now - minutes(30)), now).unwrap(), now - minutes(30));
+        // Within the allowed skew counts as now
+        assert_eq!(occurred_at(&settings, Some(now + minutes(2)), now).unwrap(), now);
+        assert!(occurred_at(&settings, Some(now + minutes(10)), now).is_err());
+        assert!(occurred_at(&settings, Some(now - minutes(90)), now).is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_record_event_with_mock_services() {
+        use crate::routes::TenantRegistry;
+        use crate::testing::TestServices;
+        use actix_web::{test, App};
+
+        let services = TestServices::new(vec![], vec![]);
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(TenantRegistry::from(services.app_state())))
+                .configure(|cfg| configure(cfg, PayloadLimitSettings::default())),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/matches/event")
+            .set_json(serde_json::json!({"userId": "a", "targetUserId": "b", "eventType": "liked"}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+        assert_eq!(services.seen.event("a", "b"), Some(EventType::Liked));
+
+        services.seen.set_failing(true);
+        let req = test::TestRequest::post()
+            .uri("/matches/event")
+            .set_json(serde_json::json!({"userId": "a", "targetUserId": "c", "eventType": "passed"}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_server_error());
+    }
+
+    #[test]
+    fn test_wants_ndjson() {
+        use actix_web::test::TestRequest;
+
+        let req = TestRequest::default().insert_header((header::ACCEPT, "application/x-ndjson")).to_http_request();
+        assert!(wants_ndjson(&req));
+
+        let req = TestRequest::default()
+            .insert_header((header::ACCEPT, "application/json, application/x-ndjson;q=0.9"))
+            .to_http_request();
+        assert!(wants_ndjson(&req));
+
+        let req = TestRequest::default().insert_header((header::ACCEPT, "application/json")).to_http_request();
+        assert!(!wants_ndjson(&req));
+        assert!(!wants_ndjson(&TestRequest::default().to_http_request()));
+    }
 }
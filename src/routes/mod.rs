@@ -1,11 +1,27 @@
 // Route exports
+pub mod admin;
+pub mod case;
+pub mod deadline;
+pub mod errors;
+pub mod etag;
+pub mod limiter;
 pub mod matches;
+pub mod signing;
+pub mod tenant;
 
-use actix_web::web;
+use actix_web::{middleware::from_fn, web};
+use crate::config::PayloadLimitSettings;
 
-pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+pub use deadline::{RequestDeadline, DEADLINE_HEADER, GRPC_TIMEOUT_HEADER};
+pub use limiter::ConcurrencyLimiter;
+pub use signing::{SignatureVerifier, SIGNATURE_HEADER};
+pub use tenant::{Tenant, TenantRegistry, TENANT_HEADER};
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig, limits: PayloadLimitSettings) {
     cfg.service(
         web::scope("/api/v1")
-            .configure(matches::configure),
+            .wrap(from_fn(signing::verify_signature))
+            .configure(|cfg| matches::configure(cfg, limits))
+            .configure(admin::configure),
     );
 }
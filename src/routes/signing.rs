@@ -0,0 +1,372 @@
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::{web, Error};
+use crate::config::{PayloadLimitSettings, SigningSettings};
+use crate::error::AppError;
+use crate::services::{Cache, CacheKey, CacheManager};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::sync::Arc;
+use thiserror::Error;
+
+/// Header carrying the request signature: `t=<unix seconds>,v1=<hex mac>`
+pub const SIGNATURE_HEADER: &str = "X-Lume-Signature";
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Reasons a signed request is rejected
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum SignatureError {
+    #[error("Missing {} header", SIGNATURE_HEADER)]
+    Missing,
+
+    #[error("Malformed {} header", SIGNATURE_HEADER)]
+    Malformed,
+
+    #[error("Signature timestamp outside the allowed window")]
+    Expired,
+
+    #[error("Invalid signature")]
+    Mismatch,
+
+    #[error("Signature already used")]
+    Replayed,
+
+    #[error("Could not check the signature for replays: {0}")]
+    NonceStore(String),
+}
+
+/// A signature header that parsed and is within the timestamp window
+struct SignedHeader<'a> {
+    timestamp: i64,
+    signatures: Vec<&'a str>,
+}
+
+/// Verifies HMAC-SHA256 signatures on server-to-server requests
+///
+/// The MAC covers `"<t>.<METHOD>.<path?query>.<body>"`. Timestamps further
+/// than `max_skew` from the local clock are rejected, and each signature is
+/// accepted once within that window. Used signatures are claimed in the
+/// nonce store (Redis `SET NX EX` via [`Cache::set_if_absent`]), so a replay
+/// is caught by any instance sharing it.
+pub struct SignatureVerifier {
+    secret: Vec<u8>,
+    max_skew_secs: i64,
+    routes: Vec<String>,
+    nonces: Arc<dyn Cache>,
+    body_limit: usize,
+}
+
+impl SignatureVerifier {
+    /// Verifier remembering used signatures in this process only, until
+    /// [`with_nonce_store`](Self::with_nonce_store) is set
+    pub fn new(secret: impl Into<Vec<u8>>, max_skew_secs: u64) -> Self {
+        let max_skew_secs = max_skew_secs.min(i64::MAX as u64 / 2) as i64;
+
+        Self {
+            secret: secret.into(),
+            max_skew_secs,
+            routes: Vec::new(),
+            nonces: Arc::new(CacheManager::in_memory(0, 0)),
+            body_limit: PayloadLimitSettings::default().largest(),
+        }
+    }
+
+    /// Require signatures on paths under these prefixes
+    pub fn with_routes(mut self, routes: Vec<String>) -> Self {
+        self.routes = routes;
+        self
+    }
+
+    /// Claim used signatures in `cache`, shared by every instance
+    pub fn with_nonce_store(mut self, cache: Arc<dyn Cache>) -> Self {
+        self.nonces = cache;
+        self
+    }
+
+    /// Largest body buffered for the MAC; set it to the largest route limit
+    /// so each route's own limit still applies after verification
+    pub fn with_body_limit(mut self, bytes: usize) -> Self {
+        self.body_limit = bytes;
+        self
+    }
+
+    /// The configured verifier, or None when signing is disabled or no
+    /// secret is set
+    pub fn from_settings(settings: &SigningSettings) -> Option<Self> {
+        if !settings.enabled {
+            return None;
+        }
+
+        let secret = settings.secret.as_deref().filter(|s| !s.is_empty())?;
+        Some(Self::new(secret, settings.max_skew_secs).with_routes(settings.routes.clone()))
+    }
+
+    /// Check whether requests to `path` must be signed
+    pub fn covers(&self, path: &str) -> bool {
+        self.routes.iter().any(|prefix| {
+            path.strip_prefix(prefix.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/') || prefix.ends_with('/'))
+        })
+    }
+
+    fn mac(&self, timestamp: i64, method: &str, path_and_query: &str, body: &[u8]) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.secret).expect("HMAC accepts keys of any length");
+        mac.update(format!("{}.{}.{}.", timestamp, method, path_and_query).as_bytes());
+        mac.update(body);
+        mac
+    }
+
+    /// Header value signing a request at `timestamp`
+    pub fn sign(&self, timestamp: i64, method: &str, path_and_query: &str, body: &[u8]) -> String {
+        let signature = self.mac(timestamp, method, path_and_query, body).finalize().into_bytes();
+        format!("t={},v1={}", timestamp, hex::encode(signature))
+    }
+
+    /// Verify a request's signature header at local time `now`
+    ///
+    /// Several `v1` entries may be sent (e.g. while rotating the secret);
+    /// one has to match.
+    pub async fn verify(
+        &self,
+        header: Option<&str>,
+        method: &str,
+        path_and_query: &str,
+        body: &[u8],
+        now: i64,
+    ) -> Result<(), SignatureError> {
+        let header = self.check_header(header, now)?;
+        let signature = self.matching_signature(&header, method, path_and_query, body)?;
+        self.claim(signature).await
+    }
+
+    /// Parse the header and check its timestamp, before any body is read
+    fn check_header<'a>(&self, header: Option<&'a str>, now: i64) -> Result<SignedHeader<'a>, SignatureError> {
+        let header = header.ok_or(SignatureError::Missing)?;
+
+        let mut timestamp = None;
+        let mut signatures = Vec::new();
+        for part in header.split(',') {
+            match part.trim().split_once('=') {
+                Some(("t", value)) => timestamp = Some(value.parse::<i64>().map_err(|_| SignatureError::Malformed)?),
+                Some(("v1", value)) => signatures.push(value),
+                _ => {}
+            }
+        }
+
+        let timestamp = timestamp.ok_or(SignatureError::Malformed)?;
+        if signatures.is_empty() {
+            return Err(SignatureError::Malformed);
+        }
+
+        if now.abs_diff(timestamp) > self.max_skew_secs as u64 {
+            return Err(SignatureError::Expired);
+        }
+
+        Ok(SignedHeader { timestamp, signatures })
+    }
+
+    /// The header's signature that matches the request
+    fn matching_signature<'a>(
+        &self,
+        header: &SignedHeader<'a>,
+        method: &str,
+        path_and_query: &str,
+        body: &[u8],
+    ) -> Result<&'a str, SignatureError> {
+        let mac = self.mac(header.timestamp, method, path_and_query, body);
+        header
+            .signatures
+            .iter()
+            .copied()
+            .find(|signature| {
+                hex::decode(signature).is_ok_and(|bytes| mac.clone().verify_slice(&bytes).is_ok())
+            })
+            .ok_or(SignatureError::Mismatch)
+    }
+
+    /// Accept `signature` once: it is claimed until it leaves the window
+    async fn claim(&self, signature: &str) -> Result<(), SignatureError> {
+        // A signature stays fresh for at most 2 × max_skew (from
+        // t - max_skew to t + max_skew on the local clock)
+        let ttl_secs = 2 * self.max_skew_secs as u64 + 1;
+        let key = CacheKey::signature(&signature.to_ascii_lowercase());
+
+        match self.nonces.set_if_absent(&key, ttl_secs).await {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(SignatureError::Replayed),
+            Err(e) => Err(SignatureError::NonceStore(e.to_string())),
+        }
+    }
+}
+
+/// Middleware rejecting unsigned requests to signed routes with 401
+///
+/// A no-op unless a `web::Data<SignatureVerifier>` is registered. The header
+/// and timestamp are checked first, so unsigned and stale requests are
+/// rejected without reading their body. The body is then buffered for the
+/// MAC, up to the verifier's body limit (413 beyond it), and handed back to
+/// the handler.
+pub async fn verify_signature<B: MessageBody>(
+    mut req: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<EitherBody<B>>, Error> {
+    let verifier = match req.app_data::<web::Data<SignatureVerifier>>() {
+        Some(verifier) if verifier.covers(req.path()) => verifier.clone(),
+        _ => return next.call(req).await.map(ServiceResponse::map_into_left_body),
+    };
+
+    let reject = |req: ServiceRequest, e: SignatureError| {
+        tracing::warn!("Rejected {} {}: {}", req.method(), req.path(), e);
+        let error = match e {
+            SignatureError::NonceStore(message) => AppError::Internal(message),
+            e => AppError::Unauthorized(e.to_string()),
+        };
+        Ok(req.error_response(error).map_into_right_body())
+    };
+
+    let header = req.headers().get(SIGNATURE_HEADER).and_then(|v| v.to_str().ok()).map(str::to_owned);
+    let header = match verifier.check_header(header.as_deref(), chrono::Utc::now().timestamp()) {
+        Ok(header) => header,
+        Err(e) => return reject(req, e),
+    };
+
+    let body = match web::Payload(req.take_payload()).to_bytes_limited(verifier.body_limit).await {
+        Ok(Ok(body)) => body,
+        Ok(Err(_)) => {
+            let error = AppError::PayloadTooLarge(format!("Signed body exceeds {} bytes", verifier.body_limit));
+            return Ok(req.error_response(error).map_into_right_body());
+        }
+        Err(e) => return Ok(req.error_response(e).map_into_right_body()),
+    };
+
+    let path_and_query = req.uri().path_and_query().map_or(req.path(), |p| p.as_str());
+    let verified = match verifier.matching_signature(&header, req.method().as_str(), path_and_query, &body) {
+        Ok(signature) => verifier.claim(signature).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = verified {
+        return reject(req, e);
+    }
+
+    req.set_payload(Payload::from(body));
+    next.call(req).await.map(ServiceResponse::map_into_left_body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_700_000_000;
+
+    fn verifier() -> SignatureVerifier {
+        SignatureVerifier::new("s3cret", 300).with_routes(vec!["/api/v1/admin".to_string()])
+    }
+
+    #[test]
+    fn test_covers() {
+        let verifier = verifier();
+        assert!(verifier.covers("/api/v1/admin"));
+        assert!(verifier.covers("/api/v1/admin/seen/prune"));
+        assert!(!verifier.covers("/api/v1/administrators"));
+        assert!(!verifier.covers("/api/v1/matches/find"));
+    }
+
+    #[tokio::test]
+    async fn test_sign_and_verify() {
+        let verifier = verifier();
+        let header = verifier.sign(NOW, "POST", "/api/v1/admin/seen/prune", b"{}");
+        assert!(header.starts_with(&format!("t={},v1=", NOW)));
+
+        assert_eq!(verifier.verify(Some(&header), "POST", "/api/v1/admin/seen/prune", b"{}", NOW + 10).await, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_rejects_tampering() {
+        let verifier = verifier();
+        let header = verifier.sign(NOW, "POST", "/api/v1/matches/event", br#"{"a":1}"#);
+
+        let cases = [
+            ("POST", "/api/v1/matches/event", br#"{"a":2}"#.as_slice()),
+            ("PUT", "/api/v1/matches/event", br#"{"a":1}"#.as_slice()),
+            ("POST", "/api/v1/matches/event?x=1", br#"{"a":1}"#.as_slice()),
+        ];
+        for (method, path, body) in cases {
+            assert_eq!(verifier.verify(Some(&header), method, path, body, NOW).await, Err(SignatureError::Mismatch));
+        }
+
+        let other = SignatureVerifier::new("other", 300).sign(NOW, "POST", "/api/v1/matches/event", br#"{"a":1}"#);
+        assert_eq!(
+            verifier.verify(Some(&other), "POST", "/api/v1/matches/event", br#"{"a":1}"#, NOW).await,
+            Err(SignatureError::Mismatch)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rejects_malformed_and_expired() {
+        let verifier = verifier();
+        let path = "/api/v1/admin/audit";
+
+        assert_eq!(verifier.verify(None, "GET", path, b"", NOW).await, Err(SignatureError::Missing));
+        for header in ["", "v1=abcd", "t=soon,v1=abcd", "t=1700000000"] {
+            assert_eq!(verifier.verify(Some(header), "GET", path, b"", NOW).await, Err(SignatureError::Malformed), "{}", header);
+        }
+
+        let header = verifier.sign(NOW - 301, "GET", path, b"");
+        assert_eq!(verifier.verify(Some(&header), "GET", path, b"", NOW).await, Err(SignatureError::Expired));
+        let header = verifier.sign(NOW + 301, "GET", path, b"");
+        assert_eq!(verifier.verify(Some(&header), "GET", path, b"", NOW).await, Err(SignatureError::Expired));
+    }
+
+    #[tokio::test]
+    async fn test_rejects_replay() {
+        let verifier = verifier();
+        let path = "/api/v1/admin/seen/prune";
+        let header = verifier.sign(NOW, "POST", path, b"");
+
+        assert_eq!(verifier.verify(Some(&header), "POST", path, b"", NOW).await, Ok(()));
+        assert_eq!(verifier.verify(Some(&header), "POST", path, b"", NOW + 1).await, Err(SignatureError::Replayed));
+
+        // Hex is case-insensitive, so re-casing it is the same signature
+        let upper = format!("t={},v1={}", NOW, header.split_once("v1=").unwrap().1.to_ascii_uppercase());
+        assert_eq!(verifier.verify(Some(&upper), "POST", path, b"", NOW).await, Err(SignatureError::Replayed));
+    }
+
+    #[tokio::test]
+    async fn test_replays_are_caught_by_any_instance_sharing_the_store() {
+        let nonces: Arc<dyn Cache> = Arc::new(CacheManager::in_memory(100, 60));
+        let first = verifier().with_nonce_store(nonces.clone());
+        let second = verifier().with_nonce_store(nonces);
+        let path = "/api/v1/admin/seen/prune";
+        let header = first.sign(NOW, "POST", path, b"");
+
+        assert_eq!(first.verify(Some(&header), "POST", path, b"", NOW).await, Ok(()));
+        assert_eq!(second.verify(Some(&header), "POST", path, b"", NOW).await, Err(SignatureError::Replayed));
+    }
+
+    #[tokio::test]
+    async fn test_rotated_secret() {
+        let verifier = verifier();
+        let path = "/api/v1/admin/audit";
+        let old = SignatureVerifier::new("old", 300).sign(NOW, "GET", path, b"");
+        let new = verifier.sign(NOW, "GET", path, b"");
+        let header = format!("{},{}", old, new.split_once(',').unwrap().1);
+
+        assert_eq!(verifier.verify(Some(&header), "GET", path, b"", NOW).await, Ok(()));
+    }
+
+    #[test]
+    fn test_from_settings() {
+        let mut settings = SigningSettings::default();
+        assert!(SignatureVerifier::from_settings(&settings).is_none());
+
+        settings.enabled = true;
+        assert!(SignatureVerifier::from_settings(&settings).is_none());
+
+        settings.secret = Some("s3cret".to_string());
+        let verifier = SignatureVerifier::from_settings(&settings).unwrap();
+        assert!(verifier.covers("/api/v1/matches/event"));
+        assert!(!verifier.covers("/api/v1/matches/find"));
+    }
+}
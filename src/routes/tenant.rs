@@ -0,0 +1,99 @@
+use actix_web::{dev::Payload, web, FromRequest, HttpRequest};
+use crate::error::{AppError, AppResult};
+use crate::routes::matches::AppState;
+use std::collections::HashMap;
+use std::future::{ready, Ready};
+use std::ops::Deref;
+use std::sync::Arc;
+
+/// Header selecting the tenant (app) a request is for
+pub const TENANT_HEADER: &str = "X-Tenant-Id";
+
+/// Client sets of every tenant served by this deployment
+///
+/// Requests without `X-Tenant-Id` use the default tenant, built from the
+/// top-level settings; each entry in `[tenants]` adds a named one.
+#[derive(Clone)]
+pub struct TenantRegistry {
+    default: Arc<AppState>,
+    tenants: HashMap<String, Arc<AppState>>,
+}
+
+impl TenantRegistry {
+    pub fn new(default: AppState) -> Self {
+        Self {
+            default: Arc::new(default),
+            tenants: HashMap::new(),
+        }
+    }
+
+    /// Serve `state` to requests with `X-Tenant-Id: <tenant_id>`
+    pub fn with_tenant(mut self, tenant_id: impl Into<String>, state: AppState) -> Self {
+        self.tenants.insert(tenant_id.into(), Arc::new(state));
+        self
+    }
+
+    /// The tenant's state, the default one for None, or None when unknown
+    pub fn resolve(&self, tenant_id: Option<&str>) -> Option<Arc<AppState>> {
+        match tenant_id {
+            None => Some(self.default.clone()),
+            Some(tenant_id) => self.tenants.get(tenant_id).cloned(),
+        }
+    }
+
+    /// Named tenants (the default one excluded)
+    pub fn tenant_ids(&self) -> impl Iterator<Item = &str> {
+        self.tenants.keys().map(String::as_str)
+    }
+}
+
+impl From<AppState> for TenantRegistry {
+    fn from(default: AppState) -> Self {
+        Self::new(default)
+    }
+}
+
+/// The requesting tenant's state, resolved from `X-Tenant-Id`
+///
+/// Extract it in handlers in place of `web::Data<AppState>`; it derefs to
+/// [`AppState`]. Unknown tenants are rejected with 400 `unknown_tenant`.
+pub struct Tenant(Arc<AppState>);
+
+impl Deref for Tenant {
+    type Target = AppState;
+
+    fn deref(&self) -> &AppState {
+        &self.0
+    }
+}
+
+impl FromRequest for Tenant {
+    type Error = AppError;
+    type Future = Ready<AppResult<Self>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(resolve(req))
+    }
+}
+
+fn resolve(req: &HttpRequest) -> AppResult<Tenant> {
+    let registry = req
+        .app_data::<web::Data<TenantRegistry>>()
+        .ok_or_else(|| AppError::Internal("Tenant registry not configured".to_string()))?;
+
+    let tenant_id = match req.headers().get(TENANT_HEADER) {
+        Some(value) => Some(value.to_str().map(str::trim).map_err(|_| AppError::BadRequest {
+            code: "unknown_tenant",
+            message: format!("{} must be visible ASCII", TENANT_HEADER),
+        })?),
+        None => None,
+    };
+
+    registry
+        .resolve(tenant_id.filter(|id| !id.is_empty()))
+        .map(Tenant)
+        .ok_or_else(|| AppError::BadRequest {
+            code: "unknown_tenant",
+            message: format!("Unknown tenant '{}'", tenant_id.unwrap_or_default()),
+        })
+}
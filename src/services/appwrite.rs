@@ -1,8 +1,18 @@
-use crate::models::{UserProfile, UserPreferences, MatchEvent};
-use reqwest::Client;
+use crate::config::{AppwriteSettings, CollectionSettings};
+use crate::models::{AppwriteEndpointStatus, UserProfile, UserPreferences, MatchEvent};
+use crate::services::store::CandidatePage;
+use crate::services::cache::SharedError;
+use chrono::{DateTime, Utc};
+use reqwest::{Client, RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::atomic::{AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use thiserror::Error;
+use tokio::task::JoinHandle;
 
 /// Errors that can occur when interacting with Appwrite
 #[derive(Debug, Error)]
@@ -23,6 +33,99 @@ pub enum AppwriteError {
     InvalidResponse(String),
 }
 
+impl SharedError for AppwriteError {
+    /// Transport errors can't be cloned and are carried over as their message
+    fn duplicate(&self) -> Self {
+        match self {
+            AppwriteError::RequestError(e) => AppwriteError::ApiError(e.to_string()),
+            AppwriteError::ApiError(message) => AppwriteError::ApiError(message.clone()),
+            AppwriteError::NotFound(message) => AppwriteError::NotFound(message.clone()),
+            AppwriteError::Unauthorized => AppwriteError::Unauthorized,
+            AppwriteError::InvalidResponse(message) => AppwriteError::InvalidResponse(message.clone()),
+        }
+    }
+}
+
+/// Appwrite document with its `$`-prefixed system fields split from the payload
+///
+/// Payload fields are read from a nested `data` object when present, otherwise
+/// from the document root (system fields are ignored by the domain models).
+#[derive(Debug, Clone)]
+pub struct AppwriteDocument<T> {
+    pub id: Option<String>,
+    pub collection_id: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub permissions: Vec<String>,
+    pub data: T,
+}
+
+/// System fields Appwrite attaches to every document
+#[derive(Debug, Default, Deserialize)]
+struct SystemFields {
+    #[serde(rename = "$id", default)]
+    id: Option<String>,
+    #[serde(rename = "$collectionId", default)]
+    collection_id: Option<String>,
+    #[serde(rename = "$createdAt", default)]
+    created_at: Option<DateTime<Utc>>,
+    #[serde(rename = "$updatedAt", default)]
+    updated_at: Option<DateTime<Utc>>,
+    #[serde(rename = "$permissions", default)]
+    permissions: Vec<String>,
+}
+
+impl<T: DeserializeOwned> AppwriteDocument<T> {
+    /// Parse a raw document returned by the Appwrite REST API
+    pub fn from_value(doc: &Value) -> Result<Self, serde_json::Error> {
+        let system = SystemFields::deserialize(doc)?;
+        let data = T::deserialize(doc.get("data").unwrap_or(doc))?;
+
+        Ok(Self {
+            id: system.id,
+            collection_id: system.collection_id,
+            created_at: system.created_at,
+            updated_at: system.updated_at,
+            permissions: system.permissions,
+            data,
+        })
+    }
+
+    /// Discard the system fields
+    pub fn into_inner(self) -> T {
+        self.data
+    }
+}
+
+impl From<AppwriteDocument<UserProfile>> for UserProfile {
+    /// Use `$createdAt` when the profile carries no creation time of its own
+    fn from(doc: AppwriteDocument<UserProfile>) -> Self {
+        let mut profile = doc.data;
+        if profile.created_at.is_none() {
+            profile.created_at = doc.created_at;
+        }
+        profile.updated_at = doc.updated_at.or(profile.updated_at);
+        profile
+    }
+}
+
+impl From<AppwriteDocument<UserPreferences>> for UserPreferences {
+    fn from(doc: AppwriteDocument<UserPreferences>) -> Self {
+        let mut preferences = doc.data;
+        preferences.updated_at = doc.updated_at.or(preferences.updated_at);
+        preferences
+    }
+}
+
+/// Default cap on `notEqual("userId", ...)` exclusions sent to Appwrite per query
+///
+/// Appwrite limits both the number of queries per request and the URL length;
+/// exclusions beyond this cap are filtered in-process instead.
+pub const DEFAULT_MAX_QUERY_EXCLUSIONS: usize = 50;
+
+/// Upper bound for the Appwrite `limit()` query
+const APPWRITE_MAX_LIMIT: usize = 5000;
+
 /// Appwrite API client
 ///
 /// Handles all communication with the Appwrite backend including:
@@ -30,12 +133,157 @@ pub enum AppwriteError {
 /// - Querying candidate profiles
 /// - Recording match events
 pub struct AppwriteClient {
-    base_url: String,
+    endpoints: Endpoints,
     api_key: String,
     project_id: String,
     database_id: String,
     client: Client,
     collections: AppwriteCollections,
+    max_query_exclusions: usize,
+    parse_failures: ParseFailures,
+}
+
+/// Consecutive failed requests before switching to the next endpoint
+pub const DEFAULT_FAILOVER_THRESHOLD: u32 = 5;
+
+/// Appwrite endpoints in priority order with the one currently in use
+struct Endpoints {
+    urls: Vec<String>,
+    active: AtomicUsize,
+    consecutive_failures: AtomicU32,
+    failovers: AtomicU64,
+    failover_threshold: u32,
+}
+
+impl Endpoints {
+    fn new(urls: Vec<String>, failover_threshold: u32) -> Self {
+        Self {
+            urls: urls.into_iter().map(|url| url.trim_end_matches('/').to_string()).collect(),
+            active: AtomicUsize::new(0),
+            consecutive_failures: AtomicU32::new(0),
+            failovers: AtomicU64::new(0),
+            failover_threshold: failover_threshold.max(1),
+        }
+    }
+
+    fn active(&self) -> usize {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    fn active_url(&self) -> &str {
+        &self.urls[self.active()]
+    }
+
+    fn record_success(&self) {
+        self.consecutive_failures.store(0, Ordering::Relaxed);
+    }
+
+    fn record_failure(&self) {
+        let failures = self.consecutive_failures.fetch_add(1, Ordering::Relaxed) + 1;
+        if failures < self.failover_threshold || self.urls.len() < 2 {
+            return;
+        }
+
+        // Only the request that crossed the threshold switches
+        let from = self.active();
+        let to = (from + 1) % self.urls.len();
+        if self.active.compare_exchange(from, to, Ordering::Relaxed, Ordering::Relaxed).is_ok() {
+            self.consecutive_failures.store(0, Ordering::Relaxed);
+            self.failovers.fetch_add(1, Ordering::Relaxed);
+            tracing::warn!(
+                "Appwrite endpoint {} failed {} requests in a row, failing over to {}",
+                self.urls[from],
+                failures,
+                self.urls[to]
+            );
+        }
+    }
+
+    fn fail_back(&self) {
+        let from = self.active.swap(0, Ordering::Relaxed);
+        if from != 0 {
+            self.consecutive_failures.store(0, Ordering::Relaxed);
+            tracing::info!("Appwrite primary {} is healthy again, switching back from {}", self.urls[0], self.urls[from]);
+        }
+    }
+
+    fn status(&self) -> AppwriteEndpointStatus {
+        let active = self.active();
+        AppwriteEndpointStatus {
+            active: self.urls[active].clone(),
+            primary: active == 0,
+            endpoints: self.urls.len(),
+            consecutive_failures: self.consecutive_failures.load(Ordering::Relaxed),
+            failovers: self.failovers.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Documents skipped because they didn't parse, by collection, field and reason
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ParseFailureStats {
+    pub total: u64,
+    /// e.g. `"profiles.hairColor: missing"` or `"profiles.age: invalid type"`
+    pub reasons: BTreeMap<String, u64>,
+}
+
+#[derive(Default)]
+struct ParseFailures {
+    reasons: Mutex<BTreeMap<String, u64>>,
+}
+
+impl ParseFailures {
+    fn record(&self, reasons: &BTreeMap<String, u64>) {
+        let mut totals = self.reasons.lock().unwrap();
+        for (reason, count) in reasons {
+            *totals.entry(reason.clone()).or_default() += count;
+        }
+    }
+
+    fn stats(&self) -> ParseFailureStats {
+        let reasons = self.reasons.lock().unwrap().clone();
+        ParseFailureStats { total: reasons.values().sum(), reasons }
+    }
+}
+
+/// Why a document failed to parse, as `collection.field: kind`
+///
+/// The offending value is left out so the set of reasons stays small.
+pub fn parse_failure_reason<T: DeserializeOwned>(collection: &str, doc: &Value, err: &serde_json::Error) -> String {
+    let message = err.to_string();
+    if let Some(field) = message.strip_prefix("missing field `").and_then(|rest| rest.strip_suffix('`')) {
+        return format!("{}.{}: missing", collection, field);
+    }
+
+    let kind = message.split(':').next().unwrap_or_default();
+    match failing_field::<T>(doc) {
+        Some(field) => format!("{}.{}: {}", collection, field, kind),
+        None => format!("{}: {}", collection, kind),
+    }
+}
+
+/// The field behind a parse error serde doesn't name: the one whose removal
+/// makes the document parse, or turns the error into that field missing
+///
+/// None when the data itself parses (the system fields were at fault).
+fn failing_field<T: DeserializeOwned>(doc: &Value) -> Option<String> {
+    let data = doc.get("data").unwrap_or(doc);
+    if T::deserialize(data).is_ok() {
+        return None;
+    }
+
+    let fields = data.as_object()?;
+    fields
+        .keys()
+        .find(|field| {
+            let mut probe = fields.clone();
+            probe.remove(field.as_str());
+            match T::deserialize(&Value::Object(probe)) {
+                Ok(_) => true,
+                Err(e) => e.to_string() == format!("missing field `{}`", field),
+            }
+        })
+        .cloned()
 }
 
 /// Collection IDs in Appwrite
@@ -62,13 +310,133 @@ impl AppwriteClient {
             .expect("Failed to create HTTP client");
 
         Self {
-            base_url,
+            endpoints: Endpoints::new(vec![base_url], DEFAULT_FAILOVER_THRESHOLD),
             api_key,
             project_id,
             database_id,
             client,
             collections,
+            max_query_exclusions: DEFAULT_MAX_QUERY_EXCLUSIONS,
+            parse_failures: ParseFailures::default(),
+        }
+    }
+
+    /// Client for `collections` with the configured endpoints and query limits
+    ///
+    /// The failover health check is not started; see
+    /// [`spawn_health_check`](Self::spawn_health_check).
+    pub fn from_settings(settings: &AppwriteSettings, collections: &CollectionSettings) -> Self {
+        Self::new(
+            settings.endpoint.clone(),
+            settings.api_key.clone(),
+            settings.project_id.clone(),
+            settings.database_id.clone(),
+            AppwriteCollections {
+                user_profiles: collections.user_profiles.clone(),
+                user_preferences: collections.user_preferences.clone(),
+                match_events: collections.match_events.clone(),
+                user_matches: collections.user_matches.clone(),
+            },
+        )
+        .with_max_query_exclusions(settings.max_query_exclusions.unwrap_or(DEFAULT_MAX_QUERY_EXCLUSIONS))
+        .with_fallback_endpoints(settings.fallback_endpoints.clone(), settings.failover_threshold)
+    }
+
+    /// Add fallback endpoints (e.g. other regions), tried in order once the
+    /// active endpoint has failed `failover_threshold` requests in a row
+    pub fn with_fallback_endpoints(mut self, fallbacks: Vec<String>, failover_threshold: u32) -> Self {
+        let mut urls = std::mem::take(&mut self.endpoints.urls);
+        urls.extend(fallbacks);
+        self.endpoints = Endpoints::new(urls, failover_threshold);
+        self
+    }
+
+    /// The endpoint requests currently go to, without a trailing slash
+    pub fn base_url(&self) -> &str {
+        self.endpoints.active_url()
+    }
+
+    /// Active endpoint and failover counters, for the health route
+    pub fn endpoint_status(&self) -> AppwriteEndpointStatus {
+        self.endpoints.status()
+    }
+
+    /// Documents skipped since startup because they didn't parse
+    pub fn parse_failures(&self) -> ParseFailureStats {
+        self.parse_failures.stats()
+    }
+
+    /// Parse a page of documents, skipping (and counting) those that don't parse
+    ///
+    /// Failures are logged once per page, aggregated by field and reason.
+    fn parse_documents<T: DeserializeOwned>(&self, collection: &str, documents: &[Value]) -> Vec<AppwriteDocument<T>> {
+        let mut failures = BTreeMap::new();
+        let parsed = documents
+            .iter()
+            .filter_map(|doc| match AppwriteDocument::<T>::from_value(doc) {
+                Ok(parsed) => Some(parsed),
+                Err(e) => {
+                    let id = doc.get("$id").and_then(Value::as_str).unwrap_or("unknown");
+                    tracing::debug!("Skipping {} document {} that failed to parse: {}", collection, id, e);
+                    *failures.entry(parse_failure_reason::<T>(collection, doc, &e)).or_insert(0u64) += 1;
+                    None
+                }
+            })
+            .collect();
+
+        if !failures.is_empty() {
+            tracing::warn!(
+                "Skipped {} of {} {} documents that failed to parse: {:?}",
+                failures.values().sum::<u64>(),
+                documents.len(),
+                collection,
+                failures
+            );
+            self.parse_failures.record(&failures);
+        }
+
+        parsed
+    }
+
+    /// Send a request, counting transport errors and 5xx responses toward failover
+    async fn send(&self, request: RequestBuilder) -> Result<Response, AppwriteError> {
+        let result = request.send().await;
+        match &result {
+            Ok(response) if !response.status().is_server_error() => self.endpoints.record_success(),
+            _ => self.endpoints.record_failure(),
         }
+        Ok(result?)
+    }
+
+    /// Whether the primary endpoint answers its health route
+    async fn primary_healthy(&self) -> bool {
+        let url = format!("{}/health/version", self.endpoints.urls[0]);
+        matches!(self.client.get(&url).send().await, Ok(response) if response.status().is_success())
+    }
+
+    /// Periodically check the primary while failed over and switch back once it recovers
+    ///
+    /// No-op with a single endpoint.
+    pub fn spawn_health_check(self: Arc<Self>, interval: Duration) -> Option<JoinHandle<()>> {
+        if self.endpoints.urls.len() < 2 {
+            return None;
+        }
+
+        Some(tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if self.endpoints.active() != 0 && self.primary_healthy().await {
+                    self.endpoints.fail_back();
+                }
+            }
+        }))
+    }
+
+    /// Set how many excluded user IDs are pushed into the Appwrite query
+    pub fn with_max_query_exclusions(mut self, max_query_exclusions: usize) -> Self {
+        self.max_query_exclusions = max_query_exclusions;
+        self
     }
 
     /// Fetch user preferences for a given user ID
@@ -82,7 +450,7 @@ impl AppwriteClient {
 
         let url = format!(
             "{}/databases/{}/collections/{}/documents?query={}",
-            self.base_url.trim_end_matches('/'),
+            self.base_url(),
             self.database_id,
             self.collections.user_preferences,
             encoded_query
@@ -90,13 +458,12 @@ impl AppwriteClient {
 
         tracing::debug!("Fetching preferences from: {}", url);
 
-        let response = self
+        let request = self
             .client
             .get(&url)
             .header("X-Appwrite-Key", &self.api_key)
-            .header("X-Appwrite-Project", &self.project_id)
-            .send()
-            .await?;
+            .header("X-Appwrite-Project", &self.project_id);
+        let response = self.send(request).await?;
 
         if !response.status().is_success() {
             return Err(AppwriteError::ApiError(format!(
@@ -117,10 +484,8 @@ impl AppwriteClient {
             .first()
             .ok_or_else(|| AppwriteError::NotFound(format!("Preferences not found for user {}", user_id)))?;
 
-        // Extract preferences data from Appwrite document format
-        let data = doc.get("data").unwrap_or(doc);
-
-        serde_json::from_value(data.clone())
+        AppwriteDocument::<UserPreferences>::from_value(doc)
+            .map(UserPreferences::from)
             .map_err(|e| AppwriteError::InvalidResponse(format!("Failed to parse preferences: {}", e)))
     }
 
@@ -129,52 +494,30 @@ impl AppwriteClient {
         &self,
         user_id: &str,
         preferences: &UserPreferences,
-        exclude_ids: &[String],
-        _limit: usize,
-    ) -> Result<Vec<UserProfile>, AppwriteError> {
+        exclude_ids: &HashSet<String>,
+        limit: usize,
+    ) -> Result<CandidatePage, AppwriteError> {
         let url = format!(
             "{}/databases/{}/collections/{}/documents",
-            self.base_url.trim_end_matches('/'),
+            self.base_url(),
             self.database_id,
             self.collections.user_profiles
         );
 
-        // Build Appwrite queries
-        let mut queries = vec![
-            format!("equal(\"isActive\", true)"),
-            format!("equal(\"isTimeout\", false)"),
-            format!("notEqual(\"userId\", \"{}\")", user_id), // Exclude self
-        ];
-
-        // Add gender preference filter
-        if !preferences.preferred_genders.is_empty() {
-            let gender_filter = preferences
-                .preferred_genders
-                .iter()
-                .map(|g| format!("\"{}\"", g))
-                .collect::<Vec<_>>()
-                .join(",");
-            queries.push(format!("in(\"gender\", [{}])", gender_filter));
-        }
-
-        // Add age range filter
-        queries.push(format!("greaterThan(\"age\", {})", preferences.min_age as i32 - 1));
-        queries.push(format!("lessThan(\"age\", {})", preferences.max_age as i32 + 1));
-
-        // Add geospatial bounding box filter
-        let bbox = crate::core::distance::calculate_bounding_box(
-            preferences.latitude,
-            preferences.longitude,
-            preferences.max_distance_km as f64,
+        let queries = build_candidate_queries(
+            user_id,
+            preferences,
+            exclude_ids,
+            self.max_query_exclusions,
+            limit,
         );
-        queries.push(format!("greaterThan(\"latitude\", {})", bbox.min_lat));
-        queries.push(format!("lessThan(\"latitude\", {})", bbox.max_lat));
-        queries.push(format!("greaterThan(\"longitude\", {})", bbox.min_lon));
-        queries.push(format!("lessThan(\"longitude\", {})", bbox.max_lon));
 
-        // Add exclude user IDs
-        for id in exclude_ids {
-            queries.push(format!("notEqual(\"userId\", \"{}\")", id));
+        if exclude_ids.len() > self.max_query_exclusions {
+            tracing::debug!(
+                "Sending {} of {} exclusions to Appwrite, filtering the rest in-process",
+                self.max_query_exclusions,
+                exclude_ids.len()
+            );
         }
 
         // Build query array for Appwrite
@@ -184,13 +527,12 @@ impl AppwriteClient {
         // Build full URL with query parameter
         let full_url = format!("{}?query={}", url, encoded_queries);
 
-        let response = self
+        let request = self
             .client
             .get(&full_url)
             .header("X-Appwrite-Key", &self.api_key)
-            .header("X-Appwrite-Project", &self.project_id)
-            .send()
-            .await?;
+            .header("X-Appwrite-Project", &self.project_id);
+        let response = self.send(request).await?;
 
         if !response.status().is_success() {
             return Err(AppwriteError::ApiError(format!(
@@ -211,18 +553,17 @@ impl AppwriteClient {
             .and_then(|d| d.as_array())
             .ok_or_else(|| AppwriteError::InvalidResponse("Missing documents array".into()))?;
 
-        let profiles: Vec<UserProfile> = documents
-            .iter()
-            .filter_map(|doc| {
-                let data = doc.get("data").unwrap_or(doc);
-                serde_json::from_value(data.clone()).ok()
-            })
+        let parsed = self.parse_documents::<UserProfile>("profiles", documents);
+        let unparseable = documents.len() - parsed.len();
+        let profiles: Vec<UserProfile> = parsed
+            .into_iter()
+            .map(UserProfile::from)
             .filter(|p: &UserProfile| p.user_id != user_id && !exclude_ids.contains(&p.user_id))
             .collect();
 
-        tracing::debug!("Queried {} candidates (total: {})", profiles.len(), total);
+        tracing::debug!("Queried {} candidates (total: {}, unparseable: {})", profiles.len(), total, unparseable);
 
-        Ok(profiles)
+        Ok(CandidatePage { profiles, unparseable })
     }
 
     /// Get a single profile by user ID
@@ -233,7 +574,7 @@ impl AppwriteClient {
 
         let url = format!(
             "{}/databases/{}/collections/{}/documents?query={}",
-            self.base_url.trim_end_matches('/'),
+            self.base_url(),
             self.database_id,
             self.collections.user_profiles,
             encoded_query
@@ -241,13 +582,12 @@ impl AppwriteClient {
 
         tracing::debug!("Fetching profile for user: {}", user_id);
 
-        let response = self
+        let request = self
             .client
             .get(&url)
             .header("X-Appwrite-Key", &self.api_key)
-            .header("X-Appwrite-Project", &self.project_id)
-            .send()
-            .await?;
+            .header("X-Appwrite-Project", &self.project_id);
+        let response = self.send(request).await?;
 
         if !response.status().is_success() {
             let status = response.status();
@@ -270,17 +610,159 @@ impl AppwriteClient {
             .first()
             .ok_or_else(|| AppwriteError::NotFound(format!("Profile not found for user {}", user_id)))?;
 
-        let data = doc.get("data").unwrap_or(doc);
-
-        serde_json::from_value(data.clone())
+        AppwriteDocument::<UserProfile>::from_value(doc)
+            .map(UserProfile::from)
             .map_err(|e| AppwriteError::InvalidResponse(format!("Failed to parse profile: {}", e)))
     }
 
+    /// Verify the endpoint is reachable and the API key can read the database
+    ///
+    /// Reads the database metadata, the cheapest authenticated call.
+    pub async fn check_auth(&self) -> Result<(), AppwriteError> {
+        let url = format!("{}/databases/{}", self.base_url(), self.database_id);
+
+        let request = self
+            .client
+            .get(&url)
+            .header("X-Appwrite-Key", &self.api_key)
+            .header("X-Appwrite-Project", &self.project_id);
+        let response = self.send(request).await?;
+
+        match response.status() {
+            status if status.is_success() => Ok(()),
+            reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::FORBIDDEN => Err(AppwriteError::Unauthorized),
+            reqwest::StatusCode::NOT_FOUND => {
+                Err(AppwriteError::NotFound(format!("Database {} not found", self.database_id)))
+            }
+            status => Err(AppwriteError::ApiError(format!("Auth check failed: {}", status))),
+        }
+    }
+
+    /// Fetch several profiles by user ID in one request
+    ///
+    /// Missing or unparseable profiles are skipped; the result order is
+    /// not guaranteed to match `user_ids`.
+    pub async fn get_profiles(&self, user_ids: &[String]) -> Result<Vec<UserProfile>, AppwriteError> {
+        if user_ids.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let ids = user_ids
+            .iter()
+            .map(|id| quoted(id))
+            .collect::<Vec<_>>()
+            .join(",");
+        let queries = vec![
+            format!("equal(\"userId\", [{}])", ids),
+            format!("limit({})", user_ids.len().min(APPWRITE_MAX_LIMIT)),
+        ];
+        let queries_json = serde_json::to_string(&queries).unwrap();
+
+        let url = format!(
+            "{}/databases/{}/collections/{}/documents?query={}",
+            self.base_url(),
+            self.database_id,
+            self.collections.user_profiles,
+            urlencoding::encode(&queries_json)
+        );
+
+        let request = self
+            .client
+            .get(&url)
+            .header("X-Appwrite-Key", &self.api_key)
+            .header("X-Appwrite-Project", &self.project_id);
+        let response = self.send(request).await?;
+
+        if !response.status().is_success() {
+            return Err(AppwriteError::ApiError(format!(
+                "Failed to fetch profiles: {}",
+                response.status()
+            )));
+        }
+
+        let json: Value = response.json().await?;
+
+        let documents = json
+            .get("documents")
+            .and_then(|d| d.as_array())
+            .ok_or_else(|| AppwriteError::InvalidResponse("Missing documents array".into()))?;
+
+        Ok(self
+            .parse_documents::<UserProfile>("profiles", documents)
+            .into_iter()
+            .map(UserProfile::from)
+            .collect())
+    }
+
+    /// List match events created at or after `since`, oldest first
+    ///
+    /// Pages through the collection until `max_events` events are read.
+    pub async fn list_events_since(
+        &self,
+        since: DateTime<Utc>,
+        max_events: usize,
+    ) -> Result<Vec<MatchEvent>, AppwriteError> {
+        let page_size = max_events.clamp(1, APPWRITE_MAX_LIMIT);
+        let mut events = Vec::new();
+
+        while events.len() < max_events {
+            let queries = vec![
+                format!("greaterThanEqual(\"created_at\", \"{}\")", since.to_rfc3339()),
+                "orderAsc(\"created_at\")".to_string(),
+                format!("limit({})", page_size.min(max_events - events.len())),
+                format!("offset({})", events.len()),
+            ];
+            let queries_json = serde_json::to_string(&queries).unwrap();
+
+            let url = format!(
+                "{}/databases/{}/collections/{}/documents?query={}",
+                self.base_url(),
+                self.database_id,
+                self.collections.match_events,
+                urlencoding::encode(&queries_json)
+            );
+
+            let request = self
+                .client
+                .get(&url)
+                .header("X-Appwrite-Key", &self.api_key)
+                .header("X-Appwrite-Project", &self.project_id);
+            let response = self.send(request).await?;
+
+            if !response.status().is_success() {
+                return Err(AppwriteError::ApiError(format!(
+                    "Failed to list events: {}",
+                    response.status()
+                )));
+            }
+
+            let json: Value = response.json().await?;
+
+            let documents = json
+                .get("documents")
+                .and_then(|d| d.as_array())
+                .ok_or_else(|| AppwriteError::InvalidResponse("Missing documents array".into()))?;
+
+            let page_len = documents.len();
+            events.extend(
+                self.parse_documents::<MatchEvent>("events", documents)
+                    .into_iter()
+                    .map(AppwriteDocument::into_inner),
+            );
+
+            if page_len < page_size {
+                break;
+            }
+        }
+
+        Ok(events)
+    }
+
     /// Record a match event
     pub async fn record_event(&self, event: MatchEvent) -> Result<(), AppwriteError> {
         let url = format!(
             "{}/databases/{}/collections/{}/documents",
-            self.base_url.trim_end_matches('/'),
+            self.base_url(),
             self.database_id,
             self.collections.match_events
         );
@@ -291,14 +773,13 @@ impl AppwriteClient {
             obj.insert("$id".to_string(), Value::String(uuid::Uuid::new_v4().to_string()));
         }
 
-        let response = self
+        let request = self
             .client
             .post(&url)
             .header("X-Appwrite-Key", &self.api_key)
             .header("X-Appwrite-Project", &self.project_id)
-            .json(&payload)
-            .send()
-            .await?;
+            .json(&payload);
+        let response = self.send(request).await?;
 
         if !response.status().is_success() {
             return Err(AppwriteError::ApiError(format!(
@@ -311,12 +792,289 @@ impl AppwriteClient {
 
         Ok(())
     }
+
+    /// Collection IDs this client reads and writes
+    pub fn collections(&self) -> &AppwriteCollections {
+        &self.collections
+    }
+
+    /// Create a document with the given ID in `collection`
+    pub async fn create_document(&self, collection: &str, document_id: &str, data: &Value) -> Result<(), AppwriteError> {
+        let url = format!(
+            "{}/databases/{}/collections/{}/documents",
+            self.base_url(),
+            self.database_id,
+            collection
+        );
+
+        let request = self
+            .client
+            .post(&url)
+            .header("X-Appwrite-Key", &self.api_key)
+            .header("X-Appwrite-Project", &self.project_id)
+            .json(&serde_json::json!({ "documentId": document_id, "data": data }));
+        let response = self.send(request).await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_else(|_| "Unable to read body".to_string());
+            return Err(AppwriteError::ApiError(format!(
+                "Failed to create document {} in {}: {} - {}",
+                document_id, collection, status, body
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// IDs of the first page of documents in `collection` whose `attribute`
+    /// starts with `prefix`
+    pub async fn list_document_ids_with_prefix(
+        &self,
+        collection: &str,
+        attribute: &str,
+        prefix: &str,
+    ) -> Result<Vec<String>, AppwriteError> {
+        let queries = vec![
+            format!("startsWith(\"{}\", {})", attribute, quoted(prefix)),
+            format!("limit({})", APPWRITE_MAX_LIMIT),
+        ];
+        let queries_json = serde_json::to_string(&queries).unwrap();
+
+        let url = format!(
+            "{}/databases/{}/collections/{}/documents?query={}",
+            self.base_url(),
+            self.database_id,
+            collection,
+            urlencoding::encode(&queries_json)
+        );
+
+        let request = self
+            .client
+            .get(&url)
+            .header("X-Appwrite-Key", &self.api_key)
+            .header("X-Appwrite-Project", &self.project_id);
+        let response = self.send(request).await?;
+
+        if !response.status().is_success() {
+            return Err(AppwriteError::ApiError(format!(
+                "Failed to list documents in {}: {}",
+                collection,
+                response.status()
+            )));
+        }
+
+        let json: Value = response.json().await?;
+
+        let documents = json
+            .get("documents")
+            .and_then(|d| d.as_array())
+            .ok_or_else(|| AppwriteError::InvalidResponse("Missing documents array".into()))?;
+
+        Ok(documents
+            .iter()
+            .filter_map(|doc| doc.get("$id").and_then(Value::as_str).map(str::to_string))
+            .collect())
+    }
+
+    /// Every active profile, paged by document ID
+    ///
+    /// Loads the Realtime profile mirror; unparseable documents are skipped
+    /// and counted like in candidate queries.
+    pub async fn list_active_profiles(&self, page_size: usize) -> Result<Vec<UserProfile>, AppwriteError> {
+        self.list_profiles(vec!["equal(\"isActive\", true)".to_string()], page_size).await
+    }
+
+    /// Profiles (active or not) updated after `since`, oldest update first
+    ///
+    /// Feeds incremental syncs of the PostgreSQL profile mirror; inactive
+    /// profiles are included so deactivations reach it too.
+    pub async fn list_profiles_updated_since(
+        &self,
+        since: DateTime<Utc>,
+        page_size: usize,
+    ) -> Result<Vec<UserProfile>, AppwriteError> {
+        let filters = vec![
+            format!("greaterThan(\"$updatedAt\", \"{}\")", since.to_rfc3339_opts(chrono::SecondsFormat::Millis, true)),
+            "orderAsc(\"$updatedAt\")".to_string(),
+        ];
+        self.list_profiles(filters, page_size).await
+    }
+
+    /// All profiles matching `filters`, following `cursorAfter` page by page
+    async fn list_profiles(&self, filters: Vec<String>, page_size: usize) -> Result<Vec<UserProfile>, AppwriteError> {
+        let page_size = page_size.clamp(1, APPWRITE_MAX_LIMIT);
+        let mut profiles = Vec::new();
+        let mut cursor: Option<String> = None;
+
+        loop {
+            let mut queries = filters.clone();
+            queries.push(format!("limit({})", page_size));
+            if let Some(cursor) = &cursor {
+                queries.push(format!("cursorAfter({})", quoted(cursor)));
+            }
+            let queries_json = serde_json::to_string(&queries).unwrap();
+
+            let url = format!(
+                "{}/databases/{}/collections/{}/documents?query={}",
+                self.base_url(),
+                self.database_id,
+                self.collections.user_profiles,
+                urlencoding::encode(&queries_json)
+            );
+
+            let request = self
+                .client
+                .get(&url)
+                .header("X-Appwrite-Key", &self.api_key)
+                .header("X-Appwrite-Project", &self.project_id);
+            let response = self.send(request).await?;
+
+            if !response.status().is_success() {
+                return Err(AppwriteError::ApiError(format!(
+                    "Failed to list profiles: {}",
+                    response.status()
+                )));
+            }
+
+            let json: Value = response.json().await?;
+
+            let documents = json
+                .get("documents")
+                .and_then(|d| d.as_array())
+                .ok_or_else(|| AppwriteError::InvalidResponse("Missing documents array".into()))?;
+
+            cursor = documents.last().and_then(|doc| doc.get("$id")).and_then(Value::as_str).map(str::to_string);
+            profiles.extend(
+                self.parse_documents::<UserProfile>("profiles", documents)
+                    .into_iter()
+                    .map(UserProfile::from),
+            );
+
+            if documents.len() < page_size || cursor.is_none() {
+                break;
+            }
+        }
+
+        Ok(profiles)
+    }
+
+    /// Realtime WebSocket URL subscribed to the profiles collection
+    pub fn realtime_url(&self) -> String {
+        let base = self.base_url();
+        let base = match base.split_once("://") {
+            Some(("https", rest)) => format!("wss://{}", rest),
+            Some(("http", rest)) => format!("ws://{}", rest),
+            _ => base.to_string(),
+        };
+        let channel = format!("databases.{}.collections.{}.documents", self.database_id, self.collections.user_profiles);
+
+        format!(
+            "{}/realtime?project={}&channels[]={}",
+            base,
+            urlencoding::encode(&self.project_id),
+            urlencoding::encode(&channel)
+        )
+    }
+
+    /// Delete a document; a document that is already gone is not an error
+    pub async fn delete_document(&self, collection: &str, document_id: &str) -> Result<(), AppwriteError> {
+        let url = format!(
+            "{}/databases/{}/collections/{}/documents/{}",
+            self.base_url(),
+            self.database_id,
+            collection,
+            document_id
+        );
+
+        let request = self
+            .client
+            .delete(&url)
+            .header("X-Appwrite-Key", &self.api_key)
+            .header("X-Appwrite-Project", &self.project_id);
+        let response = self.send(request).await?;
+
+        match response.status() {
+            status if status.is_success() || status == reqwest::StatusCode::NOT_FOUND => Ok(()),
+            status => Err(AppwriteError::ApiError(format!(
+                "Failed to delete document {} in {}: {}",
+                document_id, collection, status
+            ))),
+        }
+    }
+}
+
+/// `value` as a quoted query value, with quotes and backslashes escaped
+///
+/// User IDs come from requests, so an unescaped `"` would end the value
+/// early and let the rest rewrite the query.
+fn quoted(value: &str) -> String {
+    Value::from(value).to_string()
+}
+
+/// Build the Appwrite query list for a candidate search
+///
+/// At most `max_exclusions` IDs from `exclude_ids` become `notEqual` queries;
+/// the rest must be filtered by the caller. The fetch limit is raised by the
+/// number of in-process exclusions so they don't eat into the result page.
+fn build_candidate_queries(
+    user_id: &str,
+    preferences: &UserPreferences,
+    exclude_ids: &HashSet<String>,
+    max_exclusions: usize,
+    limit: usize,
+) -> Vec<String> {
+    let mut queries = vec![
+        "equal(\"isActive\", true)".to_string(),
+        "equal(\"isTimeout\", false)".to_string(),
+        format!("notEqual(\"userId\", {})", quoted(user_id)), // Exclude self
+    ];
+
+    // Add gender preference filter
+    if !preferences.preferred_genders.is_empty() {
+        let gender_filter = preferences
+            .preferred_genders
+            .iter()
+            .map(|g| quoted(g))
+            .collect::<Vec<_>>()
+            .join(",");
+        queries.push(format!("in(\"gender\", [{}])", gender_filter));
+    }
+
+    // Add age range filter
+    let min_age = preferences.min_age.max(crate::core::filters::MIN_AGE);
+    queries.push(format!("greaterThan(\"age\", {})", min_age as i32 - 1));
+    queries.push(format!("lessThan(\"age\", {})", preferences.max_age as i32 + 1));
+
+    // Add geospatial bounding box filter (skipped in global mode)
+    if preferences.has_location() {
+        let bbox = crate::core::distance::calculate_bounding_box(
+            preferences.latitude,
+            preferences.longitude,
+            preferences.max_distance_km as f64,
+        );
+        queries.push(format!("greaterThan(\"latitude\", {})", bbox.min_lat));
+        queries.push(format!("lessThan(\"latitude\", {})", bbox.max_lat));
+        queries.push(format!("greaterThan(\"longitude\", {})", bbox.min_lon));
+        queries.push(format!("lessThan(\"longitude\", {})", bbox.max_lon));
+    }
+
+    // Add exclude user IDs, up to the cap
+    for id in exclude_ids.iter().take(max_exclusions) {
+        queries.push(format!("notEqual(\"userId\", {})", quoted(id)));
+    }
+
+    // Over-fetch by the number of exclusions left for in-process filtering
+    let in_process = exclude_ids.len().saturating_sub(max_exclusions);
+    queries.push(format!("limit({})", (limit + in_process).min(APPWRITE_MAX_LIMIT)));
+
+    queries
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use chrono::Utc;
+    use crate::models::SCHEMA_VERSION;
 
     #[test]
     fn test_appwrite_client_creation() {
@@ -335,7 +1093,227 @@ mod tests {
             collections,
         );
 
-        assert_eq!(client.base_url, "https://appwrite.test/v1");
+        assert_eq!(client.base_url(), "https://appwrite.test/v1");
         assert_eq!(client.api_key, "test_key");
+        assert_eq!(client.max_query_exclusions, DEFAULT_MAX_QUERY_EXCLUSIONS);
+        assert_eq!(
+            client.realtime_url(),
+            "wss://appwrite.test/v1/realtime?project=test_project&channels[]=databases.test_db.collections.user_profiles.documents"
+        );
+    }
+
+    #[test]
+    fn test_endpoint_failover() {
+        let endpoints = Endpoints::new(
+            vec!["https://fra.test/v1/".to_string(), "https://nyc.test/v1".to_string()],
+            3,
+        );
+        assert_eq!(endpoints.active_url(), "https://fra.test/v1");
+
+        // A success resets the streak
+        endpoints.record_failure();
+        endpoints.record_failure();
+        endpoints.record_success();
+        endpoints.record_failure();
+        assert_eq!(endpoints.active(), 0);
+
+        endpoints.record_failure();
+        endpoints.record_failure();
+        assert_eq!(endpoints.active_url(), "https://nyc.test/v1");
+
+        let status = endpoints.status();
+        assert!(!status.primary);
+        assert_eq!(status.failovers, 1);
+        assert_eq!(status.consecutive_failures, 0);
+
+        endpoints.fail_back();
+        assert!(endpoints.status().primary);
+    }
+
+    #[test]
+    fn test_single_endpoint_never_fails_over() {
+        let endpoints = Endpoints::new(vec!["https://fra.test/v1".to_string()], 1);
+        endpoints.record_failure();
+        endpoints.record_failure();
+        assert_eq!(endpoints.active(), 0);
+        assert_eq!(endpoints.status().failovers, 0);
+    }
+
+    fn test_preferences() -> UserPreferences {
+        UserPreferences {
+            user_id: "current_user".to_string(),
+            preferred_genders: vec!["female".to_string()],
+            min_age: 21,
+            max_age: 35,
+            min_height_cm: 160,
+            max_height_cm: 180,
+            preferred_hair_colors: vec![],
+            preferred_sports: vec![],
+            preferred_interests: vec![],
+            max_distance_km: 50,
+            latitude: 52.52,
+            longitude: 13.405,
+            require_photos: None,
+            embedding: None,
+            preferred_languages: vec![],
+            require_shared_language: false,
+            verified_only: false,
+            relationship_goal: None,
+            attributes: Default::default(),
+            updated_at: None,
+            schema_version: SCHEMA_VERSION,
+        }
+    }
+
+    #[test]
+    fn test_document_system_fields() {
+        let doc = serde_json::json!({
+            "$id": "doc_1",
+            "$collectionId": "dating-profiles",
+            "$createdAt": "2024-03-01T10:00:00.000+00:00",
+            "$updatedAt": "2024-03-02T10:00:00.000+00:00",
+            "$permissions": ["read(\"any\")"],
+            "userId": "user_1",
+            "name": "Alex",
+            "age": 27,
+            "heightCm": 175,
+            "hairColor": "brown",
+            "gender": "female",
+            "latitude": 52.52,
+            "longitude": 13.405
+        });
+
+        let parsed = AppwriteDocument::<UserProfile>::from_value(&doc).unwrap();
+        assert_eq!(parsed.id.as_deref(), Some("doc_1"));
+        assert_eq!(parsed.permissions.len(), 1);
+        assert!(parsed.updated_at > parsed.created_at);
+
+        let (created_at, updated_at) = (parsed.created_at, parsed.updated_at);
+        let profile = UserProfile::from(parsed);
+        assert_eq!(profile.user_id, "user_1");
+        assert_eq!(profile.created_at, created_at);
+        assert_eq!(profile.updated_at, updated_at);
+    }
+
+    #[test]
+    fn test_document_nested_data() {
+        let doc = serde_json::json!({
+            "$id": "prefs_1",
+            "data": {
+                "userId": "user_1",
+                "preferredGenders": ["male"],
+                "minAge": 20,
+                "maxAge": 30,
+                "minHeightCm": 150,
+                "maxHeightCm": 200,
+                "preferredHairColors": [],
+                "preferredSports": [],
+                "maxDistanceKm": 25
+            }
+        });
+
+        let parsed = AppwriteDocument::<UserPreferences>::from_value(&doc).unwrap();
+        assert_eq!(parsed.id.as_deref(), Some("prefs_1"));
+        assert!(parsed.created_at.is_none());
+        assert_eq!(parsed.into_inner().max_distance_km, 25);
+    }
+
+    #[test]
+    fn test_candidate_queries_cap_exclusions() {
+        let exclude: HashSet<String> = (0..500).map(|i| format!("seen_{}", i)).collect();
+
+        let queries = build_candidate_queries("current_user", &test_preferences(), &exclude, 50, 100);
+
+        let not_equal = queries.iter().filter(|q| q.starts_with("notEqual")).count();
+        assert_eq!(not_equal, 51); // self + capped exclusions
+        assert_eq!(queries.last().unwrap(), "limit(550)");
+    }
+
+    #[test]
+    fn test_query_values_are_escaped() {
+        assert_eq!(quoted("user_1"), "\"user_1\"");
+        assert_eq!(quoted(r#"a", "b"#), r#""a\", \"b""#);
+
+        let exclude = HashSet::from([r#"x"]), limit(5000), equal("isActive", [false"#.to_string()]);
+        let queries = build_candidate_queries("me", &test_preferences(), &exclude, 50, 100);
+        assert!(queries.contains(&r#"notEqual("userId", "x\"]), limit(5000), equal(\"isActive\", [false")"#.to_string()));
+    }
+
+    #[test]
+    fn test_candidate_queries_global_mode() {
+        let mut preferences = test_preferences();
+        preferences.latitude = 0.0;
+        preferences.longitude = 0.0;
+
+        let queries = build_candidate_queries("current_user", &preferences, &HashSet::new(), 50, 100);
+
+        assert!(!queries.iter().any(|q| q.contains("latitude") || q.contains("longitude")));
+    }
+
+    #[test]
+    fn test_candidate_queries_under_cap() {
+        let exclude: HashSet<String> = (0..10).map(|i| format!("seen_{}", i)).collect();
+
+        let queries = build_candidate_queries("current_user", &test_preferences(), &exclude, 50, 100);
+
+        let not_equal = queries.iter().filter(|q| q.starts_with("notEqual")).count();
+        assert_eq!(not_equal, 11);
+        assert_eq!(queries.last().unwrap(), "limit(100)");
+    }
+
+    #[test]
+    fn test_unparseable_documents_are_counted() {
+        let collections = AppwriteCollections {
+            user_profiles: "user_profiles".to_string(),
+            user_preferences: "user_preferences".to_string(),
+            match_events: "match_events".to_string(),
+            user_matches: "user_matches".to_string(),
+        };
+        let client = AppwriteClient::new(
+            "https://appwrite.test/v1".to_string(),
+            "test_key".to_string(),
+            "test_project".to_string(),
+            "test_db".to_string(),
+            collections,
+        );
+
+        let profile = |user_id: &str| {
+            serde_json::json!({
+                "$id": user_id,
+                "userId": user_id,
+                "name": "Alex",
+                "age": 27,
+                "hairColor": "brown",
+                "gender": "female",
+                "latitude": 52.52,
+                "longitude": 13.405
+            })
+        };
+        let mut no_hair_color = profile("user_3");
+        no_hair_color.as_object_mut().unwrap().remove("hairColor");
+        let mut bad_age = profile("user_4");
+        bad_age["age"] = serde_json::json!("twenty");
+        let mut bad_age_again = profile("user_5");
+        bad_age_again["age"] = serde_json::json!("thirty");
+
+        let documents = vec![profile("user_1"), profile("user_2"), no_hair_color, bad_age, bad_age_again];
+        let parsed = client.parse_documents::<UserProfile>("profiles", &documents);
+
+        // A missing height is fine
+        assert_eq!(parsed.len(), 2);
+        assert!(parsed[0].data.height_cm.is_none());
+
+        let stats = client.parse_failures();
+        assert_eq!(stats.total, 3);
+        assert_eq!(stats.reasons["profiles.hairColor: missing"], 1);
+        assert_eq!(stats.reasons["profiles.age: invalid type"], 2);
+
+        // Bad system fields aren't pinned on a data field
+        let mut bad_created_at = profile("user_6");
+        bad_created_at["$createdAt"] = serde_json::json!("yesterday");
+        assert!(client.parse_documents::<UserProfile>("profiles", &[bad_created_at]).is_empty());
+        let stats = client.parse_failures();
+        assert_eq!(stats.total, 4);
+        assert_eq!(stats.reasons.keys().filter(|reason| reason.starts_with("profiles: ")).count(), 1);
     }
 }
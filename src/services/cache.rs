@@ -1,7 +1,16 @@
+use crate::config::CacheTtlSettings;
+use crate::services::{Cache, Singleflight};
+use chrono::{DateTime, Utc};
 use redis::aio::ConnectionManager;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
-use std::time::Duration;
+use std::any::Any;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
 /// Errors that can occur with cache operations
@@ -17,15 +26,52 @@ pub enum CacheError {
     CacheMiss(String),
 }
 
+/// Outcome of a `get_or_compute` recompute, shared with the callers waiting
+/// on it: the stored entry, or the leader's error (downcast to its type by
+/// the waiters). None when the value couldn't be serialized.
+pub type SharedRecompute = Option<Result<Vec<u8>, Arc<dyn Any + Send + Sync>>>;
+
+/// Errors `get_or_compute` can hand from the caller that computed them to
+/// the callers waiting on it
+pub trait SharedError: std::fmt::Display + Send + Sync + 'static {
+    /// Copy for a waiting caller
+    fn duplicate(&self) -> Self;
+}
+
+impl SharedError for String {
+    fn duplicate(&self) -> Self {
+        self.clone()
+    }
+}
+
 /// Multi-tier cache manager
 ///
 /// Implements L1 (in-memory) and L2 (Redis) caching strategy.
 /// L1 is fastest but limited in size, L2 is shared across instances.
+/// Without Redis (`in_memory`, used by the dev profile) only L1 is used.
 pub struct CacheManager {
-    // Store ConnectionManager in a Mutex for interior mutability
-    redis: Arc<tokio::sync::Mutex<ConnectionManager>>,
-    l1_cache: moka::future::Cache<String, Vec<u8>>,
+    /// Multiplexed connection; clones share it, so concurrent commands are
+    /// pipelined over one socket instead of queueing on a lock
+    redis: Option<ConnectionManager>,
+    /// Entries expire after their own TTL, capped at `ttl_secs`
+    l1_cache: moka::future::Cache<String, L1Entry>,
+    /// Seen-profile sets, kept deserialized so find requests can do O(1)
+    /// exclusion lookups; never in Redis, so they expire after the short
+    /// `ttls.seen_set_secs`
+    seen_sets: moka::future::Cache<String, Arc<HashSet<String>>>,
+    /// Claims from `set_if_absent` and when they lapse, used without Redis
+    claims: Mutex<HashMap<String, Instant>>,
+    /// `get_or_compute` recomputes in flight, by key
+    recomputes: Singleflight<String, SharedRecompute>,
+    l1_counters: HitCounters,
+    l2_counters: HitCounters,
+    /// Lookups by key prefix (either tier), indexed like `TRACKED_PREFIXES`
+    /// with everything else last
+    prefix_counters: [HitCounters; TRACKED_PREFIXES.len() + 1],
     ttl_secs: u64,
+    ttls: CacheTtlSettings,
+    /// Prepended to every key, so tenants sharing a Redis don't collide
+    key_prefix: String,
 }
 
 impl CacheManager {
@@ -34,15 +80,72 @@ impl CacheManager {
         let client = redis::Client::open(redis_url)?;
         let redis = redis::aio::ConnectionManager::new(client).await?;
 
+        Ok(Self {
+            redis: Some(redis),
+            ..Self::in_memory(l1_size, ttl_secs)
+        })
+    }
+
+    /// Create a cache manager without Redis (L1 only)
+    pub fn in_memory(l1_size: u64, ttl_secs: u64) -> Self {
         let l1_cache = moka::future::CacheBuilder::new(l1_size)
-            .time_to_live(Duration::from_secs(ttl_secs))
+            .expire_after(L1Expiry { max: Duration::from_secs(ttl_secs) })
             .build();
 
-        Ok(Self {
-            redis: Arc::new(tokio::sync::Mutex::new(redis)),
+        let ttls = CacheTtlSettings::default();
+        let seen_sets = seen_set_cache(l1_size, ttls.seen_set_secs.min(ttl_secs));
+
+        Self {
+            redis: None,
             l1_cache,
+            seen_sets,
+            claims: Mutex::new(HashMap::new()),
+            recomputes: Singleflight::new(),
+            l1_counters: HitCounters::default(),
+            l2_counters: HitCounters::default(),
+            prefix_counters: Default::default(),
             ttl_secs,
-        })
+            ttls,
+            key_prefix: String::new(),
+        }
+    }
+
+    /// TTLs for values stored without an explicit one, by key class
+    pub fn with_ttls(mut self, ttls: CacheTtlSettings) -> Self {
+        let capacity = self.seen_sets.policy().max_capacity().unwrap_or(0);
+        self.seen_sets = seen_set_cache(capacity, ttls.seen_set_secs.min(self.ttl_secs));
+        self.ttls = ttls;
+        self
+    }
+
+    /// TTL for a value stored under `key` without an explicit one
+    pub fn ttl_for(&self, key: &str) -> u64 {
+        match TRACKED_PREFIXES.get(prefix_index(key)) {
+            Some(&"profile") => self.ttls.profile_secs,
+            Some(&"prefs") => self.ttls.preferences_secs,
+            Some(&"candidates") => self.ttls.candidates_secs,
+            Some(&"matches") => self.ttls.matches_secs,
+            _ => self.ttl_secs,
+        }
+    }
+
+    async fn insert_l1(&self, key: String, bytes: Vec<u8>, ttl: Duration) {
+        self.l1_cache.insert(key, L1Entry { bytes, ttl }).await;
+    }
+
+    /// Prefix every key (e.g. `"acme:"`) to share one Redis between tenants
+    pub fn with_key_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.key_prefix = prefix.into();
+        self
+    }
+
+    /// The key as stored, with the prefix applied
+    fn key<'a>(&self, key: &'a str) -> Cow<'a, str> {
+        if self.key_prefix.is_empty() {
+            Cow::Borrowed(key)
+        } else {
+            Cow::Owned(format!("{}{}", self.key_prefix, key))
+        }
     }
 
     /// Get a value from cache (L1 first, then L2)
@@ -50,146 +153,979 @@ impl CacheManager {
     where
         T: for<'de> Deserialize<'de>,
     {
+        match self.get_bytes(key).await? {
+            Some(bytes) => Ok(serde_json::from_slice(&bytes)?),
+            None => Err(CacheError::CacheMiss(key.to_string())),
+        }
+    }
+
+    /// Get the raw JSON stored under `key` (L1 first, then L2)
+    pub async fn get_bytes(&self, key: &str) -> Result<Option<Vec<u8>>, CacheError> {
+        let by_prefix = &self.prefix_counters[prefix_index(key)];
+        let ttl_secs = self.ttl_for(key);
+        let key = self.key(key);
+        let key = key.as_ref();
+
         // Try L1 cache first
-        if let Some(bytes) = self.l1_cache.get(key).await {
+        if let Some(entry) = self.l1_cache.get(key).await {
             tracing::trace!("L1 cache hit: {}", key);
-            return Ok(serde_json::from_slice(&bytes)?);
+            self.l1_counters.hit();
+            by_prefix.hit();
+            return Ok(Some(entry.bytes));
         }
+        self.l1_counters.miss();
 
         // Try L2 cache (Redis)
-        let mut conn = self.redis.lock().await;
-        let value: Option<String> = redis::cmd("GET")
+        let Some(redis) = &self.redis else {
+            tracing::trace!("Cache miss: {}", key);
+            by_prefix.miss();
+            return Ok(None);
+        };
+        let mut conn = redis.clone();
+        let value: Result<(Option<Vec<u8>>, i64), _> = redis::pipe()
+            .cmd("GET")
             .arg(key)
-            .query_async(&mut *conn)
-            .await?;
-        drop(conn);
+            .cmd("PTTL")
+            .arg(key)
+            .query_async(&mut conn)
+            .await;
 
-        if let Some(json) = value {
-            tracing::trace!("L2 cache hit: {}", key);
+        match value {
+            Ok((Some(bytes), pttl)) => {
+                tracing::trace!("L2 cache hit: {}", key);
+                self.l2_counters.hit();
+                by_prefix.hit();
 
-            // Populate L1 cache
-            let bytes = json.as_bytes().to_vec();
-            self.l1_cache.insert(key.to_string(), bytes).await;
+                // Populate L1 cache, never past the Redis entry's expiry
+                self.insert_l1(key.to_string(), bytes.clone(), remaining_ttl(pttl, ttl_secs)).await;
+                Ok(Some(bytes))
+            }
+            Ok((None, _)) => {
+                tracing::trace!("Cache miss: {}", key);
+                self.l2_counters.miss();
+                by_prefix.miss();
+                Ok(None)
+            }
+            Err(e) => {
+                self.l2_counters.error();
+                by_prefix.error();
+                Err(e.into())
+            }
+        }
+    }
 
-            return Ok(serde_json::from_str(&json)?);
+    /// Raw JSON for several keys (None for misses), with one Redis pipeline
+    /// of `GET`s (and `PTTL`s) for all L1 misses
+    pub async fn get_many(&self, keys: &[String]) -> Result<Vec<Option<Vec<u8>>>, CacheError> {
+        let mut values = Vec::with_capacity(keys.len());
+        let mut missing = Vec::new();
+        for (i, key) in keys.iter().enumerate() {
+            let value = self.l1_cache.get(self.key(key).as_ref()).await.map(|entry| entry.bytes);
+            if value.is_some() {
+                self.l1_counters.hit();
+                self.prefix_counters[prefix_index(key)].hit();
+            } else {
+                self.l1_counters.miss();
+                missing.push(i);
+            }
+            values.push(value);
         }
 
-        tracing::trace!("Cache miss: {}", key);
-        Err(CacheError::CacheMiss(key.to_string()))
+        let Some(redis) = self.redis.as_ref().filter(|_| !missing.is_empty()) else {
+            for i in missing {
+                self.prefix_counters[prefix_index(&keys[i])].miss();
+            }
+            return Ok(values);
+        };
+
+        let stored: Vec<String> = missing.iter().map(|i| self.key(&keys[*i]).into_owned()).collect();
+        let mut pipe = redis::pipe();
+        for key in &stored {
+            pipe.cmd("GET").arg(key).cmd("PTTL").arg(key);
+        }
+        let mut conn = redis.clone();
+        let fetched: Result<Vec<(Option<Vec<u8>>, i64)>, _> = pipe.query_async(&mut conn).await;
+
+        let fetched = match fetched {
+            Ok(fetched) => fetched,
+            Err(e) => {
+                for i in missing {
+                    self.l2_counters.error();
+                    self.prefix_counters[prefix_index(&keys[i])].error();
+                }
+                return Err(e.into());
+            }
+        };
+
+        for ((i, key), (value, pttl)) in missing.into_iter().zip(stored).zip(fetched) {
+            let by_prefix = &self.prefix_counters[prefix_index(&keys[i])];
+            match value {
+                Some(bytes) => {
+                    self.l2_counters.hit();
+                    by_prefix.hit();
+                    self.insert_l1(key, bytes.clone(), remaining_ttl(pttl, self.ttl_for(&keys[i]))).await;
+                    values[i] = Some(bytes);
+                }
+                None => {
+                    self.l2_counters.miss();
+                    by_prefix.miss();
+                }
+            }
+        }
+
+        tracing::trace!("Cache get_many: {} keys", keys.len());
+        Ok(values)
+    }
+
+    /// Store several raw JSON values with one Redis pipeline of `SETEX`s
+    pub async fn set_many(&self, entries: Vec<(String, Vec<u8>)>, ttl_secs: u64) -> Result<(), CacheError> {
+        let entries: Vec<(String, Vec<u8>)> = entries
+            .into_iter()
+            .map(|(key, bytes)| (self.key(&key).into_owned(), bytes))
+            .collect();
+        for (key, bytes) in &entries {
+            self.insert_l1(key.clone(), bytes.clone(), Duration::from_secs(ttl_secs)).await;
+        }
+
+        let Some(redis) = self.redis.as_ref().filter(|_| !entries.is_empty()) else {
+            return Ok(());
+        };
+        let mut pipe = redis::pipe();
+        for (key, bytes) in &entries {
+            pipe.cmd("SETEX").arg(key).arg(ttl_secs).arg(bytes).ignore();
+        }
+        let mut conn = redis.clone();
+        pipe.query_async::<()>(&mut conn).await?;
+
+        tracing::trace!("Cache set_many: {} keys", entries.len());
+        Ok(())
     }
 
-    /// Set a value in cache (both L1 and L2)
+    /// Delete several keys from both tiers with one Redis `DEL`
+    pub async fn delete_many(&self, keys: &[String]) -> Result<(), CacheError> {
+        let keys: Vec<String> = keys.iter().map(|key| self.key(key).into_owned()).collect();
+        {
+            let mut claims = self.claims.lock().unwrap();
+            for key in &keys {
+                claims.remove(key);
+            }
+        }
+        for key in &keys {
+            self.l1_cache.invalidate(key).await;
+        }
+
+        let Some(redis) = self.redis.as_ref().filter(|_| !keys.is_empty()) else {
+            return Ok(());
+        };
+        let mut conn = redis.clone();
+        redis::cmd("DEL").arg(&keys).query_async::<()>(&mut conn).await?;
+        Ok(())
+    }
+
+    /// Set a value in cache (both L1 and L2) for its key class's TTL
     pub async fn set<T>(&self, key: &str, value: &T) -> Result<(), CacheError>
     where
         T: Serialize,
     {
-        let json = serde_json::to_string(value)?;
+        self.set_with_ttl(key, value, self.ttl_for(key)).await
+    }
 
-        // Set in L1 cache (uses configured TTL)
-        let bytes = json.as_bytes().to_vec();
-        self.l1_cache.insert(key.to_string(), bytes).await;
+    /// Set a value in cache with an explicit TTL
+    pub async fn set_with_ttl<T>(&self, key: &str, value: &T, ttl_secs: u64) -> Result<(), CacheError>
+    where
+        T: Serialize,
+    {
+        self.set_bytes(key, serde_json::to_vec(value)?, ttl_secs).await
+    }
+
+    /// Store raw JSON under `key` with an explicit TTL
+    pub async fn set_bytes(&self, key: &str, bytes: Vec<u8>, ttl_secs: u64) -> Result<(), CacheError> {
+        let key = self.key(key);
+        let key = key.as_ref();
+
+        self.insert_l1(key.to_string(), bytes.clone(), Duration::from_secs(ttl_secs)).await;
 
         // Set in L2 cache with explicit TTL
-        let mut conn = self.redis.lock().await;
+        let Some(redis) = &self.redis else {
+            return Ok(());
+        };
+        let mut conn = redis.clone();
         redis::cmd("SETEX")
             .arg(key)
-            .arg(self.ttl_secs)
-            .arg(json)
-            .query_async::<()>(&mut *conn)
+            .arg(ttl_secs)
+            .arg(bytes)
+            .query_async::<()>(&mut conn)
             .await?;
-        drop(conn);
 
         tracing::trace!("Cache set: {}", key);
         Ok(())
     }
 
-    /// Delete a value from both cache tiers
+    /// Claim `key` for `ttl_secs`; false when it is already claimed
+    ///
+    /// Uses Redis `SET NX EX`, so the claim is shared by every instance.
+    /// Without Redis, claims are local to this process.
+    pub async fn set_if_absent(&self, key: &str, ttl_secs: u64) -> Result<bool, CacheError> {
+        let key = self.key(key);
+        let key = key.as_ref();
+
+        let Some(redis) = &self.redis else {
+            let now = Instant::now();
+            let mut claims = self.claims.lock().unwrap();
+            if claims.get(key).is_some_and(|until| *until > now) {
+                return Ok(false);
+            }
+            // Claims are short-lived; drop lapsed ones before they pile up
+            if claims.len() >= 1024 {
+                claims.retain(|_, until| *until > now);
+            }
+            claims.insert(key.to_string(), now + Duration::from_secs(ttl_secs));
+            return Ok(true);
+        };
+
+        let mut conn = redis.clone();
+        let set: Option<String> = redis::cmd("SET")
+            .arg(key)
+            .arg(1)
+            .arg("NX")
+            .arg("EX")
+            .arg(ttl_secs.max(1))
+            .query_async(&mut conn)
+            .await?;
+        Ok(set.is_some())
+    }
+
+    /// Round-trip a PING to Redis
+    pub async fn ping(&self) -> Result<(), CacheError> {
+        let Some(redis) = &self.redis else {
+            return Ok(());
+        };
+        let mut conn = redis.clone();
+        redis::cmd("PING").query_async::<()>(&mut conn).await?;
+        Ok(())
+    }
+
+    /// Delete a value from both cache tiers, or release a claim
     pub async fn delete(&self, key: &str) -> Result<(), CacheError> {
+        let key = self.key(key);
+        let key = key.as_ref();
         self.l1_cache.invalidate(key).await;
-        let mut conn = self.redis.lock().await;
+        self.claims.lock().unwrap().remove(key);
+        let Some(redis) = &self.redis else {
+            return Ok(());
+        };
+        let mut conn = redis.clone();
         redis::cmd("DEL")
             .arg(key)
-            .query_async::<()>(&mut *conn)
+            .query_async::<()>(&mut conn)
             .await?;
         Ok(())
     }
 
-    /// Invalidate all cache entries matching a pattern
-    pub async fn invalidate_pattern(&self, pattern: &str) -> Result<(), CacheError> {
-        // For L1, we need to iterate (clear all for simplicity)
-        self.l1_cache.invalidate_all();
+    /// Invalidate all cache entries matching a Redis glob pattern, deleting
+    /// at most [`DEFAULT_INVALIDATE_MAX_KEYS`] Redis keys
+    pub async fn invalidate_pattern(&self, pattern: &str) -> Result<InvalidationReport, CacheError> {
+        self.invalidate_pattern_with(pattern, InvalidateOptions::default()).await
+    }
 
-        // For Redis, use KEYS to find matching keys
-        let mut conn = self.redis.lock().await;
-        let keys: Vec<String> = redis::cmd("KEYS")
-            .arg(pattern)
-            .query_async(&mut *conn)
-            .await?;
+    /// Invalidate entries matching a Redis glob pattern in both tiers
+    ///
+    /// Redis keys are found with `SCAN` in batches of [`SCAN_BATCH_SIZE`]
+    /// and each batch is `UNLINK`ed before the next, so Redis is never
+    /// blocked the way `KEYS` blocks it. Stops after `max_keys` matches. Only
+    /// L1 entries under the pattern's literal prefix that match it are
+    /// dropped; other entries stay cached.
+    pub async fn invalidate_pattern_with(
+        &self,
+        pattern: &str,
+        options: InvalidateOptions,
+    ) -> Result<InvalidationReport, CacheError> {
+        let pattern = self.key(pattern);
+        let pattern = pattern.as_ref();
+        let mut report = InvalidationReport { dry_run: options.dry_run, ..Default::default() };
 
-        if !keys.is_empty() {
-            redis::cmd("DEL")
-                .arg(keys)
-                .query_async::<()>(&mut *conn)
+        let prefix = literal_prefix(pattern);
+        let l1_keys: Vec<Arc<String>> = self
+            .l1_cache
+            .iter()
+            .map(|(key, _)| key)
+            .filter(|key| key.starts_with(prefix) && glob_matches(pattern.as_bytes(), key.as_bytes()))
+            .collect();
+        report.l1_matched = l1_keys.len();
+        if !options.dry_run {
+            for key in l1_keys {
+                self.l1_cache.invalidate(key.as_str()).await;
+            }
+        }
+
+        let Some(redis) = &self.redis else {
+            return Ok(report);
+        };
+
+        let mut conn = redis.clone();
+        let mut cursor: u64 = 0;
+        loop {
+            let (next, mut keys): (u64, Vec<String>) = redis::cmd("SCAN")
+                .arg(cursor)
+                .arg("MATCH")
+                .arg(pattern)
+                .arg("COUNT")
+                .arg(SCAN_BATCH_SIZE)
+                .query_async(&mut conn)
                 .await?;
+
+            let remaining = options.max_keys - report.matched;
+            if keys.len() > remaining {
+                keys.truncate(remaining);
+                report.truncated = true;
+            }
+            report.matched += keys.len();
+
+            if !options.dry_run && !keys.is_empty() {
+                let deleted: usize = redis::cmd("UNLINK").arg(&keys).query_async(&mut conn).await?;
+                report.deleted += deleted;
+            }
+
+            cursor = next;
+            if cursor == 0 {
+                break;
+            }
+            if report.matched >= options.max_keys {
+                report.truncated = true;
+                break;
+            }
         }
 
-        tracing::debug!("Invalidated cache pattern: {}", pattern);
-        Ok(())
+        tracing::debug!(
+            "Invalidated cache pattern {}: {} Redis keys matched, {} deleted, {} L1 entries{}",
+            pattern,
+            report.matched,
+            report.deleted,
+            report.l1_matched,
+            if report.truncated { " (truncated)" } else { "" }
+        );
+        Ok(report)
+    }
+
+    /// Get a user's cached seen-profile set (L1 only)
+    ///
+    /// Each instance keeps its own copy, and recording an event only
+    /// invalidates the copy of the instance that recorded it. Another
+    /// instance may serve a set missing that event until it expires, after
+    /// `cache.ttls.seen_set_secs`.
+    pub async fn get_seen_set(&self, user_id: &str) -> Option<Arc<HashSet<String>>> {
+        let key = CacheKey::seen(user_id);
+        let set = self.seen_sets.get(&key).await;
+        tracing::trace!("Seen set {}: {}", if set.is_some() { "hit" } else { "miss" }, key);
+        set
+    }
+
+    /// Store a user's seen-profile set in the L1 cache
+    pub async fn set_seen_set(&self, user_id: &str, seen: Arc<HashSet<String>>) {
+        self.seen_sets.insert(CacheKey::seen(user_id), seen).await;
+    }
+
+    /// Drop a user's cached seen-profile set, e.g. after a new event is recorded
+    pub async fn invalidate_seen_set(&self, user_id: &str) {
+        self.seen_sets.invalidate(&CacheKey::seen(user_id)).await;
+    }
+
+    /// Read-through get: the cached value, or `compute`'s result stored for
+    /// `ttl_secs`
+    ///
+    /// Concurrent misses on one key run `compute` once and share its result,
+    /// including its error. Errors are returned, not cached.
+    pub async fn get_or_compute<T, E, F, Fut>(&self, key: &str, ttl_secs: u64, compute: F) -> Result<T, E>
+    where
+        T: Serialize + DeserializeOwned,
+        E: SharedError,
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        get_or_compute(self, key, ttl_secs, 0, compute).await
+    }
+
+    /// [`get_or_compute`](Self::get_or_compute) that keeps serving an
+    /// expired value for `stale_secs` while one caller refreshes it
+    pub async fn get_or_compute_stale<T, E, F, Fut>(
+        &self,
+        key: &str,
+        ttl_secs: u64,
+        stale_secs: u64,
+        compute: F,
+    ) -> Result<T, E>
+    where
+        T: Serialize + DeserializeOwned,
+        E: SharedError,
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        get_or_compute(self, key, ttl_secs, stale_secs, compute).await
+    }
+
+    /// Recomputes in flight for `get_or_compute`
+    pub fn recomputes(&self) -> &Singleflight<String, SharedRecompute> {
+        &self.recomputes
     }
 
-    /// Get cache statistics
+    /// Hit and miss counts since startup, per tier and key prefix
     pub fn stats(&self) -> CacheStats {
+        let other = ("other", &self.prefix_counters[TRACKED_PREFIXES.len()]);
+        let prefixes = TRACKED_PREFIXES
+            .iter()
+            .copied()
+            .zip(&self.prefix_counters)
+            .chain(std::iter::once(other))
+            .map(|(prefix, counters)| (prefix.to_string(), counters.snapshot()))
+            .collect();
+
         CacheStats {
             l1_size: self.l1_cache.entry_count(),
-            l1_hit_count: 0,
-            l1_miss_count: 0,
-            l1_hit_rate: 0.0,
+            l1: self.l1_counters.snapshot(),
+            l2: self.redis.as_ref().map(|_| self.l2_counters.snapshot()),
+            prefixes,
         }
     }
 }
 
+/// Key prefixes (the part before the first `:`) with their own hit ratio
+/// in [`CacheStats`]
+pub const TRACKED_PREFIXES: [&str; 4] = ["prefs", "profile", "candidates", "matches"];
+
+/// Index into `prefix_counters` for a key, before any tenant prefix
+fn prefix_index(key: &str) -> usize {
+    let prefix = key.split(':').next().unwrap_or(key);
+    TRACKED_PREFIXES
+        .iter()
+        .position(|tracked| *tracked == prefix)
+        .unwrap_or(TRACKED_PREFIXES.len())
+}
+
+/// Lookup outcomes of one tier or key prefix
+#[derive(Default)]
+struct HitCounters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    errors: AtomicU64,
+}
+
+impl HitCounters {
+    fn hit(&self) {
+        self.hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn miss(&self) {
+        self.misses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn error(&self) {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> HitStats {
+        let hits = self.hits.load(Ordering::Relaxed);
+        let misses = self.misses.load(Ordering::Relaxed);
+        let lookups = hits + misses;
+        HitStats {
+            hits,
+            misses,
+            errors: self.errors.load(Ordering::Relaxed),
+            hit_rate: if lookups == 0 { 0.0 } else { hits as f64 / lookups as f64 },
+        }
+    }
+}
+
+/// Keys requested per `SCAN` call during pattern invalidation
+pub const SCAN_BATCH_SIZE: usize = 500;
+
+/// Most Redis keys one pattern invalidation deletes unless told otherwise
+pub const DEFAULT_INVALIDATE_MAX_KEYS: usize = 100_000;
+
+/// How [`CacheManager::invalidate_pattern_with`] runs
+#[derive(Debug, Clone, Copy)]
+pub struct InvalidateOptions {
+    /// Stop after this many matching Redis keys
+    pub max_keys: usize,
+    /// Count matching entries without deleting anything
+    pub dry_run: bool,
+}
+
+impl Default for InvalidateOptions {
+    fn default() -> Self {
+        Self { max_keys: DEFAULT_INVALIDATE_MAX_KEYS, dry_run: false }
+    }
+}
+
+/// Outcome of a pattern invalidation
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InvalidationReport {
+    /// Matching Redis keys found
+    pub matched: usize,
+    /// Redis keys deleted (0 in a dry run)
+    pub deleted: usize,
+    /// Matching entries in this process's L1 cache
+    pub l1_matched: usize,
+    /// Stopped at `max_keys`; more keys may match
+    pub truncated: bool,
+    pub dry_run: bool,
+}
+
+/// The part of a glob pattern before its first wildcard or escape
+fn literal_prefix(pattern: &str) -> &str {
+    pattern
+        .find(['*', '?', '[', '\\'])
+        .map_or(pattern, |end| &pattern[..end])
+}
+
+/// Whether `key` matches a Redis glob pattern (`*`, `?`, `[a-z]`, `[^a]`
+/// and `\` escapes)
+fn glob_matches(pattern: &[u8], key: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => key.is_empty(),
+        Some((b'*', _)) => {
+            let rest = &pattern[pattern.iter().take_while(|c| **c == b'*').count()..];
+            (0..=key.len()).any(|start| glob_matches(rest, &key[start..]))
+        }
+        Some((b'?', rest)) => !key.is_empty() && glob_matches(rest, &key[1..]),
+        Some((b'[', rest)) => {
+            let Some((&c, key_rest)) = key.split_first() else {
+                return false;
+            };
+            match match_class(rest, c) {
+                Some((matched, after)) => matched && glob_matches(after, key_rest),
+                // An unclosed class is a literal '['
+                None => c == b'[' && glob_matches(rest, key_rest),
+            }
+        }
+        Some((b'\\', [escaped, rest @ ..])) => key.first() == Some(escaped) && glob_matches(rest, &key[1..]),
+        Some((c, rest)) => key.first() == Some(c) && glob_matches(rest, &key[1..]),
+    }
+}
+
+/// Whether `c` is in the class starting after a '[', and the pattern after
+/// its closing ']'; None when the class is never closed
+fn match_class(class: &[u8], c: u8) -> Option<(bool, &[u8])> {
+    let (negated, mut class) = match class.split_first() {
+        Some((b'^', rest)) => (true, rest),
+        _ => (false, class),
+    };
+
+    let mut matched = false;
+    loop {
+        class = match class {
+            [] => return None,
+            [b']', rest @ ..] => return Some((matched != negated, rest)),
+            [b'\\', escaped, rest @ ..] => {
+                matched |= *escaped == c;
+                rest
+            }
+            [low, b'-', high, rest @ ..] if *high != b']' => {
+                matched |= (*low.min(high)..=*low.max(high)).contains(&c);
+                rest
+            }
+            [other, rest @ ..] => {
+                matched |= *other == c;
+                rest
+            }
+        };
+    }
+}
+
+/// A `get_or_compute` value and when it goes stale and expires (Unix ms)
+///
+/// The L1 TTL is shared by all keys, so freshness is kept in the entry
+/// rather than left to the cache.
+#[derive(Serialize, Deserialize)]
+struct ReadThroughEntry<T> {
+    value: T,
+    fresh_until: i64,
+    stale_until: i64,
+}
+
+/// The value of a `get_or_compute` entry read some other way, if still fresh
+pub(crate) fn fresh_value<T: DeserializeOwned>(bytes: &[u8]) -> Option<T> {
+    let entry: ReadThroughEntry<T> = serde_json::from_slice(bytes).ok()?;
+    (Utc::now().timestamp_millis() < entry.fresh_until).then_some(entry.value)
+}
+
+/// Shared by `CacheManager::get_or_compute` and `dyn Cache::get_or_compute`
+pub(crate) async fn get_or_compute<C, T, E, F, Fut>(
+    cache: &C,
+    key: &str,
+    ttl_secs: u64,
+    stale_secs: u64,
+    compute: F,
+) -> Result<T, E>
+where
+    C: Cache + ?Sized,
+    T: Serialize + DeserializeOwned,
+    E: SharedError,
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let cached = read_entry(cache, key).await;
+    serve(cache, key, ttl_secs, stale_secs, cached, &compute).await
+}
+
+/// [`get_or_compute`] that also refreshes a fresh entry in the background
+/// once it is read within the last `refresh_ahead` share (0-1) of its TTL
+///
+/// Popular keys are then replaced before they expire and their readers
+/// never wait on a recompute. The refresh runs on its own task, so it
+/// needs an owned cache and a `'static` compute.
+pub(crate) async fn get_or_refresh_ahead<T, E, F, Fut>(
+    cache: &Arc<dyn Cache>,
+    key: &str,
+    ttl_secs: u64,
+    stale_secs: u64,
+    refresh_ahead: f64,
+    compute: F,
+) -> Result<T, E>
+where
+    T: Serialize + DeserializeOwned + Send + 'static,
+    E: SharedError,
+    F: Fn() -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<T, E>> + Send + 'static,
+{
+    let cached = read_entry::<T>(cache.as_ref(), key).await;
+
+    let now = Utc::now().timestamp_millis();
+    let window_ms = (ttl_secs as f64 * 1000.0 * refresh_ahead.clamp(0.0, 1.0)) as i64;
+    match cached {
+        Some(entry) if now < entry.fresh_until && entry.fresh_until - now <= window_ms => {
+            if !cache.recomputes().is_running(&key.to_string()) {
+                let (cache, key) = (cache.clone(), key.to_string());
+                tokio::spawn(async move {
+                    if let Err(e) = recompute(cache.as_ref(), &key, ttl_secs, stale_secs, &compute).await {
+                        tracing::warn!("Refreshing {} ahead of expiry failed: {}", key, e);
+                    }
+                });
+            }
+            Ok(entry.value)
+        }
+        cached => serve(cache.as_ref(), key, ttl_secs, stale_secs, cached, &compute).await,
+    }
+}
+
+async fn read_entry<C, T>(cache: &C, key: &str) -> Option<ReadThroughEntry<T>>
+where
+    C: Cache + ?Sized,
+    T: DeserializeOwned,
+{
+    match cache.get_bytes(key).await {
+        Ok(bytes) => bytes.and_then(|bytes| serde_json::from_slice(&bytes).ok()),
+        Err(e) => {
+            tracing::warn!("Cache read for {} failed, recomputing: {}", key, e);
+            None
+        }
+    }
+}
+
+/// A cached entry while fresh or (with a refresh running) stale, else a
+/// recomputed value
+async fn serve<C, T, E, F, Fut>(
+    cache: &C,
+    key: &str,
+    ttl_secs: u64,
+    stale_secs: u64,
+    cached: Option<ReadThroughEntry<T>>,
+    compute: &F,
+) -> Result<T, E>
+where
+    C: Cache + ?Sized,
+    T: Serialize + DeserializeOwned,
+    E: SharedError,
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let now = Utc::now().timestamp_millis();
+    let stale = match cached {
+        Some(entry) if now < entry.fresh_until => return Ok(entry.value),
+        Some(entry) if now < entry.stale_until => {
+            // Another caller is already refreshing it
+            if cache.recomputes().is_running(&key.to_string()) {
+                return Ok(entry.value);
+            }
+            Some(entry.value)
+        }
+        _ => None,
+    };
+
+    match (recompute(cache, key, ttl_secs, stale_secs, compute).await, stale) {
+        (Err(e), Some(value)) => {
+            tracing::warn!("Refreshing {} failed, serving the stale value: {}", key, e);
+            Ok(value)
+        }
+        (result, _) => result,
+    }
+}
+
+/// Run `compute` for `key` once across concurrent callers and store it
+///
+/// Waiting callers get the leader's value, or a copy of its error: a failing
+/// upstream is called once per miss, not once per caller.
+async fn recompute<C, T, E, F, Fut>(cache: &C, key: &str, ttl_secs: u64, stale_secs: u64, compute: &F) -> Result<T, E>
+where
+    C: Cache + ?Sized,
+    T: Serialize + DeserializeOwned,
+    E: SharedError,
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    // The leader keeps its own typed result; waiters get the stored bytes
+    let mut own = None;
+    let slot = &mut own;
+    let shared = cache
+        .recomputes()
+        .run(key.to_string(), move || async move {
+            let result = compute().await;
+            let shared = match &result {
+                Ok(value) => store(cache, key, value, ttl_secs, stale_secs).await.map(Ok),
+                Err(e) => Some(Err(Arc::new(e.duplicate()) as Arc<dyn Any + Send + Sync>)),
+            };
+            *slot = Some(result);
+            shared
+        })
+        .await;
+
+    if let Some(result) = own {
+        return result;
+    }
+
+    match shared {
+        Some(Ok(bytes)) => {
+            if let Ok(entry) = serde_json::from_slice::<ReadThroughEntry<T>>(&bytes) {
+                return Ok(entry.value);
+            }
+        }
+        Some(Err(e)) => {
+            if let Some(e) = e.downcast_ref::<E>() {
+                return Err(e.duplicate());
+            }
+        }
+        None => {}
+    }
+
+    // Nothing usable to share (e.g. the value didn't serialize)
+    compute().await
+}
+
+/// Store `value` as a read-through entry of `key`, as a recompute would
+pub(crate) async fn store<C, T>(cache: &C, key: &str, value: &T, ttl_secs: u64, stale_secs: u64) -> Option<Vec<u8>>
+where
+    C: Cache + ?Sized,
+    T: Serialize,
+{
+    let fresh_until = Utc::now().timestamp_millis() + ttl_secs as i64 * 1000;
+    let entry = ReadThroughEntry { value, fresh_until, stale_until: fresh_until + stale_secs as i64 * 1000 };
+    let bytes = match serde_json::to_vec(&entry) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::warn!("Failed to serialize {}: {}", key, e);
+            return None;
+        }
+    };
+
+    if let Err(e) = cache.set_bytes(key, bytes.clone(), ttl_secs + stale_secs).await {
+        tracing::warn!("Failed to cache {}: {}", key, e);
+    }
+    Some(bytes)
+}
+
+#[derive(Clone)]
+struct L1Entry {
+    bytes: Vec<u8>,
+    ttl: Duration,
+}
+
+/// Expires each L1 entry after its own TTL, at most `max`
+struct L1Expiry {
+    max: Duration,
+}
+
+impl moka::Expiry<String, L1Entry> for L1Expiry {
+    fn expire_after_create(&self, _key: &String, entry: &L1Entry, _created_at: Instant) -> Option<Duration> {
+        Some(entry.ttl.min(self.max))
+    }
+
+    fn expire_after_update(
+        &self,
+        _key: &String,
+        entry: &L1Entry,
+        _updated_at: Instant,
+        _duration_until_expiry: Option<Duration>,
+    ) -> Option<Duration> {
+        Some(entry.ttl.min(self.max))
+    }
+}
+
+/// L1 TTL for an entry read from Redis: what's left of its Redis TTL
+/// (`PTTL`, in ms), at most its key class's TTL
+///
+/// Negative `PTTL`s (no expiry, or gone since the read) fall back to the
+/// class TTL.
+fn remaining_ttl(pttl_ms: i64, ttl_secs: u64) -> Duration {
+    let class_ttl = Duration::from_secs(ttl_secs);
+    u64::try_from(pttl_ms).map_or(class_ttl, |ms| Duration::from_millis(ms).min(class_ttl))
+}
+
+/// L1 cache for seen sets, kept short since other instances never invalidate it
+fn seen_set_cache(capacity: u64, ttl_secs: u64) -> moka::future::Cache<String, Arc<HashSet<String>>> {
+    moka::future::CacheBuilder::new(capacity).time_to_live(Duration::from_secs(ttl_secs)).build()
+}
+
+/// Sentinel stored under [`CacheKey::missing`] after a lookup found nothing
+///
+/// Like read-through entries it carries its own expiry (Unix ms), since
+/// L1 keeps it for the global TTL.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MissingEntry {
+    missing_until: i64,
+}
+
+/// Whether a lookup of `key` found nothing within the last sentinel TTL
+pub(crate) async fn is_missing(cache: &dyn Cache, key: &str) -> bool {
+    match cache.get_bytes(&CacheKey::missing(key)).await {
+        Ok(Some(bytes)) => serde_json::from_slice::<MissingEntry>(&bytes)
+            .is_ok_and(|entry| Utc::now().timestamp_millis() < entry.missing_until),
+        Ok(None) => false,
+        Err(e) => {
+            tracing::warn!("Cache read for missing {} failed: {}", key, e);
+            false
+        }
+    }
+}
+
+/// Remember for `ttl_secs` that a lookup of `key` found nothing
+pub(crate) async fn remember_missing(cache: &dyn Cache, key: &str, ttl_secs: u64) {
+    let entry = MissingEntry { missing_until: Utc::now().timestamp_millis() + ttl_secs as i64 * 1000 };
+    let bytes = serde_json::to_vec(&entry).expect("sentinel serializes");
+    if let Err(e) = cache.set_bytes(&CacheKey::missing(key), bytes, ttl_secs).await {
+        tracing::warn!("Failed to cache missing {}: {}", key, e);
+    }
+}
+
+/// Lookup counts and the share of them that hit (errors excluded)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HitStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub errors: u64,
+    pub hit_rate: f64,
+}
+
 /// Cache statistics
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct CacheStats {
     pub l1_size: u64,
-    pub l1_hit_count: u64,
-    pub l1_miss_count: u64,
-    pub l1_hit_rate: f64,
+    /// In-memory tier; every lookup starts here
+    pub l1: HitStats,
+    /// Redis, asked on L1 misses; None without Redis
+    pub l2: Option<HitStats>,
+    /// Lookups per key prefix, a hit in either tier counting as a hit
+    /// (`prefs`, `profile`, `candidates`, `matches` and `other`)
+    pub prefixes: BTreeMap<String, HitStats>,
 }
 
 /// Cache key builder
 pub struct CacheKey;
 
 impl CacheKey {
-    /// Build a cache key for user preferences
-    pub fn preferences(user_id: &str) -> String {
-        format!("prefs:{}", user_id)
+    /// Build a cache key for a version of a user's preferences
+    pub fn preferences(user_id: &str, updated_at: Option<DateTime<Utc>>) -> String {
+        format!("prefs:{}:v{}", user_id, version(updated_at))
     }
 
-    /// Build a cache key for candidate matches
-    pub fn candidates(user_id: &str, page: u32) -> String {
-        format!("candidates:{}:{}", user_id, page)
+    /// Build the read-through cache key for the version of a user's
+    /// preferences last fetched
+    pub fn current_preferences(user_id: &str) -> String {
+        format!("prefs:{}:current", user_id)
     }
 
-    /// Build a cache key for user profile
-    pub fn profile(user_id: &str) -> String {
-        format!("profile:{}", user_id)
+    /// Build the key remembering that a lookup of `key` found nothing
+    pub fn missing(key: &str) -> String {
+        format!("{}:missing", key)
     }
 
-    /// Build a cache key for match results
-    pub fn matches(user_id: &str) -> String {
-        format!("matches:{}", user_id)
+    /// Build a cache key for a user's candidate pool for one query
+    pub fn candidates(user_id: &str, query_fingerprint: &str) -> String {
+        format!("candidates:{}:{}", user_id, query_fingerprint)
+    }
+
+    /// Build a cache key for a version of a user's profile
+    pub fn profile(user_id: &str, updated_at: Option<DateTime<Utc>>) -> String {
+        format!("profile:{}:v{}", user_id, version(updated_at))
+    }
+
+    /// Build the read-through cache key for the version of a user's profile
+    /// last fetched
+    pub fn current_profile(user_id: &str) -> String {
+        format!("profile:{}:current", user_id)
+    }
+
+    /// Build a cache key for match results computed from the given versions
+    /// of the user's profile and preferences
+    pub fn matches(
+        user_id: &str,
+        profile_updated_at: Option<DateTime<Utc>>,
+        preferences_updated_at: Option<DateTime<Utc>>,
+    ) -> String {
+        format!("matches:{}:v{}.{}", user_id, version(profile_updated_at), version(preferences_updated_at))
+    }
+
+    /// Pattern matching every version of a user's match results
+    pub fn matches_pattern(user_id: &str) -> String {
+        format!("matches:{}:v*", user_id)
+    }
+
+    /// Build a cache key for a user's seen-profile set
+    pub fn seen(user_id: &str) -> String {
+        format!("seen:{}", user_id)
+    }
+
+    /// Build a cache key for a user's adapted overfetch multiplier
+    pub fn overfetch(user_id: &str) -> String {
+        format!("overfetch:{}", user_id)
+    }
+
+    /// Build a cache key for a user's top pick of the day
+    pub fn top_pick(user_id: &str) -> String {
+        format!("toppick:{}", user_id)
+    }
+
+    /// Build a dedup key for one user's event on a target
+    pub fn event_dedup(user_id: &str, target_user_id: &str, event_type: &str) -> String {
+        format!("event:{}:{}:{}", user_id, target_user_id, event_type)
+    }
+
+    /// Build the replay key of a used request signature
+    pub fn signature(signature: &str) -> String {
+        format!("signature:{}", signature)
     }
 }
 
+/// Version component of a key: the upstream document's `$updatedAt` in
+/// milliseconds (0 when unknown)
+///
+/// A changed document gets a new key, so entries built from the old one are
+/// never read again and just expire.
+fn version(updated_at: Option<DateTime<Utc>>) -> i64 {
+    updated_at.map_or(0, |at| at.timestamp_millis())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[cfg(feature = "testing")]
     #[tokio::test]
-    #[ignore = "Requires Redis"]
     async fn test_cache_set_get() {
-        let cache = CacheManager::new("redis://127.0.0.1:6379", 1000, 60)
+        let Some(redis) = crate::testing::containers::redis().await else {
+            return;
+        };
+        let cache = CacheManager::new(&redis.url, 1000, 60)
             .await
             .expect("Failed to create cache");
 
@@ -208,9 +1144,318 @@ mod tests {
 
     #[test]
     fn test_cache_key_builder() {
-        assert_eq!(CacheKey::preferences("user123"), "prefs:user123");
-        assert_eq!(CacheKey::candidates("user123", 1), "candidates:user123:1");
-        assert_eq!(CacheKey::profile("user123"), "profile:user123");
-        assert_eq!(CacheKey::matches("user123"), "matches:user123");
+        let updated_at = DateTime::from_timestamp_millis(1_717_243_200_123);
+        assert_eq!(CacheKey::preferences("user123", updated_at), "prefs:user123:v1717243200123");
+        assert_eq!(CacheKey::preferences("user123", None), "prefs:user123:v0");
+        assert_eq!(CacheKey::current_preferences("user123"), "prefs:user123:current");
+        assert_eq!(CacheKey::candidates("user123", "9f86d081"), "candidates:user123:9f86d081");
+        assert_eq!(CacheKey::profile("user123", updated_at), "profile:user123:v1717243200123");
+        assert_eq!(CacheKey::current_profile("user123"), "profile:user123:current");
+        assert_eq!(CacheKey::missing("profile:user123:current"), "profile:user123:current:missing");
+        assert_eq!(CacheKey::matches("user123", updated_at, None), "matches:user123:v1717243200123.0");
+        assert_eq!(CacheKey::matches_pattern("user123"), "matches:user123:v*");
+        assert_eq!(CacheKey::seen("user123"), "seen:user123");
+        assert_eq!(CacheKey::overfetch("user123"), "overfetch:user123");
+        assert_eq!(CacheKey::top_pick("user123"), "toppick:user123");
+        assert_eq!(CacheKey::event_dedup("a", "b", "liked"), "event:a:b:liked");
+        assert_eq!(CacheKey::signature("ab12"), "signature:ab12");
+    }
+
+    #[tokio::test]
+    async fn test_set_if_absent_in_memory() {
+        let cache = CacheManager::in_memory(100, 60);
+
+        assert!(cache.set_if_absent("event:a:b:liked", 60).await.unwrap());
+        assert!(!cache.set_if_absent("event:a:b:liked", 60).await.unwrap());
+        assert!(cache.set_if_absent("event:a:c:liked", 60).await.unwrap());
+
+        // Released claims and lapsed ones can be taken again
+        cache.delete("event:a:b:liked").await.unwrap();
+        assert!(cache.set_if_absent("event:a:b:liked", 60).await.unwrap());
+        assert!(cache.set_if_absent("event:a:d:liked", 0).await.unwrap());
+        assert!(cache.set_if_absent("event:a:d:liked", 0).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_get_or_compute_runs_once_per_miss() {
+        let cache = CacheManager::in_memory(100, 60);
+        let runs = std::sync::atomic::AtomicUsize::new(0);
+        let compute = || async {
+            runs.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            Ok::<_, String>("profile".to_string())
+        };
+
+        let (a, b, c) = tokio::join!(
+            cache.get_or_compute("profile:u1:current", 60, compute),
+            cache.get_or_compute("profile:u1:current", 60, compute),
+            cache.get_or_compute("profile:u1:current", 60, compute),
+        );
+        for result in [a, b, c] {
+            assert_eq!(result.unwrap(), "profile");
+        }
+        assert_eq!(cache.get_or_compute("profile:u1:current", 60, compute).await.unwrap(), "profile");
+        assert_eq!(runs.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        // Errors aren't cached
+        let failing = || async { Err::<String, _>("unavailable".to_string()) };
+        assert!(cache.get_or_compute("profile:u2:current", 60, failing).await.is_err());
+        assert_eq!(cache.get_or_compute("profile:u2:current", 60, compute).await.unwrap(), "profile");
+    }
+
+    #[tokio::test]
+    async fn test_get_or_compute_shares_the_leaders_error() {
+        let cache = CacheManager::in_memory(100, 60);
+        let runs = std::sync::atomic::AtomicUsize::new(0);
+        let failing = || async {
+            runs.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            Err::<String, _>("unavailable".to_string())
+        };
+
+        let (a, b, c) = tokio::join!(
+            cache.get_or_compute("profile:u1:current", 60, failing),
+            cache.get_or_compute("profile:u1:current", 60, failing),
+            cache.get_or_compute("profile:u1:current", 60, failing),
+        );
+        for result in [a, b, c] {
+            assert_eq!(result.unwrap_err(), "unavailable");
+        }
+        assert_eq!(runs.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_get_or_compute_serves_stale_while_refreshing() {
+        let cache = CacheManager::in_memory(100, 60);
+        let value = |v: &'static str| move || async move { Ok::<_, String>(v.to_string()) };
+
+        // A TTL of 0 makes every entry stale at once
+        assert_eq!(cache.get_or_compute_stale("k", 0, 60, value("v1")).await.unwrap(), "v1");
+
+        // A failed refresh falls back to the stale value
+        let failing = || async { Err::<String, _>("unavailable".to_string()) };
+        assert_eq!(cache.get_or_compute_stale("k", 0, 60, failing).await.unwrap(), "v1");
+
+        // Callers arriving during a refresh get the stale value; the
+        // refreshing caller gets the new one
+        let slow = || async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Ok::<_, String>("v2".to_string())
+        };
+        let (refreshed, served) = tokio::join!(cache.get_or_compute_stale("k", 0, 60, slow), async {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            cache.get_or_compute_stale("k", 0, 60, value("unused")).await
+        });
+        assert_eq!(refreshed.unwrap(), "v2");
+        assert_eq!(served.unwrap(), "v1");
+
+        // Without a stale window an expired entry is recomputed
+        assert_eq!(cache.get_or_compute("k", 0, value("v3")).await.unwrap(), "v3");
+        assert_eq!(cache.get_or_compute("k", 0, value("v4")).await.unwrap(), "v4");
+    }
+
+    #[tokio::test]
+    async fn test_ttls_by_key_class() {
+        let ttls = CacheTtlSettings {
+            profile_secs: 30,
+            preferences_secs: 600,
+            candidates_secs: 60,
+            matches_secs: 15,
+            seen_set_secs: 5,
+        };
+        let cache = CacheManager::in_memory(100, 300).with_ttls(ttls).with_key_prefix("acme:");
+        assert_eq!(cache.seen_sets.policy().time_to_live(), Some(Duration::from_secs(5)));
+        // Never longer than the global TTL
+        let short = CacheManager::in_memory(100, 2).with_ttls(CacheTtlSettings::default());
+        assert_eq!(short.seen_sets.policy().time_to_live(), Some(Duration::from_secs(2)));
+
+        assert_eq!(cache.ttl_for("profile:u1:current"), 30);
+        assert_eq!(cache.ttl_for("prefs:u1:v0"), 600);
+        assert_eq!(cache.ttl_for("candidates:u1:abc"), 60);
+        assert_eq!(cache.ttl_for("matches:u1:v0.0"), 15);
+        assert_eq!(cache.ttl_for("top_pick:u1"), 300);
+
+        // L1 honours each entry's own TTL instead of the global one
+        cache.set_with_ttl("matches:u1:v0.0", &"feed", 0).await.unwrap();
+        cache.set("prefs:u1:v0", &"prefs").await.unwrap();
+        assert!(cache.get_bytes("matches:u1:v0.0").await.unwrap().is_none());
+        assert!(cache.get_bytes("prefs:u1:v0").await.unwrap().is_some());
+    }
+
+    #[test]
+    fn test_remaining_ttl_caps_l1_at_redis_expiry() {
+        assert_eq!(remaining_ttl(4_500, 15), Duration::from_millis(4_500));
+        assert_eq!(remaining_ttl(600_000, 15), Duration::from_secs(15));
+        // No expiry, or expired since the read
+        assert_eq!(remaining_ttl(-1, 15), Duration::from_secs(15));
+        assert_eq!(remaining_ttl(-2, 15), Duration::from_secs(15));
+    }
+
+    #[tokio::test]
+    async fn test_refresh_ahead_replaces_entries_in_background() {
+        fn counting(calls: &Arc<AtomicU64>) -> impl Fn() -> std::future::Ready<Result<u64, String>> + Send + Sync + 'static {
+            let calls = calls.clone();
+            move || std::future::ready(Ok(calls.fetch_add(1, Ordering::SeqCst) + 1))
+        }
+        let cache: Arc<dyn Cache> = Arc::new(CacheManager::in_memory(100, 60));
+        let calls = Arc::new(AtomicU64::new(0));
+
+        // Read well before expiry: served from the cache, nothing refreshed
+        assert_eq!(get_or_refresh_ahead(&cache, "k", 60, 0, 0.2, counting(&calls)).await.unwrap(), 1);
+        assert_eq!(get_or_refresh_ahead(&cache, "k", 60, 0, 0.2, counting(&calls)).await.unwrap(), 1);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // Inside the window the cached value is served and replaced behind it
+        assert_eq!(get_or_refresh_ahead(&cache, "k", 60, 0, 1.0, counting(&calls)).await.unwrap(), 1);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(get_or_refresh_ahead(&cache, "k", 60, 0, 0.2, counting(&calls)).await.unwrap(), 2);
+    }
+
+    #[test]
+    fn test_glob_matches() {
+        let matches = |pattern: &str, key: &str| glob_matches(pattern.as_bytes(), key.as_bytes());
+
+        assert!(matches("matches:*", "matches:u1:v0.0"));
+        assert!(matches("matches:u1:v*", "matches:u1:v12.0"));
+        assert!(!matches("matches:u1:v*", "matches:u10:v12.0"));
+        assert!(matches("*:u1:*", "prefs:u1:current"));
+        assert!(matches("h?llo", "hello") && !matches("h?llo", "hllo"));
+        assert!(matches("h[ae]llo", "hallo") && !matches("h[ae]llo", "hillo"));
+        assert!(matches("h[^e]llo", "hallo") && !matches("h[^e]llo", "hello"));
+        assert!(matches("h[a-c]llo", "hbllo") && !matches("h[a-c]llo", "hdllo"));
+        assert!(matches("a\\*b", "a*b") && !matches("a\\*b", "axb"));
+        assert!(matches("a[b", "a[b"));
+
+        assert_eq!(literal_prefix("matches:u1:v*"), "matches:u1:v");
+        assert_eq!(literal_prefix("*"), "");
+        assert_eq!(literal_prefix("seen:u1"), "seen:u1");
+    }
+
+    #[tokio::test]
+    async fn test_invalidate_pattern_is_selective_in_l1() {
+        let cache = CacheManager::in_memory(100, 60).with_key_prefix("acme:");
+        for key in ["matches:u1:v1.0", "matches:u1:v2.0", "matches:u10:v1.0", "profile:u1:current"] {
+            cache.set(key, &"value").await.unwrap();
+        }
+
+        let options = InvalidateOptions { dry_run: true, ..Default::default() };
+        let report = cache.invalidate_pattern_with("matches:u1:v*", options).await.unwrap();
+        assert_eq!((report.l1_matched, report.deleted), (2, 0));
+        assert!(report.dry_run);
+        assert!(cache.get::<String>("matches:u1:v1.0").await.is_ok());
+
+        let report = cache.invalidate_pattern("matches:u1:v*").await.unwrap();
+        assert_eq!(report.l1_matched, 2);
+        assert!(cache.get::<String>("matches:u1:v1.0").await.is_err());
+        assert!(cache.get::<String>("matches:u1:v2.0").await.is_err());
+        assert!(cache.get::<String>("matches:u10:v1.0").await.is_ok());
+        assert!(cache.get::<String>("profile:u1:current").await.is_ok());
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn test_invalidate_pattern_scans_redis() {
+        let Some(redis) = crate::testing::containers::redis().await else {
+            return;
+        };
+        let cache = CacheManager::new(&redis.url, 1000, 60)
+            .await
+            .expect("Failed to create cache")
+            .with_key_prefix("invalidate-test:");
+        for i in 0..1200 {
+            cache.set(&format!("matches:u{}:v0.0", i), &i).await.unwrap();
+        }
+
+        let dry_run = InvalidateOptions { dry_run: true, ..Default::default() };
+        let report = cache.invalidate_pattern_with("matches:*", dry_run).await.unwrap();
+        assert_eq!((report.matched, report.deleted), (1200, 0));
+
+        let bounded = InvalidateOptions { max_keys: 1000, dry_run: false };
+        let report = cache.invalidate_pattern_with("matches:*", bounded).await.unwrap();
+        assert_eq!(report.deleted, 1000);
+        assert!(report.truncated);
+
+        let report = cache.invalidate_pattern("matches:*").await.unwrap();
+        assert_eq!(report.deleted, 200);
+        assert!(!report.truncated);
+    }
+
+    #[tokio::test]
+    async fn test_stats_count_hits_by_prefix() {
+        let cache = CacheManager::in_memory(100, 60).with_key_prefix("acme:");
+        cache.set("prefs:u1:current", &"prefs").await.unwrap();
+
+        for _ in 0..3 {
+            cache.get::<String>("prefs:u1:current").await.unwrap();
+        }
+        assert!(cache.get::<String>("prefs:u2:current").await.is_err());
+        assert!(cache.get::<String>("matches:u1:v0.0").await.is_err());
+        assert!(cache.get::<String>("toppick:u1").await.is_err());
+
+        let stats = cache.stats();
+        assert_eq!((stats.l1.hits, stats.l1.misses), (3, 3));
+        assert_eq!(stats.l1.hit_rate, 0.5);
+        assert!(stats.l2.is_none());
+        assert_eq!(stats.prefixes["prefs"], HitStats { hits: 3, misses: 1, errors: 0, hit_rate: 0.75 });
+        assert_eq!(stats.prefixes["matches"].misses, 1);
+        assert_eq!(stats.prefixes["other"].misses, 1);
+        assert_eq!(stats.prefixes["candidates"], HitStats::default());
+    }
+
+    #[tokio::test]
+    async fn test_many_key_operations() {
+        let cache = CacheManager::in_memory(100, 60).with_key_prefix("acme:");
+        let keys: Vec<String> = ["prefs:u1:current", "prefs:u2:current", "prefs:u3:current"].map(String::from).into();
+
+        cache
+            .set_many(vec![(keys[0].clone(), b"1".to_vec()), (keys[2].clone(), b"3".to_vec())], 60)
+            .await
+            .unwrap();
+        let values = cache.get_many(&keys).await.unwrap();
+        assert_eq!(values, vec![Some(b"1".to_vec()), None, Some(b"3".to_vec())]);
+        assert!(cache.l1_cache.contains_key("acme:prefs:u3:current"));
+        assert_eq!(cache.stats().prefixes["prefs"].hits, 2);
+
+        cache.delete_many(&keys[..1]).await.unwrap();
+        let values = cache.get_many(&keys).await.unwrap();
+        assert_eq!(values, vec![None, None, Some(b"3".to_vec())]);
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn test_many_key_operations_in_redis() {
+        let Some(redis) = crate::testing::containers::redis().await else {
+            return;
+        };
+        let cache = CacheManager::new(&redis.url, 1000, 60)
+            .await
+            .expect("Failed to create cache")
+            .with_key_prefix("many-test:");
+        let keys: Vec<String> = (0..50).map(|i| format!("candidates:u{}:pool", i)).collect();
+
+        cache.set_many(keys.iter().map(|key| (key.clone(), key.clone().into_bytes())).collect(), 60).await.unwrap();
+        cache.l1_cache.invalidate_all();
+        cache.l1_cache.run_pending_tasks().await;
+
+        let values = cache.get_many(&keys).await.unwrap();
+        assert!(values.iter().zip(&keys).all(|(value, key)| value.as_deref() == Some(key.as_bytes())));
+        assert_eq!(cache.stats().l2.unwrap().hits, 50);
+
+        cache.delete_many(&keys).await.unwrap();
+        assert!(cache.get_many(&keys).await.unwrap().iter().all(Option::is_none));
+    }
+
+    #[tokio::test]
+    async fn test_key_prefix() {
+        let cache = CacheManager::in_memory(100, 60);
+        assert_eq!(cache.key("matches:u1"), "matches:u1");
+
+        let cache = cache.with_key_prefix("acme:");
+        assert_eq!(cache.key("matches:u1"), "acme:matches:u1");
+
+        cache.set("matches:u1", &"feed").await.unwrap();
+        assert_eq!(cache.get::<String>("matches:u1").await.unwrap(), "feed");
+        assert!(cache.l1_cache.contains_key("acme:matches:u1"));
     }
 }
@@ -0,0 +1,816 @@
+//! Stand-ins for PostgreSQL and Appwrite used by the `--dev` profile
+//!
+//! [`SqliteSeenStore`] keeps swipe history in a local SQLite file and
+//! [`InMemoryProfileStore`] serves profiles and preferences loaded from the
+//! CSV files written by `generate-test-data`, so the API runs without any
+//! external services.
+
+use crate::core::filters::MIN_AGE;
+use crate::models::{MatchEvent, UserPreferences, UserProfile};
+use crate::services::appwrite::AppwriteError;
+use crate::services::postgres::{
+    AuditEntry, AuditFilter, EventType, MatchActivity, MatchRun, PostgresError, ProfileView, ReceivedLike, SeenProfile, SeenStats,
+    AUDIT_LOG_QUERY, COUNT_LIKES_RECEIVED_QUERY, COUNT_VIEWERS_QUERY, INSERT_AUDIT_QUERY, INSERT_LIKE_QUERY,
+    INSERT_MATCH_RUN_QUERY, LIKES_RECEIVED_QUERY, STALE_MATCHES_QUERY, VIEWERS_QUERY,
+};
+use crate::services::store::{CandidatePage, ProfileStore, SeenStore};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::{Row, SqlitePool};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::RwLock;
+use thiserror::Error;
+
+/// `seen_profiles` and `audit_log` as in the PostgreSQL migrations, with the
+/// enum, JSON and timestamps as TEXT
+const SQLITE_SCHEMA: &str = r#"
+    CREATE TABLE IF NOT EXISTS seen_profiles (
+        user_id TEXT NOT NULL,
+        target_user_id TEXT NOT NULL,
+        event_type TEXT NOT NULL DEFAULT 'viewed',
+        seen_at TEXT NOT NULL,
+        received_at TEXT,
+        expired_at TEXT,
+        PRIMARY KEY (user_id, target_user_id)
+    );
+    CREATE INDEX IF NOT EXISTS idx_seen_profiles_target_user_id ON seen_profiles(target_user_id);
+    CREATE INDEX IF NOT EXISTS idx_seen_profiles_seen_at ON seen_profiles(seen_at);
+
+    CREATE TABLE IF NOT EXISTS likes_received (
+        target_user_id TEXT NOT NULL,
+        liker_id TEXT NOT NULL,
+        liked_at TEXT NOT NULL,
+        received_at TEXT,
+        PRIMARY KEY (target_user_id, liker_id)
+    );
+
+    CREATE TABLE IF NOT EXISTS audit_log (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        actor TEXT NOT NULL,
+        action TEXT NOT NULL,
+        target TEXT,
+        details TEXT,
+        created_at TEXT NOT NULL
+    );
+    CREATE TRIGGER IF NOT EXISTS audit_log_no_update BEFORE UPDATE ON audit_log
+    BEGIN SELECT RAISE(ABORT, 'audit_log is append-only'); END;
+    CREATE TRIGGER IF NOT EXISTS audit_log_no_delete BEFORE DELETE ON audit_log
+    BEGIN SELECT RAISE(ABORT, 'audit_log is append-only'); END;
+
+    CREATE TABLE IF NOT EXISTS match_runs (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        user_id TEXT NOT NULL,
+        requested_limit INTEGER NOT NULL,
+        overfetch_multiplier INTEGER NOT NULL,
+        candidates INTEGER NOT NULL,
+        matches INTEGER NOT NULL,
+        partial INTEGER NOT NULL DEFAULT 0,
+        duration_ms INTEGER NOT NULL,
+        market TEXT,
+        city TEXT,
+        created_at TEXT NOT NULL,
+        raw_scores TEXT
+    );
+"#;
+
+/// Seen-profile store backed by a local SQLite database
+pub struct SqliteSeenStore {
+    pool: SqlitePool,
+}
+
+impl SqliteSeenStore {
+    /// Open (creating if needed) the database at `url` and create the schema
+    ///
+    /// `sqlite::memory:` gives a throwaway database. A single connection is
+    /// used: SQLite serializes writes anyway, and an in-memory database is
+    /// private to the connection that created it.
+    pub async fn connect(url: &str) -> Result<Self, PostgresError> {
+        let options = SqliteConnectOptions::from_str(url)?.create_if_missing(true);
+        let pool = SqlitePoolOptions::new().max_connections(1).connect_with(options).await?;
+
+        sqlx::raw_sql(SQLITE_SCHEMA).execute(&pool).await?;
+        // Dev databases created before match expiry lack the column; errors
+        // here just mean it already exists
+        let _ = sqlx::query("ALTER TABLE seen_profiles ADD COLUMN expired_at TEXT").execute(&pool).await;
+        let _ = sqlx::query("ALTER TABLE seen_profiles ADD COLUMN received_at TEXT").execute(&pool).await;
+        let _ = sqlx::query("ALTER TABLE match_runs ADD COLUMN raw_scores TEXT").execute(&pool).await;
+
+        Ok(Self { pool })
+    }
+
+    async fn insert_like(&self, liker_id: &str, target_user_id: &str, liked_at: DateTime<Utc>) -> Result<(), PostgresError> {
+        sqlx::query(INSERT_LIKE_QUERY)
+            .bind(target_user_id)
+            .bind(liker_id)
+            .bind(liked_at)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    fn seen_profile(row: &sqlx::sqlite::SqliteRow) -> SeenProfile {
+        SeenProfile {
+            user_id: row.get("user_id"),
+            target_user_id: row.get("target_user_id"),
+            event_type: row.get("event_type"),
+            seen_at: row.get("seen_at"),
+        }
+    }
+}
+
+#[async_trait]
+impl SeenStore for SqliteSeenStore {
+    async fn record_seen_at(
+        &self,
+        user_id: &str,
+        target_user_id: &str,
+        event_type: EventType,
+        occurred_at: DateTime<Utc>,
+    ) -> Result<(), PostgresError> {
+        sqlx::query(
+            r#"
+            INSERT INTO seen_profiles (user_id, target_user_id, event_type, seen_at, received_at)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (user_id, target_user_id)
+            DO UPDATE SET
+                event_type = excluded.event_type,
+                seen_at = excluded.seen_at,
+                received_at = excluded.received_at,
+                expired_at = NULL
+            WHERE seen_profiles.seen_at <= excluded.seen_at
+            "#,
+        )
+        .bind(user_id)
+        .bind(target_user_id)
+        .bind(event_type)
+        .bind(occurred_at)
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await?;
+
+        if event_type == EventType::Liked {
+            self.insert_like(user_id, target_user_id, occurred_at).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn insert_seen_if_absent(
+        &self,
+        user_id: &str,
+        target_user_id: &str,
+        event_type: EventType,
+        seen_at: DateTime<Utc>,
+    ) -> Result<bool, PostgresError> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO seen_profiles (user_id, target_user_id, event_type, seen_at, received_at)
+            VALUES ($1, $2, $3, $4, $4)
+            ON CONFLICT (user_id, target_user_id) DO NOTHING
+            "#,
+        )
+        .bind(user_id)
+        .bind(target_user_id)
+        .bind(event_type)
+        .bind(seen_at)
+        .execute(&self.pool)
+        .await?;
+
+        let inserted = result.rows_affected() > 0;
+        if inserted && event_type == EventType::Liked {
+            self.insert_like(user_id, target_user_id, seen_at).await?;
+        }
+
+        Ok(inserted)
+    }
+
+    async fn get_seen_since(&self, since: DateTime<Utc>, limit: usize) -> Result<Vec<SeenProfile>, PostgresError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT user_id, target_user_id, event_type, seen_at
+            FROM seen_profiles
+            WHERE seen_at >= $1
+            ORDER BY seen_at ASC
+            LIMIT $2
+            "#,
+        )
+        .bind(since)
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(Self::seen_profile).collect())
+    }
+
+    async fn get_seen_profiles(&self, user_id: &str) -> Result<Vec<String>, PostgresError> {
+        let rows = sqlx::query("SELECT target_user_id FROM seen_profiles WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.iter().map(|row| row.get("target_user_id")).collect())
+    }
+
+    async fn get_likes_received(&self, user_id: &str, limit: usize, offset: usize) -> Result<Vec<ReceivedLike>, PostgresError> {
+        let rows = sqlx::query(LIKES_RECEIVED_QUERY)
+            .bind(user_id)
+            .bind(limit as i64)
+            .bind(offset as i64)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| ReceivedLike {
+                user_id: row.get("user_id"),
+                liked_at: row.get("liked_at"),
+            })
+            .collect())
+    }
+
+    async fn count_likes_received(&self, user_id: &str) -> Result<i64, PostgresError> {
+        let row = sqlx::query(COUNT_LIKES_RECEIVED_QUERY).bind(user_id).fetch_one(&self.pool).await?;
+
+        Ok(row.get("count"))
+    }
+
+    async fn get_viewers(&self, user_id: &str, since: DateTime<Utc>, limit: usize, offset: usize) -> Result<Vec<ProfileView>, PostgresError> {
+        let rows = sqlx::query(VIEWERS_QUERY)
+            .bind(user_id)
+            .bind(since)
+            .bind(limit as i64)
+            .bind(offset as i64)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| ProfileView {
+                user_id: row.get("user_id"),
+                event_type: row.get("event_type"),
+                viewed_at: row.get("seen_at"),
+            })
+            .collect())
+    }
+
+    async fn count_viewers(&self, user_id: &str, since: DateTime<Utc>) -> Result<i64, PostgresError> {
+        let row = sqlx::query(COUNT_VIEWERS_QUERY)
+            .bind(user_id)
+            .bind(since)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(row.get("count"))
+    }
+
+    async fn count_likes_since(&self, user_id: &str, since: DateTime<Utc>) -> Result<i64, PostgresError> {
+        let row = sqlx::query(
+            r#"
+            SELECT COUNT(*) AS count
+            FROM seen_profiles
+            WHERE user_id = $1
+              AND event_type IN ('liked', 'matched')
+              AND received_at >= $2
+            "#,
+        )
+        .bind(user_id)
+        .bind(since)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(row.get("count"))
+    }
+
+    async fn prune_seen_profiles(&self, retention_days: u32) -> Result<u64, PostgresError> {
+        let cutoff = Utc::now() - chrono::Duration::days(retention_days as i64);
+        let result = sqlx::query("DELETE FROM seen_profiles WHERE event_type IN ('viewed', 'passed') AND seen_at < $1")
+            .bind(cutoff)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn expire_matches(&self, expiry_days: u32) -> Result<u64, PostgresError> {
+        let now = Utc::now();
+        let cutoff = now - chrono::Duration::days(expiry_days as i64);
+        let result = sqlx::query(
+            r#"
+            UPDATE seen_profiles
+            SET expired_at = $2
+            WHERE event_type = 'matched'
+              AND expired_at IS NULL
+              AND seen_at < $1
+              AND NOT EXISTS (
+                  SELECT 1 FROM seen_profiles r
+                  WHERE r.user_id = seen_profiles.target_user_id
+                    AND r.target_user_id = seen_profiles.user_id
+                    AND r.seen_at >= $1
+              )
+            "#,
+        )
+        .bind(cutoff)
+        .bind(now)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn get_stale_matches(&self, user_id: &str, inactive_since: DateTime<Utc>) -> Result<Vec<MatchActivity>, PostgresError> {
+        let rows = sqlx::query(STALE_MATCHES_QUERY)
+            .bind(user_id)
+            .bind(inactive_since)
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.iter()
+            .map(|row| {
+                let last_activity_at: String = row.get("last_activity_at");
+                Ok(MatchActivity {
+                    user_id: row.get("partner_id"),
+                    last_activity_at: DateTime::parse_from_rfc3339(&last_activity_at)
+                        .map_err(|e| PostgresError::InvalidInput(e.to_string()))?
+                        .with_timezone(&Utc),
+                })
+            })
+            .collect()
+    }
+
+    async fn get_seen_stats(&self, user_id: &str) -> Result<SeenStats, PostgresError> {
+        let row = sqlx::query(
+            r#"
+            SELECT
+                COUNT(*) AS total_seen,
+                COUNT(*) FILTER (WHERE event_type = 'viewed') AS viewed,
+                COUNT(*) FILTER (WHERE event_type = 'liked') AS liked,
+                COUNT(*) FILTER (WHERE event_type = 'passed') AS passed,
+                COUNT(*) FILTER (WHERE event_type = 'matched') AS matched,
+                MAX(seen_at) AS last_seen_at
+            FROM seen_profiles
+            WHERE user_id = $1
+            "#,
+        )
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(SeenStats {
+            user_id: user_id.to_string(),
+            total_seen: row.get("total_seen"),
+            viewed: row.get("viewed"),
+            liked: row.get("liked"),
+            passed: row.get("passed"),
+            matched: row.get("matched"),
+            last_seen_at: row
+                .get::<Option<String>, _>("last_seen_at")
+                .and_then(|ts| DateTime::parse_from_rfc3339(&ts).ok())
+                .map(|ts| ts.with_timezone(&Utc)),
+        })
+    }
+
+    async fn clear_seen_profiles(&self, user_id: &str) -> Result<u64, PostgresError> {
+        let result = sqlx::query("DELETE FROM seen_profiles WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn append_audit(&self, entry: &AuditEntry) -> Result<(), PostgresError> {
+        sqlx::query(INSERT_AUDIT_QUERY)
+            .bind(&entry.actor)
+            .bind(&entry.action)
+            .bind(&entry.target)
+            .bind(entry.details.as_ref().map(sqlx::types::Json))
+            .bind(entry.created_at)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_audit_log(&self, filter: &AuditFilter, limit: usize, offset: usize) -> Result<Vec<AuditEntry>, PostgresError> {
+        let rows = sqlx::query(AUDIT_LOG_QUERY)
+            .bind(&filter.actor)
+            .bind(&filter.action)
+            .bind(&filter.target)
+            .bind(limit as i64)
+            .bind(offset as i64)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| AuditEntry {
+                actor: row.get("actor"),
+                action: row.get("action"),
+                target: row.get("target"),
+                details: row.get::<Option<sqlx::types::Json<Value>>, _>("details").map(|json| json.0),
+                created_at: row.get("created_at"),
+            })
+            .collect())
+    }
+
+    async fn record_match_run(&self, run: &MatchRun) -> Result<(), PostgresError> {
+        sqlx::query(INSERT_MATCH_RUN_QUERY)
+            .bind(&run.user_id)
+            .bind(run.limit as i64)
+            .bind(run.overfetch_multiplier as i64)
+            .bind(run.candidates as i64)
+            .bind(run.matches as i64)
+            .bind(run.partial)
+            .bind(run.duration_ms as i64)
+            .bind(&run.market)
+            .bind(&run.city)
+            .bind(run.created_at)
+            .bind(run.raw_scores_json())
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn warm_up(&self, _connections: u32) -> Result<u32, PostgresError> {
+        self.health_check().await?;
+        Ok(self.pool.size())
+    }
+
+    async fn health_check(&self) -> Result<bool, PostgresError> {
+        sqlx::query("SELECT 1").fetch_one(&self.pool).await?;
+        Ok(true)
+    }
+}
+
+/// Errors loading the dev profile seed data
+#[derive(Debug, Error)]
+pub enum SeedError {
+    #[error("Failed to read {path}: {source}")]
+    Csv { path: String, source: csv::Error },
+
+    #[error("Invalid row {row} in {path}: {source}")]
+    Row { path: String, row: usize, source: serde_json::Error },
+}
+
+/// Profile store holding everything in memory
+///
+/// Candidate queries apply the same pre-filters Appwrite does (active, not
+/// timed out, gender, age, bounding box, exclusions); the matcher applies
+/// the rest. Recorded match events are kept in memory.
+#[derive(Default)]
+pub struct InMemoryProfileStore {
+    profiles: RwLock<HashMap<String, UserProfile>>,
+    preferences: RwLock<HashMap<String, UserPreferences>>,
+    events: RwLock<Vec<MatchEvent>>,
+}
+
+impl InMemoryProfileStore {
+    pub fn new(profiles: Vec<UserProfile>, preferences: Vec<UserPreferences>) -> Self {
+        Self {
+            profiles: RwLock::new(profiles.into_iter().map(|p| (p.user_id.clone(), p)).collect()),
+            preferences: RwLock::new(preferences.into_iter().map(|p| (p.user_id.clone(), p)).collect()),
+            events: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// Load the `test_profiles.csv` / `test_preferences.csv` pair written by `generate-test-data`
+    pub fn from_csv(profiles: &Path, preferences: &Path) -> Result<Self, SeedError> {
+        Ok(Self::new(read_csv(profiles)?, read_csv(preferences)?))
+    }
+
+    /// Add or replace a profile
+    pub fn insert_profile(&self, profile: UserProfile) {
+        self.profiles.write().unwrap().insert(profile.user_id.clone(), profile);
+    }
+
+    /// Add or replace a user's preferences
+    pub fn insert_preferences(&self, preferences: UserPreferences) {
+        self.preferences.write().unwrap().insert(preferences.user_id.clone(), preferences);
+    }
+
+    pub fn profile_count(&self) -> usize {
+        self.profiles.read().unwrap().len()
+    }
+}
+
+/// Read CSV rows into `T` via the same JSON field names Appwrite documents use
+///
+/// Cells that parse as JSON numbers, booleans or arrays keep that type (the
+/// generator writes list attributes as JSON arrays); everything else is a
+/// string, and empty cells are left out so field defaults apply.
+fn read_csv<T: DeserializeOwned>(path: &Path) -> Result<Vec<T>, SeedError> {
+    let csv_error = |source| SeedError::Csv { path: path.display().to_string(), source };
+
+    let mut reader = csv::Reader::from_path(path).map_err(csv_error)?;
+    let headers = reader.headers().map_err(csv_error)?.clone();
+
+    let mut rows = Vec::new();
+    for (index, record) in reader.records().enumerate() {
+        let record = record.map_err(csv_error)?;
+        let object: Map<String, Value> = headers
+            .iter()
+            .zip(record.iter())
+            .filter(|(_, cell)| !cell.is_empty())
+            .map(|(header, cell)| (header.to_string(), cell_value(cell)))
+            .collect();
+
+        rows.push(serde_json::from_value(Value::Object(object)).map_err(|source| SeedError::Row {
+            path: path.display().to_string(),
+            row: index + 1,
+            source,
+        })?);
+    }
+
+    Ok(rows)
+}
+
+fn cell_value(cell: &str) -> Value {
+    match serde_json::from_str::<Value>(cell) {
+        Ok(value @ (Value::Number(_) | Value::Bool(_) | Value::Array(_))) => value,
+        _ => Value::String(cell.to_string()),
+    }
+}
+
+/// The Appwrite candidate pre-filters, applied in-process
+pub(crate) fn is_candidate(profile: &UserProfile, user_id: &str, preferences: &UserPreferences, exclude_ids: &HashSet<String>) -> bool {
+    if !profile.is_active || profile.timeout() || profile.user_id == user_id || exclude_ids.contains(&profile.user_id) {
+        return false;
+    }
+
+    if !preferences.preferred_genders.is_empty() && !preferences.preferred_genders.contains(&profile.gender) {
+        return false;
+    }
+
+    if profile.age < preferences.min_age.max(MIN_AGE) || profile.age > preferences.max_age {
+        return false;
+    }
+
+    if preferences.has_location() {
+        let bbox = crate::core::distance::calculate_bounding_box(
+            preferences.latitude,
+            preferences.longitude,
+            preferences.max_distance_km as f64,
+        );
+        if profile.latitude < bbox.min_lat
+            || profile.latitude > bbox.max_lat
+            || profile.longitude < bbox.min_lon
+            || profile.longitude > bbox.max_lon
+        {
+            return false;
+        }
+    }
+
+    true
+}
+
+#[async_trait]
+impl ProfileStore for InMemoryProfileStore {
+    async fn get_preferences(&self, user_id: &str) -> Result<UserPreferences, AppwriteError> {
+        self.preferences
+            .read()
+            .unwrap()
+            .get(user_id)
+            .cloned()
+            .ok_or_else(|| AppwriteError::NotFound(format!("Preferences not found for user {}", user_id)))
+    }
+
+    async fn query_candidates(
+        &self,
+        user_id: &str,
+        preferences: &UserPreferences,
+        exclude_ids: &HashSet<String>,
+        limit: usize,
+    ) -> Result<CandidatePage, AppwriteError> {
+        let profiles = self.profiles.read().unwrap();
+        Ok(profiles
+            .values()
+            .filter(|profile| is_candidate(profile, user_id, preferences, exclude_ids))
+            .take(limit)
+            .cloned()
+            .collect::<Vec<_>>()
+            .into())
+    }
+
+    async fn get_profile(&self, user_id: &str) -> Result<UserProfile, AppwriteError> {
+        self.profiles
+            .read()
+            .unwrap()
+            .get(user_id)
+            .cloned()
+            .ok_or_else(|| AppwriteError::NotFound(format!("Profile not found for user {}", user_id)))
+    }
+
+    async fn get_profiles(&self, user_ids: &[String]) -> Result<Vec<UserProfile>, AppwriteError> {
+        let profiles = self.profiles.read().unwrap();
+        Ok(user_ids.iter().filter_map(|id| profiles.get(id).cloned()).collect())
+    }
+
+    async fn list_events_since(&self, since: DateTime<Utc>, max_events: usize) -> Result<Vec<MatchEvent>, AppwriteError> {
+        let mut events: Vec<MatchEvent> = self
+            .events
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|event| event.created_at >= since)
+            .cloned()
+            .collect();
+        events.sort_by_key(|event| event.created_at);
+        events.truncate(max_events);
+        Ok(events)
+    }
+
+    async fn record_event(&self, event: MatchEvent) -> Result<(), AppwriteError> {
+        self.events.write().unwrap().push(event);
+        Ok(())
+    }
+
+    async fn check_auth(&self) -> Result<(), AppwriteError> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::MatchEventType;
+    use crate::testing::{preferences, profile};
+
+    #[test]
+    fn test_cell_value() {
+        assert_eq!(cell_value("42"), Value::from(42));
+        assert_eq!(cell_value("true"), Value::Bool(true));
+        assert_eq!(cell_value(r#"["tennis","yoga"]"#), serde_json::json!(["tennis", "yoga"]));
+        assert_eq!(cell_value("Alex 3"), Value::String("Alex 3".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_candidates() {
+        let store = InMemoryProfileStore::new(
+            vec![profile("me", "male", 30), profile("a", "female", 29), profile("b", "male", 29), profile("c", "female", 60)],
+            vec![],
+        );
+        let preferences = preferences("me", &["female"], 25, 35);
+
+        let candidates = store.query_candidates("me", &preferences, &HashSet::new(), 10).await.unwrap();
+        assert_eq!(candidates.profiles.len(), 1);
+        assert_eq!(candidates.profiles[0].user_id, "a");
+
+        let excluded = HashSet::from(["a".to_string()]);
+        assert!(store.query_candidates("me", &preferences, &excluded, 10).await.unwrap().profiles.is_empty());
+        assert!(matches!(store.get_preferences("me").await, Err(AppwriteError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_seen_store() {
+        let store = SqliteSeenStore::connect("sqlite::memory:").await.unwrap();
+
+        store.record_seen("a", "b", EventType::Viewed).await.unwrap();
+        store.record_seen("a", "b", EventType::Liked).await.unwrap();
+        store.record_seen("a", "c", EventType::Passed).await.unwrap();
+        store.record_seen("d", "a", EventType::Liked).await.unwrap();
+
+        let mut seen = store.get_seen_profiles("a").await.unwrap();
+        seen.sort();
+        assert_eq!(seen, vec!["b", "c"]);
+
+        let stats = store.get_seen_stats("a").await.unwrap();
+        assert_eq!((stats.total_seen, stats.liked, stats.passed), (2, 1, 1));
+        assert!(stats.last_seen_at.is_some());
+
+        assert_eq!(store.count_likes_received("a").await.unwrap(), 1);
+        store.record_seen("a", "d", EventType::from(MatchEventType::Matched)).await.unwrap();
+        assert_eq!(store.count_likes_received("a").await.unwrap(), 0);
+
+        let inserted = store
+            .insert_seen_if_absent("a", "b", EventType::Passed, Utc::now())
+            .await
+            .unwrap();
+        assert!(!inserted);
+        assert_eq!(store.get_seen_since(Utc::now() - chrono::Duration::minutes(1), 10).await.unwrap().len(), 4);
+
+        // a liked b and matched d just now; the yesterday like doesn't count
+        store
+            .insert_seen_if_absent("a", "e", EventType::Liked, Utc::now() - chrono::Duration::days(1))
+            .await
+            .unwrap();
+        let today = Utc::now() - chrono::Duration::hours(1);
+        assert_eq!(store.count_likes_since("a", today).await.unwrap(), 2);
+
+        // d liked a; a liked b but passed c, which stays hidden from c
+        let viewers = store.get_viewers("a", today, 10, 0).await.unwrap();
+        assert_eq!(viewers.len(), 1);
+        assert_eq!((viewers[0].user_id.as_str(), viewers[0].event_type), ("d", EventType::Liked));
+        assert_eq!(store.count_viewers("b", today).await.unwrap(), 1);
+        assert_eq!(store.count_viewers("c", today).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_late_events_keep_newer() {
+        let store = SqliteSeenStore::connect("sqlite::memory:").await.unwrap();
+        let hour_ago = Utc::now() - chrono::Duration::hours(1);
+
+        store.record_seen("a", "b", EventType::Passed).await.unwrap();
+        // A like queued offline an hour ago arrives after the pass
+        store.record_seen_at("a", "b", EventType::Liked, hour_ago).await.unwrap();
+        let seen = store.get_seen_since(hour_ago - chrono::Duration::minutes(1), 10).await.unwrap();
+        assert_eq!(seen[0].event_type, EventType::Passed);
+        // The pass doesn't withdraw the like from b's queue
+        assert_eq!(store.count_likes_received("b").await.unwrap(), 1);
+
+        store.record_seen_at("a", "c", EventType::Liked, hour_ago).await.unwrap();
+        let likes = store.get_likes_received("c", 10, 0).await.unwrap();
+        assert_eq!(likes[0].liked_at, hour_ago);
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_match_expiry() {
+        let store = SqliteSeenStore::connect("sqlite::memory:").await.unwrap();
+        let long_ago = Utc::now() - chrono::Duration::days(40);
+
+        // a/b matched 40 days ago; a/c matched 40 days ago but c wrote since
+        store.insert_seen_if_absent("a", "b", EventType::Matched, long_ago).await.unwrap();
+        store.insert_seen_if_absent("b", "a", EventType::Liked, long_ago).await.unwrap();
+        store.insert_seen_if_absent("a", "c", EventType::Matched, long_ago).await.unwrap();
+        store.record_seen("c", "a", EventType::Liked).await.unwrap();
+
+        let stale = store.get_stale_matches("a", Utc::now() - chrono::Duration::days(7)).await.unwrap();
+        assert_eq!(stale.len(), 1);
+        assert_eq!(stale[0].user_id, "b");
+        assert_eq!(store.get_stale_matches("b", Utc::now()).await.unwrap().len(), 1);
+
+        assert_eq!(store.expire_matches(30).await.unwrap(), 1);
+        assert!(store.get_stale_matches("a", Utc::now()).await.unwrap().iter().all(|m| m.user_id == "c"));
+
+        // A new event on the row reactivates the match
+        store.record_seen("a", "b", EventType::Matched).await.unwrap();
+        assert_eq!(store.get_stale_matches("a", Utc::now() + chrono::Duration::minutes(1)).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_audit_log() {
+        let store = SqliteSeenStore::connect("sqlite::memory:").await.unwrap();
+        store.record_seen("a", "b", EventType::Passed).await.unwrap();
+        store.record_seen("a", "c", EventType::Liked).await.unwrap();
+        store.record_seen("b", "a", EventType::Liked).await.unwrap();
+
+        assert_eq!(store.clear_seen_profiles("a").await.unwrap(), 2);
+        assert!(store.get_seen_profiles("a").await.unwrap().is_empty());
+        assert_eq!(store.get_seen_profiles("b").await.unwrap(), vec!["a"]);
+
+        let clear = AuditEntry::new("ops", "seen.clear")
+            .with_target("a")
+            .with_details(serde_json::json!({ "deleted": 2 }));
+        store.append_audit(&clear).await.unwrap();
+        store.append_audit(&AuditEntry::new("admin", "matches.expire")).await.unwrap();
+
+        let entries = store.get_audit_log(&AuditFilter::default(), 10, 0).await.unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].action, "matches.expire");
+        assert_eq!(entries[1].details, Some(serde_json::json!({ "deleted": 2 })));
+
+        let filter = AuditFilter { target: Some("a".to_string()), ..Default::default() };
+        let entries = store.get_audit_log(&filter, 10, 0).await.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!((entries[0].actor.as_str(), entries[0].target.as_deref()), ("ops", Some("a")));
+
+        // Append-only
+        assert!(sqlx::query("DELETE FROM audit_log").execute(&store.pool).await.is_err());
+        assert!(sqlx::query("UPDATE audit_log SET actor = 'x'").execute(&store.pool).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_match_runs() {
+        let store = SqliteSeenStore::connect("sqlite::memory:").await.unwrap();
+        let run = MatchRun {
+            user_id: "a".to_string(),
+            limit: 20,
+            overfetch_multiplier: 10,
+            candidates: 64,
+            matches: 12,
+            partial: true,
+            duration_ms: 85,
+            market: Some("de".to_string()),
+            city: Some("de-ber".to_string()),
+            raw_scores: vec![("b".to_string(), 61.5)],
+            created_at: Utc::now(),
+        };
+        store.record_match_run(&run).await.unwrap();
+
+        let row = sqlx::query("SELECT requested_limit, overfetch_multiplier, matches, partial, city, raw_scores FROM match_runs WHERE user_id = 'a'")
+            .fetch_one(&store.pool)
+            .await
+            .unwrap();
+        assert_eq!(row.get::<i64, _>("requested_limit"), 20);
+        assert_eq!(row.get::<i64, _>("overfetch_multiplier"), 10);
+        assert_eq!(row.get::<i64, _>("matches"), 12);
+        assert!(row.get::<bool, _>("partial"));
+        assert_eq!(row.get::<Option<String>, _>("city").as_deref(), Some("de-ber"));
+        assert_eq!(row.get::<Option<String>, _>("raw_scores").as_deref(), Some(r#"{"b":61.5}"#));
+    }
+}
@@ -0,0 +1,315 @@
+use crate::config::{EventBackend, EventSettings};
+use crate::models::{MatchEvent, MatchEventType};
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use thiserror::Error;
+
+/// Version of the published JSON envelope; bump on breaking changes
+pub const EVENT_SCHEMA_VERSION: u32 = 1;
+
+/// Errors that can occur when connecting to or publishing on the event stream
+#[derive(Debug, Error)]
+pub enum PublishError {
+    #[error("Event backend '{0:?}' is not compiled in (enable the cargo feature)")]
+    BackendUnavailable(EventBackend),
+
+    #[error("Failed to connect to event stream: {0}")]
+    Connect(String),
+
+    #[error("Failed to publish event: {0}")]
+    Publish(String),
+
+    #[error("Failed to serialize event: {0}")]
+    Serialize(#[from] serde_json::Error),
+}
+
+/// Envelope for every published message
+///
+/// Consumers should check `schemaVersion` and dispatch on `type`.
+/// `tenant` is the `X-Tenant-Id` the event was recorded for, absent for
+/// the default tenant.
+#[derive(Debug, Clone, Serialize)]
+pub struct EventEnvelope<T: Serialize> {
+    #[serde(rename = "schemaVersion")]
+    pub schema_version: u32,
+    #[serde(rename = "type")]
+    pub event_type: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    pub id: String,
+    #[serde(rename = "emittedAt")]
+    pub emitted_at: chrono::DateTime<chrono::Utc>,
+    pub data: T,
+}
+
+impl<T: Serialize> EventEnvelope<T> {
+    fn new(event_type: &'static str, tenant: Option<String>, data: T) -> Self {
+        Self {
+            schema_version: EVENT_SCHEMA_VERSION,
+            event_type,
+            tenant,
+            id: uuid::Uuid::new_v4().to_string(),
+            emitted_at: chrono::Utc::now(),
+            data,
+        }
+    }
+}
+
+/// A swipe (`type: "match_event"`)
+#[derive(Debug, Clone, Serialize)]
+pub struct SwipePayload {
+    #[serde(rename = "userId")]
+    pub user_id: String,
+    #[serde(rename = "targetUserId")]
+    pub target_user_id: String,
+    #[serde(rename = "eventType")]
+    pub event_type: MatchEventType,
+    #[serde(rename = "createdAt")]
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Two users matched (`type: "mutual_match"`)
+#[derive(Debug, Clone, Serialize)]
+pub struct MutualMatchPayload {
+    /// The two user IDs, sorted so each pair has one representation
+    #[serde(rename = "userIds")]
+    pub user_ids: [String; 2],
+    #[serde(rename = "matchedAt")]
+    pub matched_at: chrono::DateTime<chrono::Utc>,
+}
+
+enum Backend {
+    Disabled,
+    #[cfg(feature = "nats")]
+    Nats(async_nats::Client),
+    #[cfg(feature = "kafka")]
+    Kafka(Vec<rskafka::client::partition::PartitionClient>),
+}
+
+/// Publishes match events and mutual matches to Kafka or NATS
+///
+/// Publishing is best-effort and happens off the request path: failures
+/// are logged and counted, never surfaced to clients. Each tenant has its
+/// own publisher ([`for_tenant`](Self::for_tenant)) that tags its events
+/// and counts them separately, over the shared connection.
+pub struct EventPublisher {
+    backend: Arc<Backend>,
+    topic: String,
+    tenant: Option<String>,
+    published: AtomicU64,
+    failed: AtomicU64,
+}
+
+/// Publisher counters for /metrics
+#[derive(Debug, Clone, Serialize)]
+pub struct PublisherStats {
+    pub enabled: bool,
+    pub published: u64,
+    pub failed: u64,
+}
+
+impl EventPublisher {
+    /// A publisher that drops everything
+    pub fn disabled() -> Self {
+        Self {
+            backend: Arc::new(Backend::Disabled),
+            topic: String::new(),
+            tenant: None,
+            published: AtomicU64::new(0),
+            failed: AtomicU64::new(0),
+        }
+    }
+
+    /// Connect to the configured backend (disabled publisher when turned off)
+    pub async fn connect(settings: &EventSettings) -> Result<Self, PublishError> {
+        if !settings.enabled {
+            return Ok(Self::disabled());
+        }
+
+        let backend = match settings.backend {
+            #[cfg(feature = "nats")]
+            EventBackend::Nats => async_nats::connect(settings.url.as_str())
+                .await
+                .map(Backend::Nats)
+                .map_err(|e| PublishError::Connect(e.to_string())),
+            #[cfg(feature = "kafka")]
+            EventBackend::Kafka => {
+                use rskafka::client::{partition::UnknownTopicHandling, ClientBuilder};
+
+                let brokers = settings.url.split(',').map(|b| b.trim().to_string()).collect();
+                let client = ClientBuilder::new(brokers)
+                    .build()
+                    .await
+                    .map_err(|e| PublishError::Connect(e.to_string()))?;
+
+                let mut partitions = Vec::with_capacity(settings.kafka_partitions.max(1) as usize);
+                for partition in 0..settings.kafka_partitions.max(1) {
+                    partitions.push(
+                        client
+                            .partition_client(settings.topic.clone(), partition, UnknownTopicHandling::Retry)
+                            .await
+                            .map_err(|e| PublishError::Connect(e.to_string()))?,
+                    );
+                }
+                Ok(Backend::Kafka(partitions))
+            }
+            #[allow(unreachable_patterns)]
+            backend => Err(PublishError::BackendUnavailable(backend)),
+        }?;
+
+        tracing::info!("Publishing match events to {:?} topic {}", settings.backend, settings.topic);
+
+        Ok(Self {
+            backend: Arc::new(backend),
+            topic: settings.topic.clone(),
+            tenant: None,
+            published: AtomicU64::new(0),
+            failed: AtomicU64::new(0),
+        })
+    }
+
+    /// Publisher for a named tenant, sharing this one's connection
+    pub fn for_tenant(&self, tenant_id: &str) -> Self {
+        Self {
+            backend: self.backend.clone(),
+            topic: self.topic.clone(),
+            tenant: Some(tenant_id.to_string()),
+            published: AtomicU64::new(0),
+            failed: AtomicU64::new(0),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        !matches!(*self.backend, Backend::Disabled)
+    }
+
+    /// Publish a swipe, plus a mutual match for `matched` events, in the background
+    pub fn publish_match_event(self: &Arc<Self>, event: &MatchEvent) {
+        if !self.enabled() {
+            return;
+        }
+
+        let swipe = EventEnvelope::new(
+            "match_event",
+            self.tenant.clone(),
+            SwipePayload {
+                user_id: event.user_id.clone(),
+                target_user_id: event.target_user_id.clone(),
+                event_type: event.event_type,
+                created_at: event.created_at,
+            },
+        );
+
+        let mutual = (event.event_type == MatchEventType::Matched).then(|| {
+            let mut user_ids = [event.user_id.clone(), event.target_user_id.clone()];
+            user_ids.sort();
+            EventEnvelope::new("mutual_match", self.tenant.clone(), MutualMatchPayload { user_ids, matched_at: event.created_at })
+        });
+
+        let publisher = self.clone();
+        tokio::spawn(async move {
+            publisher.send_logged(&swipe.data.user_id, &swipe).await;
+            if let Some(mutual) = mutual {
+                publisher.send_logged(&mutual.data.user_ids[0], &mutual).await;
+            }
+        });
+    }
+
+    async fn send_logged<T: Serialize>(&self, key: &str, envelope: &EventEnvelope<T>) {
+        match self.send(key, envelope).await {
+            Ok(()) => {
+                self.published.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(e) => {
+                self.failed.fetch_add(1, Ordering::Relaxed);
+                tracing::warn!("Failed to publish {} {}: {}", envelope.event_type, envelope.id, e);
+            }
+        }
+    }
+
+    /// Publish one envelope, keyed (Kafka partitioning) by `key`
+    pub async fn send<T: Serialize>(&self, key: &str, envelope: &EventEnvelope<T>) -> Result<(), PublishError> {
+        let payload = serde_json::to_vec(envelope)?;
+
+        match self.backend.as_ref() {
+            Backend::Disabled => {
+                let _ = (key, payload, &self.topic);
+                Ok(())
+            }
+            #[cfg(feature = "nats")]
+            Backend::Nats(client) => client
+                .publish(self.topic.clone(), payload.into())
+                .await
+                .map_err(|e| PublishError::Publish(e.to_string())),
+            #[cfg(feature = "kafka")]
+            Backend::Kafka(partitions) => {
+                use rskafka::client::partition::Compression;
+                use rskafka::record::Record;
+                use std::hash::{Hash, Hasher};
+
+                let mut hasher = std::collections::hash_map::DefaultHasher::new();
+                key.hash(&mut hasher);
+                let partition = &partitions[(hasher.finish() % partitions.len() as u64) as usize];
+
+                let record = Record {
+                    key: Some(key.as_bytes().to_vec()),
+                    value: Some(payload),
+                    headers: Default::default(),
+                    timestamp: envelope.emitted_at,
+                };
+                partition
+                    .produce(vec![record], Compression::NoCompression)
+                    .await
+                    .map(|_| ())
+                    .map_err(|e| PublishError::Publish(e.to_string()))
+            }
+        }
+    }
+
+    pub fn stats(&self) -> PublisherStats {
+        PublisherStats {
+            enabled: self.enabled(),
+            published: self.published.load(Ordering::Relaxed),
+            failed: self.failed.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_envelope_schema() {
+        let envelope = EventEnvelope::new(
+            "mutual_match",
+            None,
+            MutualMatchPayload {
+                user_ids: ["a".to_string(), "b".to_string()],
+                matched_at: chrono::Utc::now(),
+            },
+        );
+
+        let json = serde_json::to_value(&envelope).unwrap();
+        assert_eq!(json["schemaVersion"], EVENT_SCHEMA_VERSION);
+        assert_eq!(json["type"], "mutual_match");
+        assert_eq!(json["data"]["userIds"][1], "b");
+        assert!(json.get("tenant").is_none());
+
+        let envelope = EventEnvelope::new("match_event", Some("acme".to_string()), json!({}));
+        assert_eq!(serde_json::to_value(&envelope).unwrap()["tenant"], "acme");
+    }
+
+    #[tokio::test]
+    async fn test_disabled_publisher() {
+        let publisher = EventPublisher::connect(&EventSettings::default()).await.unwrap();
+        assert!(!publisher.enabled());
+        assert_eq!(publisher.stats().published, 0);
+
+        let tenant = publisher.for_tenant("acme");
+        assert_eq!(tenant.tenant.as_deref(), Some("acme"));
+        assert!(!tenant.enabled());
+    }
+}
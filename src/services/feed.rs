@@ -0,0 +1,1720 @@
+use crate::config::{FeedSettings, LatencyBudgetSettings, MarketSettings, MatchingSettings, OverfetchSettings, ReadThroughSettings, TierSettings};
+use crate::core::{MatchOptions, MatchResult, MatchRules, Matcher, ReverseGeocoder};
+use crate::models::{FeedDiagnostics, GeoPoint, ScoredMatch, TopPick, UserPreferences, UserProfile, WeightsOverride};
+use crate::services::cache;
+use crate::services::{AppwriteError, CacheError, CacheKey, Cache, CandidatePage, MatchRun, ProfileStore, SeenStore, ShadowEvaluator, ShadowStats, SharedError, Singleflight, VectorIndex};
+use redis::aio::ConnectionManager;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::future::Future;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tokio::task::JoinHandle;
+
+/// Run `lookup`, unless one for `key` found nothing within `ttl_secs`
+/// (0 = always run it)
+///
+/// A NotFound is remembered under [`CacheKey::missing`], so repeated
+/// requests for a deleted user fail fast without asking Appwrite; the
+/// lookup runs again once the sentinel expires.
+async fn unless_missing<T>(
+    cache: &dyn Cache,
+    key: &str,
+    ttl_secs: u64,
+    not_found: impl FnOnce() -> String,
+    lookup: impl Future<Output = Result<T, AppwriteError>>,
+) -> Result<T, AppwriteError> {
+    if ttl_secs == 0 {
+        return lookup.await;
+    }
+    if cache::is_missing(cache, key).await {
+        return Err(AppwriteError::NotFound(not_found()));
+    }
+
+    let result = lookup.await;
+    if matches!(result, Err(AppwriteError::NotFound(_))) {
+        cache::remember_missing(cache, key, ttl_secs).await;
+    }
+    result
+}
+
+/// Cache keys of a document read through [`FeedGenerator::read_versioned`]
+struct VersionedKeys<T> {
+    name: &'static str,
+    /// Read-through entry holding the version last fetched
+    current: fn(&str) -> String,
+    versioned: fn(&str, Option<chrono::DateTime<chrono::Utc>>) -> String,
+    version: fn(&T) -> Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl<T> Clone for VersionedKeys<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for VersionedKeys<T> {}
+
+const PROFILE_KEYS: VersionedKeys<UserProfile> = VersionedKeys {
+    name: "Profile",
+    current: CacheKey::current_profile,
+    versioned: CacheKey::profile,
+    version: |profile| profile.updated_at,
+};
+
+const PREFERENCES_KEYS: VersionedKeys<UserPreferences> = VersionedKeys {
+    name: "Preferences",
+    current: CacheKey::current_preferences,
+    versioned: CacheKey::preferences,
+    version: |preferences| preferences.updated_at,
+};
+
+/// Store a document under its versioned key, logging failures
+async fn store_document<T: Serialize>(cache: &dyn Cache, key: &str, document: &T, ttl_secs: u64) {
+    if let Err(e) = cache.set_with_ttl(key, document, ttl_secs).await {
+        tracing::warn!("Failed to cache {}: {}", key, e);
+    }
+}
+
+/// Errors that can occur while building a match feed
+#[derive(Debug, Error)]
+pub enum FeedError {
+    #[error("Failed to fetch user profile: {0}")]
+    Profile(#[source] AppwriteError),
+
+    #[error("Failed to fetch preferences: {0}")]
+    Preferences(#[source] AppwriteError),
+
+    #[error("Failed to query candidates: {0}")]
+    Candidates(#[source] AppwriteError),
+
+    #[error("Profile is snoozed")]
+    Snoozed,
+
+    #[error("Passport mode is not available on this tier")]
+    PassportRequiresPremium,
+
+    #[error("Latency budget exhausted while fetching the {0}")]
+    TimedOut(&'static str),
+
+    #[error("Matching is not available in geofence {0}")]
+    Geofenced(String),
+
+    #[error("Too many matching pipelines in flight")]
+    Overloaded { retry_after_secs: u64 },
+}
+
+impl FeedError {
+    /// Copy of an error shared between coalesced requests
+    fn duplicate(&self) -> Self {
+        match self {
+            FeedError::Profile(e) => FeedError::Profile(e.duplicate()),
+            FeedError::Preferences(e) => FeedError::Preferences(e.duplicate()),
+            FeedError::Candidates(e) => FeedError::Candidates(e.duplicate()),
+            FeedError::Snoozed => FeedError::Snoozed,
+            FeedError::PassportRequiresPremium => FeedError::PassportRequiresPremium,
+            FeedError::Geofenced(name) => FeedError::Geofenced(name.clone()),
+            FeedError::TimedOut(stage) => FeedError::TimedOut(stage),
+            FeedError::Overloaded { retry_after_secs } => FeedError::Overloaded { retry_after_secs: *retry_after_secs },
+        }
+    }
+}
+
+/// Per-request feed options (everything beyond the user's stored preferences)
+#[derive(Debug, Clone, Default)]
+pub struct FeedOptions {
+    pub limit: usize,
+    pub exclude_user_ids: Vec<String>,
+    pub override_location: Option<GeoPoint>,
+    pub weights: Option<WeightsOverride>,
+    /// Minimum score for this request; unset keeps the matcher's
+    pub min_score: Option<f64>,
+    /// Relax the candidate query until at least this many matches are found
+    pub min_results: Option<usize>,
+}
+
+impl FeedOptions {
+    /// Plain requests can be served from a pre-generated feed
+    pub fn is_default(&self) -> bool {
+        self.exclude_user_ids.is_empty()
+            && self.override_location.is_none()
+            && self.weights.is_none()
+            && self.min_score.is_none()
+            && self.min_results.is_none()
+    }
+}
+
+/// A computed feed and how it was produced
+#[derive(Debug, Clone)]
+pub struct Feed {
+    pub result: MatchResult,
+    /// The user has no preferences yet, so defaults were synthesized
+    pub default_preferences: bool,
+    /// Stages skipped to stay within the latency budget (empty when complete)
+    pub warnings: Vec<&'static str>,
+    /// Per-stage candidate counts (all zero for pre-generated feeds)
+    pub diagnostics: FeedDiagnostics,
+    /// Market and nearest known city the feed was searched from
+    pub market: Option<String>,
+    pub city: Option<String>,
+    /// Relaxations applied to reach `min_results`, in order
+    pub relaxations: Vec<String>,
+    /// Versions of the user's documents the feed was computed from
+    pub source: SourceVersions,
+}
+
+/// `$updatedAt` of the user's profile and preferences documents (None when
+/// unknown, or for synthesized default preferences)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SourceVersions {
+    pub profile: Option<chrono::DateTime<chrono::Utc>>,
+    pub preferences: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl SourceVersions {
+    /// Key a feed computed from these versions is stored under
+    pub fn matches_key(&self, user_id: &str) -> String {
+        CacheKey::matches(user_id, self.profile, self.preferences)
+    }
+}
+
+impl Feed {
+    /// Some stage was skipped, so the feed may be incomplete
+    pub fn partial(&self) -> bool {
+        !self.warnings.is_empty()
+    }
+}
+
+/// Deadline of one pipeline run
+struct Budget {
+    deadline: Option<Instant>,
+}
+
+impl Budget {
+    fn start(settings: &LatencyBudgetSettings) -> Self {
+        Self {
+            deadline: (settings.total_ms > 0).then(|| Instant::now() + Duration::from_millis(settings.total_ms)),
+        }
+    }
+
+    /// Run a stage within its own limit (0 = none) and what's left of the
+    /// total; Err when time ran out
+    async fn run<T>(&self, limit_ms: u64, stage: impl Future<Output = T>) -> Result<T, tokio::time::error::Elapsed> {
+        let remaining = self.deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+        let limit = (limit_ms > 0).then(|| Duration::from_millis(limit_ms));
+
+        match remaining.into_iter().chain(limit).min() {
+            Some(timeout) => tokio::time::timeout(timeout, stage).await,
+            None => Ok(stage.await),
+        }
+    }
+}
+
+/// Double the multiplier after a short run, halve it back towards the base
+/// after a full one
+fn next_overfetch_multiplier(settings: &OverfetchSettings, run: &MatchRun) -> usize {
+    let base = settings.multiplier.max(1);
+    let max = settings.max_multiplier.max(base);
+
+    if run.short() {
+        (run.overfetch_multiplier * 2).clamp(base, max)
+    } else {
+        (run.overfetch_multiplier / 2).clamp(base, max)
+    }
+}
+
+/// A rendered feed stored under `CacheKey::matches`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredFeed {
+    pub matches: Vec<ScoredMatch>,
+    pub total_candidates: usize,
+    pub global_mode: bool,
+    #[serde(default)]
+    pub default_preferences: bool,
+    /// Fewer matches than requested were found, so the list is every match there is
+    pub exhaustive: bool,
+    #[serde(default)]
+    pub market: Option<String>,
+    #[serde(default)]
+    pub city: Option<String>,
+    pub generated_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Runs the full find-matches pipeline: seen profiles, profile and
+/// preferences lookup, candidate query and scoring
+///
+/// Shared by the find endpoint and the feed pre-generation worker.
+pub struct FeedGenerator {
+    appwrite: Arc<dyn ProfileStore>,
+    cache: Arc<dyn Cache>,
+    postgres: Arc<dyn SeenStore>,
+    matcher: Matcher,
+    matching: MatchingSettings,
+    tiers: TierSettings,
+    shadow: Option<Arc<ShadowEvaluator>>,
+    vector: Option<(Arc<dyn VectorIndex>, usize)>,
+    rules: MatchRules,
+    markets: MarketSettings,
+    geocoder: Option<ReverseGeocoder>,
+    /// Cache Appwrite lookups; None reads through to Appwrite every time
+    read_through: Option<ReadThroughSettings>,
+    /// Identical find requests in flight, keyed by user and options
+    in_flight: Singleflight<String, Result<Arc<Feed>, Arc<FeedError>>>,
+}
+
+impl FeedGenerator {
+    pub fn new(
+        appwrite: Arc<dyn ProfileStore>,
+        cache: Arc<dyn Cache>,
+        postgres: Arc<dyn SeenStore>,
+        matcher: Matcher,
+        matching: MatchingSettings,
+    ) -> Self {
+        Self {
+            appwrite,
+            cache,
+            postgres,
+            matcher,
+            matching,
+            tiers: TierSettings::default(),
+            shadow: None,
+            vector: None,
+            rules: MatchRules::default(),
+            markets: MarketSettings::default(),
+            geocoder: None,
+            read_through: None,
+            in_flight: Singleflight::new(),
+        }
+    }
+
+    /// Gate passport mode and score breakdowns by the user's tier
+    pub fn with_tiers(mut self, tiers: TierSettings) -> Self {
+        self.tiers = tiers;
+        self
+    }
+
+    /// Also rank sampled feeds with a shadow configuration
+    pub fn with_shadow(mut self, shadow: Option<ShadowEvaluator>) -> Self {
+        self.shadow = shadow.map(Arc::new);
+        self
+    }
+
+    /// Merge the `top_n` nearest profiles by embedding into the geo candidates
+    pub fn with_vector_index(mut self, index: Option<Arc<dyn VectorIndex>>, top_n: usize) -> Self {
+        self.vector = index.map(|index| (index, top_n));
+        self
+    }
+
+    /// Drop candidates blocked by the deployment's hard-block rules
+    pub fn with_rules(mut self, rules: MatchRules) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    /// Tag requests and candidates with their market and city, and apply
+    /// per-market overrides
+    pub fn with_markets(mut self, markets: MarketSettings) -> Self {
+        self.geocoder = markets.geocoding.then(|| ReverseGeocoder::new(markets.city_radius_km));
+        self.markets = markets;
+        self
+    }
+
+    /// Cache profile, preferences and candidate lookups for the configured TTLs
+    pub fn with_read_through(mut self, settings: ReadThroughSettings) -> Self {
+        self.read_through = settings.enabled.then_some(settings);
+        self
+    }
+
+    /// Shadow evaluation metrics, when shadow scoring is enabled
+    pub fn shadow_stats(&self) -> Option<ShadowStats> {
+        self.shadow.as_ref().map(|shadow| shadow.stats())
+    }
+
+    /// Find requests answered by another identical request's pipeline
+    pub fn coalesced_requests(&self) -> u64 {
+        self.in_flight.coalesced()
+    }
+
+    /// [`generate`](Self::generate), sharing one run between identical
+    /// concurrent requests (e.g. a double-tapped refresh)
+    pub async fn generate_coalesced(&self, user_id: &str, options: &FeedOptions) -> Result<Feed, FeedError> {
+        self.generate_coalesced_with(user_id, options, async { Ok(()) }).await
+    }
+
+    /// [`generate_coalesced`](Self::generate_coalesced), admitted by `admit`
+    ///
+    /// Only the request that runs the pipeline awaits `admit`, and holds
+    /// what it returns (e.g. a limiter permit) until the feed is generated.
+    /// Requests sharing its run wait without one, and share its error when
+    /// it isn't admitted.
+    pub async fn generate_coalesced_with<P>(
+        &self,
+        user_id: &str,
+        options: &FeedOptions,
+        admit: impl Future<Output = Result<P, FeedError>>,
+    ) -> Result<Feed, FeedError> {
+        let key = format!(
+            "{}:{}",
+            user_id,
+            serde_json::json!([
+                options.limit,
+                options.exclude_user_ids,
+                options.override_location,
+                options.weights,
+                options.min_score,
+                options.min_results,
+            ])
+        );
+
+        let shared = self
+            .in_flight
+            .run(key, || async {
+                let _admitted = admit.await.map_err(Arc::new)?;
+                self.generate(user_id, options).await.map(Arc::new).map_err(Arc::new)
+            })
+            .await;
+
+        match shared {
+            Ok(feed) => Ok(Arc::try_unwrap(feed).unwrap_or_else(|feed| (*feed).clone())),
+            Err(e) => Err(Arc::try_unwrap(e).unwrap_or_else(|e| e.duplicate())),
+        }
+    }
+
+    /// Already seen profiles (L1 seen-set cache, then PostgreSQL)
+    ///
+    /// A PostgreSQL failure yields an empty set so matching can proceed.
+    pub async fn seen_set(&self, user_id: &str) -> Arc<HashSet<String>> {
+        if let Some(set) = self.cache.get_seen_set(user_id).await {
+            return set;
+        }
+
+        match self.postgres.get_seen_profiles(user_id).await {
+            Ok(ids) => {
+                let set = Arc::new(ids.into_iter().collect::<HashSet<String>>());
+                self.cache.set_seen_set(user_id, set.clone()).await;
+                set
+            }
+            Err(e) => {
+                tracing::warn!("Failed to fetch seen profiles for {}, proceeding without filtering: {}", user_id, e);
+                Arc::new(HashSet::new())
+            }
+        }
+    }
+
+    /// Compute a fresh feed for the user
+    ///
+    /// Runs within `matching.latency_budget`: stages that can be skipped are
+    /// dropped when out of time, with a warning on the feed.
+    pub async fn generate(&self, user_id: &str, options: &FeedOptions) -> Result<Feed, FeedError> {
+        let started = Instant::now();
+        let limits = &self.matching.latency_budget;
+        let budget = Budget::start(limits);
+        let mut warnings = Vec::new();
+
+        let seen_set = match budget.run(limits.seen_profiles_ms, self.seen_set(user_id)).await {
+            Ok(seen_set) => seen_set,
+            Err(_) => {
+                tracing::warn!("Seen profiles for {} timed out, proceeding without filtering", user_id);
+                warnings.push("seen_profiles_timeout");
+                Arc::new(HashSet::new())
+            }
+        };
+
+        // Add client-provided exclude IDs (if any) without mutating the cached set
+        let seen_profile_ids = if options.exclude_user_ids.is_empty() {
+            seen_set
+        } else {
+            let mut ids = (*seen_set).clone();
+            ids.extend(options.exclude_user_ids.iter().cloned());
+            Arc::new(ids)
+        };
+
+        tracing::debug!("Excluding {} seen profiles for user {}", seen_profile_ids.len(), user_id);
+        let mut diagnostics = FeedDiagnostics { seen_excluded: seen_profile_ids.len(), ..Default::default() };
+
+        // Fetch user profile to get location data
+        let user_profile = budget
+            .run(0, self.profile(user_id))
+            .await
+            .map_err(|_| FeedError::TimedOut("user profile"))?
+            .map_err(FeedError::Profile)?;
+
+        // Snoozed users are hidden from others, so they don't get a feed either
+        if user_profile.snoozed() {
+            return Err(FeedError::Snoozed);
+        }
+
+        let tier = self.tiers.limits(user_profile.tier());
+
+        // Fetch user preferences from Appwrite; new users without any get synthesized defaults
+        let policy = &self.matching.default_preferences;
+        let stored_preferences = budget
+            .run(0, self.preferences(user_id))
+            .await
+            .map_err(|_| FeedError::TimedOut("preferences"))?;
+        let (mut preferences, default_preferences) = match stored_preferences {
+            Ok(preferences) => (preferences, false),
+            Err(AppwriteError::NotFound(_)) if policy.enabled => {
+                tracing::info!("No preferences for {}, using defaults", user_id);
+                let preferences = UserPreferences::defaults_for(&user_profile, policy.age_spread, policy.max_distance_km);
+                (preferences, true)
+            }
+            Err(e) => return Err(FeedError::Preferences(e)),
+        };
+        let source = SourceVersions { profile: user_profile.updated_at, preferences: preferences.updated_at };
+
+        preferences.require_photos.get_or_insert(self.matching.require_photos);
+        preferences.embedding = user_profile.embedding.clone();
+        if preferences.preferred_languages.is_empty() {
+            preferences.preferred_languages = user_profile.languages.clone();
+        }
+        preferences.relationship_goal = preferences.relationship_goal.or(user_profile.relationship_goal);
+        for (name, value) in &user_profile.attributes {
+            preferences.attributes.entry(name.clone()).or_insert_with(|| value.clone());
+        }
+
+        // Update preferences with the search location: the passport override if
+        // given, otherwise the stored profile location (which is never modified)
+        match options.override_location {
+            Some(location) => {
+                if self.matching.passport_premium_only && !tier.passport {
+                    return Err(FeedError::PassportRequiresPremium);
+                }
+
+                tracing::debug!("Passport mode for {}: searching from ({}, {})", user_id, location.lat, location.lon);
+                preferences.latitude = location.lat;
+                preferences.longitude = location.lon;
+            }
+            None => {
+                preferences.latitude = user_profile.latitude;
+                preferences.longitude = user_profile.longitude;
+            }
+        }
+
+        // Refused markets: neither where the user is nor where they search from
+        self.check_geofences(user_profile.latitude, user_profile.longitude)?;
+        self.check_geofences(preferences.latitude, preferences.longitude)?;
+
+        // Tag the request with the market and city searched from, falling
+        // back to the profile's market without a location
+        let city = self
+            .geocoder
+            .filter(|_| preferences.has_location())
+            .and_then(|geocoder| geocoder.locate(preferences.latitude, preferences.longitude));
+        let market = city
+            .map(|city| city.market.clone())
+            .or_else(|| user_profile.market.as_ref().map(|market| market.to_lowercase()));
+        let city = city.map(|city| city.id.clone());
+        let market_overrides = market.as_deref().and_then(|market| self.markets.overrides_for(market));
+        if let Some(radius) = market_overrides.and_then(|o| o.max_distance_km).filter(|_| default_preferences) {
+            preferences.max_distance_km = radius;
+        }
+        // Request weights win over the market's
+        let weights = match market_overrides {
+            Some(overrides) => Some(options.weights.unwrap_or_default().or(overrides.weights)),
+            None => options.weights,
+        };
+
+        // Query candidates from Appwrite, overfetching so enough survive filtering
+        let multiplier = self.overfetch_multiplier(user_id).await;
+        let query = self.query_candidates(user_id, &preferences, &seen_profile_ids, options.limit * multiplier);
+        let mut candidates = match budget.run(limits.candidates_ms, query).await {
+            Ok(page) => {
+                let page = page.map_err(FeedError::Candidates)?;
+                diagnostics.unparseable = page.unparseable;
+                page.profiles
+            }
+            Err(_) => {
+                tracing::warn!("Candidate query for {} timed out, returning a partial feed", user_id);
+                warnings.push("candidates_timeout");
+                Vec::new()
+            }
+        };
+
+        diagnostics.geo_candidates = candidates.len();
+
+        // Kept for relaxed retries, which re-run only the geo query
+        let mut semantic = Vec::new();
+        if let Some(embedding) = user_profile.embedding.as_deref() {
+            let query = self.semantic_candidates(user_id, embedding, &seen_profile_ids, &candidates);
+            match budget.run(0, query).await {
+                Ok(profiles) => {
+                    diagnostics.vector_candidates = profiles.len();
+                    semantic = profiles;
+                    candidates.extend(semantic.iter().cloned());
+                }
+                Err(_) => {
+                    tracing::warn!("Vector retrieval for {} ran out of budget, using geo candidates only", user_id);
+                    warnings.push("vector_timeout");
+                }
+            }
+        }
+
+        diagnostics.blocked_by_rules = self.prepare_candidates(&user_profile, &mut candidates);
+
+        tracing::debug!("Found {} candidates for {}", candidates.len(), user_id);
+
+        // Keep a copy of the inputs for a sampled shadow ranking; request and
+        // market weight overrides would skew the comparison, so those are skipped
+        let shadow = self
+            .shadow
+            .as_ref()
+            .filter(|shadow| weights.is_none() && shadow.sampled())
+            .map(|shadow| (shadow, preferences.clone(), candidates.clone()));
+
+        // Run matching algorithm, with request-scoped weights if given
+        let mut result = self.score_with(
+            &preferences,
+            candidates,
+            options.limit,
+            weights.as_ref(),
+            options.min_score,
+            tier.score_breakdown,
+        );
+
+        if let Some((shadow, preferences, candidates)) = shadow {
+            shadow.evaluate(preferences, candidates, options.limit, result.matches.clone());
+        }
+
+        // Short of minResults: retry with more overfetch or a wider radius
+        // until enough matches are found, a retry fails or options run out
+        let mut relaxations = Vec::new();
+        let mut multiplier = multiplier;
+        let mut radius_widened = false;
+        let relaxation = &self.matching.relaxation;
+        let max_multiplier = self.matching.overfetch.max_multiplier.max(multiplier);
+        let wanted = options.min_results.unwrap_or(0).min(options.limit);
+        for _ in 0..relaxation.max_retries {
+            if result.matches.len() >= wanted || !warnings.is_empty() {
+                break;
+            }
+
+            let mut relaxed = preferences.clone();
+            let mut relaxed_multiplier = multiplier;
+            let step = if diagnostics.geo_candidates >= options.limit * multiplier && multiplier < max_multiplier {
+                relaxed_multiplier = (multiplier * 2).min(max_multiplier);
+                format!("overfetch:{}x", relaxed_multiplier)
+            } else if preferences.has_location() && preferences.max_distance_km < relaxation.max_distance_km {
+                let widened = (preferences.max_distance_km as f64 * relaxation.radius_factor.max(1.0)).ceil();
+                relaxed.max_distance_km = (widened as u16).clamp(preferences.max_distance_km + 1, relaxation.max_distance_km);
+                format!("radius:{}km", relaxed.max_distance_km)
+            } else {
+                tracing::debug!("No relaxations left for {} at {} matches", user_id, result.matches.len());
+                break;
+            };
+
+            let query = self.query_candidates(user_id, &relaxed, &seen_profile_ids, options.limit * relaxed_multiplier);
+            let page = match budget.run(limits.candidates_ms, query).await {
+                Ok(Ok(page)) => page,
+                Ok(Err(e)) => {
+                    tracing::warn!("Relaxed candidate query ({}) for {} failed: {}", step, user_id, e);
+                    break;
+                }
+                Err(_) => {
+                    tracing::debug!("Relaxed candidate query ({}) for {} ran out of budget", step, user_id);
+                    break;
+                }
+            };
+
+            let mut candidates = page.profiles;
+            let geo_candidates = candidates.len();
+            let known: HashSet<String> = candidates.iter().map(|c| c.user_id.clone()).collect();
+            candidates.extend(semantic.iter().filter(|c| !known.contains(&c.user_id)).cloned());
+            let blocked_by_rules = self.prepare_candidates(&user_profile, &mut candidates);
+
+            tracing::debug!("Relaxing feed for {} ({}): {} candidates", user_id, step, candidates.len());
+            result = self.score_with(
+                &relaxed,
+                candidates,
+                options.limit,
+                weights.as_ref(),
+                options.min_score,
+                tier.score_breakdown,
+            );
+
+            radius_widened |= relaxed.max_distance_km != preferences.max_distance_km;
+            diagnostics.geo_candidates = geo_candidates;
+            diagnostics.unparseable = page.unparseable;
+            diagnostics.blocked_by_rules = blocked_by_rules;
+            preferences = relaxed;
+            multiplier = relaxed_multiplier;
+            relaxations.push(step);
+        }
+
+        diagnostics.matching = result.diagnostics;
+
+        let run = MatchRun {
+            user_id: user_id.to_string(),
+            limit: options.limit,
+            overfetch_multiplier: multiplier,
+            candidates: diagnostics.geo_candidates,
+            matches: result.matches.len(),
+            partial: !warnings.is_empty(),
+            duration_ms: started.elapsed().as_millis() as u64,
+            market: market.clone(),
+            city: city.clone(),
+            raw_scores: result.matches.iter().map(|m| (m.user_id.clone(), m.raw_score)).collect(),
+            created_at: chrono::Utc::now(),
+        };
+        // A timed-out candidate query says nothing about the overfetch, and
+        // a widened radius says nothing about the user's own one
+        self.record_run(run, !warnings.contains(&"candidates_timeout") && !radius_widened);
+
+        Ok(Feed { result, default_preferences, warnings, diagnostics, market, city, relaxations, source })
+    }
+
+    /// Tag candidates with their city and drop those blocked by the
+    /// hard-block rules; returns how many were blocked
+    fn prepare_candidates(&self, user_profile: &UserProfile, candidates: &mut Vec<UserProfile>) -> usize {
+        if let Some(geocoder) = self.geocoder {
+            candidates.iter_mut().for_each(|candidate| geocoder.tag(candidate));
+        }
+
+        // Hard-block rules, on the geo and semantic candidates alike
+        if self.rules.is_empty() {
+            return 0;
+        }
+
+        let before = candidates.len();
+        candidates.retain(|candidate| self.rules.allows(user_profile, candidate));
+        let blocked = before - candidates.len();
+        tracing::debug!("Rules blocked {} candidates for {}", blocked, user_profile.user_id);
+        blocked
+    }
+
+    /// Overfetch multiplier for the user's next candidate query: the
+    /// adapted one from earlier runs, or the configured base
+    async fn overfetch_multiplier(&self, user_id: &str) -> usize {
+        let settings = &self.matching.overfetch;
+        let base = settings.multiplier.max(1);
+        if !settings.adaptive {
+            return base;
+        }
+
+        match self.cache.get::<usize>(&CacheKey::overfetch(user_id)).await {
+            Ok(multiplier) => multiplier.clamp(base, settings.max_multiplier.max(base)),
+            Err(CacheError::CacheMiss(_)) => base,
+            Err(e) => {
+                tracing::debug!("Failed to read overfetch multiplier for {}: {}", user_id, e);
+                base
+            }
+        }
+    }
+
+    /// Adapt the user's overfetch multiplier to the run and record it in
+    /// `match_runs`, off the request path
+    fn record_run(&self, run: MatchRun, adapt: bool) {
+        let cache = self.cache.clone();
+        let postgres = self.postgres.clone();
+        let settings = self.matching.overfetch;
+
+        tokio::spawn(async move {
+            let next = next_overfetch_multiplier(&settings, &run);
+            if adapt && settings.adaptive && next != run.overfetch_multiplier {
+                tracing::debug!("Overfetch multiplier for {}: {} -> {}", run.user_id, run.overfetch_multiplier, next);
+                let key = CacheKey::overfetch(&run.user_id);
+                if let Err(e) = cache.set_with_ttl(&key, &next, settings.state_ttl_secs).await {
+                    tracing::warn!("Failed to store overfetch multiplier for {}: {}", run.user_id, e);
+                }
+            }
+
+            if let Err(e) = postgres.record_match_run(&run).await {
+                tracing::warn!("Failed to record match run for {}: {}", run.user_id, e);
+            }
+        });
+    }
+
+    /// Nearest profiles by embedding that the geo query didn't return
+    ///
+    /// They still pass through every filter, distance included. Failures are
+    /// logged and yield nothing, so the feed falls back to geo candidates only.
+    async fn semantic_candidates(
+        &self,
+        user_id: &str,
+        embedding: &[f32],
+        seen_profile_ids: &HashSet<String>,
+        candidates: &[UserProfile],
+    ) -> Vec<UserProfile> {
+        let Some((index, top_n)) = &self.vector else {
+            return Vec::new();
+        };
+
+        let ids = match index.nearest(embedding, *top_n).await {
+            Ok(ids) => ids,
+            Err(e) => {
+                tracing::warn!("Vector retrieval failed for {}, using geo candidates only: {}", user_id, e);
+                return Vec::new();
+            }
+        };
+
+        let known: HashSet<&str> = candidates.iter().map(|c| c.user_id.as_str()).collect();
+        let mut new_ids: Vec<String> = Vec::new();
+        for id in ids {
+            if id != user_id && !known.contains(id.as_str()) && !seen_profile_ids.contains(&id) && !new_ids.contains(&id) {
+                new_ids.push(id);
+            }
+        }
+
+        if new_ids.is_empty() {
+            return Vec::new();
+        }
+
+        match self.appwrite.get_profiles(&new_ids).await {
+            Ok(profiles) => {
+                tracing::debug!("Vector retrieval added {} candidates for {}", profiles.len(), user_id);
+                profiles
+            }
+            Err(e) => {
+                tracing::warn!("Failed to fetch semantic candidates for {}: {}", user_id, e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Filter, score and rank the given candidates exactly as a feed would
+    ///
+    /// Used directly by the bulk scoring endpoint, where the caller supplies
+    /// both the preferences and the candidates.
+    pub fn score(
+        &self,
+        preferences: &UserPreferences,
+        candidates: Vec<UserProfile>,
+        limit: usize,
+        weights: Option<&WeightsOverride>,
+    ) -> MatchResult {
+        self.score_with(preferences, candidates, limit, weights, None, false)
+    }
+
+    fn score_with(
+        &self,
+        preferences: &UserPreferences,
+        candidates: Vec<UserProfile>,
+        limit: usize,
+        weights: Option<&WeightsOverride>,
+        min_score: Option<f64>,
+        score_breakdown: bool,
+    ) -> MatchResult {
+        let options = MatchOptions {
+            limit: Some(limit),
+            min_score,
+            score_breakdown,
+            ..Default::default()
+        };
+
+        match weights {
+            Some(overrides) => {
+                let weights = overrides.apply(self.matcher.weights());
+                tracing::info!("Using request weight overrides for {}: {:?}", preferences.user_id, weights);
+                self.matcher
+                    .clone()
+                    .with_weights(weights)
+                    .find_matches_with(preferences, candidates, &options)
+            }
+            None => self.matcher.find_matches_with(preferences, candidates, &options),
+        }
+    }
+
+    /// Reject a location inside one of the matcher's `refuse` geofences
+    pub fn check_geofences(&self, lat: f64, lon: f64) -> Result<(), FeedError> {
+        match self.matcher.geofences().refusing(lat, lon) {
+            Some(fence) => {
+                tracing::info!("Refusing matching at ({}, {}): inside geofence {}", lat, lon, fence.name);
+                Err(FeedError::Geofenced(fence.name.clone()))
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// A user's profile, read through the cache when enabled
+    async fn profile(&self, user_id: &str) -> Result<UserProfile, AppwriteError> {
+        let appwrite = self.appwrite.clone();
+        self.read_versioned(user_id, PROFILE_KEYS, move |user_id| {
+            let appwrite = appwrite.clone();
+            async move { appwrite.get_profile(&user_id).await }
+        })
+        .await
+    }
+
+    /// A user's stored preferences, read through the cache when enabled
+    async fn preferences(&self, user_id: &str) -> Result<UserPreferences, AppwriteError> {
+        let appwrite = self.appwrite.clone();
+        self.read_versioned(user_id, PREFERENCES_KEYS, move |user_id| {
+            let appwrite = appwrite.clone();
+            async move { appwrite.get_preferences(&user_id).await }
+        })
+        .await
+    }
+
+    /// A user's document, read through the cache when enabled
+    ///
+    /// Documents are cached under their version's key (e.g.
+    /// `profile:{id}:v{millis}`), and the read-through entry under the
+    /// `:current` key holds just the version last fetched. A refresh fetches
+    /// the document, stores it under its version, then moves `:current` to
+    /// it. [`source_versions`](Self::source_versions) moves it forward too.
+    async fn read_versioned<T, F, Fut>(&self, user_id: &str, keys: VersionedKeys<T>, fetch: F) -> Result<T, AppwriteError>
+    where
+        T: Serialize + DeserializeOwned + Send + 'static,
+        F: Fn(String) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<T, AppwriteError>> + Send + 'static,
+    {
+        let Some(settings) = &self.read_through else {
+            return fetch(user_id.to_string()).await;
+        };
+
+        let key = (keys.current)(user_id);
+        let ttl_secs = self.cache.ttl_for(&key);
+        // Documents outlive the entries pointing at them
+        let document_ttl_secs = ttl_secs + settings.stale_secs;
+        let fetch = Arc::new(fetch);
+        let (cache, owned_fetch, owned_id, owned_key) = (self.cache.clone(), fetch.clone(), user_id.to_string(), key.clone());
+        let not_found_ttl_secs = settings.not_found_ttl_secs;
+        let version = cache::get_or_refresh_ahead(
+            &self.cache,
+            &key,
+            ttl_secs,
+            settings.stale_secs,
+            settings.refresh_ahead,
+            move || {
+                let (cache, fetch, user_id, key) = (cache.clone(), owned_fetch.clone(), owned_id.clone(), owned_key.clone());
+                async move {
+                    let not_found = || format!("{} not found for user {}", keys.name, user_id);
+                    let document = unless_missing(cache.as_ref(), &key, not_found_ttl_secs, not_found, fetch(user_id.clone())).await?;
+                    let version = (keys.version)(&document);
+                    store_document(cache.as_ref(), &(keys.versioned)(&user_id, version), &document, document_ttl_secs).await;
+                    Ok(version)
+                }
+            },
+        )
+        .await?;
+
+        match self.cache.get::<T>(&(keys.versioned)(user_id, version)).await {
+            Ok(document) => Ok(document),
+            Err(e) => {
+                if !matches!(e, CacheError::CacheMiss(_)) {
+                    tracing::warn!("Failed to read cached {} of {}: {}", keys.name, user_id, e);
+                }
+                // Evicted before the entry pointing at it
+                let document = fetch(user_id.to_string()).await?;
+                let versioned = (keys.versioned)(user_id, (keys.version)(&document));
+                store_document(self.cache.as_ref(), &versioned, &document, document_ttl_secs).await;
+                Ok(document)
+            }
+        }
+    }
+
+    /// Cache a document just read from Appwrite as its user's current one
+    async fn remember_current<T: Serialize>(&self, user_id: &str, keys: VersionedKeys<T>, document: &T) {
+        let Some(settings) = &self.read_through else {
+            return;
+        };
+
+        let key = (keys.current)(user_id);
+        let ttl_secs = self.cache.ttl_for(&key);
+        let version = (keys.version)(document);
+        store_document(self.cache.as_ref(), &(keys.versioned)(user_id, version), document, ttl_secs + settings.stale_secs).await;
+        cache::store(self.cache.as_ref(), &key, &version, ttl_secs, settings.stale_secs).await;
+    }
+
+    /// Stored preferences of several users, in order
+    ///
+    /// With read-through caching, fresh cached entries come from two batched
+    /// cache lookups (their versions, then those versions' documents) and
+    /// only the rest are looked up one by one.
+    async fn preferences_many(&self, user_ids: &[String]) -> Vec<Result<UserPreferences, AppwriteError>> {
+        let cached = match self.read_through {
+            Some(_) => self.cached_preferences(user_ids).await,
+            None => vec![None; user_ids.len()],
+        };
+
+        futures_util::future::join_all(user_ids.iter().zip(cached).map(|(user_id, cached)| async move {
+            match cached {
+                Some(preferences) => Ok(preferences),
+                None => self.preferences(user_id).await,
+            }
+        }))
+        .await
+    }
+
+    async fn cached_preferences(&self, user_ids: &[String]) -> Vec<Option<UserPreferences>> {
+        let current: Vec<String> = user_ids.iter().map(|user_id| CacheKey::current_preferences(user_id)).collect();
+        let versions: Vec<Option<Option<chrono::DateTime<chrono::Utc>>>> = match self.cache.get_many(&current).await {
+            Ok(entries) => entries.iter().map(|bytes| bytes.as_deref().and_then(cache::fresh_value)).collect(),
+            Err(e) => {
+                tracing::warn!("Batched preferences lookup failed, reading them one by one: {}", e);
+                return vec![None; user_ids.len()];
+            }
+        };
+
+        let keys: Vec<String> = user_ids
+            .iter()
+            .zip(&versions)
+            .filter_map(|(user_id, version)| version.map(|version| CacheKey::preferences(user_id, version)))
+            .collect();
+        let mut documents = match self.cache.get_many(&keys).await {
+            Ok(documents) => documents.into_iter(),
+            Err(e) => {
+                tracing::warn!("Batched preferences lookup failed, reading them one by one: {}", e);
+                return vec![None; user_ids.len()];
+            }
+        };
+
+        versions
+            .into_iter()
+            .map(|version| {
+                version?;
+                documents.next().flatten().and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            })
+            .collect()
+    }
+
+    /// Candidates for `preferences`, read through the cache when enabled
+    ///
+    /// Pools are shared by requests asking the same query and re-filtered
+    /// against the current exclusions, so a profile swiped since the pool
+    /// was fetched is still left out.
+    async fn query_candidates(
+        &self,
+        user_id: &str,
+        preferences: &UserPreferences,
+        exclude_ids: &Arc<HashSet<String>>,
+        limit: usize,
+    ) -> Result<CandidatePage, AppwriteError> {
+        let Some(settings) = &self.read_through else {
+            return self.appwrite.query_candidates(user_id, preferences, exclude_ids, limit).await;
+        };
+
+        let key = CacheKey::candidates(user_id, &candidate_query_fingerprint(preferences, limit));
+        let (appwrite, owned_id, owned_preferences, owned_excludes) =
+            (self.appwrite.clone(), user_id.to_string(), preferences.clone(), exclude_ids.clone());
+        let mut page: CandidatePage = cache::get_or_refresh_ahead(
+            &self.cache,
+            &key,
+            self.cache.ttl_for(&key),
+            settings.stale_secs,
+            settings.refresh_ahead,
+            move || {
+                let (appwrite, user_id) = (appwrite.clone(), owned_id.clone());
+                let (preferences, exclude_ids) = (owned_preferences.clone(), owned_excludes.clone());
+                async move { appwrite.query_candidates(&user_id, &preferences, &exclude_ids, limit).await }
+            },
+        )
+        .await?;
+        page.profiles.retain(|profile| !exclude_ids.contains(&profile.user_id));
+        Ok(page)
+    }
+
+    /// The current versions of the user's profile and preferences
+    ///
+    /// Read from Appwrite, not the cache: a cached document may predate an
+    /// edit, and the check would then serve a feed computed before it. The
+    /// documents read are cached as current, so the pipeline that may follow
+    /// sees them too. None when either can't be read; the live pipeline
+    /// reports why.
+    async fn source_versions(&self, user_id: &str) -> Option<SourceVersions> {
+        let (profile, preferences) = futures_util::join!(self.appwrite.get_profile(user_id), self.appwrite.get_preferences(user_id));
+
+        let profile = profile.ok().filter(|profile| !profile.snoozed())?;
+        self.remember_current(user_id, PROFILE_KEYS, &profile).await;
+        let preferences = match preferences {
+            Ok(preferences) => {
+                self.remember_current(user_id, PREFERENCES_KEYS, &preferences).await;
+                preferences.updated_at
+            }
+            Err(AppwriteError::NotFound(_)) => None,
+            Err(_) => return None,
+        };
+
+        Some(SourceVersions { profile: profile.updated_at, preferences })
+    }
+
+    /// Serve from a pre-generated feed, if one is stored
+    ///
+    /// The feed is looked up under the current versions of the user's
+    /// profile and preferences, so a feed computed before either changed is
+    /// never served. Profiles seen since the feed was generated are dropped.
+    /// Returns None when there is no feed or too few unseen matches are left
+    /// in it.
+    pub async fn pregenerated(&self, user_id: &str, limit: usize) -> Option<Feed> {
+        let source = self.source_versions(user_id).await?;
+        let stored: StoredFeed = match self.cache.get(&source.matches_key(user_id)).await {
+            Ok(stored) => stored,
+            Err(CacheError::CacheMiss(_)) => return None,
+            Err(e) => {
+                tracing::warn!("Failed to read pre-generated feed for {}: {}", user_id, e);
+                return None;
+            }
+        };
+
+        let seen = self.seen_set(user_id).await;
+        let matches: Vec<ScoredMatch> = stored
+            .matches
+            .into_iter()
+            .filter(|m| !seen.contains(&m.user_id))
+            .take(limit)
+            .collect();
+
+        if matches.len() < limit && !stored.exhaustive {
+            tracing::debug!("Pre-generated feed for {} has too few unseen matches", user_id);
+            return None;
+        }
+
+        Some(Feed {
+            result: MatchResult {
+                matches,
+                total_candidates: stored.total_candidates,
+                global_mode: stored.global_mode,
+                diagnostics: Default::default(),
+            },
+            default_preferences: stored.default_preferences,
+            warnings: Vec::new(),
+            diagnostics: FeedDiagnostics::default(),
+            market: stored.market,
+            city: stored.city,
+            relaxations: Vec::new(),
+            source,
+        })
+    }
+
+    /// The user's top pick of the day, chosen on first request and pinned
+    /// until midnight in their time zone (UTC when unknown)
+    ///
+    /// Among the best `top_pick.pool_size` unseen feed matches, picks the one
+    /// with the highest reciprocal score: the geometric mean of the user's
+    /// score for them and theirs for the user. Candidates whose preferences
+    /// rule the user out are skipped; ones without preferences count with the
+    /// user's score alone. None when disabled or nobody qualifies.
+    pub async fn top_pick(&self, user_id: &str) -> Result<Option<TopPick>, FeedError> {
+        self.top_pick_with(user_id, async { Ok(()) }).await
+    }
+
+    /// [`top_pick`](Self::top_pick), admitting a feed pipeline run on a miss
+    /// like [`generate_coalesced_with`](Self::generate_coalesced_with)
+    pub async fn top_pick_with<P>(
+        &self,
+        user_id: &str,
+        admit: impl Future<Output = Result<P, FeedError>>,
+    ) -> Result<Option<TopPick>, FeedError> {
+        if !self.matching.top_pick.enabled {
+            return Ok(None);
+        }
+
+        let key = CacheKey::top_pick(user_id);
+        let now = chrono::Utc::now();
+        match self.cache.get::<TopPick>(&key).await {
+            Ok(pick) if pick.expires_at > now => return Ok(Some(pick)),
+            Ok(_) | Err(CacheError::CacheMiss(_)) => {}
+            Err(e) => tracing::warn!("Failed to read top pick for {}: {}", user_id, e),
+        }
+
+        let options = FeedOptions { limit: self.matching.top_pick.pool_size.max(1), ..Default::default() };
+        let feed = self.generate_coalesced_with(user_id, &options, admit).await?;
+        if feed.result.matches.is_empty() {
+            return Ok(None);
+        }
+
+        let profile = self.profile(user_id).await.map_err(FeedError::Profile)?;
+        let candidate_ids: Vec<String> = feed.result.matches.iter().map(|candidate| candidate.user_id.clone()).collect();
+        let their_preferences = self.preferences_many(&candidate_ids).await;
+
+        let reverse_options = MatchOptions { limit: Some(1), min_score: Some(0.0), ..Default::default() };
+        let best = feed
+            .result
+            .matches
+            .into_iter()
+            .zip(their_preferences)
+            .filter_map(|(candidate, preferences)| {
+                let their_score = match preferences {
+                    Ok(preferences) => self
+                        .matcher
+                        .find_matches_with(&preferences, vec![profile.clone()], &reverse_options)
+                        .matches
+                        .first()?
+                        .match_score,
+                    Err(AppwriteError::NotFound(_)) => candidate.match_score,
+                    Err(e) => {
+                        tracing::warn!("Failed to fetch preferences of {} for a top pick: {}", candidate.user_id, e);
+                        candidate.match_score
+                    }
+                };
+                let reciprocal_score = (candidate.match_score * their_score).max(0.0).sqrt();
+                Some((candidate, reciprocal_score))
+            })
+            .max_by(|(_, a), (_, b)| a.total_cmp(b));
+
+        let Some((candidate, reciprocal_score)) = best else {
+            return Ok(None);
+        };
+        let (day, expires_at) = next_local_midnight(profile.timezone.as_deref(), now);
+        let pick = TopPick { candidate, reciprocal_score, day, expires_at };
+
+        let ttl_secs = (expires_at - now).num_seconds().max(1) as u64;
+        if let Err(e) = self.cache.set_with_ttl(&key, &pick, ttl_secs).await {
+            tracing::warn!("Failed to store top pick for {}: {}", user_id, e);
+        }
+
+        Ok(Some(pick))
+    }
+}
+
+/// Today's date in `timezone` and the UTC instant of the next local midnight
+fn next_local_midnight(
+    timezone: Option<&str>,
+    now: chrono::DateTime<chrono::Utc>,
+) -> (chrono::NaiveDate, chrono::DateTime<chrono::Utc>) {
+    use chrono::TimeZone;
+
+    let tz = timezone.and_then(|tz| tz.parse::<chrono_tz::Tz>().ok()).unwrap_or(chrono_tz::UTC);
+    let day = now.with_timezone(&tz).date_naive();
+    let midnight = day
+        .succ_opt()
+        .and_then(|next| tz.from_local_datetime(&next.and_time(chrono::NaiveTime::MIN)).earliest())
+        .map(|midnight| midnight.with_timezone(&chrono::Utc))
+        // Midnight skipped by a DST change
+        .unwrap_or(now + chrono::Duration::hours(24));
+
+    (day, midnight)
+}
+
+/// Identifies the candidate query `preferences` and `limit` produce (the
+/// filters Appwrite applies), so identical queries share a cached pool
+fn candidate_query_fingerprint(preferences: &UserPreferences, limit: usize) -> String {
+    let location = preferences
+        .has_location()
+        .then_some((preferences.latitude, preferences.longitude, preferences.max_distance_km));
+    let query = serde_json::json!([preferences.preferred_genders, preferences.min_age, preferences.max_age, location, limit]);
+    hex::encode(&Sha256::digest(query.to_string().as_bytes())[..8])
+}
+
+/// Consumes user IDs from a Redis list and stores their rendered feeds
+///
+/// Producers push user IDs with `RPUSH <queue_key> <userId>`; the API
+/// serves the stored feed for plain find requests until it expires.
+pub struct FeedWorker {
+    generator: Arc<FeedGenerator>,
+    cache: Arc<dyn Cache>,
+    settings: FeedSettings,
+}
+
+impl FeedWorker {
+    pub fn new(generator: Arc<FeedGenerator>, cache: Arc<dyn Cache>, settings: FeedSettings) -> Self {
+        Self {
+            generator,
+            cache,
+            settings,
+        }
+    }
+
+    /// Generate and store one user's feed
+    pub async fn process(&self, user_id: &str) -> Result<StoredFeed, FeedError> {
+        let size = self.settings.feed_size;
+        let Feed { result, default_preferences, warnings, market, city, source, .. } =
+            self.generator.generate(user_id, &FeedOptions { limit: size, ..Default::default() }).await?;
+
+        let feed = StoredFeed {
+            exhaustive: result.matches.len() < size,
+            matches: result.matches,
+            total_candidates: result.total_candidates,
+            global_mode: result.global_mode,
+            default_preferences,
+            market,
+            city,
+            generated_at: chrono::Utc::now(),
+        };
+
+        // A partial feed would be served as complete until it expires
+        if !warnings.is_empty() {
+            tracing::warn!("Not storing partial feed for {} ({})", user_id, warnings.join(", "));
+        } else if let Err(e) = self
+            .cache
+            .set_with_ttl(&source.matches_key(user_id), &feed, self.settings.feed_ttl_secs)
+            .await
+        {
+            tracing::warn!("Failed to store feed for {}: {}", user_id, e);
+        }
+
+        Ok(feed)
+    }
+
+    /// Spawn the queue consumers, each on its own Redis connection
+    /// (BLPOP blocks the connection it runs on)
+    pub async fn spawn(self: Arc<Self>, redis_url: &str) -> Result<Vec<JoinHandle<()>>, CacheError> {
+        let client = redis::Client::open(redis_url)?;
+        let mut handles = Vec::with_capacity(self.settings.worker_concurrency);
+
+        tracing::info!(
+            "Feed worker consuming {} with {} consumers (feed size: {}, TTL: {}s)",
+            self.settings.queue_key,
+            self.settings.worker_concurrency.max(1),
+            self.settings.feed_size,
+            self.settings.feed_ttl_secs
+        );
+
+        for _ in 0..self.settings.worker_concurrency.max(1) {
+            let conn = ConnectionManager::new(client.clone()).await?;
+            handles.push(tokio::spawn(self.clone().consume(conn)));
+        }
+
+        Ok(handles)
+    }
+
+    async fn consume(self: Arc<Self>, mut conn: ConnectionManager) {
+        loop {
+            let popped: Result<Option<(String, String)>, redis::RedisError> = redis::cmd("BLPOP")
+                .arg(&self.settings.queue_key)
+                .arg(self.settings.poll_timeout_secs)
+                .query_async(&mut conn)
+                .await;
+
+            let user_id = match popped {
+                Ok(Some((_, user_id))) => user_id,
+                Ok(None) => continue,
+                Err(e) => {
+                    tracing::error!("Feed queue read failed: {}", e);
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    continue;
+                }
+            };
+
+            let started = std::time::Instant::now();
+            match self.process(&user_id).await {
+                Ok(feed) => tracing::debug!(
+                    "Generated feed for {}: {} matches in {}ms",
+                    user_id,
+                    feed.matches.len(),
+                    started.elapsed().as_millis()
+                ),
+                Err(e) => tracing::warn!("Feed generation failed for {}: {}", user_id, e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{EventType, MatchEvent};
+    use crate::services::CandidatePage;
+    use crate::testing::{matching_settings, preferences, profile, MockCache, MockProfileStore, MockSeenStore};
+    use async_trait::async_trait;
+    use chrono::{DateTime, Utc};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Profile store answering profile lookups and candidate queries after
+    /// a delay, counting the lookups and recording the query limits
+    struct SlowProfiles {
+        inner: MockProfileStore,
+        lookups: AtomicUsize,
+        query_limits: std::sync::Mutex<Vec<usize>>,
+        profile_delay: Duration,
+        candidates_delay: Duration,
+    }
+
+    #[async_trait]
+    impl ProfileStore for SlowProfiles {
+        async fn get_preferences(&self, user_id: &str) -> Result<UserPreferences, AppwriteError> {
+            self.inner.get_preferences(user_id).await
+        }
+
+        async fn query_candidates(
+            &self,
+            user_id: &str,
+            preferences: &UserPreferences,
+            exclude_ids: &HashSet<String>,
+            limit: usize,
+        ) -> Result<CandidatePage, AppwriteError> {
+            self.query_limits.lock().unwrap().push(limit);
+            tokio::time::sleep(self.candidates_delay).await;
+            self.inner.query_candidates(user_id, preferences, exclude_ids, limit).await
+        }
+
+        async fn get_profile(&self, user_id: &str) -> Result<UserProfile, AppwriteError> {
+            self.lookups.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(self.profile_delay).await;
+            self.inner.get_profile(user_id).await
+        }
+
+        async fn get_profiles(&self, user_ids: &[String]) -> Result<Vec<UserProfile>, AppwriteError> {
+            self.inner.get_profiles(user_ids).await
+        }
+
+        async fn list_events_since(&self, since: DateTime<Utc>, max_events: usize) -> Result<Vec<MatchEvent>, AppwriteError> {
+            self.inner.list_events_since(since, max_events).await
+        }
+
+        async fn record_event(&self, event: MatchEvent) -> Result<(), AppwriteError> {
+            self.inner.record_event(event).await
+        }
+
+        async fn check_auth(&self) -> Result<(), AppwriteError> {
+            self.inner.check_auth().await
+        }
+    }
+
+    fn slow_feed_with(
+        profile_delay: Duration,
+        candidates_delay: Duration,
+        latency_budget: LatencyBudgetSettings,
+    ) -> (Arc<SlowProfiles>, FeedGenerator) {
+        let profiles = Arc::new(SlowProfiles {
+            inner: MockProfileStore::new(
+                vec![profile("alice", "male", 30), profile("beth", "female", 28)],
+                vec![preferences("alice", &["female"], 25, 35)],
+            ),
+            lookups: AtomicUsize::new(0),
+            query_limits: Default::default(),
+            profile_delay,
+            candidates_delay,
+        });
+        let feed = FeedGenerator::new(
+            profiles.clone(),
+            Arc::new(MockCache::new()),
+            Arc::new(MockSeenStore::new()),
+            Matcher::default(),
+            MatchingSettings { latency_budget, ..matching_settings() },
+        );
+        (profiles, feed)
+    }
+
+    fn slow_feed() -> (Arc<SlowProfiles>, FeedGenerator) {
+        slow_feed_with(Duration::from_millis(50), Duration::ZERO, LatencyBudgetSettings::default())
+    }
+
+    #[tokio::test]
+    async fn test_identical_requests_are_coalesced() {
+        let (profiles, feed) = slow_feed();
+        let options = FeedOptions { limit: 20, ..Default::default() };
+
+        let (a, b) = tokio::join!(feed.generate_coalesced("alice", &options), feed.generate_coalesced("alice", &options));
+        assert_eq!(a.unwrap().result.matches.len(), 1);
+        assert_eq!(b.unwrap().result.matches.len(), 1);
+        assert_eq!(profiles.lookups.load(Ordering::SeqCst), 1);
+        assert_eq!(feed.coalesced_requests(), 1);
+
+        // Different options are a different request
+        let other = FeedOptions { limit: 10, ..Default::default() };
+        let (a, b) = tokio::join!(feed.generate_coalesced("alice", &options), feed.generate_coalesced("alice", &other));
+        assert!(a.is_ok() && b.is_ok());
+        assert_eq!(profiles.lookups.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_coalesced_errors_are_shared() {
+        let (profiles, feed) = slow_feed();
+        let options = FeedOptions { limit: 20, ..Default::default() };
+
+        let (a, b) = tokio::join!(feed.generate_coalesced("nobody", &options), feed.generate_coalesced("nobody", &options));
+        for result in [a, b] {
+            assert!(matches!(result, Err(FeedError::Profile(AppwriteError::NotFound(_)))));
+        }
+        assert_eq!(profiles.lookups.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_only_the_leader_is_admitted() {
+        let (profiles, feed) = slow_feed();
+        let options = FeedOptions { limit: 20, ..Default::default() };
+        let admitted = AtomicUsize::new(0);
+        let admit = || async {
+            admitted.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        };
+
+        let (a, b) = tokio::join!(
+            feed.generate_coalesced_with("alice", &options, admit()),
+            feed.generate_coalesced_with("alice", &options, admit())
+        );
+        assert!(a.is_ok() && b.is_ok());
+        assert_eq!(admitted.load(Ordering::SeqCst), 1);
+
+        // Not admitted: the pipeline never runs
+        let shed = async { Err::<(), _>(FeedError::Overloaded { retry_after_secs: 2 }) };
+        let result = feed.generate_coalesced_with("alice", &options, shed).await;
+        assert!(matches!(result, Err(FeedError::Overloaded { retry_after_secs: 2 })));
+        assert_eq!(profiles.lookups.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_read_through_shares_lookups_and_pools() {
+        let (profiles, _) = slow_feed_with(Duration::ZERO, Duration::ZERO, LatencyBudgetSettings::default());
+        let overfetch = OverfetchSettings { adaptive: false, ..OverfetchSettings::default() };
+        let feed = FeedGenerator::new(
+            profiles.clone(),
+            Arc::new(MockCache::new()),
+            Arc::new(MockSeenStore::new()),
+            Matcher::default(),
+            MatchingSettings { overfetch, ..matching_settings() },
+        )
+        .with_read_through(ReadThroughSettings::default());
+
+        let options = FeedOptions { limit: 20, ..Default::default() };
+        assert_eq!(feed.generate("alice", &options).await.unwrap().result.matches.len(), 1);
+        assert_eq!(feed.generate("alice", &options).await.unwrap().result.matches.len(), 1);
+        assert_eq!(profiles.lookups.load(Ordering::SeqCst), 1);
+        assert_eq!(profiles.query_limits.lock().unwrap().len(), 1);
+
+        // The cached pool still honours exclusions added since it was fetched
+        let excluding = FeedOptions { exclude_user_ids: vec!["beth".to_string()], ..options.clone() };
+        assert!(feed.generate("alice", &excluding).await.unwrap().result.matches.is_empty());
+        assert_eq!(profiles.query_limits.lock().unwrap().len(), 1);
+
+        // A different query is a different pool
+        let smaller = FeedOptions { limit: 10, ..options };
+        assert_eq!(feed.generate("alice", &smaller).await.unwrap().result.matches.len(), 1);
+        assert_eq!(*profiles.query_limits.lock().unwrap(), vec![100, 50]);
+    }
+
+    #[tokio::test]
+    async fn test_missing_profiles_are_cached_briefly() {
+        let (profiles, _) = slow_feed_with(Duration::ZERO, Duration::ZERO, LatencyBudgetSettings::default());
+        let cache = Arc::new(MockCache::new());
+        let feed = |not_found_ttl_secs| {
+            FeedGenerator::new(profiles.clone(), cache.clone(), Arc::new(MockSeenStore::new()), Matcher::default(), matching_settings())
+                .with_read_through(ReadThroughSettings { not_found_ttl_secs, ..ReadThroughSettings::default() })
+        };
+        let options = FeedOptions { limit: 20, ..Default::default() };
+
+        for _ in 0..3 {
+            let result = feed(15).generate("ghost", &options).await;
+            assert!(matches!(result, Err(FeedError::Profile(AppwriteError::NotFound(_)))));
+        }
+        assert_eq!(profiles.lookups.load(Ordering::SeqCst), 1);
+
+        // Without negative caching every request asks the store
+        assert!(feed(0).generate("ghost", &options).await.is_err());
+        assert_eq!(profiles.lookups.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_default_options_are_servable() {
+        let options = FeedOptions { limit: 20, ..Default::default() };
+        assert!(options.is_default());
+
+        let options = FeedOptions {
+            limit: 20,
+            exclude_user_ids: vec!["user_1".to_string()],
+            ..Default::default()
+        };
+        assert!(!options.is_default());
+
+        let options = FeedOptions { limit: 20, min_results: Some(10), ..Default::default() };
+        assert!(!options.is_default());
+    }
+
+    #[tokio::test]
+    async fn test_min_results_widens_radius() {
+        let located = |user_id: &str, lat: f64| UserProfile { latitude: lat, ..profile(user_id, "female", 28) };
+        let profiles = Arc::new(MockProfileStore::new(
+            vec![
+                profile("alice", "male", 30),
+                profile("beth", "female", 28),
+                // ~78 km and ~131 km north of Berlin
+                located("carla", 53.22),
+                located("dana", 53.7),
+            ],
+            vec![preferences("alice", &["female"], 25, 35)],
+        ));
+        let feed = FeedGenerator::new(
+            profiles,
+            Arc::new(MockCache::new()),
+            Arc::new(MockSeenStore::new()),
+            Matcher::default(),
+            matching_settings(),
+        );
+
+        let plain = feed.generate("alice", &FeedOptions { limit: 5, ..Default::default() }).await.unwrap();
+        assert_eq!(plain.result.matches.len(), 1);
+        assert!(plain.relaxations.is_empty());
+
+        let options = FeedOptions { limit: 5, min_results: Some(3), ..Default::default() };
+        let relaxed = feed.generate("alice", &options).await.unwrap();
+        assert_eq!(relaxed.result.matches.len(), 3);
+        assert_eq!(relaxed.relaxations, vec!["radius:75km", "radius:113km", "radius:170km"]);
+
+        // Retries are bounded even when minResults is never reached
+        let options = FeedOptions { limit: 5, min_results: Some(5), ..Default::default() };
+        let exhausted = feed.generate("alice", &options).await.unwrap();
+        assert_eq!(exhausted.result.matches.len(), 3);
+        assert_eq!(exhausted.relaxations.len(), 3);
+
+        // Nothing clears a minimum score of 100
+        let options = FeedOptions { limit: 5, min_score: Some(100.0), ..Default::default() };
+        assert!(feed.generate("alice", &options).await.unwrap().result.matches.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_pregenerated_feed_follows_document_versions() {
+        let at = |secs| DateTime::from_timestamp(secs, 0);
+        let alice_preferences = UserPreferences { updated_at: at(1_700_000_000), ..preferences("alice", &["female"], 25, 35) };
+        let profiles = Arc::new(MockProfileStore::new(
+            vec![profile("alice", "male", 30), profile("beth", "female", 28)],
+            vec![alice_preferences.clone()],
+        ));
+        let cache: Arc<dyn Cache> = Arc::new(MockCache::new());
+        let feed = Arc::new(FeedGenerator::new(
+            profiles.clone(),
+            cache.clone(),
+            Arc::new(MockSeenStore::new()),
+            Matcher::default(),
+            matching_settings(),
+        ));
+
+        FeedWorker::new(feed.clone(), cache, FeedSettings::default()).process("alice").await.unwrap();
+        let served = feed.pregenerated("alice", 1).await.expect("a stored feed");
+        assert_eq!(served.result.matches[0].user_id, "beth");
+        assert_eq!(served.source.preferences, at(1_700_000_000));
+
+        // Editing the preferences moves the feed to a new key
+        profiles.insert_preferences(UserPreferences { updated_at: at(1_700_000_100), ..alice_preferences });
+        assert!(feed.pregenerated("alice", 1).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_version_check_skips_the_read_through_cache() {
+        let at = |secs| DateTime::from_timestamp(secs, 0);
+        let alice_preferences = UserPreferences { updated_at: at(1_700_000_000), ..preferences("alice", &["female"], 25, 35) };
+        let profiles = Arc::new(MockProfileStore::new(
+            vec![profile("alice", "male", 30), profile("beth", "female", 28)],
+            vec![alice_preferences.clone()],
+        ));
+        let cache: Arc<dyn Cache> = Arc::new(MockCache::new());
+        let feed = Arc::new(
+            FeedGenerator::new(profiles.clone(), cache.clone(), Arc::new(MockSeenStore::new()), Matcher::default(), matching_settings())
+                .with_read_through(ReadThroughSettings::default()),
+        );
+
+        // The worker's pipeline caches the preferences it read
+        FeedWorker::new(feed.clone(), cache.clone(), FeedSettings::default()).process("alice").await.unwrap();
+        assert!(cache.get_bytes(&CacheKey::preferences("alice", at(1_700_000_000))).await.unwrap().is_some());
+
+        // An edit within the cache TTL still retires the stored feed
+        profiles.insert_preferences(UserPreferences { updated_at: at(1_700_000_100), ..alice_preferences });
+        assert!(feed.pregenerated("alice", 1).await.is_none());
+
+        // and the live pipeline that follows reads the new version
+        let live = feed.generate("alice", &FeedOptions { limit: 20, ..Default::default() }).await.unwrap();
+        assert_eq!(live.source.preferences, at(1_700_000_100));
+    }
+
+    #[tokio::test]
+    async fn test_top_pick_is_reciprocal_and_pinned() {
+        let profiles = Arc::new(MockProfileStore::new(
+            vec![profile("alice", "male", 30), profile("beth", "female", 28), profile("cara", "female", 31)],
+            vec![
+                preferences("alice", &["female"], 25, 35),
+                // beth is only looking for younger men
+                preferences("beth", &["male"], 20, 25),
+                preferences("cara", &["male"], 25, 35),
+            ],
+        ));
+        let seen = Arc::new(MockSeenStore::new());
+        let feed = FeedGenerator::new(profiles, Arc::new(MockCache::new()), seen.clone(), Matcher::default(), matching_settings());
+
+        let pick = feed.top_pick("alice").await.unwrap().expect("a top pick");
+        assert_eq!(pick.candidate.user_id, "cara");
+        assert!(pick.reciprocal_score > 0.0);
+        assert!(pick.expires_at > chrono::Utc::now());
+
+        // Still the pick after alice swiped on cara
+        seen.record_seen("alice", "cara", EventType::Liked).await.unwrap();
+        feed.cache.invalidate_seen_set("alice").await;
+        assert_eq!(feed.top_pick("alice").await.unwrap().unwrap().candidate.user_id, "cara");
+    }
+
+    #[test]
+    fn test_next_local_midnight() {
+        let now = chrono::DateTime::parse_from_rfc3339("2024-03-30T22:30:00Z").unwrap().with_timezone(&chrono::Utc);
+
+        let (day, midnight) = next_local_midnight(None, now);
+        assert_eq!(day.to_string(), "2024-03-30");
+        assert_eq!(midnight.to_rfc3339(), "2024-03-31T00:00:00+00:00");
+
+        // Already the 31st in Berlin (UTC+1), whose next midnight is CEST
+        let (day, midnight) = next_local_midnight(Some("Europe/Berlin"), now);
+        assert_eq!(day.to_string(), "2024-03-31");
+        assert_eq!(midnight.to_rfc3339(), "2024-03-31T22:00:00+00:00");
+
+        assert_eq!(next_local_midnight(Some("Not/AZone"), now).0.to_string(), "2024-03-30");
+    }
+
+    #[tokio::test]
+    async fn test_candidate_timeout_returns_partial_feed() {
+        let budget = LatencyBudgetSettings { total_ms: 0, seen_profiles_ms: 0, candidates_ms: 20 };
+        let (_, feed) = slow_feed_with(Duration::ZERO, Duration::from_millis(500), budget);
+
+        let result = feed.generate("alice", &FeedOptions { limit: 20, ..Default::default() }).await.unwrap();
+        assert!(result.partial());
+        assert_eq!(result.warnings, vec!["candidates_timeout"]);
+        assert!(result.result.matches.is_empty());
+
+        let budget = LatencyBudgetSettings { candidates_ms: 0, ..budget };
+        let (_, feed) = slow_feed_with(Duration::ZERO, Duration::from_millis(10), budget);
+        let result = feed.generate("alice", &FeedOptions { limit: 20, ..Default::default() }).await.unwrap();
+        assert!(!result.partial());
+        assert_eq!(result.result.matches.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_total_budget_bounds_required_stages() {
+        let budget = LatencyBudgetSettings { total_ms: 20, seen_profiles_ms: 0, candidates_ms: 0 };
+        let (_, feed) = slow_feed_with(Duration::from_millis(500), Duration::ZERO, budget);
+
+        let err = feed.generate("alice", &FeedOptions { limit: 20, ..Default::default() }).await.unwrap_err();
+        assert!(matches!(err, FeedError::TimedOut("user profile")));
+    }
+
+    #[tokio::test]
+    async fn test_short_runs_raise_overfetch() {
+        let (profiles, _) = slow_feed_with(Duration::ZERO, Duration::ZERO, LatencyBudgetSettings::default());
+        let seen = Arc::new(MockSeenStore::new());
+        let feed = FeedGenerator::new(
+            profiles.clone(),
+            Arc::new(MockCache::new()),
+            seen.clone(),
+            Matcher::default(),
+            matching_settings(),
+        );
+
+        // Only one candidate exists, so every run with limit 5 comes back short
+        for _ in 0..4 {
+            let result = feed.generate("alice", &FeedOptions { limit: 5, ..Default::default() }).await.unwrap();
+            assert_eq!(result.result.matches.len(), 1);
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        // A full run steps back down
+        feed.generate("alice", &FeedOptions { limit: 1, ..Default::default() }).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        feed.generate("alice", &FeedOptions { limit: 1, ..Default::default() }).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        assert_eq!(*profiles.query_limits.lock().unwrap(), vec![25, 50, 100, 100, 20, 10]);
+
+        let runs = seen.match_runs();
+        assert_eq!(runs.len(), 6);
+        assert_eq!(runs.iter().map(|run| run.overfetch_multiplier).collect::<Vec<_>>(), vec![5, 10, 20, 20, 20, 10]);
+        assert!(runs[0].short() && !runs[4].short());
+        assert_eq!((runs[0].candidates, runs[0].matches, runs[0].partial), (1, 1, false));
+    }
+
+    #[tokio::test]
+    async fn test_fixed_overfetch() {
+        let (profiles, _) = slow_feed_with(Duration::ZERO, Duration::ZERO, LatencyBudgetSettings::default());
+        let overfetch = OverfetchSettings { multiplier: 3, adaptive: false, ..Default::default() };
+        let feed = FeedGenerator::new(
+            profiles.clone(),
+            Arc::new(MockCache::new()),
+            Arc::new(MockSeenStore::new()),
+            Matcher::default(),
+            MatchingSettings { overfetch, ..matching_settings() },
+        );
+
+        for _ in 0..3 {
+            feed.generate("alice", &FeedOptions { limit: 5, ..Default::default() }).await.unwrap();
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(*profiles.query_limits.lock().unwrap(), vec![15, 15, 15]);
+    }
+}
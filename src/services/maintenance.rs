@@ -0,0 +1,489 @@
+use crate::config::MaintenanceSettings;
+use crate::models::MatchEvent;
+use crate::services::{AppwriteError, EventType, PostgresError, ProfileStore, SeenProfile, SeenStore};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+
+/// Outcome of a single seen-profiles prune run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PruneReport {
+    pub deleted: u64,
+    pub retention_days: u32,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pub duration_ms: u64,
+    pub manual: bool,
+}
+
+/// Prunes old `viewed`/`passed` rows from seen_profiles
+///
+/// Liked and matched rows are never pruned. Shared between the scheduled
+/// background job and the admin endpoints so both report the same status.
+pub struct SeenPruner {
+    postgres: Arc<dyn SeenStore>,
+    settings: MaintenanceSettings,
+    last_run: RwLock<Option<PruneReport>>,
+}
+
+impl SeenPruner {
+    pub fn new(postgres: Arc<dyn SeenStore>, settings: MaintenanceSettings) -> Self {
+        Self {
+            postgres,
+            settings,
+            last_run: RwLock::new(None),
+        }
+    }
+
+    /// Configured retention window in days
+    pub fn retention_days(&self) -> u32 {
+        self.settings.seen_retention_days
+    }
+
+    /// Run a prune now, optionally overriding the configured retention
+    pub async fn run(&self, retention_days: Option<u32>, manual: bool) -> Result<PruneReport, PostgresError> {
+        let retention_days = retention_days.unwrap_or(self.settings.seen_retention_days);
+        let started_at = chrono::Utc::now();
+        let started = std::time::Instant::now();
+
+        let deleted = self.postgres.prune_seen_profiles(retention_days).await?;
+
+        let report = PruneReport {
+            deleted,
+            retention_days,
+            started_at,
+            duration_ms: started.elapsed().as_millis() as u64,
+            manual,
+        };
+
+        tracing::info!(
+            "Pruned {} seen profiles older than {} days in {}ms",
+            report.deleted,
+            report.retention_days,
+            report.duration_ms
+        );
+
+        *self.last_run.write().await = Some(report.clone());
+        Ok(report)
+    }
+
+    /// Result of the most recent run, if any
+    pub async fn last_run(&self) -> Option<PruneReport> {
+        self.last_run.read().await.clone()
+    }
+
+    /// Spawn the scheduled prune loop (no-op when disabled in settings)
+    ///
+    /// The first run happens one interval after startup.
+    pub fn spawn(self: Arc<Self>) -> Option<JoinHandle<()>> {
+        if !self.settings.prune_enabled {
+            tracing::info!("Seen-profiles prune job disabled");
+            return None;
+        }
+
+        let period = Duration::from_secs(self.settings.prune_interval_secs.max(60));
+        tracing::info!(
+            "Seen-profiles prune job every {:?} (retention: {} days)",
+            period,
+            self.settings.seen_retention_days
+        );
+
+        Some(tokio::spawn(async move {
+            let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.run(None, false).await {
+                    tracing::error!("Seen-profiles prune job failed: {}", e);
+                }
+            }
+        }))
+    }
+}
+
+/// Outcome of a single match expiry run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExpiryReport {
+    pub expired: u64,
+    pub expiry_days: u32,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pub duration_ms: u64,
+    pub manual: bool,
+}
+
+/// Marks mutual matches with no follow-up event inactive
+///
+/// Disabled unless `match_expiry_days` is set. Shared between the scheduled
+/// job, the admin endpoints and the expiring-matches endpoint.
+pub struct MatchExpirer {
+    postgres: Arc<dyn SeenStore>,
+    settings: MaintenanceSettings,
+    last_run: RwLock<Option<ExpiryReport>>,
+}
+
+impl MatchExpirer {
+    pub fn new(postgres: Arc<dyn SeenStore>, settings: MaintenanceSettings) -> Self {
+        Self {
+            postgres,
+            settings,
+            last_run: RwLock::new(None),
+        }
+    }
+
+    /// Configured expiry in days, None when expiry is disabled
+    pub fn expiry_days(&self) -> Option<u32> {
+        self.settings.match_expiry_days
+    }
+
+    /// Default look-ahead for listing expiring matches
+    pub fn warning_days(&self) -> u32 {
+        self.settings.match_expiry_warning_days
+    }
+
+    /// Expire matches idle for `expiry_days` now
+    pub async fn run(&self, expiry_days: u32, manual: bool) -> Result<ExpiryReport, PostgresError> {
+        let started_at = chrono::Utc::now();
+        let started = std::time::Instant::now();
+
+        let expired = self.postgres.expire_matches(expiry_days).await?;
+
+        let report = ExpiryReport {
+            expired,
+            expiry_days,
+            started_at,
+            duration_ms: started.elapsed().as_millis() as u64,
+            manual,
+        };
+
+        tracing::info!(
+            "Expired {} matches idle for {} days in {}ms",
+            report.expired,
+            report.expiry_days,
+            report.duration_ms
+        );
+
+        *self.last_run.write().await = Some(report.clone());
+        Ok(report)
+    }
+
+    /// Result of the most recent run, if any
+    pub async fn last_run(&self) -> Option<ExpiryReport> {
+        self.last_run.read().await.clone()
+    }
+
+    /// Spawn the scheduled expiry loop (no-op when expiry is disabled)
+    ///
+    /// The first run happens one interval after startup.
+    pub fn spawn(self: Arc<Self>) -> Option<JoinHandle<()>> {
+        let Some(expiry_days) = self.settings.match_expiry_days else {
+            tracing::info!("Match expiry job disabled");
+            return None;
+        };
+
+        let period = Duration::from_secs(self.settings.match_expiry_interval_secs.max(60));
+        tracing::info!("Match expiry job every {:?} (expiry: {} days)", period, expiry_days);
+
+        Some(tokio::spawn(async move {
+            let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.run(expiry_days, false).await {
+                    tracing::error!("Match expiry job failed: {}", e);
+                }
+            }
+        }))
+    }
+}
+
+/// Errors that can occur during an event reconciliation run
+#[derive(Debug, Error)]
+pub enum ReconcileError {
+    #[error("PostgreSQL error: {0}")]
+    Postgres(#[from] PostgresError),
+
+    #[error("Appwrite error: {0}")]
+    Appwrite(#[from] AppwriteError),
+}
+
+/// Outcome of a single dual-write reconciliation run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReconcileReport {
+    pub window_hours: u32,
+    pub postgres_events: usize,
+    pub appwrite_events: usize,
+    pub missing_in_appwrite: usize,
+    pub missing_in_postgres: usize,
+    pub resynced: usize,
+    pub failed: usize,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pub duration_ms: u64,
+    pub manual: bool,
+}
+
+impl ReconcileReport {
+    /// Events found in only one of the two stores
+    pub fn divergence(&self) -> usize {
+        self.missing_in_appwrite + self.missing_in_postgres
+    }
+}
+
+/// Reconciliation metrics for /metrics
+#[derive(Debug, Clone, Serialize)]
+pub struct ReconcileStats {
+    pub runs: u64,
+    pub divergence_total: u64,
+    pub last_run: Option<ReconcileReport>,
+}
+
+/// Events in one store that the other is missing
+#[derive(Debug, Default)]
+pub struct EventDiff<'a> {
+    pub missing_in_appwrite: Vec<&'a SeenProfile>,
+    pub missing_in_postgres: Vec<&'a MatchEvent>,
+}
+
+/// Compare recent seen records with recent Appwrite events
+///
+/// PostgreSQL keeps only the latest event per (user, target) pair, so an
+/// Appwrite event counts as missing there only if the pair is absent. A
+/// PostgreSQL record counts as missing in Appwrite if no event with the same
+/// pair and type exists.
+pub fn diff_events<'a>(seen: &'a [SeenProfile], events: &'a [MatchEvent]) -> EventDiff<'a> {
+    let appwrite_keys: HashSet<(&str, &str, EventType)> = events
+        .iter()
+        .map(|e| (e.user_id.as_str(), e.target_user_id.as_str(), EventType::from(e.event_type)))
+        .collect();
+    let postgres_pairs: HashSet<(&str, &str)> = seen
+        .iter()
+        .map(|s| (s.user_id.as_str(), s.target_user_id.as_str()))
+        .collect();
+
+    EventDiff {
+        missing_in_appwrite: seen
+            .iter()
+            .filter(|s| !appwrite_keys.contains(&(s.user_id.as_str(), s.target_user_id.as_str(), s.event_type)))
+            .collect(),
+        missing_in_postgres: events
+            .iter()
+            .filter(|e| !postgres_pairs.contains(&(e.user_id.as_str(), e.target_user_id.as_str())))
+            .collect(),
+    }
+}
+
+/// Re-syncs match events between PostgreSQL and Appwrite
+///
+/// `record_event` writes both stores best-effort, so they drift. Shared
+/// between the scheduled job and the admin endpoints.
+pub struct EventReconciler {
+    postgres: Arc<dyn SeenStore>,
+    appwrite: Arc<dyn ProfileStore>,
+    settings: MaintenanceSettings,
+    last_run: RwLock<Option<ReconcileReport>>,
+    runs: AtomicU64,
+    divergence_total: AtomicU64,
+}
+
+/// Slack on the Appwrite side of the window, since its timestamps are set
+/// separately from PostgreSQL's
+const RECONCILE_CLOCK_SKEW_SECS: i64 = 300;
+
+impl EventReconciler {
+    pub fn new(postgres: Arc<dyn SeenStore>, appwrite: Arc<dyn ProfileStore>, settings: MaintenanceSettings) -> Self {
+        Self {
+            postgres,
+            appwrite,
+            settings,
+            last_run: RwLock::new(None),
+            runs: AtomicU64::new(0),
+            divergence_total: AtomicU64::new(0),
+        }
+    }
+
+    /// Configured reconciliation window in hours
+    pub fn window_hours(&self) -> u32 {
+        self.settings.reconcile_window_hours
+    }
+
+    /// Reconcile now, optionally overriding the configured window
+    pub async fn run(&self, window_hours: Option<u32>, manual: bool) -> Result<ReconcileReport, ReconcileError> {
+        let window_hours = window_hours.unwrap_or(self.settings.reconcile_window_hours);
+        let started_at = chrono::Utc::now();
+        let started = std::time::Instant::now();
+        let since = started_at - chrono::Duration::hours(window_hours as i64);
+        let max_events = self.settings.reconcile_max_events;
+
+        let seen = self.postgres.get_seen_since(since, max_events).await?;
+        let events = self
+            .appwrite
+            .list_events_since(since - chrono::Duration::seconds(RECONCILE_CLOCK_SKEW_SECS), max_events)
+            .await?;
+
+        let diff = diff_events(&seen, &events);
+        let mut resynced = 0;
+        let mut failed = 0;
+
+        for record in &diff.missing_in_appwrite {
+            let event = MatchEvent {
+                user_id: record.user_id.clone(),
+                target_user_id: record.target_user_id.clone(),
+                event_type: record.event_type.into(),
+                created_at: record.seen_at,
+            };
+            match self.appwrite.record_event(event).await {
+                Ok(()) => resynced += 1,
+                Err(e) => {
+                    failed += 1;
+                    tracing::warn!("Failed to re-sync event {} -> {} to Appwrite: {}", record.user_id, record.target_user_id, e);
+                }
+            }
+        }
+
+        for event in &diff.missing_in_postgres {
+            match self
+                .postgres
+                .insert_seen_if_absent(&event.user_id, &event.target_user_id, event.event_type.into(), event.created_at)
+                .await
+            {
+                Ok(_) => resynced += 1,
+                Err(e) => {
+                    failed += 1;
+                    tracing::warn!("Failed to re-sync event {} -> {} to PostgreSQL: {}", event.user_id, event.target_user_id, e);
+                }
+            }
+        }
+
+        let report = ReconcileReport {
+            window_hours,
+            postgres_events: seen.len(),
+            appwrite_events: events.len(),
+            missing_in_appwrite: diff.missing_in_appwrite.len(),
+            missing_in_postgres: diff.missing_in_postgres.len(),
+            resynced,
+            failed,
+            started_at,
+            duration_ms: started.elapsed().as_millis() as u64,
+            manual,
+        };
+
+        tracing::info!(
+            "Reconciled events over {}h: {} missing in Appwrite, {} missing in PostgreSQL, {} re-synced, {} failed in {}ms",
+            report.window_hours,
+            report.missing_in_appwrite,
+            report.missing_in_postgres,
+            report.resynced,
+            report.failed,
+            report.duration_ms
+        );
+
+        self.runs.fetch_add(1, Ordering::Relaxed);
+        self.divergence_total.fetch_add(report.divergence() as u64, Ordering::Relaxed);
+        *self.last_run.write().await = Some(report.clone());
+        Ok(report)
+    }
+
+    /// Result of the most recent run, if any
+    pub async fn last_run(&self) -> Option<ReconcileReport> {
+        self.last_run.read().await.clone()
+    }
+
+    /// Run count, cumulative divergence and the most recent run
+    pub async fn stats(&self) -> ReconcileStats {
+        ReconcileStats {
+            runs: self.runs.load(Ordering::Relaxed),
+            divergence_total: self.divergence_total.load(Ordering::Relaxed),
+            last_run: self.last_run().await,
+        }
+    }
+
+    /// Spawn the scheduled reconciliation loop (no-op when disabled in settings)
+    ///
+    /// The first run happens one interval after startup.
+    pub fn spawn(self: Arc<Self>) -> Option<JoinHandle<()>> {
+        if !self.settings.reconcile_enabled {
+            tracing::info!("Event reconciliation job disabled");
+            return None;
+        }
+
+        let period = Duration::from_secs(self.settings.reconcile_interval_secs.max(60));
+        tracing::info!(
+            "Event reconciliation job every {:?} (window: {}h)",
+            period,
+            self.settings.reconcile_window_hours
+        );
+
+        Some(tokio::spawn(async move {
+            let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.run(None, false).await {
+                    tracing::error!("Event reconciliation job failed: {}", e);
+                }
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::MatchEventType;
+    use chrono::Utc;
+
+    fn seen(user: &str, target: &str, event_type: EventType) -> SeenProfile {
+        SeenProfile {
+            user_id: user.to_string(),
+            target_user_id: target.to_string(),
+            event_type,
+            seen_at: Utc::now(),
+        }
+    }
+
+    fn event(user: &str, target: &str, event_type: MatchEventType) -> MatchEvent {
+        MatchEvent {
+            user_id: user.to_string(),
+            target_user_id: target.to_string(),
+            event_type,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_diff_events() {
+        let seen = vec![
+            seen("a", "b", EventType::Liked),
+            seen("a", "c", EventType::Viewed),
+        ];
+        let events = vec![
+            // Older viewed event for a pair PostgreSQL has since upgraded to liked
+            event("a", "b", MatchEventType::Viewed),
+            event("a", "b", MatchEventType::Liked),
+            event("a", "d", MatchEventType::Passed),
+        ];
+
+        let diff = diff_events(&seen, &events);
+
+        assert_eq!(diff.missing_in_appwrite.len(), 1);
+        assert_eq!(diff.missing_in_appwrite[0].target_user_id, "c");
+        assert_eq!(diff.missing_in_postgres.len(), 1);
+        assert_eq!(diff.missing_in_postgres[0].target_user_id, "d");
+    }
+
+    #[test]
+    fn test_diff_events_in_sync() {
+        let seen = vec![seen("a", "b", EventType::Matched)];
+        let events = vec![event("a", "b", MatchEventType::Matched)];
+
+        let diff = diff_events(&seen, &events);
+        assert!(diff.missing_in_appwrite.is_empty());
+        assert!(diff.missing_in_postgres.is_empty());
+    }
+}
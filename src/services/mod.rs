@@ -1,8 +1,34 @@
 // Service exports
 pub mod appwrite;
 pub mod cache;
+pub mod dev;
+pub mod events;
+pub mod feed;
+pub mod maintenance;
 pub mod postgres;
+pub mod profile_sync;
+pub mod profiling;
+pub mod realtime;
+pub mod seed;
+pub mod shadow;
+pub mod singleflight;
+pub mod store;
+pub mod vector;
+pub mod warmup;
 
-pub use appwrite::{AppwriteClient, AppwriteCollections, AppwriteError};
-pub use cache::{CacheManager, CacheKey, CacheError, CacheStats};
-pub use postgres::{PostgresClient, PostgresError, EventType, SeenStats};
+pub use appwrite::{AppwriteClient, AppwriteCollections, AppwriteDocument, AppwriteError, ParseFailureStats, DEFAULT_MAX_QUERY_EXCLUSIONS};
+pub use cache::{CacheManager, CacheKey, CacheError, CacheStats, HitStats, InvalidateOptions, InvalidationReport, SharedError, SharedRecompute};
+pub use events::{EventPublisher, EventEnvelope, PublishError, PublisherStats, EVENT_SCHEMA_VERSION};
+pub use postgres::{PostgresClient, PostgresError, EventType, SeenProfile, SeenStats, PoolStats, ReceivedLike, MatchActivity, ProfileView, AuditEntry, AuditFilter, MatchRun, ProfileSyncState, RecordedEvent};
+pub use feed::{Feed, FeedGenerator, FeedWorker, FeedOptions, FeedError, SourceVersions, StoredFeed};
+pub use maintenance::{SeenPruner, PruneReport, EventReconciler, ReconcileReport, ReconcileStats, ReconcileError, MatchExpirer, ExpiryReport};
+pub use realtime::{MirrorStats, MirroredProfileStore, ProfileMirror, RealtimeError, RealtimeSubscriber};
+pub use profile_sync::{ProfileSync, ProfileSyncError, ProfileSyncReport, SyncedProfileStore};
+pub use profiling::{capture_profile, profiling_available, ProfileFormat, ProfilingError, DEFAULT_PROFILE_FREQUENCY, DEFAULT_PROFILE_SECS, MAX_PROFILE_SECS};
+pub use seed::{SeedGenerator, SeedReport, SeedUser, Seeder};
+pub use shadow::{ShadowEvaluator, ShadowStats};
+pub use singleflight::Singleflight;
+pub use store::{Cache, CandidatePage, ProfileStore, SeenStore};
+pub use dev::{InMemoryProfileStore, SqliteSeenStore, SeedError};
+pub use vector::{connect_vector_index, PgVectorIndex, QdrantIndex, VectorError, VectorIndex};
+pub use warmup::{warm_up, WarmupError, WarmupReport};
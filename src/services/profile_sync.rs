@@ -0,0 +1,350 @@
+use crate::config::ProfileSyncSettings;
+use crate::models::{AppwriteEndpointStatus, MatchEvent, UserPreferences, UserProfile};
+use crate::services::appwrite::{AppwriteClient, AppwriteError, ParseFailureStats};
+use crate::services::dev::is_candidate;
+use crate::services::postgres::{PostgresClient, PostgresError, ProfileSyncState};
+use crate::services::store::{CandidatePage, ProfileStore};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+
+/// How long one instance may hold the sync before another can take over
+const SYNC_LEASE_SECS: u64 = 900;
+
+/// Incremental syncs re-read this much before the watermark, covering
+/// clock skew between this service and Appwrite
+const WATERMARK_OVERLAP_SECS: i64 = 60;
+
+/// How long readers trust the last sync time they read
+const FRESHNESS_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Errors that can occur during a profile sync run
+#[derive(Debug, Error)]
+pub enum ProfileSyncError {
+    #[error("PostgreSQL error: {0}")]
+    Postgres(#[from] PostgresError),
+
+    #[error("Appwrite error: {0}")]
+    Appwrite(#[from] AppwriteError),
+}
+
+/// Outcome of a single profile sync run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileSyncReport {
+    /// Listed every active profile rather than the changes since the watermark
+    pub full: bool,
+    /// Profiles read from Appwrite
+    pub fetched: usize,
+    /// Mirror rows inserted or replaced
+    pub upserted: u64,
+    /// Profiles gone from Appwrite, found by a full sync
+    pub tombstoned: u64,
+    /// Expired tombstones deleted
+    pub purged: u64,
+    pub watermark: Option<DateTime<Utc>>,
+    pub started_at: DateTime<Utc>,
+    pub duration_ms: u64,
+    pub manual: bool,
+}
+
+/// Copies Appwrite profiles into the PostgreSQL `profile_mirror` table
+///
+/// Incremental runs fetch profiles updated since the stored watermark; a
+/// full run lists every active profile and tombstones the rows it didn't
+/// see. A lease in `profile_sync_state` keeps instances from syncing at
+/// the same time.
+pub struct ProfileSync {
+    appwrite: Arc<AppwriteClient>,
+    postgres: Arc<PostgresClient>,
+    settings: ProfileSyncSettings,
+    last_run: RwLock<Option<ProfileSyncReport>>,
+}
+
+impl ProfileSync {
+    pub fn new(appwrite: Arc<AppwriteClient>, postgres: Arc<PostgresClient>, settings: ProfileSyncSettings) -> Self {
+        Self {
+            appwrite,
+            postgres,
+            settings,
+            last_run: RwLock::new(None),
+        }
+    }
+
+    /// Sync now; `full` forces a full sync
+    ///
+    /// None when another instance holds the lease.
+    pub async fn run(&self, full: bool, manual: bool) -> Result<Option<ProfileSyncReport>, ProfileSyncError> {
+        let Some(state) = self.postgres.lease_profile_sync(SYNC_LEASE_SECS).await? else {
+            tracing::debug!("Profile sync is running on another instance");
+            return Ok(None);
+        };
+
+        let (report, next) = match self.sync(&state, full, manual).await {
+            Ok(synced) => synced,
+            Err(e) => {
+                // Release the lease with the previous progress
+                if let Err(release) = self.postgres.complete_profile_sync(&state).await {
+                    tracing::warn!("Failed to release the profile sync lease: {}", release);
+                }
+                return Err(e);
+            }
+        };
+        self.postgres.complete_profile_sync(&next).await?;
+
+        tracing::info!(
+            "Synced {} profiles into the mirror ({}, {} tombstoned) in {}ms",
+            report.fetched,
+            if report.full { "full" } else { "incremental" },
+            report.tombstoned,
+            report.duration_ms
+        );
+
+        *self.last_run.write().await = Some(report.clone());
+        Ok(Some(report))
+    }
+
+    async fn sync(
+        &self,
+        state: &ProfileSyncState,
+        full: bool,
+        manual: bool,
+    ) -> Result<(ProfileSyncReport, ProfileSyncState), ProfileSyncError> {
+        let started_at = Utc::now();
+        let started = Instant::now();
+        let full = full || needs_full_sync(state, started_at, &self.settings);
+
+        let profiles = match state.watermark {
+            Some(watermark) if !full => {
+                let since = watermark - chrono::Duration::seconds(WATERMARK_OVERLAP_SECS);
+                self.appwrite.list_profiles_updated_since(since, self.settings.page_size).await?
+            }
+            _ => self.appwrite.list_active_profiles(self.settings.page_size).await?,
+        };
+
+        let mut upserted = 0;
+        for chunk in profiles.chunks(self.settings.page_size.max(1)) {
+            upserted += self.postgres.upsert_mirrored_profiles(chunk, started_at).await?;
+        }
+
+        let (tombstoned, purged) = if full {
+            (
+                self.postgres.tombstone_unsynced_profiles(started_at).await?,
+                self.postgres.purge_profile_tombstones(self.settings.tombstone_retention_days).await?,
+            )
+        } else {
+            (0, 0)
+        };
+
+        // A full listing has every change made before it started
+        let latest = profiles.iter().filter_map(|p| p.updated_at).max();
+        let watermark = if full { Some(started_at) } else { state.watermark.max(latest) };
+
+        let next = ProfileSyncState {
+            watermark,
+            last_sync_at: Some(started_at),
+            last_full_sync_at: if full { Some(started_at) } else { state.last_full_sync_at },
+        };
+
+        let report = ProfileSyncReport {
+            full,
+            fetched: profiles.len(),
+            upserted,
+            tombstoned,
+            purged,
+            watermark,
+            started_at,
+            duration_ms: started.elapsed().as_millis() as u64,
+            manual,
+        };
+
+        Ok((report, next))
+    }
+
+    /// Result of this instance's most recent run, if any
+    pub async fn last_run(&self) -> Option<ProfileSyncReport> {
+        self.last_run.read().await.clone()
+    }
+
+    /// Spawn the scheduled sync loop (no-op when disabled in settings)
+    ///
+    /// The first run happens right away, so a restarted instance doesn't
+    /// leave the mirror to go stale.
+    pub fn spawn(self: Arc<Self>) -> Option<JoinHandle<()>> {
+        if !self.settings.enabled {
+            tracing::info!("Profile sync job disabled");
+            return None;
+        }
+
+        let period = Duration::from_secs(self.settings.interval_secs.max(10));
+        tracing::info!(
+            "Profile sync job every {:?} (full sync every {}s)",
+            period,
+            self.settings.full_sync_interval_secs
+        );
+
+        Some(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.run(false, false).await {
+                    tracing::error!("Profile sync job failed: {}", e);
+                }
+            }
+        }))
+    }
+}
+
+/// Whether a sync from `state` at `now` has to list every profile
+fn needs_full_sync(state: &ProfileSyncState, now: DateTime<Utc>, settings: &ProfileSyncSettings) -> bool {
+    let full_interval = chrono::Duration::seconds(settings.full_sync_interval_secs as i64);
+    state.watermark.is_none() || state.last_full_sync_at.is_none_or(|at| now - at >= full_interval)
+}
+
+/// Whether a mirror last synced at `last_sync_at` is recent enough to read
+fn within_lag(last_sync_at: Option<DateTime<Utc>>, now: DateTime<Utc>, max_lag_secs: u64) -> bool {
+    last_sync_at.is_some_and(|at| now - at <= chrono::Duration::seconds(max_lag_secs as i64))
+}
+
+/// Profile store answering candidate queries from the PostgreSQL profile
+/// mirror while it is fresh, and from `inner` otherwise
+///
+/// Freshness is the last sync time in `profile_sync_state`, so it works on
+/// instances that don't run the sync job themselves.
+pub struct SyncedProfileStore {
+    inner: Arc<dyn ProfileStore>,
+    postgres: Arc<PostgresClient>,
+    max_lag_secs: u64,
+    /// When `profile_sync_state` was last read, and the sync time it held
+    last_sync: Mutex<Option<(Instant, Option<DateTime<Utc>>)>>,
+}
+
+impl SyncedProfileStore {
+    pub fn new(inner: Arc<dyn ProfileStore>, postgres: Arc<PostgresClient>, settings: &ProfileSyncSettings) -> Self {
+        Self {
+            inner,
+            postgres,
+            max_lag_secs: settings.max_lag_secs,
+            last_sync: Mutex::new(None),
+        }
+    }
+
+    /// Whether the mirror was synced within `max_lag_secs`
+    pub async fn is_fresh(&self) -> bool {
+        let cached = *self.last_sync.lock().unwrap();
+        let last_sync_at = match cached {
+            Some((checked_at, last_sync_at)) if checked_at.elapsed() < FRESHNESS_CHECK_INTERVAL => last_sync_at,
+            _ => {
+                let last_sync_at = match self.postgres.get_profile_sync_state().await {
+                    Ok(state) => state.last_sync_at,
+                    Err(e) => {
+                        tracing::warn!("Failed to read the profile sync state: {}", e);
+                        None
+                    }
+                };
+                *self.last_sync.lock().unwrap() = Some((Instant::now(), last_sync_at));
+                last_sync_at
+            }
+        };
+
+        within_lag(last_sync_at, Utc::now(), self.max_lag_secs)
+    }
+}
+
+#[async_trait]
+impl ProfileStore for SyncedProfileStore {
+    async fn get_preferences(&self, user_id: &str) -> Result<UserPreferences, AppwriteError> {
+        self.inner.get_preferences(user_id).await
+    }
+
+    async fn query_candidates(
+        &self,
+        user_id: &str,
+        preferences: &UserPreferences,
+        exclude_ids: &HashSet<String>,
+        limit: usize,
+    ) -> Result<CandidatePage, AppwriteError> {
+        if self.is_fresh().await {
+            match self.postgres.query_mirrored_candidates(user_id, preferences, exclude_ids, limit).await {
+                Ok(profiles) => {
+                    let profiles: Vec<UserProfile> = profiles
+                        .into_iter()
+                        .filter(|profile| is_candidate(profile, user_id, preferences, exclude_ids))
+                        .collect();
+                    return Ok(profiles.into());
+                }
+                Err(e) => tracing::warn!("Profile mirror query failed, asking Appwrite: {}", e),
+            }
+        }
+
+        self.inner.query_candidates(user_id, preferences, exclude_ids, limit).await
+    }
+
+    async fn get_profile(&self, user_id: &str) -> Result<UserProfile, AppwriteError> {
+        self.inner.get_profile(user_id).await
+    }
+
+    async fn get_profiles(&self, user_ids: &[String]) -> Result<Vec<UserProfile>, AppwriteError> {
+        self.inner.get_profiles(user_ids).await
+    }
+
+    async fn list_events_since(&self, since: DateTime<Utc>, max_events: usize) -> Result<Vec<MatchEvent>, AppwriteError> {
+        self.inner.list_events_since(since, max_events).await
+    }
+
+    async fn record_event(&self, event: MatchEvent) -> Result<(), AppwriteError> {
+        self.inner.record_event(event).await
+    }
+
+    async fn check_auth(&self) -> Result<(), AppwriteError> {
+        self.inner.check_auth().await
+    }
+
+    fn endpoint_status(&self) -> Option<AppwriteEndpointStatus> {
+        self.inner.endpoint_status()
+    }
+
+    fn parse_failures(&self) -> Option<ParseFailureStats> {
+        self.inner.parse_failures()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_full_sync_schedule() {
+        let settings = ProfileSyncSettings::default();
+        let now = Utc::now();
+
+        // Nothing mirrored yet
+        assert!(needs_full_sync(&ProfileSyncState::default(), now, &settings));
+
+        let state = ProfileSyncState {
+            watermark: Some(now - chrono::Duration::minutes(5)),
+            last_sync_at: Some(now - chrono::Duration::minutes(5)),
+            last_full_sync_at: Some(now - chrono::Duration::hours(2)),
+        };
+        assert!(!needs_full_sync(&state, now, &settings));
+
+        let overdue = ProfileSyncState { last_full_sync_at: Some(now - chrono::Duration::days(2)), ..state.clone() };
+        assert!(needs_full_sync(&overdue, now, &settings));
+    }
+
+    #[test]
+    fn test_mirror_freshness() {
+        let now = Utc::now();
+
+        assert!(within_lag(Some(now - chrono::Duration::minutes(10)), now, 900));
+        assert!(!within_lag(Some(now - chrono::Duration::minutes(20)), now, 900));
+        assert!(!within_lag(None, now, 900));
+    }
+}
@@ -0,0 +1,173 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use thiserror::Error;
+
+/// Profile length when the caller doesn't ask for one
+pub const DEFAULT_PROFILE_SECS: u64 = 30;
+
+/// Longest profile a caller may ask for
+pub const MAX_PROFILE_SECS: u64 = 120;
+
+/// Default sampling frequency in Hz (off the timer tick, to avoid aliasing)
+pub const DEFAULT_PROFILE_FREQUENCY: i32 = 99;
+
+/// The sampler is process-wide, so only one capture may run at a time
+static CAPTURING: AtomicBool = AtomicBool::new(false);
+
+/// Claim on [`CAPTURING`], released when dropped
+///
+/// It lives on the sampling thread, so the flag is released when sampling
+/// ends or panics, even if the request waiting for it was dropped.
+struct CaptureGuard;
+
+impl CaptureGuard {
+    fn acquire() -> Option<Self> {
+        (!CAPTURING.swap(true, Ordering::AcqRel)).then_some(Self)
+    }
+}
+
+impl Drop for CaptureGuard {
+    fn drop(&mut self) {
+        CAPTURING.store(false, Ordering::Release);
+    }
+}
+
+/// Errors that can occur while capturing a CPU profile
+#[derive(Debug, Error)]
+pub enum ProfilingError {
+    #[error("Built without the `profiling` feature")]
+    Disabled,
+
+    #[error("A profile is already being captured")]
+    Busy,
+
+    #[error("Profiler failed: {0}")]
+    Profiler(String),
+}
+
+/// Output of a CPU profile capture
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ProfileFormat {
+    /// Interactive SVG flamegraph
+    #[default]
+    Flamegraph,
+    /// pprof protobuf, for `go tool pprof` and similar
+    Pprof,
+}
+
+impl ProfileFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ProfileFormat::Flamegraph => "image/svg+xml",
+            ProfileFormat::Pprof => "application/octet-stream",
+        }
+    }
+}
+
+/// Whether this build can capture CPU profiles
+pub fn profiling_available() -> bool {
+    cfg!(feature = "profiling")
+}
+
+/// Sample the whole process's CPU stacks for `duration` and render them
+///
+/// Samples every thread, so a capture on a busy instance shows what all
+/// requests were doing. Sampling runs on a blocking thread; the async
+/// runtime keeps serving meanwhile.
+pub async fn capture_profile(duration: Duration, frequency: i32, format: ProfileFormat) -> Result<Vec<u8>, ProfilingError> {
+    if !profiling_available() {
+        return Err(ProfilingError::Disabled);
+    }
+
+    let guard = CaptureGuard::acquire().ok_or(ProfilingError::Busy)?;
+
+    tokio::task::spawn_blocking(move || {
+        let _guard = guard;
+        capture_blocking(duration, frequency, format)
+    })
+    .await
+    .map_err(|e| ProfilingError::Profiler(e.to_string()))
+    .and_then(|result| result)
+}
+
+#[cfg(feature = "profiling")]
+fn capture_blocking(duration: Duration, frequency: i32, format: ProfileFormat) -> Result<Vec<u8>, ProfilingError> {
+    let profiler_error = |e: pprof::Error| ProfilingError::Profiler(e.to_string());
+
+    let guard = pprof::ProfilerGuardBuilder::default()
+        .frequency(frequency.clamp(1, 1000))
+        .blocklist(&["libc", "libgcc", "pthread", "vdso"])
+        .build()
+        .map_err(profiler_error)?;
+
+    std::thread::sleep(duration);
+
+    let report = guard.report().build().map_err(profiler_error)?;
+    let mut body = Vec::new();
+    match format {
+        ProfileFormat::Flamegraph => report.flamegraph(&mut body).map_err(profiler_error)?,
+        ProfileFormat::Pprof => {
+            use pprof::protos::Message;
+
+            report
+                .pprof()
+                .map_err(profiler_error)?
+                .encode(&mut body)
+                .map_err(|e| ProfilingError::Profiler(e.to_string()))?;
+        }
+    }
+
+    Ok(body)
+}
+
+#[cfg(not(feature = "profiling"))]
+fn capture_blocking(_duration: Duration, _frequency: i32, _format: ProfileFormat) -> Result<Vec<u8>, ProfilingError> {
+    Err(ProfilingError::Disabled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(not(feature = "profiling"))]
+    #[tokio::test]
+    async fn test_disabled_without_feature() {
+        let result = capture_profile(Duration::from_millis(10), DEFAULT_PROFILE_FREQUENCY, ProfileFormat::Flamegraph).await;
+        assert!(matches!(result, Err(ProfilingError::Disabled)));
+    }
+
+    #[cfg(feature = "profiling")]
+    #[tokio::test]
+    async fn test_one_capture_at_a_time() {
+        let capture = || capture_profile(Duration::from_millis(200), DEFAULT_PROFILE_FREQUENCY, ProfileFormat::Flamegraph);
+
+        let (a, b) = tokio::join!(capture(), async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            capture().await
+        });
+        assert!(!a.unwrap().is_empty());
+        assert!(matches!(b, Err(ProfilingError::Busy)));
+
+        // Free again once the first one finished
+        let pprof = capture_profile(Duration::from_millis(50), DEFAULT_PROFILE_FREQUENCY, ProfileFormat::Pprof).await;
+        assert!(pprof.is_ok());
+
+        // The client went away mid-capture: sampling finishes in the
+        // background, then the sampler is free again
+        let abandoned = tokio::time::timeout(Duration::from_millis(20), capture()).await;
+        assert!(abandoned.is_err());
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        assert!(capture().await.is_ok());
+    }
+
+    // Captures are refused before claiming the flag without the feature,
+    // so this can't race the capture tests
+    #[cfg(not(feature = "profiling"))]
+    #[test]
+    fn test_capture_guard_releases_on_drop() {
+        let guard = CaptureGuard::acquire().unwrap();
+        assert!(CaptureGuard::acquire().is_none());
+        drop(guard);
+        assert!(CaptureGuard::acquire().is_some());
+    }
+}
@@ -0,0 +1,491 @@
+use crate::config::RealtimeSettings;
+use crate::core::ReverseGeocoder;
+use crate::models::{AppwriteEndpointStatus, MatchEvent, UserPreferences, UserProfile};
+use crate::services::appwrite::{AppwriteClient, AppwriteDocument, AppwriteError, ParseFailureStats};
+use crate::services::dev::is_candidate;
+use crate::services::store::{CandidatePage, ProfileStore};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::task::JoinHandle;
+
+/// Errors that end a Realtime subscription (it is then reconnected)
+#[derive(Debug, Error)]
+pub enum RealtimeError {
+    #[error("Built without the `realtime` feature")]
+    Disabled,
+
+    #[error("WebSocket error: {0}")]
+    WebSocket(String),
+
+    #[error("Failed to load profiles: {0}")]
+    Appwrite(#[from] AppwriteError),
+
+    #[error("Subscription closed by the server")]
+    Closed,
+}
+
+/// Size and freshness of the profile mirror
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MirrorStats {
+    /// Loaded and subscribed; queries are answered from memory
+    pub ready: bool,
+    pub profiles: usize,
+    /// Mirrored profiles by market ("" for profiles outside every city)
+    pub markets: BTreeMap<String, usize>,
+    /// Realtime changes applied since startup
+    pub updates: u64,
+    pub last_loaded_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Default)]
+struct MirrorState {
+    /// Active profiles by market, then user ID
+    by_market: HashMap<String, HashMap<String, UserProfile>>,
+    /// Market each mirrored user is filed under
+    market_of: HashMap<String, String>,
+    last_loaded_at: Option<DateTime<Utc>>,
+}
+
+/// In-memory copy of the active profiles in some markets
+///
+/// Loaded from Appwrite, then kept current by Realtime change events (see
+/// [`RealtimeSubscriber`]). Only answers while `ready`, i.e. loaded and
+/// subscribed; a dropped subscription may have missed changes.
+pub struct ProfileMirror {
+    state: RwLock<MirrorState>,
+    geocoder: ReverseGeocoder,
+    /// Mirrored markets (lowercase); empty mirrors every profile
+    markets: HashSet<String>,
+    ready: AtomicBool,
+    updates: AtomicU64,
+}
+
+impl ProfileMirror {
+    /// Mirror of `markets` (empty = everything); profiles without a market
+    /// get the one of the nearest city within `city_radius_km`
+    pub fn new(markets: &[String], city_radius_km: f64) -> Self {
+        Self {
+            state: RwLock::new(MirrorState::default()),
+            geocoder: ReverseGeocoder::new(city_radius_km),
+            markets: markets.iter().map(|market| market.to_lowercase()).collect(),
+            ready: AtomicBool::new(false),
+            updates: AtomicU64::new(0),
+        }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::Acquire)
+    }
+
+    fn set_ready(&self, ready: bool) {
+        self.ready.store(ready, Ordering::Release);
+    }
+
+    fn market(&self, profile: &UserProfile) -> String {
+        profile
+            .market
+            .clone()
+            .or_else(|| self.geocoder.locate(profile.latitude, profile.longitude).map(|city| city.market.clone()))
+            .unwrap_or_default()
+            .to_lowercase()
+    }
+
+    fn mirrors(&self, market: &str) -> bool {
+        self.markets.is_empty() || self.markets.contains(market)
+    }
+
+    /// Replace the contents with a full load and start answering queries
+    pub fn load(&self, profiles: Vec<UserProfile>) {
+        let mut state = MirrorState { last_loaded_at: Some(Utc::now()), ..Default::default() };
+        for profile in profiles {
+            let market = self.market(&profile);
+            if profile.is_active && self.mirrors(&market) {
+                state.market_of.insert(profile.user_id.clone(), market.clone());
+                state.by_market.entry(market).or_default().insert(profile.user_id.clone(), profile);
+            }
+        }
+
+        *self.state.write().unwrap() = state;
+        self.set_ready(true);
+    }
+
+    /// Add or replace a profile; deactivated ones and those moved out of the
+    /// mirrored markets are dropped
+    pub fn upsert(&self, profile: UserProfile) {
+        let market = self.market(&profile);
+        let mut state = self.state.write().unwrap();
+        remove_from(&mut state, &profile.user_id);
+        if profile.is_active && self.mirrors(&market) {
+            state.market_of.insert(profile.user_id.clone(), market.clone());
+            state.by_market.entry(market).or_default().insert(profile.user_id.clone(), profile);
+        }
+    }
+
+    pub fn remove(&self, user_id: &str) {
+        remove_from(&mut self.state.write().unwrap(), user_id);
+    }
+
+    /// A mirrored profile, when ready
+    pub fn get(&self, user_id: &str) -> Option<UserProfile> {
+        if !self.is_ready() {
+            return None;
+        }
+
+        let state = self.state.read().unwrap();
+        let market = state.market_of.get(user_id)?;
+        state.by_market.get(market)?.get(user_id).cloned()
+    }
+
+    /// Whether every candidate of a search is mirrored
+    ///
+    /// With only some markets mirrored, the search must have a location
+    /// inside a mirrored city, and every city its radius could reach must be
+    /// in a mirrored market too.
+    fn covers(&self, preferences: &UserPreferences) -> bool {
+        if self.markets.is_empty() {
+            return true;
+        }
+        if !preferences.has_location() {
+            return false;
+        }
+
+        let (lat, lon) = (preferences.latitude, preferences.longitude);
+        let reach_km = preferences.max_distance_km as f64 + self.geocoder.radius_km();
+        self.geocoder.locate(lat, lon).is_some_and(|city| self.mirrors(&city.market))
+            && self.geocoder.cities_within(lat, lon, reach_km).all(|city| self.mirrors(&city.market))
+    }
+
+    /// Candidates for a search, with the same pre-filters as the Appwrite
+    /// query; None when not ready or the search reaches unmirrored markets
+    pub fn query_candidates(
+        &self,
+        user_id: &str,
+        preferences: &UserPreferences,
+        exclude_ids: &HashSet<String>,
+        limit: usize,
+    ) -> Option<CandidatePage> {
+        if !self.is_ready() || !self.covers(preferences) {
+            return None;
+        }
+
+        let state = self.state.read().unwrap();
+        let profiles: Vec<UserProfile> = state
+            .by_market
+            .values()
+            .flat_map(|profiles| profiles.values())
+            .filter(|profile| is_candidate(profile, user_id, preferences, exclude_ids))
+            .take(limit)
+            .cloned()
+            .collect();
+
+        Some(profiles.into())
+    }
+
+    /// Apply one Realtime message; other message types are ignored
+    pub fn apply_message(&self, text: &str) -> Result<(), serde_json::Error> {
+        let message: RealtimeMessage = serde_json::from_str(text)?;
+        match message.kind.as_str() {
+            "event" => {
+                let event: RealtimeEvent = serde_json::from_value(message.data)?;
+                if event.events.iter().any(|name| name.ends_with(".delete")) {
+                    if let Some(user_id) = event.payload.get("userId").and_then(Value::as_str) {
+                        self.remove(user_id);
+                    }
+                } else {
+                    let profile = UserProfile::from(AppwriteDocument::<UserProfile>::from_value(&event.payload)?);
+                    self.upsert(profile);
+                }
+                self.updates.fetch_add(1, Ordering::Relaxed);
+            }
+            "error" => tracing::warn!("Realtime error: {}", message.data),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    pub fn stats(&self) -> MirrorStats {
+        let state = self.state.read().unwrap();
+        MirrorStats {
+            ready: self.is_ready(),
+            profiles: state.market_of.len(),
+            markets: state.by_market.iter().map(|(market, profiles)| (market.clone(), profiles.len())).collect(),
+            updates: self.updates.load(Ordering::Relaxed),
+            last_loaded_at: state.last_loaded_at,
+        }
+    }
+}
+
+fn remove_from(state: &mut MirrorState, user_id: &str) {
+    if let Some(market) = state.market_of.remove(user_id) {
+        if let Some(profiles) = state.by_market.get_mut(&market) {
+            profiles.remove(user_id);
+        }
+    }
+}
+
+/// A message on the Realtime socket (`connected`, `event`, `error`, ...)
+#[derive(Deserialize)]
+struct RealtimeMessage {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    data: Value,
+}
+
+/// A document change; `events` holds names like
+/// `databases.*.collections.*.documents.*.update`
+#[derive(Deserialize)]
+struct RealtimeEvent {
+    #[serde(default)]
+    events: Vec<String>,
+    #[serde(default)]
+    payload: Value,
+}
+
+/// Keeps a [`ProfileMirror`] current from an Appwrite Realtime subscription
+///
+/// Each connection subscribes first and then reloads the mirror, so no
+/// change between the two is lost. While disconnected the mirror is not
+/// ready and queries go to Appwrite.
+pub struct RealtimeSubscriber {
+    appwrite: Arc<AppwriteClient>,
+    mirror: Arc<ProfileMirror>,
+    settings: RealtimeSettings,
+}
+
+impl RealtimeSubscriber {
+    pub fn new(appwrite: Arc<AppwriteClient>, mirror: Arc<ProfileMirror>, settings: RealtimeSettings) -> Self {
+        Self { appwrite, mirror, settings }
+    }
+
+    /// Subscribe in the background, reconnecting whenever the socket drops
+    ///
+    /// None when built without the `realtime` feature.
+    pub fn spawn(self) -> Option<JoinHandle<()>> {
+        if !cfg!(feature = "realtime") {
+            tracing::error!("{}; candidates are read from Appwrite", RealtimeError::Disabled);
+            return None;
+        }
+
+        Some(tokio::spawn(async move {
+            loop {
+                if let Err(e) = self.subscribe().await {
+                    tracing::warn!("Realtime subscription ended, reconnecting: {}", e);
+                }
+                self.mirror.set_ready(false);
+                tokio::time::sleep(Duration::from_secs(self.settings.reconnect_secs.max(1))).await;
+            }
+        }))
+    }
+
+    #[cfg(feature = "realtime")]
+    async fn subscribe(&self) -> Result<(), RealtimeError> {
+        use futures_util::{SinkExt, StreamExt};
+        use tokio_tungstenite::tungstenite::Message;
+
+        let websocket_error = |e: tokio_tungstenite::tungstenite::Error| RealtimeError::WebSocket(e.to_string());
+
+        let (mut socket, _) = tokio_tungstenite::connect_async(self.appwrite.realtime_url())
+            .await
+            .map_err(websocket_error)?;
+        if let Some(session) = &self.settings.session {
+            let authentication = serde_json::json!({ "type": "authentication", "data": { "session": session } });
+            socket.send(Message::Text(authentication.to_string().into())).await.map_err(websocket_error)?;
+        }
+
+        // Changes arriving during the load queue on the socket and are
+        // applied after it
+        let profiles = self.appwrite.list_active_profiles(self.settings.page_size).await?;
+        let loaded = profiles.len();
+        self.mirror.load(profiles);
+        tracing::info!("Profile mirror loaded {} active profiles, following Realtime changes", loaded);
+
+        // Appwrite drops connections that stay silent
+        let mut heartbeat = tokio::time::interval(Duration::from_secs(20));
+        loop {
+            tokio::select! {
+                message = socket.next() => match message {
+                    Some(Ok(Message::Text(text))) => {
+                        if let Err(e) = self.mirror.apply_message(&text) {
+                            tracing::warn!("Skipping Realtime message that failed to parse: {}", e);
+                        }
+                    }
+                    Some(Ok(Message::Close(_))) | None => return Err(RealtimeError::Closed),
+                    Some(Ok(_)) => {}
+                    Some(Err(e)) => return Err(websocket_error(e)),
+                },
+                _ = heartbeat.tick() => {
+                    socket.send(Message::Text(r#"{"type":"ping"}"#.to_string().into())).await.map_err(websocket_error)?;
+                }
+            }
+        }
+    }
+
+    #[cfg(not(feature = "realtime"))]
+    async fn subscribe(&self) -> Result<(), RealtimeError> {
+        Err(RealtimeError::Disabled)
+    }
+}
+
+/// Profile store answering candidate and profile lookups from a
+/// [`ProfileMirror`] when it can, and from `inner` otherwise
+pub struct MirroredProfileStore {
+    inner: Arc<dyn ProfileStore>,
+    mirror: Arc<ProfileMirror>,
+}
+
+impl MirroredProfileStore {
+    pub fn new(inner: Arc<dyn ProfileStore>, mirror: Arc<ProfileMirror>) -> Self {
+        Self { inner, mirror }
+    }
+
+    pub fn mirror(&self) -> &Arc<ProfileMirror> {
+        &self.mirror
+    }
+}
+
+#[async_trait]
+impl ProfileStore for MirroredProfileStore {
+    async fn get_preferences(&self, user_id: &str) -> Result<UserPreferences, AppwriteError> {
+        self.inner.get_preferences(user_id).await
+    }
+
+    async fn query_candidates(
+        &self,
+        user_id: &str,
+        preferences: &UserPreferences,
+        exclude_ids: &HashSet<String>,
+        limit: usize,
+    ) -> Result<CandidatePage, AppwriteError> {
+        match self.mirror.query_candidates(user_id, preferences, exclude_ids, limit) {
+            Some(page) => Ok(page),
+            None => self.inner.query_candidates(user_id, preferences, exclude_ids, limit).await,
+        }
+    }
+
+    async fn get_profile(&self, user_id: &str) -> Result<UserProfile, AppwriteError> {
+        match self.mirror.get(user_id) {
+            Some(profile) => Ok(profile),
+            None => self.inner.get_profile(user_id).await,
+        }
+    }
+
+    async fn get_profiles(&self, user_ids: &[String]) -> Result<Vec<UserProfile>, AppwriteError> {
+        self.inner.get_profiles(user_ids).await
+    }
+
+    async fn list_events_since(&self, since: DateTime<Utc>, max_events: usize) -> Result<Vec<MatchEvent>, AppwriteError> {
+        self.inner.list_events_since(since, max_events).await
+    }
+
+    async fn record_event(&self, event: MatchEvent) -> Result<(), AppwriteError> {
+        self.inner.record_event(event).await
+    }
+
+    async fn check_auth(&self) -> Result<(), AppwriteError> {
+        self.inner.check_auth().await
+    }
+
+    fn endpoint_status(&self) -> Option<AppwriteEndpointStatus> {
+        self.inner.endpoint_status()
+    }
+
+    fn parse_failures(&self) -> Option<ParseFailureStats> {
+        self.inner.parse_failures()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{preferences, profile, MockProfileStore};
+
+    fn located(user_id: &str, lat: f64, lon: f64) -> UserProfile {
+        UserProfile { latitude: lat, longitude: lon, ..profile(user_id, "female", 28) }
+    }
+
+    fn change(event: &str, profile: &UserProfile) -> String {
+        serde_json::json!({
+            "type": "event",
+            "data": {
+                "events": [format!("databases.db.collections.profiles.documents.{}.{}", profile.user_id, event)],
+                "payload": profile,
+            }
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn test_mirror_follows_changes() {
+        let mirror = ProfileMirror::new(&[], 50.0);
+        let seeker = preferences("alice", &["female"], 25, 35);
+        let none = HashSet::new();
+        assert!(mirror.query_candidates("alice", &seeker, &none, 10).is_none());
+
+        mirror.load(vec![located("beth", 52.52, 13.40), UserProfile { is_active: false, ..located("carla", 52.5, 13.4) }]);
+        assert_eq!(mirror.stats().profiles, 1);
+        assert_eq!(mirror.query_candidates("alice", &seeker, &none, 10).unwrap().profiles.len(), 1);
+
+        let dana = located("dana", 52.53, 13.41);
+        mirror.apply_message(&change("create", &dana)).unwrap();
+        assert_eq!(mirror.query_candidates("alice", &seeker, &none, 10).unwrap().profiles.len(), 2);
+
+        mirror.apply_message(&change("update", &UserProfile { is_active: false, ..dana.clone() })).unwrap();
+        assert!(mirror.get("dana").is_none());
+
+        mirror.apply_message(&change("delete", &located("beth", 52.52, 13.40))).unwrap();
+        assert!(mirror.query_candidates("alice", &seeker, &none, 10).unwrap().profiles.is_empty());
+        assert_eq!(mirror.stats().updates, 3);
+
+        mirror.apply_message(r#"{"type":"connected","data":{"channels":[]}}"#).unwrap();
+        assert!(mirror.apply_message("not json").is_err());
+    }
+
+    #[test]
+    fn test_mirror_only_answers_searches_it_covers() {
+        let mirror = ProfileMirror::new(&["DE".to_string()], 50.0);
+        // Berlin, and a profile in Paris
+        mirror.load(vec![located("beth", 52.52, 13.40), located("odile", 48.86, 2.35)]);
+        assert_eq!(mirror.stats().markets.get("de"), Some(&1));
+        assert!(mirror.get("odile").is_none());
+
+        let none = HashSet::new();
+        let berlin = UserPreferences { latitude: 52.5, longitude: 13.4, max_distance_km: 50, ..preferences("alice", &["female"], 25, 35) };
+        assert_eq!(mirror.query_candidates("alice", &berlin, &none, 10).unwrap().profiles.len(), 1);
+
+        // A Paris search, or one reaching past the border, goes to Appwrite
+        let paris = UserPreferences { latitude: 48.86, longitude: 2.35, ..berlin.clone() };
+        assert!(mirror.query_candidates("alice", &paris, &none, 10).is_none());
+        let wide = UserPreferences { max_distance_km: 1000, ..berlin.clone() };
+        assert!(mirror.query_candidates("alice", &wide, &none, 10).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_store_falls_back_until_ready() {
+        let inner = Arc::new(MockProfileStore::new(
+            vec![profile("alice", "male", 30), located("beth", 52.52, 13.40)],
+            vec![preferences("alice", &["female"], 25, 35)],
+        ));
+        let mirror = Arc::new(ProfileMirror::new(&[], 50.0));
+        let store = MirroredProfileStore::new(inner, mirror.clone());
+        let seeker = preferences("alice", &["female"], 25, 35);
+
+        let page = store.query_candidates("alice", &seeker, &HashSet::new(), 10).await.unwrap();
+        assert_eq!(page.profiles.len(), 1);
+
+        // Once loaded, the mirror answers, even without the store's data
+        mirror.load(vec![located("carla", 52.5, 13.4), located("dana", 52.5, 13.4)]);
+        let page = store.query_candidates("alice", &seeker, &HashSet::new(), 10).await.unwrap();
+        assert_eq!(page.profiles.len(), 2);
+        assert_eq!(store.get_profile("carla").await.unwrap().user_id, "carla");
+        assert_eq!(store.get_profile("alice").await.unwrap().user_id, "alice");
+    }
+}
@@ -0,0 +1,303 @@
+use crate::config::{HubCity, SeedSettings};
+use crate::services::{AppwriteClient, AppwriteError};
+use crate::util::rng;
+use futures_util::stream::{self, StreamExt};
+use rand::distributions::WeightedIndex;
+use rand::prelude::*;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+const NAMES: &[&str] = &[
+    "Alex", "Jordan", "Taylor", "Morgan", "Casey", "Riley", "Quinn", "Avery",
+    "Blake", "Carter", "Dakota", "Emerson", "Finley", "Gray", "Hayden", "Indigo",
+    "Jade", "Kai", "Lake", "Milo", "Nova", "Onyx", "Phoenix", "River", "Sage",
+    "Skyler", "Tatum", "Unity", "Valentine", "Willow", "Xavier", "Zion", "Luna",
+    "Max", "Sam", "Charlie", "Drew", "Ellis", "Frankie", "Grayson", "Harper", "Ivy",
+];
+
+const HAIR_COLORS: &[&str] = &["blonde", "brown", "black", "white", "red", "gray", "other"];
+
+const SPORTS: &[&str] = &[
+    "basketball", "football", "tennis", "cycling", "running", "swimming", "yoga",
+    "martial_arts", "dancing", "hiking", "gym", "climbing", "skiing", "surfing",
+    "boxing", "golf", "baseball", "soccer", "volleyball", "skating", "badminton",
+];
+
+/// Kilometres per degree of latitude
+const KM_PER_DEGREE: f64 = 111.32;
+
+/// One generated user: their profile and preferences documents
+#[derive(Debug, Clone)]
+pub struct SeedUser {
+    pub user_id: String,
+    pub profile: Value,
+    pub preferences: Value,
+}
+
+/// Outcome of a seed or purge run
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SeedReport {
+    pub profiles: usize,
+    pub preferences: usize,
+    pub failed: usize,
+}
+
+/// Generates load-test users from [`SeedSettings`]
+pub struct SeedGenerator {
+    settings: SeedSettings,
+    genders: Vec<String>,
+    gender_index: WeightedIndex<f64>,
+    rng: StdRng,
+}
+
+impl SeedGenerator {
+    /// Fails when no gender has a positive weight
+    pub fn new(settings: SeedSettings) -> Result<Self, rand::distributions::WeightedError> {
+        let genders = settings.genders.keys().cloned().collect();
+        let gender_index = WeightedIndex::new(settings.genders.values().copied())?;
+        let rng = rng::seeded_or_entropy(settings.rng_seed);
+
+        Ok(Self { settings, genders, gender_index, rng })
+    }
+
+    /// The `index`-th seeded user (`{user_id_prefix}{index:06}`)
+    pub fn user(&mut self, index: usize) -> SeedUser {
+        let user_id = format!("{}{:06}", self.settings.user_id_prefix, index);
+        let rng = &mut self.rng;
+
+        let min_age = self.settings.min_age.max(18);
+        let age = rng.gen_range(min_age..=self.settings.max_age.max(min_age));
+        let height_cm: u16 = rng.gen_range(150..=210);
+        let gender = self.genders[self.gender_index.sample(rng)].clone();
+        let (lat, lon, city) = match self.settings.cities.choose(rng) {
+            Some(city) => scatter(city, rng),
+            None => (0.0, 0.0, "nowhere".to_string()),
+        };
+        let sports = sample(SPORTS, rng.gen_range(1..=5), rng);
+
+        let profile = json!({
+            "userId": user_id,
+            "name": format!("{} {}", NAMES.choose(rng).unwrap(), index),
+            "age": age,
+            "heightCm": height_cm,
+            "hairColor": HAIR_COLORS.choose(rng).unwrap(),
+            "gender": gender,
+            "latitude": lat,
+            "longitude": lon,
+            "isActive": true,
+            "isVerified": rng.gen_bool(self.settings.verified_fraction.clamp(0.0, 1.0)),
+            "isTimeout": false,
+            "sportsPreferences": sports,
+            "description": format!("Load-test profile from {}", city),
+            "imageFileIds": [],
+        });
+
+        // Mostly the other binary gender (plus non-binary), sometimes anyone
+        let preferred_genders: Vec<String> = match gender.as_str() {
+            "male" if rng.gen_bool(0.8) => vec!["female".to_string(), "non_binary".to_string()],
+            "female" if rng.gen_bool(0.8) => vec!["male".to_string(), "non_binary".to_string()],
+            _ => self.genders.clone(),
+        };
+        let min_distance_km = self.settings.min_distance_km.max(1);
+        let max_distance_km = rng.gen_range(min_distance_km..=self.settings.max_distance_km.max(min_distance_km));
+
+        let preferences = json!({
+            "userId": user_id,
+            "preferredGenders": preferred_genders,
+            "minAge": age.saturating_sub(5).max(18),
+            "maxAge": age.saturating_add(10).min(99),
+            "minHeightCm": height_cm.saturating_sub(15).max(140),
+            "maxHeightCm": (height_cm + 20).min(230),
+            "preferredHairColors": sample(HAIR_COLORS, rng.gen_range(2..=5), rng),
+            "preferredSports": sample(SPORTS, rng.gen_range(3..=8), rng),
+            "maxDistanceKm": max_distance_km,
+            "latitude": lat,
+            "longitude": lon,
+        });
+
+        SeedUser { user_id, profile, preferences }
+    }
+}
+
+/// A point uniformly distributed within the city's radius
+fn scatter(city: &HubCity, rng: &mut StdRng) -> (f64, f64, String) {
+    let distance_km = city.radius_km.max(0.0) * rng.gen::<f64>().sqrt();
+    let bearing = rng.gen_range(0.0..std::f64::consts::TAU);
+    let lat = city.lat + distance_km * bearing.cos() / KM_PER_DEGREE;
+    let lon = city.lon + distance_km * bearing.sin() / (KM_PER_DEGREE * city.lat.to_radians().cos().max(0.01));
+
+    (lat, lon, city.name.clone())
+}
+
+fn sample(options: &[&str], count: usize, rng: &mut StdRng) -> Vec<String> {
+    options
+        .choose_multiple(rng, count.min(options.len()))
+        .map(|s| s.to_string())
+        .collect()
+}
+
+/// Writes seeded users to Appwrite and removes them again
+pub struct Seeder {
+    appwrite: Arc<AppwriteClient>,
+    settings: SeedSettings,
+}
+
+impl Seeder {
+    pub fn new(appwrite: Arc<AppwriteClient>, settings: SeedSettings) -> Self {
+        Self { appwrite, settings }
+    }
+
+    /// Create `settings.count` profiles with their preferences
+    ///
+    /// Documents are keyed by user ID, so re-running with the same prefix
+    /// fails on the existing ones instead of duplicating them.
+    pub async fn seed(&self) -> Result<SeedReport, rand::distributions::WeightedError> {
+        let mut generator = SeedGenerator::new(self.settings.clone())?;
+        let users: Vec<SeedUser> = (0..self.settings.count).map(|i| generator.user(i)).collect();
+        let collections = self.appwrite.collections();
+
+        let results: Vec<(bool, bool)> = stream::iter(users)
+            .map(|user| async move {
+                let profile = self
+                    .appwrite
+                    .create_document(&collections.user_profiles, &user.user_id, &user.profile)
+                    .await;
+                let preferences = self
+                    .appwrite
+                    .create_document(&collections.user_preferences, &user.user_id, &user.preferences)
+                    .await;
+
+                for e in [&profile, &preferences].into_iter().filter_map(|r| r.as_ref().err()) {
+                    tracing::warn!("Failed to seed {}: {}", user.user_id, e);
+                }
+                (profile.is_ok(), preferences.is_ok())
+            })
+            .buffer_unordered(self.settings.concurrency.max(1))
+            .collect()
+            .await;
+
+        Ok(tally(&results))
+    }
+
+    /// Delete every profile and preferences document with a seeded user ID
+    pub async fn purge(&self) -> Result<SeedReport, AppwriteError> {
+        let collections = self.appwrite.collections();
+        let profiles = self.purge_collection(&collections.user_profiles).await?;
+        let preferences = self.purge_collection(&collections.user_preferences).await?;
+
+        Ok(SeedReport {
+            profiles: profiles.0,
+            preferences: preferences.0,
+            failed: profiles.1 + preferences.1,
+        })
+    }
+
+    /// (deleted, failed) in one collection
+    async fn purge_collection(&self, collection: &str) -> Result<(usize, usize), AppwriteError> {
+        let mut deleted = 0;
+        let mut failed = 0;
+
+        loop {
+            let ids = self
+                .appwrite
+                .list_document_ids_with_prefix(collection, "userId", &self.settings.user_id_prefix)
+                .await?;
+            if ids.is_empty() {
+                break;
+            }
+
+            let results: Vec<bool> = stream::iter(ids)
+                .map(|id| async move {
+                    let result = self.appwrite.delete_document(collection, &id).await;
+                    if let Err(e) = &result {
+                        tracing::warn!("Failed to purge {}: {}", id, e);
+                    }
+                    result.is_ok()
+                })
+                .buffer_unordered(self.settings.concurrency.max(1))
+                .collect()
+                .await;
+
+            let page_deleted = results.iter().filter(|ok| **ok).count();
+            deleted += page_deleted;
+            failed += results.len() - page_deleted;
+
+            // The same page would come back forever
+            if page_deleted == 0 {
+                break;
+            }
+        }
+
+        Ok((deleted, failed))
+    }
+}
+
+fn tally(results: &[(bool, bool)]) -> SeedReport {
+    SeedReport {
+        profiles: results.iter().filter(|(profile, _)| *profile).count(),
+        preferences: results.iter().filter(|(_, preferences)| *preferences).count(),
+        failed: results.iter().filter(|(profile, preferences)| !(*profile && *preferences)).count(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::haversine_distance;
+    use crate::models::{UserPreferences, UserProfile};
+
+    fn settings() -> SeedSettings {
+        SeedSettings { rng_seed: Some(7), ..SeedSettings::default() }
+    }
+
+    #[test]
+    fn test_seeded_users_are_reproducible() {
+        let mut a = SeedGenerator::new(settings()).unwrap();
+        let mut b = SeedGenerator::new(settings()).unwrap();
+
+        for i in 0..20 {
+            let (a, b) = (a.user(i), b.user(i));
+            assert_eq!(a.profile, b.profile);
+            assert_eq!(a.preferences, b.preferences);
+        }
+    }
+
+    #[test]
+    fn test_seeded_users_parse_and_respect_settings() {
+        let settings = settings();
+        let mut generator = SeedGenerator::new(settings.clone()).unwrap();
+
+        for i in 0..200 {
+            let user = generator.user(i);
+            assert!(user.user_id.starts_with(&settings.user_id_prefix));
+
+            let profile: UserProfile = serde_json::from_value(user.profile).unwrap();
+            let preferences: UserPreferences = serde_json::from_value(user.preferences).unwrap();
+            assert!((settings.min_age..=settings.max_age).contains(&profile.age));
+            assert!(settings.genders.contains_key(&profile.gender));
+            assert!((settings.min_distance_km..=settings.max_distance_km).contains(&preferences.max_distance_km));
+
+            let nearest_city_km = settings
+                .cities
+                .iter()
+                .map(|city| haversine_distance(city.lat, city.lon, profile.latitude, profile.longitude))
+                .fold(f64::MAX, f64::min);
+            assert!(nearest_city_km <= 10.5, "{} km from any city", nearest_city_km);
+        }
+    }
+
+    #[test]
+    fn test_gender_weights() {
+        let settings = SeedSettings {
+            genders: [("female".to_string(), 1.0), ("male".to_string(), 0.0)].into(),
+            ..settings()
+        };
+        let mut generator = SeedGenerator::new(settings).unwrap();
+
+        assert!((0..50).all(|i| generator.user(i).profile["gender"] == "female"));
+
+        let none = SeedSettings { genders: Default::default(), ..SeedSettings::default() };
+        assert!(SeedGenerator::new(none).is_err());
+    }
+}
@@ -0,0 +1,157 @@
+use crate::config::ShadowSettings;
+use crate::core::{compare_rankings, Matcher, RankingDiff};
+use crate::models::{ScoredMatch, UserPreferences, UserProfile};
+use rand::Rng;
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+
+/// Shadow evaluation metrics for /metrics
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ShadowStats {
+    pub evaluations: u64,
+    /// Mean over evaluations that had at least two profiles in common
+    pub mean_kendall_tau: Option<f64>,
+    pub mean_top10_overlap: Option<f64>,
+    pub last_diff: Option<RankingDiff>,
+}
+
+#[derive(Debug, Default)]
+struct ShadowTotals {
+    evaluations: u64,
+    tau_sum: f64,
+    tau_count: u64,
+    overlap_sum: f64,
+    last_diff: Option<RankingDiff>,
+}
+
+/// Ranks served candidates with a second matcher configuration and records
+/// how the rankings differ
+///
+/// Runs on the blocking pool after the live result is computed, so it never
+/// delays or changes the response.
+pub struct ShadowEvaluator {
+    matcher: Matcher,
+    sample_rate: f64,
+    totals: Mutex<ShadowTotals>,
+}
+
+impl ShadowEvaluator {
+    /// Build the shadow matcher from the live one; None when disabled
+    pub fn from_settings(live: &Matcher, settings: &ShadowSettings) -> Option<Self> {
+        if !settings.enabled {
+            return None;
+        }
+
+        let mut builder = Matcher::builder()
+            .weights(settings.weights.apply(live.weights()))
+            .distance_formula(settings.distance_formula.unwrap_or(live.distance_formula()))
+            .min_score(settings.min_score.unwrap_or(live.min_score()));
+        if let Some(max_results) = live.max_results() {
+            builder = builder.max_results(max_results);
+        }
+        let matcher = builder
+            .dedupe(live.dedupe())
+            .freshness(live.freshness(), live.new_profile_days())
+            .relationship_goals(live.relationship_goals().clone())
+            .attribute_scorers(live.attribute_scorers().clone())
+            .shuffle_bands(live.shuffle_band_width())
+            .geofences(live.geofences().clone())
+            .calibration(live.calibration().clone())
+            .build();
+
+        tracing::info!(
+            "Shadow scoring enabled for {:.0}% of feeds with weights: {:?}",
+            settings.sample_rate.clamp(0.0, 1.0) * 100.0,
+            matcher.weights()
+        );
+
+        Some(Self {
+            matcher,
+            sample_rate: settings.sample_rate.clamp(0.0, 1.0),
+            totals: Mutex::new(ShadowTotals::default()),
+        })
+    }
+
+    /// Whether this feed generation should also be scored in shadow
+    pub fn sampled(&self) -> bool {
+        self.sample_rate >= 1.0 || rand::thread_rng().gen_bool(self.sample_rate)
+    }
+
+    /// Score the candidates in shadow and record the diff against `served`
+    pub fn evaluate(
+        self: &Arc<Self>,
+        preferences: UserPreferences,
+        candidates: Vec<UserProfile>,
+        limit: usize,
+        served: Vec<ScoredMatch>,
+    ) {
+        let evaluator = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let shadow = evaluator.matcher.find_matches(&preferences, candidates, limit);
+            let diff = compare_rankings(&served, &shadow.matches);
+
+            tracing::info!(
+                target: "shadow",
+                user_id = %preferences.user_id,
+                served = diff.served,
+                shadow = diff.shadow,
+                common = diff.common,
+                kendall_tau = ?diff.kendall_tau,
+                top10_overlap = diff.top10_overlap,
+                "Shadow ranking diff"
+            );
+
+            evaluator.record(diff);
+        });
+    }
+
+    fn record(&self, diff: RankingDiff) {
+        let mut totals = self.totals.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        totals.evaluations += 1;
+        totals.overlap_sum += diff.top10_overlap;
+        if let Some(tau) = diff.kendall_tau {
+            totals.tau_sum += tau;
+            totals.tau_count += 1;
+        }
+        totals.last_diff = Some(diff);
+    }
+
+    /// Evaluation count, mean agreement and the most recent diff
+    pub fn stats(&self) -> ShadowStats {
+        let totals = self.totals.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        ShadowStats {
+            evaluations: totals.evaluations,
+            mean_kendall_tau: (totals.tau_count > 0).then(|| totals.tau_sum / totals.tau_count as f64),
+            mean_top10_overlap: (totals.evaluations > 0).then(|| totals.overlap_sum / totals.evaluations as f64),
+            last_diff: totals.last_diff,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disabled_by_default() {
+        assert!(ShadowEvaluator::from_settings(&Matcher::default(), &ShadowSettings::default()).is_none());
+    }
+
+    #[test]
+    fn test_stats_means() {
+        let settings = ShadowSettings { enabled: true, ..Default::default() };
+        let evaluator = ShadowEvaluator::from_settings(&Matcher::default(), &settings).unwrap();
+        assert!(evaluator.sampled());
+        assert!(evaluator.stats().mean_top10_overlap.is_none());
+
+        let diff = |tau, overlap| RankingDiff { served: 10, shadow: 10, common: 10, kendall_tau: tau, top10_overlap: overlap };
+        evaluator.record(diff(Some(1.0), 1.0));
+        evaluator.record(diff(Some(0.5), 0.5));
+        evaluator.record(diff(None, 0.0));
+
+        let stats = evaluator.stats();
+        assert_eq!(stats.evaluations, 3);
+        assert_eq!(stats.mean_kendall_tau, Some(0.75));
+        assert_eq!(stats.mean_top10_overlap, Some(0.5));
+    }
+}
@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tokio::sync::watch;
+
+/// Coalesces concurrent calls with the same key into one execution
+///
+/// The first caller for a key (the leader) runs the work; callers arriving
+/// while it is in flight wait for and share its result. Nothing is cached:
+/// the key is released as soon as the leader finishes. If the leader is
+/// cancelled (e.g. its client disconnected), waiting callers run the work
+/// themselves.
+pub struct Singleflight<K, V> {
+    in_flight: Mutex<HashMap<K, watch::Receiver<Option<V>>>>,
+    coalesced: AtomicU64,
+}
+
+impl<K, V> Default for Singleflight<K, V> {
+    fn default() -> Self {
+        Self {
+            in_flight: Mutex::new(HashMap::new()),
+            coalesced: AtomicU64::new(0),
+        }
+    }
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> Singleflight<K, V> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `work` for `key`, or wait for the run already in flight
+    pub async fn run<F, Fut>(&self, key: K, work: F) -> V
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = V>,
+    {
+        let leader = {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            match in_flight.get(&key) {
+                Some(rx) => Err(rx.clone()),
+                None => {
+                    let (tx, rx) = watch::channel(None);
+                    in_flight.insert(key.clone(), rx);
+                    Ok(tx)
+                }
+            }
+        };
+
+        let tx = match leader {
+            Ok(tx) => tx,
+            Err(mut rx) => {
+                if let Ok(value) = rx.wait_for(Option::is_some).await {
+                    self.coalesced.fetch_add(1, Ordering::Relaxed);
+                    return value.clone().expect("waited for a value");
+                }
+                // The leader was dropped before finishing
+                return work().await;
+            }
+        };
+
+        // Release the key even if this future is dropped mid-flight
+        let _release = Release { flights: self, key: &key, tx: &tx };
+
+        let value = work().await;
+        tx.send_replace(Some(value.clone()));
+        value
+    }
+
+    /// Calls served by another call's execution
+    pub fn coalesced(&self) -> u64 {
+        self.coalesced.load(Ordering::Relaxed)
+    }
+
+    /// Whether a run for `key` is in flight
+    pub fn is_running(&self, key: &K) -> bool {
+        self.in_flight.lock().unwrap().contains_key(key)
+    }
+
+    /// Keys currently in flight
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.lock().unwrap().len()
+    }
+}
+
+struct Release<'a, K: Eq + Hash, V> {
+    flights: &'a Singleflight<K, V>,
+    key: &'a K,
+    tx: &'a watch::Sender<Option<V>>,
+}
+
+impl<K: Eq + Hash, V> Drop for Release<'_, K, V> {
+    fn drop(&mut self) {
+        let mut in_flight = self.flights.in_flight.lock().unwrap();
+        if in_flight.get(self.key).is_some_and(|rx| rx.same_channel(&self.tx.subscribe())) {
+            in_flight.remove(self.key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_concurrent_calls_share_one_run() {
+        let flights = Arc::new(Singleflight::<String, usize>::new());
+        let runs = Arc::new(AtomicUsize::new(0));
+
+        let calls = (0..5).map(|_| {
+            let flights = flights.clone();
+            let runs = runs.clone();
+            tokio::spawn(async move {
+                flights
+                    .run("alice".to_string(), || async {
+                        tokio::time::sleep(Duration::from_millis(50)).await;
+                        runs.fetch_add(1, Ordering::SeqCst) + 1
+                    })
+                    .await
+            })
+        });
+
+        for call in futures_util::future::join_all(calls).await {
+            assert_eq!(call.unwrap(), 1);
+        }
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+        assert_eq!(flights.coalesced(), 4);
+        assert_eq!(flights.in_flight(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_sequential_and_distinct_calls_run_separately() {
+        let flights = Singleflight::<&str, &str>::new();
+
+        assert_eq!(flights.run("a", || async { "first" }).await, "first");
+        assert_eq!(flights.run("a", || async { "second" }).await, "second");
+
+        let (a, b) = tokio::join!(
+            flights.run("a", || async { "a" }),
+            flights.run("b", || async { "b" }),
+        );
+        assert_eq!((a, b), ("a", "b"));
+        assert_eq!(flights.coalesced(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_leader_releases_waiters() {
+        let flights = Arc::new(Singleflight::<&str, &str>::new());
+
+        let leader = tokio::spawn({
+            let flights = flights.clone();
+            async move {
+                flights
+                    .run("a", || async {
+                        tokio::time::sleep(Duration::from_secs(60)).await;
+                        "leader"
+                    })
+                    .await
+            }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        let follower = tokio::spawn({
+            let flights = flights.clone();
+            async move { flights.run("a", || async { "follower" }).await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        leader.abort();
+
+        assert_eq!(follower.await.unwrap(), "follower");
+        assert_eq!(flights.in_flight(), 0);
+    }
+}
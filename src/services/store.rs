@@ -0,0 +1,492 @@
+use crate::models::{AppwriteEndpointStatus, MatchEvent, UserPreferences, UserProfile};
+use crate::services::appwrite::{AppwriteClient, AppwriteError, ParseFailureStats};
+use crate::services::cache::{self, CacheError, CacheManager, CacheStats, SharedError, SharedRecompute};
+use crate::services::Singleflight;
+use crate::services::postgres::{AuditEntry, AuditFilter, EventType, MatchActivity, MatchRun, PoolStats, PostgresClient, PostgresError, ProfileView, ReceivedLike, RecordedEvent, SeenProfile, SeenStats};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::future::Future;
+use std::sync::Arc;
+
+/// Storage for seen profiles and swipe history
+///
+/// Implemented by [`PostgresClient`] in production and by the SQLite store
+/// in the `--dev` profile. Both are sqlx-backed and share `PostgresError`.
+#[async_trait]
+pub trait SeenStore: Send + Sync {
+    /// Record that a user has seen a profile now, replacing any earlier event for the pair
+    async fn record_seen(&self, user_id: &str, target_user_id: &str, event_type: EventType) -> Result<(), PostgresError> {
+        self.record_seen_at(user_id, target_user_id, event_type, Utc::now()).await
+    }
+
+    /// Record an event that occurred at `occurred_at`
+    ///
+    /// Replaces an earlier event for the pair but never a later one, so a
+    /// swipe queued offline can't overwrite what happened since. A like is
+    /// also kept apart for [`get_likes_received`](Self::get_likes_received),
+    /// so replacing it here doesn't withdraw it.
+    async fn record_seen_at(
+        &self,
+        user_id: &str,
+        target_user_id: &str,
+        event_type: EventType,
+        occurred_at: DateTime<Utc>,
+    ) -> Result<(), PostgresError>;
+
+    /// Record a match event: its history entry, the pair's seen record and
+    /// any mutual match it completes, all or nothing
+    ///
+    /// Stores without an event history (and without transactions) just
+    /// record the seen record and never report a mutual match.
+    async fn record_event(
+        &self,
+        user_id: &str,
+        target_user_id: &str,
+        event_type: EventType,
+        occurred_at: DateTime<Utc>,
+    ) -> Result<RecordedEvent, PostgresError> {
+        self.record_seen_at(user_id, target_user_id, event_type, occurred_at).await?;
+        Ok(RecordedEvent::default())
+    }
+
+    /// Insert a seen record with its original timestamp unless the pair already exists
+    async fn insert_seen_if_absent(
+        &self,
+        user_id: &str,
+        target_user_id: &str,
+        event_type: EventType,
+        seen_at: DateTime<Utc>,
+    ) -> Result<bool, PostgresError>;
+
+    /// Seen records written at or after `since`, oldest first
+    async fn get_seen_since(&self, since: DateTime<Utc>, limit: usize) -> Result<Vec<SeenProfile>, PostgresError>;
+
+    /// All user IDs the given user has already seen
+    async fn get_seen_profiles(&self, user_id: &str) -> Result<Vec<String>, PostgresError>;
+
+    /// Pending likes for `user_id`, newest first: likes the user hasn't
+    /// answered with a like, match or pass
+    async fn get_likes_received(&self, user_id: &str, limit: usize, offset: usize) -> Result<Vec<ReceivedLike>, PostgresError>;
+
+    /// Number of pending likes for `user_id`
+    async fn count_likes_received(&self, user_id: &str) -> Result<i64, PostgresError>;
+
+    /// Users who viewed or liked `user_id` since `since`, newest first
+    async fn get_viewers(&self, user_id: &str, since: DateTime<Utc>, limit: usize, offset: usize) -> Result<Vec<ProfileView>, PostgresError>;
+
+    /// Number of users who viewed or liked `user_id` since `since`
+    async fn count_viewers(&self, user_id: &str, since: DateTime<Utc>) -> Result<i64, PostgresError>;
+
+    /// Number of likes and match-backs `user_id` gave since `since`, by
+    /// when the server received them (not the client's `occurredAt`)
+    async fn count_likes_since(&self, user_id: &str, since: DateTime<Utc>) -> Result<i64, PostgresError>;
+
+    /// Delete `viewed`/`passed` records older than the retention window
+    async fn prune_seen_profiles(&self, retention_days: u32) -> Result<u64, PostgresError>;
+
+    /// Mark matches with no event for the pair within `expiry_days` inactive
+    async fn expire_matches(&self, expiry_days: u32) -> Result<u64, PostgresError>;
+
+    /// Active matches of `user_id` with no event for the pair since `inactive_since`
+    async fn get_stale_matches(&self, user_id: &str, inactive_since: DateTime<Utc>) -> Result<Vec<MatchActivity>, PostgresError>;
+
+    /// Swipe statistics for a user
+    async fn get_seen_stats(&self, user_id: &str) -> Result<SeenStats, PostgresError>;
+
+    /// Delete every seen record of `user_id`; returns how many were removed
+    async fn clear_seen_profiles(&self, user_id: &str) -> Result<u64, PostgresError>;
+
+    /// Append an entry to the append-only audit log
+    async fn append_audit(&self, entry: &AuditEntry) -> Result<(), PostgresError>;
+
+    /// Audit log entries matching `filter`, newest first
+    async fn get_audit_log(&self, filter: &AuditFilter, limit: usize, offset: usize) -> Result<Vec<AuditEntry>, PostgresError>;
+
+    /// Record telemetry for one find pipeline run
+    async fn record_match_run(&self, run: &MatchRun) -> Result<(), PostgresError>;
+
+    /// Open `connections` connections up front; returns the pool size afterwards
+    async fn warm_up(&self, connections: u32) -> Result<u32, PostgresError>;
+
+    async fn health_check(&self) -> Result<bool, PostgresError>;
+
+    /// Connection pool statistics, for stores that have them
+    fn pool_stats(&self) -> Option<PoolStats> {
+        None
+    }
+}
+
+/// Result of a candidate query
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CandidatePage {
+    pub profiles: Vec<UserProfile>,
+    /// Documents dropped because they failed to parse
+    pub unparseable: usize,
+}
+
+impl From<Vec<UserProfile>> for CandidatePage {
+    fn from(profiles: Vec<UserProfile>) -> Self {
+        Self { profiles, unparseable: 0 }
+    }
+}
+
+/// Source of profiles and preferences, and sink for match events
+///
+/// Implemented by [`AppwriteClient`] in production and by the in-memory
+/// store seeded from CSV in the `--dev` profile.
+#[async_trait]
+pub trait ProfileStore: Send + Sync {
+    async fn get_preferences(&self, user_id: &str) -> Result<UserPreferences, AppwriteError>;
+
+    /// Candidate profiles for a search, excluding the user and `exclude_ids`
+    async fn query_candidates(
+        &self,
+        user_id: &str,
+        preferences: &UserPreferences,
+        exclude_ids: &HashSet<String>,
+        limit: usize,
+    ) -> Result<CandidatePage, AppwriteError>;
+
+    async fn get_profile(&self, user_id: &str) -> Result<UserProfile, AppwriteError>;
+
+    /// Several profiles by user ID; missing ones are skipped
+    async fn get_profiles(&self, user_ids: &[String]) -> Result<Vec<UserProfile>, AppwriteError>;
+
+    /// Match events created at or after `since`, oldest first
+    async fn list_events_since(&self, since: DateTime<Utc>, max_events: usize) -> Result<Vec<MatchEvent>, AppwriteError>;
+
+    async fn record_event(&self, event: MatchEvent) -> Result<(), AppwriteError>;
+
+    /// Verify the store is reachable and readable
+    async fn check_auth(&self) -> Result<(), AppwriteError>;
+
+    /// Endpoint failover state, for stores with multiple endpoints
+    fn endpoint_status(&self) -> Option<AppwriteEndpointStatus> {
+        None
+    }
+
+    /// Documents skipped because they didn't parse, for stores reading raw documents
+    fn parse_failures(&self) -> Option<ParseFailureStats> {
+        None
+    }
+}
+
+/// Key-value cache for rendered feeds plus the per-user seen-set cache
+///
+/// Implemented by [`CacheManager`] (L1 + Redis, or L1 only). Values are
+/// stored as JSON bytes; use the typed `get`/`set_with_ttl` on `dyn Cache`.
+#[async_trait]
+pub trait Cache: Send + Sync {
+    /// Raw JSON stored under `key`, None on a miss
+    async fn get_bytes(&self, key: &str) -> Result<Option<Vec<u8>>, CacheError>;
+
+    async fn set_bytes(&self, key: &str, bytes: Vec<u8>, ttl_secs: u64) -> Result<(), CacheError>;
+
+    async fn delete(&self, key: &str) -> Result<(), CacheError>;
+
+    /// Raw JSON for several keys in order, None for misses
+    ///
+    /// Implementations batch the lookups into as few round trips as they
+    /// can; the default looks keys up one at a time.
+    async fn get_many(&self, keys: &[String]) -> Result<Vec<Option<Vec<u8>>>, CacheError> {
+        let mut values = Vec::with_capacity(keys.len());
+        for key in keys {
+            values.push(self.get_bytes(key).await?);
+        }
+        Ok(values)
+    }
+
+    async fn set_many(&self, entries: Vec<(String, Vec<u8>)>, ttl_secs: u64) -> Result<(), CacheError> {
+        for (key, bytes) in entries {
+            self.set_bytes(&key, bytes, ttl_secs).await?;
+        }
+        Ok(())
+    }
+
+    async fn delete_many(&self, keys: &[String]) -> Result<(), CacheError> {
+        for key in keys {
+            self.delete(key).await?;
+        }
+        Ok(())
+    }
+
+    /// Claim `key` for `ttl_secs`; false when it is already claimed
+    async fn set_if_absent(&self, key: &str, ttl_secs: u64) -> Result<bool, CacheError>;
+
+    async fn get_seen_set(&self, user_id: &str) -> Option<Arc<HashSet<String>>>;
+
+    async fn set_seen_set(&self, user_id: &str, seen: Arc<HashSet<String>>);
+
+    async fn invalidate_seen_set(&self, user_id: &str);
+
+    async fn ping(&self) -> Result<(), CacheError>;
+
+    fn stats(&self) -> CacheStats;
+
+    /// TTL for a value stored under `key` without an explicit one
+    fn ttl_for(&self, key: &str) -> u64;
+
+    /// Recomputes in flight for `get_or_compute`, by key
+    fn recomputes(&self) -> &Singleflight<String, SharedRecompute>;
+}
+
+impl dyn Cache {
+    /// Get and deserialize a value; a miss is `CacheError::CacheMiss`
+    pub async fn get<T: DeserializeOwned>(&self, key: &str) -> Result<T, CacheError> {
+        match self.get_bytes(key).await? {
+            Some(bytes) => Ok(serde_json::from_slice(&bytes)?),
+            None => Err(CacheError::CacheMiss(key.to_string())),
+        }
+    }
+
+    /// Serialize and store a value for its key class's TTL
+    pub async fn set<T: Serialize>(&self, key: &str, value: &T) -> Result<(), CacheError> {
+        self.set_with_ttl(key, value, self.ttl_for(key)).await
+    }
+
+    /// Serialize and store a value with an explicit TTL
+    pub async fn set_with_ttl<T: Serialize>(&self, key: &str, value: &T, ttl_secs: u64) -> Result<(), CacheError> {
+        self.set_bytes(key, serde_json::to_vec(value)?, ttl_secs).await
+    }
+
+    /// Read-through get, see [`CacheManager::get_or_compute`]
+    pub async fn get_or_compute<T, E, F, Fut>(&self, key: &str, ttl_secs: u64, compute: F) -> Result<T, E>
+    where
+        T: Serialize + DeserializeOwned,
+        E: SharedError,
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        cache::get_or_compute(self, key, ttl_secs, 0, compute).await
+    }
+
+    /// Read-through get serving stale values, see
+    /// [`CacheManager::get_or_compute_stale`]
+    pub async fn get_or_compute_stale<T, E, F, Fut>(
+        &self,
+        key: &str,
+        ttl_secs: u64,
+        stale_secs: u64,
+        compute: F,
+    ) -> Result<T, E>
+    where
+        T: Serialize + DeserializeOwned,
+        E: SharedError,
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        cache::get_or_compute(self, key, ttl_secs, stale_secs, compute).await
+    }
+}
+
+#[async_trait]
+impl Cache for CacheManager {
+    async fn get_bytes(&self, key: &str) -> Result<Option<Vec<u8>>, CacheError> {
+        CacheManager::get_bytes(self, key).await
+    }
+
+    async fn set_bytes(&self, key: &str, bytes: Vec<u8>, ttl_secs: u64) -> Result<(), CacheError> {
+        CacheManager::set_bytes(self, key, bytes, ttl_secs).await
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), CacheError> {
+        CacheManager::delete(self, key).await
+    }
+
+    async fn get_many(&self, keys: &[String]) -> Result<Vec<Option<Vec<u8>>>, CacheError> {
+        CacheManager::get_many(self, keys).await
+    }
+
+    async fn set_many(&self, entries: Vec<(String, Vec<u8>)>, ttl_secs: u64) -> Result<(), CacheError> {
+        CacheManager::set_many(self, entries, ttl_secs).await
+    }
+
+    async fn delete_many(&self, keys: &[String]) -> Result<(), CacheError> {
+        CacheManager::delete_many(self, keys).await
+    }
+
+    async fn set_if_absent(&self, key: &str, ttl_secs: u64) -> Result<bool, CacheError> {
+        CacheManager::set_if_absent(self, key, ttl_secs).await
+    }
+
+    async fn get_seen_set(&self, user_id: &str) -> Option<Arc<HashSet<String>>> {
+        CacheManager::get_seen_set(self, user_id).await
+    }
+
+    async fn set_seen_set(&self, user_id: &str, seen: Arc<HashSet<String>>) {
+        CacheManager::set_seen_set(self, user_id, seen).await
+    }
+
+    async fn invalidate_seen_set(&self, user_id: &str) {
+        CacheManager::invalidate_seen_set(self, user_id).await
+    }
+
+    async fn ping(&self) -> Result<(), CacheError> {
+        CacheManager::ping(self).await
+    }
+
+    fn stats(&self) -> CacheStats {
+        CacheManager::stats(self)
+    }
+
+    fn ttl_for(&self, key: &str) -> u64 {
+        CacheManager::ttl_for(self, key)
+    }
+
+    fn recomputes(&self) -> &Singleflight<String, SharedRecompute> {
+        CacheManager::recomputes(self)
+    }
+}
+
+#[async_trait]
+impl SeenStore for PostgresClient {
+    async fn record_seen_at(
+        &self,
+        user_id: &str,
+        target_user_id: &str,
+        event_type: EventType,
+        occurred_at: DateTime<Utc>,
+    ) -> Result<(), PostgresError> {
+        PostgresClient::record_seen_at(self, user_id, target_user_id, event_type, occurred_at).await
+    }
+
+    async fn record_event(
+        &self,
+        user_id: &str,
+        target_user_id: &str,
+        event_type: EventType,
+        occurred_at: DateTime<Utc>,
+    ) -> Result<RecordedEvent, PostgresError> {
+        PostgresClient::record_event(self, user_id, target_user_id, event_type, occurred_at).await
+    }
+
+    async fn insert_seen_if_absent(
+        &self,
+        user_id: &str,
+        target_user_id: &str,
+        event_type: EventType,
+        seen_at: DateTime<Utc>,
+    ) -> Result<bool, PostgresError> {
+        PostgresClient::insert_seen_if_absent(self, user_id, target_user_id, event_type, seen_at).await
+    }
+
+    async fn get_seen_since(&self, since: DateTime<Utc>, limit: usize) -> Result<Vec<SeenProfile>, PostgresError> {
+        PostgresClient::get_seen_since(self, since, limit).await
+    }
+
+    async fn get_seen_profiles(&self, user_id: &str) -> Result<Vec<String>, PostgresError> {
+        PostgresClient::get_seen_profiles(self, user_id).await
+    }
+
+    async fn get_likes_received(&self, user_id: &str, limit: usize, offset: usize) -> Result<Vec<ReceivedLike>, PostgresError> {
+        PostgresClient::get_likes_received(self, user_id, limit, offset).await
+    }
+
+    async fn count_likes_received(&self, user_id: &str) -> Result<i64, PostgresError> {
+        PostgresClient::count_likes_received(self, user_id).await
+    }
+
+    async fn get_viewers(&self, user_id: &str, since: DateTime<Utc>, limit: usize, offset: usize) -> Result<Vec<ProfileView>, PostgresError> {
+        PostgresClient::get_viewers(self, user_id, since, limit, offset).await
+    }
+
+    async fn count_viewers(&self, user_id: &str, since: DateTime<Utc>) -> Result<i64, PostgresError> {
+        PostgresClient::count_viewers(self, user_id, since).await
+    }
+
+    async fn count_likes_since(&self, user_id: &str, since: DateTime<Utc>) -> Result<i64, PostgresError> {
+        PostgresClient::count_likes_since(self, user_id, since).await
+    }
+
+    async fn prune_seen_profiles(&self, retention_days: u32) -> Result<u64, PostgresError> {
+        PostgresClient::prune_seen_profiles(self, retention_days).await
+    }
+
+    async fn expire_matches(&self, expiry_days: u32) -> Result<u64, PostgresError> {
+        PostgresClient::expire_matches(self, expiry_days).await
+    }
+
+    async fn get_stale_matches(&self, user_id: &str, inactive_since: DateTime<Utc>) -> Result<Vec<MatchActivity>, PostgresError> {
+        PostgresClient::get_stale_matches(self, user_id, inactive_since).await
+    }
+
+    async fn get_seen_stats(&self, user_id: &str) -> Result<SeenStats, PostgresError> {
+        PostgresClient::get_seen_stats(self, user_id).await
+    }
+
+    async fn clear_seen_profiles(&self, user_id: &str) -> Result<u64, PostgresError> {
+        PostgresClient::clear_seen_profiles(self, user_id).await
+    }
+
+    async fn append_audit(&self, entry: &AuditEntry) -> Result<(), PostgresError> {
+        PostgresClient::append_audit(self, entry).await
+    }
+
+    async fn get_audit_log(&self, filter: &AuditFilter, limit: usize, offset: usize) -> Result<Vec<AuditEntry>, PostgresError> {
+        PostgresClient::get_audit_log(self, filter, limit, offset).await
+    }
+
+    async fn record_match_run(&self, run: &MatchRun) -> Result<(), PostgresError> {
+        PostgresClient::record_match_run(self, run).await
+    }
+
+    async fn warm_up(&self, connections: u32) -> Result<u32, PostgresError> {
+        PostgresClient::warm_up(self, connections).await
+    }
+
+    async fn health_check(&self) -> Result<bool, PostgresError> {
+        PostgresClient::health_check(self).await
+    }
+
+    fn pool_stats(&self) -> Option<PoolStats> {
+        Some(PostgresClient::pool_stats(self))
+    }
+}
+
+#[async_trait]
+impl ProfileStore for AppwriteClient {
+    async fn get_preferences(&self, user_id: &str) -> Result<UserPreferences, AppwriteError> {
+        AppwriteClient::get_preferences(self, user_id).await
+    }
+
+    async fn query_candidates(
+        &self,
+        user_id: &str,
+        preferences: &UserPreferences,
+        exclude_ids: &HashSet<String>,
+        limit: usize,
+    ) -> Result<CandidatePage, AppwriteError> {
+        AppwriteClient::query_candidates(self, user_id, preferences, exclude_ids, limit).await
+    }
+
+    async fn get_profile(&self, user_id: &str) -> Result<UserProfile, AppwriteError> {
+        AppwriteClient::get_profile(self, user_id).await
+    }
+
+    async fn get_profiles(&self, user_ids: &[String]) -> Result<Vec<UserProfile>, AppwriteError> {
+        AppwriteClient::get_profiles(self, user_ids).await
+    }
+
+    async fn list_events_since(&self, since: DateTime<Utc>, max_events: usize) -> Result<Vec<MatchEvent>, AppwriteError> {
+        AppwriteClient::list_events_since(self, since, max_events).await
+    }
+
+    async fn record_event(&self, event: MatchEvent) -> Result<(), AppwriteError> {
+        AppwriteClient::record_event(self, event).await
+    }
+
+    async fn check_auth(&self) -> Result<(), AppwriteError> {
+        AppwriteClient::check_auth(self).await
+    }
+
+    fn endpoint_status(&self) -> Option<AppwriteEndpointStatus> {
+        Some(AppwriteClient::endpoint_status(self))
+    }
+
+    fn parse_failures(&self) -> Option<ParseFailureStats> {
+        Some(AppwriteClient::parse_failures(self))
+    }
+}
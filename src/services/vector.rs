@@ -0,0 +1,251 @@
+use crate::config::{VectorBackend, VectorSettings};
+use crate::services::postgres::is_sql_identifier;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Deserialize;
+use sqlx::postgres::PgPoolOptions;
+use sqlx::{PgPool, Row};
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+
+/// Errors that can occur when querying the vector index
+#[derive(Debug, Error)]
+pub enum VectorError {
+    #[error("HTTP request failed: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("Vector index error ({status}): {message}")]
+    Api { status: u16, message: String },
+
+    #[error("SQLx error: {0}")]
+    Sqlx(#[from] sqlx::Error),
+
+    #[error("Vector query timed out")]
+    Timeout,
+
+    #[error("Invalid vector configuration: {0}")]
+    Config(String),
+}
+
+/// Nearest-neighbour lookup over profile embeddings
+///
+/// Implemented for Qdrant and pgvector; results are user IDs, nearest first.
+/// The caller removes seen profiles and runs every filter on the results.
+#[async_trait]
+pub trait VectorIndex: Send + Sync {
+    async fn nearest(&self, embedding: &[f32], limit: usize) -> Result<Vec<String>, VectorError>;
+}
+
+/// Connect the configured vector index, or None when retrieval is disabled
+///
+/// pgvector falls back to `database_url` when `vector.url` is unset.
+pub async fn connect_vector_index(
+    settings: &VectorSettings,
+    database_url: &str,
+) -> Result<Option<Arc<dyn VectorIndex>>, VectorError> {
+    if !settings.enabled {
+        return Ok(None);
+    }
+
+    let timeout = Duration::from_millis(settings.timeout_ms);
+    let index: Arc<dyn VectorIndex> = match settings.backend {
+        VectorBackend::Qdrant => Arc::new(QdrantIndex::new(
+            settings.url.clone().unwrap_or_else(|| "http://localhost:6333".to_string()),
+            settings.api_key.clone(),
+            settings.collection.clone(),
+            settings.id_field.clone(),
+            timeout,
+        )?),
+        VectorBackend::Pgvector => {
+            let url = settings.url.as_deref().unwrap_or(database_url);
+            let pool = PgPoolOptions::new().max_connections(5).connect(url).await?;
+            Arc::new(PgVectorIndex::new(pool, &settings.collection, timeout)?)
+        }
+    };
+
+    tracing::info!(
+        "Vector retrieval enabled: {:?} {} (top {})",
+        settings.backend,
+        settings.collection,
+        settings.top_n
+    );
+
+    Ok(Some(index))
+}
+
+/// Qdrant over its REST API
+///
+/// Point IDs must be integers or UUIDs, so the user ID is read from the
+/// `id_field` payload field.
+pub struct QdrantIndex {
+    client: Client,
+    search_url: String,
+    api_key: Option<String>,
+    id_field: String,
+}
+
+impl QdrantIndex {
+    pub fn new(
+        base_url: String,
+        api_key: Option<String>,
+        collection: String,
+        id_field: String,
+        timeout: Duration,
+    ) -> Result<Self, VectorError> {
+        let client = Client::builder().timeout(timeout).build()?;
+
+        Ok(Self {
+            client,
+            search_url: format!("{}/collections/{}/points/search", base_url.trim_end_matches('/'), collection),
+            api_key,
+            id_field,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct QdrantSearchResponse {
+    result: Vec<QdrantPoint>,
+}
+
+#[derive(Debug, Deserialize)]
+struct QdrantPoint {
+    #[serde(default)]
+    payload: serde_json::Map<String, serde_json::Value>,
+}
+
+#[async_trait]
+impl VectorIndex for QdrantIndex {
+    async fn nearest(&self, embedding: &[f32], limit: usize) -> Result<Vec<String>, VectorError> {
+        let body = serde_json::json!({
+            "vector": embedding,
+            "limit": limit,
+            "with_payload": [self.id_field],
+        });
+
+        let mut request = self.client.post(&self.search_url).json(&body);
+        if let Some(api_key) = &self.api_key {
+            request = request.header("api-key", api_key);
+        }
+
+        let response = request.send().await.map_err(|e| {
+            if e.is_timeout() {
+                VectorError::Timeout
+            } else {
+                VectorError::Http(e)
+            }
+        })?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(VectorError::Api {
+                status: status.as_u16(),
+                message: response.text().await.unwrap_or_default(),
+            });
+        }
+
+        let search: QdrantSearchResponse = response.json().await?;
+        Ok(search
+            .result
+            .into_iter()
+            .filter_map(|point| point.payload.get(&self.id_field)?.as_str().map(str::to_string))
+            .collect())
+    }
+}
+
+/// pgvector table with `user_id` and `embedding vector(n)` columns, ranked
+/// by cosine distance
+pub struct PgVectorIndex {
+    pool: PgPool,
+    query: String,
+    timeout: Duration,
+}
+
+impl PgVectorIndex {
+    pub fn new(pool: PgPool, table: &str, timeout: Duration) -> Result<Self, VectorError> {
+        // The table name is interpolated, so only plain (optionally
+        // schema-qualified) identifiers are accepted
+        if !table.split('.').all(is_sql_identifier) {
+            return Err(VectorError::Config(format!("invalid pgvector table name '{}'", table)));
+        }
+
+        Ok(Self {
+            pool,
+            query: format!(
+                "SELECT user_id FROM {} ORDER BY embedding <=> $1::vector LIMIT $2",
+                table
+            ),
+            timeout,
+        })
+    }
+}
+
+/// pgvector's text input format: `[0.1,0.2,0.3]`
+fn vector_literal(embedding: &[f32]) -> String {
+    let values: Vec<String> = embedding.iter().map(|v| v.to_string()).collect();
+    format!("[{}]", values.join(","))
+}
+
+#[async_trait]
+impl VectorIndex for PgVectorIndex {
+    async fn nearest(&self, embedding: &[f32], limit: usize) -> Result<Vec<String>, VectorError> {
+        let query = sqlx::query(&self.query)
+            .bind(vector_literal(embedding))
+            .bind(limit as i64)
+            .fetch_all(&self.pool);
+
+        let rows = tokio::time::timeout(self.timeout, query)
+            .await
+            .map_err(|_| VectorError::Timeout)??;
+
+        Ok(rows.iter().map(|row| row.get("user_id")).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vector_literal() {
+        assert_eq!(vector_literal(&[0.5, -1.0, 2.25]), "[0.5,-1,2.25]");
+        assert_eq!(vector_literal(&[]), "[]");
+    }
+
+    #[test]
+    fn test_qdrant_search_url() {
+        let index = QdrantIndex::new(
+            "http://qdrant:6333/".to_string(),
+            None,
+            "profiles".to_string(),
+            "userId".to_string(),
+            Duration::from_millis(200),
+        )
+        .unwrap();
+        assert_eq!(index.search_url, "http://qdrant:6333/collections/profiles/points/search");
+    }
+
+    #[test]
+    fn test_qdrant_payload_ids() {
+        let body = r#"{"result": [
+            {"id": 1, "score": 0.9, "payload": {"userId": "user_1"}},
+            {"id": 2, "score": 0.8, "payload": {}},
+            {"id": 3, "score": 0.7}
+        ], "status": "ok"}"#;
+        let search: QdrantSearchResponse = serde_json::from_str(body).unwrap();
+        let ids: Vec<&str> = search.result.iter().filter_map(|p| p.payload.get("userId")?.as_str()).collect();
+        assert_eq!(ids, vec!["user_1"]);
+    }
+
+    #[tokio::test]
+    async fn test_pgvector_table_name() {
+        let pool = PgPoolOptions::new().connect_lazy("postgres://localhost/lume").unwrap();
+        let timeout = Duration::from_millis(200);
+
+        assert!(PgVectorIndex::new(pool.clone(), "public.profile_embeddings", timeout).is_ok());
+        for table in ["", "profiles; DROP TABLE seen_profiles", "a..b", "emb\"eddings"] {
+            assert!(PgVectorIndex::new(pool.clone(), table, timeout).is_err(), "{}", table);
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use crate::config::{HubCity, WarmupSettings};
+use crate::core::calculate_bounding_box;
+use crate::models::BoundingBox;
+use crate::services::{AppwriteError, Cache, CacheError, PostgresError, ProfileStore, SeenStore};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// Errors that stop the service from accepting traffic
+#[derive(Debug, Error)]
+pub enum WarmupError {
+    #[error("PostgreSQL warm-up failed: {0}")]
+    Postgres(#[source] PostgresError),
+
+    #[error("Redis warm-up failed: {0}")]
+    Cache(#[source] CacheError),
+
+    #[error("Appwrite check failed: {0}")]
+    Appwrite(#[source] AppwriteError),
+
+    #[error("Warm-up did not finish within {0}s")]
+    TimedOut(u64),
+}
+
+/// Outcome of a successful warm-up
+#[derive(Debug)]
+pub struct WarmupReport {
+    pub postgres_connections: u32,
+    pub appwrite_checked: bool,
+    pub hub_boxes: Vec<(String, BoundingBox)>,
+    pub duration_ms: u64,
+}
+
+/// Prime dependencies before the listener is bound
+///
+/// Opens `min_connections` PostgreSQL connections, pings Redis and (if
+/// enabled) checks the Appwrite API key, concurrently and within the
+/// configured timeout, then precomputes the hub bounding boxes.
+pub async fn warm_up(
+    postgres: &dyn SeenStore,
+    cache: &dyn Cache,
+    appwrite: &dyn ProfileStore,
+    settings: &WarmupSettings,
+    min_connections: u32,
+) -> Result<WarmupReport, WarmupError> {
+    let started = Instant::now();
+
+    let checks = async {
+        tokio::try_join!(
+            async { postgres.warm_up(min_connections).await.map_err(WarmupError::Postgres) },
+            async { cache.ping().await.map_err(WarmupError::Cache) },
+            async {
+                if settings.check_appwrite {
+                    appwrite.check_auth().await.map_err(WarmupError::Appwrite)?;
+                }
+                Ok(settings.check_appwrite)
+            },
+        )
+    };
+
+    let (postgres_connections, (), appwrite_checked) =
+        tokio::time::timeout(Duration::from_secs(settings.timeout_secs), checks)
+            .await
+            .map_err(|_| WarmupError::TimedOut(settings.timeout_secs))??;
+
+    Ok(WarmupReport {
+        postgres_connections,
+        appwrite_checked,
+        hub_boxes: hub_boxes(&settings.hubs),
+        duration_ms: started.elapsed().as_millis() as u64,
+    })
+}
+
+/// Search bounding boxes for the configured hub cities
+pub fn hub_boxes(hubs: &[HubCity]) -> Vec<(String, BoundingBox)> {
+    hubs.iter()
+        .map(|hub| (hub.name.clone(), calculate_bounding_box(hub.lat, hub.lon, hub.radius_km)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hub_boxes() {
+        let hubs = vec![HubCity {
+            name: "Berlin".to_string(),
+            lat: 52.52,
+            lon: 13.405,
+            radius_km: 50.0,
+        }];
+
+        let boxes = hub_boxes(&hubs);
+        assert_eq!(boxes.len(), 1);
+        assert_eq!(boxes[0].0, "Berlin");
+        assert!(boxes[0].1.min_lat < 52.52 && boxes[0].1.max_lat > 52.52);
+    }
+}
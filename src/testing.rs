@@ -0,0 +1,628 @@
+//! In-memory service implementations for tests
+//!
+//! Enabled with the `testing` feature. [`TestServices`] wires the mocks into
+//! an [`AppState`] so route handlers can be exercised with `actix_web::test`
+//! without PostgreSQL, Redis or Appwrite. [`containers`] provides real
+//! PostgreSQL and Redis servers for the tests that need them.
+
+use crate::config::{DefaultPreferencesSettings, EventDedupSettings, EventTimeSettings, LatencyBudgetSettings, MaintenanceSettings, MatchingSettings, OverfetchSettings, RelaxationSettings, TierSettings, TopPickSettings, ViewerSettings};
+use crate::core::Matcher;
+use crate::models::{UserPreferences, UserProfile, SCHEMA_VERSION};
+use crate::routes::matches::AppState;
+use crate::routes::ConcurrencyLimiter;
+use crate::services::{
+    AuditEntry, AuditFilter, Cache, CacheError, CacheStats, EventPublisher, EventReconciler, EventType, FeedGenerator, MatchActivity, MatchRun,
+    MatchExpirer, PostgresError, ProfileStore, ProfileView, ReceivedLike, SeenProfile, SeenPruner, SeenStats, SeenStore, SharedRecompute, Singleflight,
+    VectorError, VectorIndex,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+#[cfg(feature = "testing")]
+pub mod containers;
+
+pub use crate::services::InMemoryProfileStore as MockProfileStore;
+
+/// (user, target) -> latest event and when it was recorded
+type SeenRecords = HashMap<(String, String), (EventType, DateTime<Utc>)>;
+
+/// Seen store holding records in a map, with switchable failures
+#[derive(Default)]
+pub struct MockSeenStore {
+    records: Mutex<SeenRecords>,
+    /// (liker, target) -> first like, kept whatever the liker records next
+    likes: Mutex<HashMap<(String, String), DateTime<Utc>>>,
+    /// (user, target) -> when the server received the latest event
+    received: Mutex<HashMap<(String, String), DateTime<Utc>>>,
+    /// Matched pairs marked inactive by `expire_matches`
+    expired: Mutex<HashSet<(String, String)>>,
+    audit: Mutex<Vec<AuditEntry>>,
+    match_runs: Mutex<Vec<MatchRun>>,
+    failing: AtomicBool,
+}
+
+impl MockSeenStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Make every call fail (as if the database were down) until reset
+    pub fn set_failing(&self, failing: bool) {
+        self.failing.store(failing, Ordering::Relaxed);
+    }
+
+    /// The latest event `user_id` recorded for `target_user_id`
+    pub fn event(&self, user_id: &str, target_user_id: &str) -> Option<EventType> {
+        self.records
+            .lock()
+            .unwrap()
+            .get(&(user_id.to_string(), target_user_id.to_string()))
+            .map(|(event_type, _)| *event_type)
+    }
+
+    /// Recorded find pipeline runs, oldest first
+    pub fn match_runs(&self) -> Vec<MatchRun> {
+        self.match_runs.lock().unwrap().clone()
+    }
+
+    fn check(&self) -> Result<(), PostgresError> {
+        if self.failing.load(Ordering::Relaxed) {
+            return Err(PostgresError::InvalidInput("mock seen store failure".to_string()));
+        }
+        Ok(())
+    }
+
+    fn viewers(&self, user_id: &str, since: DateTime<Utc>) -> Vec<ProfileView> {
+        let records = self.records.lock().unwrap();
+        let mut views: Vec<ProfileView> = records
+            .iter()
+            .filter(|((_, target), (event_type, seen_at))| {
+                target == user_id && matches!(event_type, EventType::Viewed | EventType::Liked) && *seen_at >= since
+            })
+            .map(|((viewer, _), (event_type, seen_at))| ProfileView {
+                user_id: viewer.clone(),
+                event_type: *event_type,
+                viewed_at: *seen_at,
+            })
+            .collect();
+        views.sort_by_key(|view| std::cmp::Reverse(view.viewed_at));
+        views
+    }
+
+    fn pending_likes(&self, user_id: &str) -> Vec<ReceivedLike> {
+        let records = self.records.lock().unwrap();
+        let likes = self.likes.lock().unwrap();
+        let mut pending: Vec<ReceivedLike> = likes
+            .iter()
+            .filter(|((liker, target), _)| {
+                let answer = (user_id.to_string(), liker.clone());
+                target == user_id
+                    && !likes.contains_key(&answer)
+                    && !matches!(
+                        records.get(&answer),
+                        Some((EventType::Liked | EventType::Matched | EventType::Passed, _))
+                    )
+            })
+            .map(|((liker, _), liked_at)| ReceivedLike {
+                user_id: liker.clone(),
+                liked_at: *liked_at,
+            })
+            .collect();
+        pending.sort_by_key(|like| std::cmp::Reverse(like.liked_at));
+        pending
+    }
+
+    fn record_like(&self, liker_id: &str, target_user_id: &str, liked_at: DateTime<Utc>) {
+        self.likes
+            .lock()
+            .unwrap()
+            .entry((liker_id.to_string(), target_user_id.to_string()))
+            .or_insert(liked_at);
+    }
+}
+
+#[async_trait]
+impl SeenStore for MockSeenStore {
+    async fn record_seen_at(
+        &self,
+        user_id: &str,
+        target_user_id: &str,
+        event_type: EventType,
+        occurred_at: DateTime<Utc>,
+    ) -> Result<(), PostgresError> {
+        self.check()?;
+        if event_type == EventType::Liked {
+            self.record_like(user_id, target_user_id, occurred_at);
+        }
+        let key = (user_id.to_string(), target_user_id.to_string());
+        let mut records = self.records.lock().unwrap();
+        if records.get(&key).is_some_and(|(_, seen_at)| *seen_at > occurred_at) {
+            return Ok(());
+        }
+        records.insert(key.clone(), (event_type, occurred_at));
+        drop(records);
+        self.received.lock().unwrap().insert(key, Utc::now());
+        self.expired
+            .lock()
+            .unwrap()
+            .remove(&(user_id.to_string(), target_user_id.to_string()));
+        Ok(())
+    }
+
+    async fn insert_seen_if_absent(
+        &self,
+        user_id: &str,
+        target_user_id: &str,
+        event_type: EventType,
+        seen_at: DateTime<Utc>,
+    ) -> Result<bool, PostgresError> {
+        self.check()?;
+        let mut records = self.records.lock().unwrap();
+        let key = (user_id.to_string(), target_user_id.to_string());
+        if records.contains_key(&key) {
+            return Ok(false);
+        }
+        if event_type == EventType::Liked {
+            self.record_like(user_id, target_user_id, seen_at);
+        }
+        self.received.lock().unwrap().insert(key.clone(), seen_at);
+        records.insert(key, (event_type, seen_at));
+        Ok(true)
+    }
+
+    async fn get_seen_since(&self, since: DateTime<Utc>, limit: usize) -> Result<Vec<SeenProfile>, PostgresError> {
+        self.check()?;
+        let mut seen: Vec<SeenProfile> = self
+            .records
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, (_, seen_at))| *seen_at >= since)
+            .map(|((user_id, target_user_id), (event_type, seen_at))| SeenProfile {
+                user_id: user_id.clone(),
+                target_user_id: target_user_id.clone(),
+                event_type: *event_type,
+                seen_at: *seen_at,
+            })
+            .collect();
+        seen.sort_by_key(|record| record.seen_at);
+        seen.truncate(limit);
+        Ok(seen)
+    }
+
+    async fn get_seen_profiles(&self, user_id: &str) -> Result<Vec<String>, PostgresError> {
+        self.check()?;
+        Ok(self
+            .records
+            .lock()
+            .unwrap()
+            .keys()
+            .filter(|(user, _)| user == user_id)
+            .map(|(_, target)| target.clone())
+            .collect())
+    }
+
+    async fn get_likes_received(&self, user_id: &str, limit: usize, offset: usize) -> Result<Vec<ReceivedLike>, PostgresError> {
+        self.check()?;
+        Ok(self.pending_likes(user_id).into_iter().skip(offset).take(limit).collect())
+    }
+
+    async fn count_likes_received(&self, user_id: &str) -> Result<i64, PostgresError> {
+        self.check()?;
+        Ok(self.pending_likes(user_id).len() as i64)
+    }
+
+    async fn get_viewers(&self, user_id: &str, since: DateTime<Utc>, limit: usize, offset: usize) -> Result<Vec<ProfileView>, PostgresError> {
+        self.check()?;
+        Ok(self.viewers(user_id, since).into_iter().skip(offset).take(limit).collect())
+    }
+
+    async fn count_viewers(&self, user_id: &str, since: DateTime<Utc>) -> Result<i64, PostgresError> {
+        self.check()?;
+        Ok(self.viewers(user_id, since).len() as i64)
+    }
+
+    async fn count_likes_since(&self, user_id: &str, since: DateTime<Utc>) -> Result<i64, PostgresError> {
+        self.check()?;
+        let records = self.records.lock().unwrap();
+        let received = self.received.lock().unwrap();
+        Ok(records
+            .iter()
+            .filter(|(key, (event_type, _))| {
+                key.0 == user_id
+                    && matches!(event_type, EventType::Liked | EventType::Matched)
+                    && received.get(*key).is_some_and(|received_at| *received_at >= since)
+            })
+            .count() as i64)
+    }
+
+    async fn prune_seen_profiles(&self, retention_days: u32) -> Result<u64, PostgresError> {
+        self.check()?;
+        let cutoff = Utc::now() - chrono::Duration::days(retention_days as i64);
+        let mut records = self.records.lock().unwrap();
+        let before = records.len();
+        records.retain(|_, (event_type, seen_at)| {
+            !matches!(event_type, EventType::Viewed | EventType::Passed) || *seen_at >= cutoff
+        });
+        Ok((before - records.len()) as u64)
+    }
+
+    async fn expire_matches(&self, expiry_days: u32) -> Result<u64, PostgresError> {
+        self.check()?;
+        let cutoff = Utc::now() - chrono::Duration::days(expiry_days as i64);
+        let records = self.records.lock().unwrap();
+        let mut expired = self.expired.lock().unwrap();
+        let mut count = 0;
+        for ((user, target), (event_type, seen_at)) in records.iter() {
+            let key = (user.clone(), target.clone());
+            let reply_is_recent = records
+                .get(&(target.clone(), user.clone()))
+                .is_some_and(|(_, reply_at)| *reply_at >= cutoff);
+            if *event_type == EventType::Matched && *seen_at < cutoff && !reply_is_recent && expired.insert(key) {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    async fn get_stale_matches(&self, user_id: &str, inactive_since: DateTime<Utc>) -> Result<Vec<MatchActivity>, PostgresError> {
+        self.check()?;
+        let records = self.records.lock().unwrap();
+        let expired = self.expired.lock().unwrap();
+        let partners: HashSet<&String> = records
+            .iter()
+            .filter(|(key, (event_type, _))| *event_type == EventType::Matched && !expired.contains(*key))
+            .filter_map(|((user, target), _)| match (user == user_id, target == user_id) {
+                (true, _) => Some(target),
+                (_, true) => Some(user),
+                _ => None,
+            })
+            .collect();
+
+        let mut stale: Vec<MatchActivity> = partners
+            .into_iter()
+            .filter_map(|partner| {
+                let last_activity_at = [
+                    records.get(&(user_id.to_string(), partner.clone())),
+                    records.get(&(partner.clone(), user_id.to_string())),
+                ]
+                .into_iter()
+                .flatten()
+                .map(|(_, seen_at)| *seen_at)
+                .max()?;
+                (last_activity_at < inactive_since).then(|| MatchActivity {
+                    user_id: partner.clone(),
+                    last_activity_at,
+                })
+            })
+            .collect();
+        stale.sort_by_key(|activity| activity.last_activity_at);
+        Ok(stale)
+    }
+
+    async fn get_seen_stats(&self, user_id: &str) -> Result<SeenStats, PostgresError> {
+        self.check()?;
+        let records = self.records.lock().unwrap();
+        let mine: Vec<&(EventType, DateTime<Utc>)> =
+            records.iter().filter(|((user, _), _)| user == user_id).map(|(_, record)| record).collect();
+        let count = |kind: EventType| mine.iter().filter(|(event_type, _)| *event_type == kind).count() as i64;
+
+        Ok(SeenStats {
+            user_id: user_id.to_string(),
+            total_seen: mine.len() as i64,
+            viewed: count(EventType::Viewed),
+            liked: count(EventType::Liked),
+            passed: count(EventType::Passed),
+            matched: count(EventType::Matched),
+            last_seen_at: mine.iter().map(|(_, seen_at)| *seen_at).max(),
+        })
+    }
+
+    async fn clear_seen_profiles(&self, user_id: &str) -> Result<u64, PostgresError> {
+        self.check()?;
+        let mut records = self.records.lock().unwrap();
+        let before = records.len();
+        records.retain(|(user, _), _| user != user_id);
+        Ok((before - records.len()) as u64)
+    }
+
+    async fn append_audit(&self, entry: &AuditEntry) -> Result<(), PostgresError> {
+        self.check()?;
+        self.audit.lock().unwrap().push(entry.clone());
+        Ok(())
+    }
+
+    async fn get_audit_log(&self, filter: &AuditFilter, limit: usize, offset: usize) -> Result<Vec<AuditEntry>, PostgresError> {
+        self.check()?;
+        let audit = self.audit.lock().unwrap();
+        Ok(audit.iter().rev().filter(|entry| filter.matches(entry)).skip(offset).take(limit).cloned().collect())
+    }
+
+    async fn record_match_run(&self, run: &MatchRun) -> Result<(), PostgresError> {
+        self.check()?;
+        self.match_runs.lock().unwrap().push(run.clone());
+        Ok(())
+    }
+
+    async fn warm_up(&self, connections: u32) -> Result<u32, PostgresError> {
+        self.check()?;
+        Ok(connections)
+    }
+
+    async fn health_check(&self) -> Result<bool, PostgresError> {
+        self.check()?;
+        Ok(true)
+    }
+}
+
+/// Cache holding values in a map; TTLs are ignored except for claims
+#[derive(Default)]
+pub struct MockCache {
+    values: Mutex<HashMap<String, Vec<u8>>>,
+    seen_sets: Mutex<HashMap<String, Arc<HashSet<String>>>>,
+    claims: Mutex<HashMap<String, std::time::Instant>>,
+    recomputes: Singleflight<String, SharedRecompute>,
+}
+
+impl MockCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.values.lock().unwrap().contains_key(key)
+    }
+}
+
+#[async_trait]
+impl Cache for MockCache {
+    async fn get_bytes(&self, key: &str) -> Result<Option<Vec<u8>>, CacheError> {
+        Ok(self.values.lock().unwrap().get(key).cloned())
+    }
+
+    async fn set_bytes(&self, key: &str, bytes: Vec<u8>, _ttl_secs: u64) -> Result<(), CacheError> {
+        self.values.lock().unwrap().insert(key.to_string(), bytes);
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), CacheError> {
+        self.values.lock().unwrap().remove(key);
+        self.claims.lock().unwrap().remove(key);
+        Ok(())
+    }
+
+    async fn set_if_absent(&self, key: &str, ttl_secs: u64) -> Result<bool, CacheError> {
+        let now = std::time::Instant::now();
+        let mut claims = self.claims.lock().unwrap();
+        if claims.get(key).is_some_and(|until| *until > now) {
+            return Ok(false);
+        }
+        claims.insert(key.to_string(), now + std::time::Duration::from_secs(ttl_secs));
+        Ok(true)
+    }
+
+    async fn get_seen_set(&self, user_id: &str) -> Option<Arc<HashSet<String>>> {
+        self.seen_sets.lock().unwrap().get(user_id).cloned()
+    }
+
+    async fn set_seen_set(&self, user_id: &str, seen: Arc<HashSet<String>>) {
+        self.seen_sets.lock().unwrap().insert(user_id.to_string(), seen);
+    }
+
+    async fn invalidate_seen_set(&self, user_id: &str) {
+        self.seen_sets.lock().unwrap().remove(user_id);
+    }
+
+    async fn ping(&self) -> Result<(), CacheError> {
+        Ok(())
+    }
+
+    fn stats(&self) -> CacheStats {
+        CacheStats {
+            l1_size: self.values.lock().unwrap().len() as u64,
+            ..CacheStats::default()
+        }
+    }
+
+    fn ttl_for(&self, _key: &str) -> u64 {
+        300
+    }
+
+    fn recomputes(&self) -> &Singleflight<String, SharedRecompute> {
+        &self.recomputes
+    }
+}
+
+/// Vector index returning fixed user IDs regardless of the query embedding
+#[derive(Default)]
+pub struct MockVectorIndex {
+    user_ids: Vec<String>,
+    failing: AtomicBool,
+}
+
+impl MockVectorIndex {
+    pub fn new(user_ids: &[&str]) -> Self {
+        Self {
+            user_ids: user_ids.iter().map(|id| id.to_string()).collect(),
+            failing: AtomicBool::new(false),
+        }
+    }
+
+    pub fn set_failing(&self, failing: bool) {
+        self.failing.store(failing, Ordering::SeqCst);
+    }
+}
+
+#[async_trait]
+impl VectorIndex for MockVectorIndex {
+    async fn nearest(&self, _embedding: &[f32], limit: usize) -> Result<Vec<String>, VectorError> {
+        if self.failing.load(Ordering::SeqCst) {
+            return Err(VectorError::Timeout);
+        }
+        Ok(self.user_ids.iter().take(limit).cloned().collect())
+    }
+}
+
+/// The mock services, kept around so tests can seed and inspect them
+pub struct TestServices {
+    pub profiles: Arc<MockProfileStore>,
+    pub seen: Arc<MockSeenStore>,
+    pub cache: Arc<MockCache>,
+}
+
+impl TestServices {
+    pub fn new(profiles: Vec<UserProfile>, preferences: Vec<UserPreferences>) -> Self {
+        Self {
+            profiles: Arc::new(MockProfileStore::new(profiles, preferences)),
+            seen: Arc::new(MockSeenStore::new()),
+            cache: Arc::new(MockCache::new()),
+        }
+    }
+
+    /// Application state over the mocks, with the default matcher, match
+    /// expiry after 14 days, and background jobs constructed but not spawned
+    pub fn app_state(&self) -> AppState {
+        let profiles: Arc<dyn ProfileStore> = self.profiles.clone();
+        let seen: Arc<dyn SeenStore> = self.seen.clone();
+        let cache: Arc<dyn Cache> = self.cache.clone();
+
+        AppState {
+            appwrite: profiles.clone(),
+            cache: cache.clone(),
+            postgres: seen.clone(),
+            pruner: Arc::new(SeenPruner::new(seen.clone(), MaintenanceSettings::default())),
+            reconciler: Arc::new(EventReconciler::new(seen.clone(), profiles.clone(), MaintenanceSettings::default())),
+            expirer: Arc::new(MatchExpirer::new(seen.clone(), MaintenanceSettings {
+                match_expiry_days: Some(14),
+                ..MaintenanceSettings::default()
+            })),
+            admin_token: None,
+            feed: Arc::new(FeedGenerator::new(profiles, cache, seen, Matcher::default(), matching_settings())),
+            events: Arc::new(EventPublisher::disabled()),
+            serve_pregenerated: false,
+            tiers: TierSettings::default(),
+            viewers: ViewerSettings::default(),
+            event_time: EventTimeSettings::default(),
+            event_dedup: EventDedupSettings::default(),
+            find_limiter: Arc::new(ConcurrencyLimiter::unlimited()),
+            profile: None,
+        }
+    }
+}
+
+/// Matching settings as in `config/default.toml`
+pub fn matching_settings() -> MatchingSettings {
+    MatchingSettings {
+        max_distance_km: Some(100),
+        default_limit: Some(20),
+        max_limit: Some(100),
+        passport_premium_only: false,
+        require_photos: false,
+        default_preferences: DefaultPreferencesSettings::default(),
+        exploration_epsilon: 0.0,
+        rng_seed: None,
+        freshness_fraction: 0.0,
+        new_profile_days: 7,
+        shuffle_band_width: 0.0,
+        latency_budget: LatencyBudgetSettings::default(),
+        overfetch: OverfetchSettings::default(),
+        relaxation: RelaxationSettings::default(),
+        top_pick: TopPickSettings::default(),
+    }
+}
+
+/// An active, located profile in Berlin
+pub fn profile(user_id: &str, gender: &str, age: u8) -> UserProfile {
+    serde_json::from_value(serde_json::json!({
+        "userId": user_id,
+        "name": user_id,
+        "age": age,
+        "heightCm": 175,
+        "hairColor": "brown",
+        "gender": gender,
+        "latitude": 52.52,
+        "longitude": 13.405,
+        "imageFileIds": ["photo"],
+    }))
+    .expect("valid test profile")
+}
+
+/// Preferences accepting `genders` aged `min_age..=max_age` within 50 km of Berlin
+pub fn preferences(user_id: &str, genders: &[&str], min_age: u8, max_age: u8) -> UserPreferences {
+    UserPreferences {
+        user_id: user_id.to_string(),
+        preferred_genders: genders.iter().map(|g| g.to_string()).collect(),
+        min_age,
+        max_age,
+        min_height_cm: 0,
+        max_height_cm: 250,
+        preferred_hair_colors: vec![],
+        preferred_sports: vec![],
+        preferred_interests: vec![],
+        max_distance_km: 50,
+        latitude: 52.52,
+        longitude: 13.405,
+        require_photos: None,
+        embedding: None,
+        preferred_languages: vec![],
+        require_shared_language: false,
+        verified_only: false,
+        relationship_goal: None,
+        attributes: Default::default(),
+        updated_at: None,
+        schema_version: SCHEMA_VERSION,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_mock_seen_store_likes() {
+        let store = MockSeenStore::new();
+        store.record_seen("b", "a", EventType::Liked).await.unwrap();
+        store.record_seen("c", "a", EventType::Liked).await.unwrap();
+        store.record_seen("a", "c", EventType::Passed).await.unwrap();
+
+        assert_eq!(store.count_likes_received("a").await.unwrap(), 1);
+        assert_eq!(store.get_likes_received("a", 10, 0).await.unwrap()[0].user_id, "b");
+        assert_eq!(store.get_seen_stats("a").await.unwrap().passed, 1);
+
+        // b viewing a again doesn't withdraw the like
+        store.record_seen("b", "a", EventType::Viewed).await.unwrap();
+        assert_eq!(store.count_likes_received("a").await.unwrap(), 1);
+
+        store.set_failing(true);
+        assert!(store.get_seen_profiles("a").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_mock_seen_store_match_expiry() {
+        let store = MockSeenStore::new();
+        let long_ago = Utc::now() - chrono::Duration::days(40);
+        store.insert_seen_if_absent("a", "b", EventType::Matched, long_ago).await.unwrap();
+        store.insert_seen_if_absent("a", "c", EventType::Matched, long_ago).await.unwrap();
+        store.record_seen("c", "a", EventType::Liked).await.unwrap();
+
+        assert_eq!(store.expire_matches(30).await.unwrap(), 1);
+        assert_eq!(store.expire_matches(30).await.unwrap(), 0);
+
+        let stale = store.get_stale_matches("a", Utc::now() + chrono::Duration::minutes(1)).await.unwrap();
+        assert_eq!(stale.len(), 1);
+        assert_eq!(stale[0].user_id, "c");
+    }
+
+    #[tokio::test]
+    async fn test_mock_cache_typed_access() {
+        let cache: Arc<dyn Cache> = Arc::new(MockCache::new());
+        cache.set_with_ttl("key", &vec![1, 2, 3], 60).await.unwrap();
+
+        let value: Vec<i32> = cache.get("key").await.unwrap();
+        assert_eq!(value, vec![1, 2, 3]);
+        assert!(matches!(cache.get::<Vec<i32>>("missing").await, Err(CacheError::CacheMiss(_))));
+    }
+}
@@ -0,0 +1,58 @@
+//! PostgreSQL and Redis for integration tests
+//!
+//! Each helper first looks for a running server in an environment variable
+//! (`LUME_TEST_DATABASE_URL`, `LUME_TEST_REDIS_URL`), so `docker compose up
+//! postgres redis` or a CI service works as is. Without one it starts a
+//! throwaway container with testcontainers, which is removed when the
+//! returned [`TestContainer`] is dropped. When Docker isn't reachable either,
+//! the helpers return `None` and the calling test should skip.
+
+use std::any::Any;
+use testcontainers::runners::AsyncRunner;
+use testcontainers::{ContainerAsync, ContainerRequest, Image, ImageExt};
+use testcontainers_modules::postgres::Postgres;
+use testcontainers_modules::redis::{Redis, REDIS_PORT};
+
+/// Same images as docker-compose.yml
+const POSTGRES_TAG: &str = "16-alpine";
+const REDIS_TAG: &str = "7-alpine";
+
+/// Connection URL of a test server, keeping its container (if any) alive
+pub struct TestContainer {
+    pub url: String,
+    _container: Option<Box<dyn Any + Send>>,
+}
+
+/// PostgreSQL from `LUME_TEST_DATABASE_URL` or a new container
+pub async fn postgres() -> Option<TestContainer> {
+    if let Ok(url) = std::env::var("LUME_TEST_DATABASE_URL") {
+        return Some(TestContainer { url, _container: None });
+    }
+
+    let container = start(Postgres::default().with_tag(POSTGRES_TAG)).await?;
+    let port = container.get_host_port_ipv4(5432).await.ok()?;
+    let host = container.get_host().await.ok()?;
+    Some(TestContainer { url: format!("postgres://postgres:postgres@{}:{}/postgres", host, port), _container: Some(Box::new(container)) })
+}
+
+/// Redis from `LUME_TEST_REDIS_URL` or a new container
+pub async fn redis() -> Option<TestContainer> {
+    if let Ok(url) = std::env::var("LUME_TEST_REDIS_URL") {
+        return Some(TestContainer { url, _container: None });
+    }
+
+    let container = start(Redis::default().with_tag(REDIS_TAG)).await?;
+    let port = container.get_host_port_ipv4(REDIS_PORT).await.ok()?;
+    let host = container.get_host().await.ok()?;
+    Some(TestContainer { url: format!("redis://{}:{}", host, port), _container: Some(Box::new(container)) })
+}
+
+async fn start<I: Image>(request: ContainerRequest<I>) -> Option<ContainerAsync<I>> {
+    match request.start().await {
+        Ok(container) => Some(container),
+        Err(e) => {
+            eprintln!("Could not start a test container ({}), skipping", e);
+            None
+        }
+    }
+}
@@ -0,0 +1,2 @@
+// Shared utilities
+pub mod rng;
@@ -0,0 +1,1083 @@
+// HTTP API tests for Lume Algo
+//
+// Drives the real actix handlers through actix_web::test against the mock
+// services from `lume_algo::testing`; no Appwrite, PostgreSQL or Redis needed.
+
+use actix_web::dev::{Service, ServiceResponse};
+use actix_web::http::StatusCode;
+use actix_web::{test, web, App};
+use lume_algo::config::{ConcurrencySettings, MarketSettings, PayloadLimitSettings, TierSettings};
+use lume_algo::core::{Geofences, MatchRules, Matcher};
+use lume_algo::models::Tier;
+use lume_algo::routes::{self, errors::{handle_json_payload_error, handle_query_payload_error}, ConcurrencyLimiter, SignatureVerifier, TenantRegistry, SIGNATURE_HEADER, TENANT_HEADER};
+use lume_algo::services::postgres::EventType;
+use lume_algo::services::{FeedGenerator, SeenStore};
+use lume_algo::testing::{matching_settings, preferences, profile, MockVectorIndex, TestServices};
+use std::sync::Arc;
+use serde_json::{json, Value};
+
+/// Mock services with one searcher ("alice") and three women in Berlin,
+/// one of them outside alice's age range
+fn services() -> TestServices {
+    TestServices::new(
+        vec![
+            profile("alice", "male", 30),
+            profile("beth", "female", 28),
+            profile("cara", "female", 31),
+            profile("dana", "female", 45),
+        ],
+        vec![preferences("alice", &["female"], 25, 35)],
+    )
+}
+
+/// The app as main.rs builds it, minus middleware
+async fn init_app(
+    services: &TestServices,
+) -> impl Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error> {
+    init_app_with(services.app_state()).await
+}
+
+async fn init_app_with(
+    tenants: impl Into<TenantRegistry>,
+) -> impl Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error> {
+    test::init_service(
+        App::new()
+            .app_data(web::Data::new(tenants.into()))
+            .app_data(web::JsonConfig::default().error_handler(handle_json_payload_error))
+            .app_data(web::QueryConfig::default().error_handler(handle_query_payload_error))
+            .configure(|cfg| routes::configure_routes(cfg, PayloadLimitSettings::default())),
+    )
+    .await
+}
+
+async fn post<S>(app: &S, path: &str, body: Value) -> (StatusCode, Value)
+where
+    S: Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error>,
+{
+    let req = test::TestRequest::post().uri(path).set_json(body).to_request();
+    let resp = test::call_service(app, req).await;
+    let status = resp.status();
+    (status, test::read_body_json(resp).await)
+}
+
+async fn get<S>(app: &S, path: &str) -> (StatusCode, Value)
+where
+    S: Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error>,
+{
+    let resp = test::call_service(app, test::TestRequest::get().uri(path).to_request()).await;
+    let status = resp.status();
+    (status, test::read_body_json(resp).await)
+}
+
+fn match_ids(body: &Value) -> Vec<String> {
+    let mut ids: Vec<String> = body["matches"]
+        .as_array()
+        .expect("matches array")
+        .iter()
+        .map(|m| m["userId"].as_str().unwrap().to_string())
+        .collect();
+    ids.sort();
+    ids
+}
+
+// ==================== find_matches ====================
+
+#[actix_web::test]
+async fn test_find_matches_happy_path() {
+    let services = services();
+    let app = init_app(&services).await;
+
+    let (status, body) = post(&app, "/api/v1/matches/find", json!({ "userId": "alice" })).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(match_ids(&body), vec!["beth", "cara"]);
+    assert_eq!(body["total_results"], 2);
+    assert_eq!(body["global_mode"], false);
+    assert_eq!(body["default_preferences"], false);
+    assert_eq!(body["partial"], false);
+    assert_eq!(body["warnings"], json!([]));
+    assert!(body["matches"][0]["matchScore"].as_f64().unwrap() > 0.0);
+}
+
+#[actix_web::test]
+async fn test_find_matches_respects_limit() {
+    let services = services();
+    let app = init_app(&services).await;
+
+    let (status, body) = post(&app, "/api/v1/matches/find", json!({ "userId": "alice", "limit": 1 })).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["matches"].as_array().unwrap().len(), 1);
+}
+
+#[actix_web::test]
+async fn test_top_pick() {
+    let services = services();
+    let app = init_app(&services).await;
+
+    let (status, body) = get(&app, "/api/v1/matches/top-pick?userId=alice").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["userId"], "alice");
+    let pick = body["topPick"]["match"]["userId"].as_str().expect("a top pick").to_string();
+    assert!(["beth", "cara"].contains(&pick.as_str()));
+    assert!(body["topPick"]["reciprocalScore"].as_f64().unwrap() > 0.0);
+
+    // Find responses only carry the pick when asked for
+    let (_, body) = post(&app, "/api/v1/matches/find", json!({ "userId": "alice" })).await;
+    assert!(body.get("top_pick").is_none());
+
+    let (_, body) = post(&app, "/api/v1/matches/find", json!({ "userId": "alice", "topPick": true })).await;
+    assert_eq!(body["top_pick"]["match"]["userId"], pick.as_str());
+}
+
+#[actix_web::test]
+async fn test_find_matches_unknown_user() {
+    let services = services();
+    let app = init_app(&services).await;
+
+    let (status, body) = post(&app, "/api/v1/matches/find", json!({ "userId": "nobody" })).await;
+
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["error_code"], "profile_not_found");
+}
+
+#[actix_web::test]
+async fn test_find_matches_gateway_deadline() {
+    let services = services();
+    let app = init_app(&services).await;
+
+    let find = |header: (&'static str, &'static str)| {
+        test::TestRequest::post()
+            .uri("/api/v1/matches/find")
+            .insert_header(header)
+            .set_json(json!({ "userId": "alice" }))
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, find(("grpc-timeout", "5S"))).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    // The gateway already gave up: nothing is computed
+    let resp = test::call_service(&app, find(("X-Request-Deadline", "1700000000000"))).await;
+    assert_eq!(resp.status(), StatusCode::GATEWAY_TIMEOUT);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["error_code"], "deadline_exceeded");
+
+    let resp = test::call_service(&app, find(("grpc-timeout", "soon"))).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["error_code"], "invalid_deadline");
+}
+
+#[actix_web::test]
+async fn test_find_matches_merges_vector_candidates() {
+    let mut alice = profile("alice", "male", 30);
+    alice.embedding = Some(vec![1.0, 0.0]);
+    let services = TestServices::new(
+        vec![alice, profile("beth", "female", 28), profile("cara", "female", 31), profile("dana", "female", 45)],
+        vec![preferences("alice", &["female"], 25, 35)],
+    );
+
+    // dana is outside the age range, so only vector retrieval returns her;
+    // duplicates, unknown IDs and the user themself are dropped
+    let index = Arc::new(MockVectorIndex::new(&["dana", "beth", "ghost", "alice"]));
+    let mut state = services.app_state();
+    state.feed = Arc::new(
+        FeedGenerator::new(
+            state.appwrite.clone(),
+            state.cache.clone(),
+            state.postgres.clone(),
+            Matcher::default(),
+            matching_settings(),
+        )
+        .with_vector_index(Some(index.clone()), 50),
+    );
+    let app = init_app_with(state).await;
+
+    let (status, body) = post(&app, "/api/v1/matches/find", json!({ "userId": "alice" })).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["total_results"], 3);
+    // Semantic candidates still go through every filter
+    assert_eq!(match_ids(&body), vec!["beth", "cara"]);
+
+    // A failing index falls back to the geo candidates
+    index.set_failing(true);
+    let (status, body) = post(&app, "/api/v1/matches/find", json!({ "userId": "alice" })).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["total_results"], 2);
+}
+
+#[actix_web::test]
+async fn test_find_matches_enforces_rules() {
+    let tenant = |mut profile: lume_algo::models::UserProfile, tenant_id: &str| {
+        profile.tenant_id = Some(tenant_id.to_string());
+        profile
+    };
+    let services = TestServices::new(
+        vec![
+            tenant(profile("alice", "male", 30), "acme"),
+            tenant(profile("beth", "female", 28), "acme"),
+            tenant(profile("cara", "female", 31), "globex"),
+        ],
+        vec![preferences("alice", &["female"], 25, 35)],
+    );
+
+    let mut state = services.app_state();
+    state.feed = Arc::new(
+        FeedGenerator::new(
+            state.appwrite.clone(),
+            state.cache.clone(),
+            state.postgres.clone(),
+            Matcher::default(),
+            matching_settings(),
+        )
+        .with_rules(MatchRules { tenant_isolation: true, ..Default::default() }),
+    );
+    let app = init_app_with(state).await;
+
+    let (status, body) = post(&app, "/api/v1/matches/find", json!({ "userId": "alice" })).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(match_ids(&body), vec!["beth"]);
+}
+
+#[actix_web::test]
+async fn test_find_matches_enforces_geofences() {
+    let services = services();
+    let app_with = |geofences: Value| {
+        let mut state = services.app_state();
+        let geofences: Geofences = serde_json::from_value(geofences).unwrap();
+        state.feed = Arc::new(FeedGenerator::new(
+            state.appwrite.clone(),
+            state.cache.clone(),
+            state.postgres.clone(),
+            Matcher::builder().geofences(geofences).build(),
+            matching_settings(),
+        ));
+        init_app_with(state)
+    };
+    let berlin = json!({"min_lat": 52.3, "max_lat": 52.7, "min_lon": 13.0, "max_lon": 13.8});
+    let paris = json!({"min_lat": 48.7, "max_lat": 49.0, "min_lon": 2.2, "max_lon": 2.5});
+
+    // Excluded markets drop the candidates located there
+    let app = app_with(json!([{ "name": "de-be", "bounding_box": berlin }])).await;
+    let (status, body) = post(&app, "/api/v1/matches/find", json!({ "userId": "alice" })).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(match_ids(&body).is_empty());
+
+    // Refused markets reject users located or searching there
+    let app = app_with(json!([{ "name": "de-be", "action": "refuse", "bounding_box": berlin }])).await;
+    let (status, body) = post(&app, "/api/v1/matches/find", json!({ "userId": "alice" })).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["error_code"], "market_unavailable");
+
+    let app = app_with(json!([{ "name": "fr-75", "action": "refuse", "bounding_box": paris }])).await;
+    let (status, _) = post(&app, "/api/v1/matches/find", json!({ "userId": "alice" })).await;
+    assert_eq!(status, StatusCode::OK);
+    let request = json!({ "userId": "alice", "overrideLocation": { "lat": 48.86, "lon": 2.35 } });
+    let (status, body) = post(&app, "/api/v1/matches/find", request).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["error_code"], "market_unavailable");
+}
+
+#[actix_web::test]
+async fn test_find_matches_tags_markets() {
+    let services = services();
+    let app_with = |markets: MarketSettings| {
+        let mut state = services.app_state();
+        state.admin_token = Some("secret".to_string());
+        state.feed = Arc::new(
+            FeedGenerator::new(
+                state.appwrite.clone(),
+                state.cache.clone(),
+                state.postgres.clone(),
+                Matcher::default(),
+                matching_settings(),
+            )
+            .with_markets(markets),
+        );
+        init_app_with(state)
+    };
+    let score = |body: &Value| body["matches"][0]["matchScore"].as_f64().unwrap();
+
+    let app = app_with(MarketSettings::default()).await;
+    let (status, body) = post(&app, "/api/v1/matches/find", json!({ "userId": "alice" })).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!((body["market"].as_str(), body["city"].as_str()), (Some("de"), Some("de-ber")));
+    assert_eq!(body["matches"][0]["city"], "de-ber");
+    assert_eq!(body["matches"][0]["market"], "de");
+    let base_score = score(&body);
+
+    // Market weights apply over the deployment's; request weights win over both
+    let markets: MarketSettings =
+        serde_json::from_value(json!({ "overrides": { "de": { "weights": { "distance": 0.6 } } } })).unwrap();
+    let app = app_with(markets).await;
+    let (_, body) = post(&app, "/api/v1/matches/find", json!({ "userId": "alice" })).await;
+    assert!(score(&body) > base_score);
+    let req = test::TestRequest::post()
+        .uri("/api/v1/matches/find")
+        .insert_header(("X-Admin-Token", "secret"))
+        .set_json(json!({ "userId": "alice", "weights": { "distance": 0.35 } }))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(score(&body), base_score);
+
+    // Searching from outside every known city tags no city
+    let request = json!({ "userId": "alice", "overrideLocation": { "lat": 0.0, "lon": 0.5 } });
+    let (_, body) = post(&app, "/api/v1/matches/find", request).await;
+    assert!(body.get("city").is_none());
+}
+
+// ==================== Validation ====================
+
+#[actix_web::test]
+async fn test_find_matches_empty_user_id() {
+    let services = services();
+    let app = init_app(&services).await;
+
+    let (status, body) = post(&app, "/api/v1/matches/find", json!({ "userId": "" })).await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error_code"], "validation_failed");
+    assert_eq!(body["validation_errors"][0]["field"], "userId");
+}
+
+#[actix_web::test]
+async fn test_find_matches_weight_out_of_range() {
+    let services = services();
+    let app = init_app(&services).await;
+
+    let (status, body) = post(
+        &app,
+        "/api/v1/matches/find",
+        json!({ "userId": "alice", "weights": { "distance": 1.5 } }),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error_code"], "validation_failed");
+    assert_eq!(body["validation_errors"][0]["field"], "weights.distance");
+}
+
+#[actix_web::test]
+async fn test_find_matches_weights_need_admin_token() {
+    let services = services();
+    let mut state = services.app_state();
+    state.admin_token = Some("secret".to_string());
+    let app = init_app_with(state).await;
+    let request = json!({ "userId": "alice", "weights": { "distance": 0.5 } });
+
+    let (status, body) = post(&app, "/api/v1/matches/find", request.clone()).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["error_code"], "weights_not_allowed");
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/matches/find")
+        .insert_header(("X-Admin-Token", "wrong"))
+        .set_json(request.clone())
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::FORBIDDEN);
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/matches/find")
+        .insert_header(("X-Admin-Token", "secret"))
+        .set_json(request)
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn test_score_candidates_weights_need_admin_token() {
+    let services = services();
+    let mut state = services.app_state();
+    state.admin_token = Some("secret".to_string());
+    let app = init_app_with(state).await;
+    let request = json!({
+        "preferences": preferences("alice", &["female"], 25, 35),
+        "candidates": [profile("beth", "female", 28)],
+        "weights": { "distance": 0.5 },
+    });
+
+    let (status, body) = post(&app, "/api/v1/matches/score", request.clone()).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["error_code"], "weights_not_allowed");
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/matches/score")
+        .insert_header(("X-Admin-Token", "secret"))
+        .set_json(request)
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn test_find_matches_invalid_json() {
+    let services = services();
+    let app = init_app(&services).await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/matches/find")
+        .insert_header(("content-type", "application/json"))
+        .set_payload(r#"{"userId": "alice","#)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["error_code"], "invalid_json");
+}
+
+#[actix_web::test]
+async fn test_record_event_invalid_event_type() {
+    let services = services();
+    let app = init_app(&services).await;
+
+    let (status, body) = post(
+        &app,
+        "/api/v1/matches/event",
+        json!({ "userId": "alice", "targetUserId": "beth", "eventType": "superliked" }),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error_code"], "invalid_event_type");
+    assert_eq!(services.seen.event("alice", "beth"), None);
+}
+
+#[actix_web::test]
+async fn test_record_event_missing_target() {
+    let services = services();
+    let app = init_app(&services).await;
+
+    let (status, body) = post(
+        &app,
+        "/api/v1/matches/event",
+        json!({ "userId": "alice", "targetUserId": "", "eventType": "liked" }),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["validation_errors"][0]["field"], "targetUserId");
+}
+
+// ==================== Seen-profile exclusion ====================
+
+#[actix_web::test]
+async fn test_seen_profiles_are_excluded() {
+    let services = services();
+    let app = init_app(&services).await;
+
+    let (status, _) = post(&app, "/api/v1/matches/find", json!({ "userId": "alice" })).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = post(
+        &app,
+        "/api/v1/matches/event",
+        json!({ "userId": "alice", "targetUserId": "beth", "eventType": "passed" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // The cached seen set from the first search must not hide the new event
+    let (status, body) = post(&app, "/api/v1/matches/find", json!({ "userId": "alice" })).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(match_ids(&body), vec!["cara"]);
+
+    let (status, body) = get(&app, "/api/v1/matches/seen?userId=alice").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["seenProfiles"], json!(["beth"]));
+}
+
+#[actix_web::test]
+async fn test_exclude_user_ids() {
+    let services = services();
+    let app = init_app(&services).await;
+
+    let (status, body) = post(
+        &app,
+        "/api/v1/matches/find",
+        json!({ "userId": "alice", "excludeUserIds": ["cara"] }),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(match_ids(&body), vec!["beth"]);
+}
+
+#[actix_web::test]
+async fn test_conditional_requests() {
+    use actix_web::http::header;
+
+    let services = services();
+    let app = init_app(&services).await;
+
+    let find = || test::TestRequest::post().uri("/api/v1/matches/find").set_json(json!({ "userId": "alice" }));
+    let resp = test::call_service(&app, find().to_request()).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let etag = resp.headers().get(header::ETAG).expect("ETag").to_str().unwrap().to_string();
+
+    // An unchanged feed is not sent again
+    let resp = test::call_service(&app, find().insert_header((header::IF_NONE_MATCH, etag.as_str())).to_request()).await;
+    assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(resp.headers().get(header::ETAG).unwrap().to_str().unwrap(), etag);
+    assert!(test::read_body(resp).await.is_empty());
+
+    let seen = || test::TestRequest::get().uri("/api/v1/matches/seen?userId=alice");
+    let resp = test::call_service(&app, seen().to_request()).await;
+    let seen_etag = resp.headers().get(header::ETAG).unwrap().to_str().unwrap().to_string();
+    let resp = test::call_service(&app, seen().insert_header((header::IF_NONE_MATCH, seen_etag.as_str())).to_request()).await;
+    assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+
+    // A new event changes both bodies
+    let event = json!({ "userId": "alice", "targetUserId": "beth", "eventType": "passed" });
+    let (status, _) = post(&app, "/api/v1/matches/event", event).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let resp = test::call_service(&app, seen().insert_header((header::IF_NONE_MATCH, seen_etag.as_str())).to_request()).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let resp = test::call_service(&app, find().insert_header((header::IF_NONE_MATCH, etag.as_str())).to_request()).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(match_ids(&body), vec!["cara"]);
+}
+
+// ==================== Event recording ====================
+
+#[actix_web::test]
+async fn test_record_event_flow() {
+    let services = services();
+    let app = init_app(&services).await;
+
+    let (status, body) = post(
+        &app,
+        "/api/v1/matches/event",
+        json!({ "userId": "beth", "targetUserId": "alice", "eventType": "Liked" }),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["success"], true);
+    assert!(body["event_id"].as_str().is_some_and(|id| !id.is_empty()));
+    assert_eq!(services.seen.event("beth", "alice"), Some(EventType::Liked));
+
+    // alice now has a pending like from beth
+    let (status, body) = get(&app, "/api/v1/matches/likes-received?userId=alice").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["count"], 1);
+    assert_eq!(body["likes"][0]["userId"], "beth");
+
+    let (status, body) = get(&app, "/api/v1/matches/stats?userId=beth").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["profilesSeen"], 1);
+    assert_eq!(body["likesGiven"], 1);
+
+    // Liking back resolves the pending like
+    let (status, _) = post(
+        &app,
+        "/api/v1/matches/event",
+        json!({ "userId": "alice", "targetUserId": "beth", "eventType": "matched" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (_, body) = get(&app, "/api/v1/matches/likes-received?userId=alice&countOnly=true").await;
+    assert_eq!(body["count"], 0);
+}
+
+#[actix_web::test]
+async fn test_record_event_store_failure() {
+    let services = services();
+    let app = init_app(&services).await;
+    services.seen.set_failing(true);
+
+    let (status, body) = post(
+        &app,
+        "/api/v1/matches/event",
+        json!({ "userId": "alice", "targetUserId": "beth", "eventType": "liked" }),
+    )
+    .await;
+
+    assert!(status.is_server_error());
+    assert_eq!(body["success"], Value::Null);
+    assert_eq!(services.seen.event("alice", "beth"), None);
+
+    // The failed event doesn't block the retry as a duplicate
+    services.seen.set_failing(false);
+    let (status, body) = post(
+        &app,
+        "/api/v1/matches/event",
+        json!({ "userId": "alice", "targetUserId": "beth", "eventType": "liked" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["duplicate"], false);
+    assert_eq!(services.seen.event("alice", "beth"), Some(EventType::Liked));
+}
+
+#[actix_web::test]
+async fn test_duplicate_events_within_window() {
+    let services = services();
+    let app = init_app(&services).await;
+    let like = json!({ "userId": "alice", "targetUserId": "beth", "eventType": "liked" });
+
+    let (status, body) = post(&app, "/api/v1/matches/event", like.clone()).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["duplicate"], false);
+
+    // The double tap is acknowledged but not written; the pass in between is
+    services.seen.set_failing(true);
+    let (status, body) = post(&app, "/api/v1/matches/event", like).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["success"], true);
+    assert_eq!(body["duplicate"], true);
+
+    services.seen.set_failing(false);
+    let (status, body) = post(
+        &app,
+        "/api/v1/matches/event",
+        json!({ "userId": "alice", "targetUserId": "beth", "eventType": "passed" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["duplicate"], false);
+    assert_eq!(services.seen.event("alice", "beth"), Some(EventType::Passed));
+}
+
+// ==================== Match expiry ====================
+
+#[actix_web::test]
+async fn test_expiring_matches() {
+    let services = services();
+    let app = init_app(&services).await;
+    let now = chrono::Utc::now();
+
+    // The test state expires matches after 14 days
+    services
+        .seen
+        .insert_seen_if_absent("alice", "beth", EventType::Matched, now - chrono::Duration::days(12))
+        .await
+        .unwrap();
+    services
+        .seen
+        .insert_seen_if_absent("cara", "alice", EventType::Matched, now - chrono::Duration::days(2))
+        .await
+        .unwrap();
+
+    let (status, body) = get(&app, "/api/v1/matches/expiring?userId=alice").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["expiryDays"], 14);
+    assert_eq!(body["matches"].as_array().unwrap().len(), 1);
+    assert_eq!(body["matches"][0]["userId"], "beth");
+
+    let (_, body) = get(&app, "/api/v1/matches/expiring?userId=alice&withinDays=13").await;
+    assert_eq!(body["matches"].as_array().unwrap().len(), 2);
+
+    // A follow-up from beth resets the clock
+    let (status, _) = post(
+        &app,
+        "/api/v1/matches/event",
+        json!({ "userId": "beth", "targetUserId": "alice", "eventType": "matched" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (_, body) = get(&app, "/api/v1/matches/expiring?userId=alice").await;
+    assert_eq!(body["matches"], json!([]));
+}
+
+// ==================== Tiers ====================
+
+/// `services()` with alice on the given tier
+fn services_with_tier(tier: Tier) -> TestServices {
+    let mut alice = profile("alice", "male", 30);
+    alice.tier = Some(tier);
+    TestServices::new(
+        vec![alice, profile("beth", "female", 28), profile("cara", "female", 31)],
+        vec![preferences("alice", &["female"], 25, 35)],
+    )
+}
+
+#[actix_web::test]
+async fn test_daily_like_limit() {
+    let services = services_with_tier(Tier::Free);
+    let mut state = services.app_state();
+    state.tiers.free.daily_like_limit = Some(1);
+    let app = init_app_with(state).await;
+
+    let like = |target: &str| json!({ "userId": "alice", "targetUserId": target, "eventType": "liked" });
+
+    // Backdating the like past midnight doesn't take it off today's count
+    let mut backdated = like("beth");
+    backdated["occurredAt"] = json!(chrono::Utc::now() - chrono::Duration::days(2));
+    let (status, _) = post(&app, "/api/v1/matches/event", backdated).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, body) = post(&app, "/api/v1/matches/event", like("cara")).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(body["error_code"], "rate_limited");
+    assert_eq!(services.seen.event("alice", "cara"), None);
+
+    // Passing is never limited
+    let (status, _) = post(
+        &app,
+        "/api/v1/matches/event",
+        json!({ "userId": "alice", "targetUserId": "cara", "eventType": "passed" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn test_premium_is_not_like_limited() {
+    let services = services_with_tier(Tier::Premium);
+    let mut state = services.app_state();
+    state.tiers.free.daily_like_limit = Some(0);
+    let app = init_app_with(state).await;
+
+    let (status, _) = post(
+        &app,
+        "/api/v1/matches/event",
+        json!({ "userId": "alice", "targetUserId": "beth", "eventType": "liked" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn test_score_breakdown_by_tier() {
+    let free = services_with_tier(Tier::Free);
+    let app = init_app(&free).await;
+    let (_, body) = post(&app, "/api/v1/matches/find", json!({ "userId": "alice" })).await;
+    assert!(body["matches"][0].get("scoreBreakdown").is_none());
+
+    let premium = services_with_tier(Tier::Premium);
+    let app = init_app(&premium).await;
+    let (status, body) = post(&app, "/api/v1/matches/find", json!({ "userId": "alice" })).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let first = &body["matches"][0];
+    let breakdown = &first["scoreBreakdown"];
+    let total: f64 = ["distance", "age", "sports", "verified", "height", "activity", "tierBoost"]
+        .iter()
+        .map(|factor| breakdown[factor].as_f64().unwrap())
+        .sum();
+    assert!((total - first["matchScore"].as_f64().unwrap()).abs() < 1e-6);
+}
+
+#[actix_web::test]
+async fn test_passport_by_tier() {
+    let mut matching = matching_settings();
+    matching.passport_premium_only = true;
+    let request = json!({ "userId": "alice", "overrideLocation": { "lat": 52.5, "lon": 13.4 } });
+
+    for (tier, expected) in [(Tier::Free, StatusCode::FORBIDDEN), (Tier::Plus, StatusCode::OK)] {
+        let services = services_with_tier(tier);
+        let mut state = services.app_state();
+        state.feed = Arc::new(
+            FeedGenerator::new(
+                state.appwrite.clone(),
+                state.cache.clone(),
+                state.postgres.clone(),
+                Matcher::default(),
+                matching.clone(),
+            )
+            .with_tiers(TierSettings::default()),
+        );
+        let app = init_app_with(state).await;
+
+        let (status, body) = post(&app, "/api/v1/matches/find", request.clone()).await;
+        assert_eq!(status, expected, "{:?}: {}", tier, body);
+    }
+}
+
+// ==================== Who viewed me ====================
+
+#[actix_web::test]
+async fn test_viewers() {
+    let services = services_with_tier(Tier::Premium);
+    let app = init_app(&services).await;
+    let now = chrono::Utc::now();
+    let seen = |user: &'static str, event_type: EventType, days_ago: i64| {
+        services
+            .seen
+            .insert_seen_if_absent(user, "alice", event_type, now - chrono::Duration::days(days_ago))
+    };
+
+    seen("beth", EventType::Viewed, 2).await.unwrap();
+    seen("cara", EventType::Liked, 1).await.unwrap();
+    seen("dana", EventType::Passed, 1).await.unwrap();
+    seen("erin", EventType::Viewed, 40).await.unwrap();
+
+    let (status, body) = get(&app, "/api/v1/matches/viewers?userId=alice").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["count"], 2);
+    assert_eq!(body["windowDays"], 30);
+    assert_eq!(body["viewers"][0]["userId"], "cara");
+    assert_eq!(body["viewers"][0]["eventType"], "liked");
+    assert_eq!(body["viewers"][1]["userId"], "beth");
+
+    let (_, body) = get(&app, "/api/v1/matches/viewers?userId=alice&limit=1&offset=1").await;
+    assert_eq!(body["count"], 2);
+    assert_eq!(body["viewers"].as_array().unwrap().len(), 1);
+    assert_eq!(body["viewers"][0]["userId"], "beth");
+}
+
+#[actix_web::test]
+async fn test_viewers_gated() {
+    let services = services_with_tier(Tier::Plus);
+    let app = init_app(&services).await;
+    let (status, body) = get(&app, "/api/v1/matches/viewers?userId=alice").await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["error_code"], "premium_required");
+
+    let services = services_with_tier(Tier::Premium);
+    let mut state = services.app_state();
+    state.viewers.enabled = false;
+    let app = init_app_with(state).await;
+    let (status, body) = get(&app, "/api/v1/matches/viewers?userId=alice").await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["error_code"], "viewers_disabled");
+}
+
+// ==================== Admin audit log ====================
+
+#[actix_web::test]
+async fn test_admin_actions_are_audited() {
+    let services = services();
+    services.seen.record_seen("alice", "beth", EventType::Passed).await.unwrap();
+    let mut state = services.app_state();
+    state.admin_token = Some("secret".to_string());
+    let app = init_app_with(state).await;
+
+    let admin = |req: test::TestRequest| {
+        req.insert_header(("X-Admin-Token", "secret"))
+            .insert_header(("X-Admin-Actor", "ops@lume"))
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, admin(test::TestRequest::delete().uri("/api/v1/admin/users/alice/seen"))).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["deleted"], 1);
+    assert_eq!(services.seen.event("alice", "beth"), None);
+
+    let resp = test::call_service(&app, admin(test::TestRequest::post().uri("/api/v1/admin/matches/expire"))).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    // Rejected before anything is deleted, so it isn't audited
+    let resp = test::call_service(&app, admin(test::TestRequest::post().uri("/api/v1/admin/seen/prune?retentionDays=0"))).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["error_code"], "invalid_retention");
+
+    let resp = test::call_service(&app, admin(test::TestRequest::get().uri("/api/v1/admin/audit"))).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(resp).await;
+    let entries = body["entries"].as_array().unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0]["action"], "matches.expire");
+    assert_eq!(entries[1]["action"], "seen.clear");
+    assert_eq!(entries[1]["actor"], "ops@lume");
+    assert_eq!(entries[1]["target"], "alice");
+    assert_eq!(entries[1]["details"]["deleted"], 1);
+
+    let resp = test::call_service(
+        &app,
+        admin(test::TestRequest::get().uri("/api/v1/admin/audit?action=seen.clear&limit=5")),
+    )
+    .await;
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["entries"].as_array().unwrap().len(), 1);
+    assert_eq!(body["limit"], 5);
+
+    // The audit log is behind the admin token too
+    let (status, _) = get(&app, "/api/v1/admin/audit").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let req = test::TestRequest::get().uri("/api/v1/admin/audit").insert_header(("X-Admin-Token", "secreT")).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn test_admin_cache_stats() {
+    let mut state = services().app_state();
+    state.admin_token = Some("secret".to_string());
+    let app = init_app_with(state).await;
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/admin/cache/stats")
+        .insert_header(("X-Admin-Token", "secret"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["stats"]["l1"]["hits"], 0);
+    assert_eq!(body["recomputes"]["inFlight"], 0);
+
+    let (status, _) = get(&app, "/api/v1/admin/cache/stats").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+// ==================== Tenants ====================
+
+#[actix_web::test]
+async fn test_tenant_resolution() {
+    let default = services();
+    let acme = TestServices::new(
+        vec![profile("zoe", "male", 30), profile("yara", "female", 29)],
+        vec![preferences("zoe", &["female"], 25, 35)],
+    );
+    let app = init_app_with(TenantRegistry::new(default.app_state()).with_tenant("acme", acme.app_state())).await;
+
+    let find = |user_id: &str, tenant: Option<&str>| {
+        let mut req = test::TestRequest::post()
+            .uri("/api/v1/matches/find")
+            .set_json(json!({ "userId": user_id }));
+        if let Some(tenant) = tenant {
+            req = req.insert_header((TENANT_HEADER, tenant));
+        }
+        req.to_request()
+    };
+
+    // Without the header the default tenant answers
+    let resp = test::call_service(&app, find("alice", None)).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(match_ids(&test::read_body_json(resp).await), vec!["beth", "cara"]);
+
+    let resp = test::call_service(&app, find("zoe", Some("acme"))).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(match_ids(&test::read_body_json(resp).await), vec!["yara"]);
+
+    // Each tenant only sees its own profiles
+    let resp = test::call_service(&app, find("alice", Some("acme"))).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    let resp = test::call_service(&app, find("alice", Some("globex"))).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["error_code"], "unknown_tenant");
+}
+
+#[actix_web::test]
+async fn test_signed_routes() {
+    let services = services();
+    let verifier = SignatureVerifier::new("s3cret", 300)
+        .with_routes(vec!["/api/v1/matches/event".to_string()])
+        .with_body_limit(1024);
+    let signer = SignatureVerifier::new("s3cret", 300);
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(TenantRegistry::from(services.app_state())))
+            .app_data(web::Data::new(verifier))
+            .app_data(web::JsonConfig::default().error_handler(handle_json_payload_error))
+            .configure(|cfg| routes::configure_routes(cfg, PayloadLimitSettings::default())),
+    )
+    .await;
+
+    let body = json!({ "userId": "alice", "targetUserId": "beth", "eventType": "passed" }).to_string();
+    let event = |signature: Option<&str>| {
+        let mut req = test::TestRequest::post()
+            .uri("/api/v1/matches/event")
+            .insert_header(("Content-Type", "application/json"))
+            .set_payload(body.clone());
+        if let Some(signature) = signature {
+            req = req.insert_header((SIGNATURE_HEADER, signature.to_string()));
+        }
+        req.to_request()
+    };
+
+    let resp = test::call_service(&app, event(None)).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    let error: Value = test::read_body_json(resp).await;
+    assert_eq!(error["error_code"], "unauthorized");
+
+    // The handler still sees the body the signature was checked against
+    let signature = signer.sign(chrono::Utc::now().timestamp(), "POST", "/api/v1/matches/event", body.as_bytes());
+    let resp = test::call_service(&app, event(Some(&signature))).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(services.seen.get_seen_profiles("alice").await.unwrap(), vec!["beth".to_string()]);
+
+    let resp = test::call_service(&app, event(Some(&signature))).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    let stale = signer.sign(chrono::Utc::now().timestamp() - 600, "POST", "/api/v1/matches/event", body.as_bytes());
+    let resp = test::call_service(&app, event(Some(&stale))).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    // Bodies over the verifier's limit aren't buffered
+    let large = json!({ "userId": "alice", "targetUserId": "x".repeat(2048), "eventType": "passed" }).to_string();
+    let signature = signer.sign(chrono::Utc::now().timestamp(), "POST", "/api/v1/matches/event", large.as_bytes());
+    let req = test::TestRequest::post()
+        .uri("/api/v1/matches/event")
+        .insert_header(("Content-Type", "application/json"))
+        .insert_header((SIGNATURE_HEADER, signature))
+        .set_payload(large)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    // Routes outside the configured prefixes stay unsigned
+    let (status, _) = post(&app, "/api/v1/matches/find", json!({ "userId": "alice" })).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn test_find_matches_sheds_load_when_saturated() {
+    let services = services();
+    let mut state = services.app_state();
+    state.find_limiter = Arc::new(ConcurrencyLimiter::new(&ConcurrencySettings {
+        max_concurrent: 1,
+        queue_timeout_ms: 10,
+        retry_after_secs: 2,
+    }));
+    let limiter = state.find_limiter.clone();
+    let app = init_app_with(state).await;
+
+    // Another pipeline holds the only slot
+    let permit = limiter.acquire().await.unwrap();
+    let req = test::TestRequest::post()
+        .uri("/api/v1/matches/find")
+        .set_json(json!({ "userId": "alice" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(resp.headers().get("Retry-After").unwrap(), "2");
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["error_code"], "overloaded");
+
+    drop(permit);
+    let (status, body) = post(&app, "/api/v1/matches/find", json!({ "userId": "alice" })).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(match_ids(&body), vec!["beth", "cara"]);
+
+    let (_, metrics) = get(&app, "/api/v1/metrics").await;
+    assert_eq!(metrics["find_concurrency"]["rejected"], 1);
+    assert_eq!(metrics["find_concurrency"]["in_flight"], 0);
+}
+
+#[actix_web::test]
+async fn test_find_matches_diagnostics() {
+    let services = services();
+    let app = init_app(&services).await;
+
+    let (status, body) = post(&app, "/api/v1/matches/find", json!({ "userId": "alice" })).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.get("diagnostics").is_none());
+
+    let (status, body) = post(
+        &app,
+        "/api/v1/matches/find",
+        json!({ "userId": "alice", "excludeUserIds": ["beth"], "diagnostics": true }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(match_ids(&body), vec!["cara"]);
+
+    let diagnostics = &body["diagnostics"];
+    assert_eq!(diagnostics["seen_excluded"], 1);
+    assert_eq!(diagnostics["geo_candidates"], 1);
+    assert_eq!(diagnostics["unparseable"], 0);
+    assert_eq!(diagnostics["vector_candidates"], 0);
+    assert_eq!(diagnostics["blocked_by_rules"], 0);
+    assert_eq!(diagnostics["geo"], 0);
+    assert_eq!(diagnostics["demographics"], 0);
+    assert_eq!(diagnostics["ranked"], 1);
+}
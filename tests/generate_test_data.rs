@@ -1,10 +1,15 @@
-/// Test data generator for Lume Algo
-///
-/// Generates CSV files containing test profiles and preferences
-/// that can be imported via Appwrite Console.
-///
-/// Run: cargo run --bin generate-test-data
-
+//! Test data generator for Lume Algo
+//!
+//! Generates CSV files containing test profiles and preferences
+//! that can be imported via Appwrite Console.
+//!
+//! Run: cargo run --bin generate-test-data
+//! Set `SEED=<u64>` to reproduce a previous data set.
+
+use lume_algo::util::rng;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::Rng;
 use std::fs::File;
 use std::io::{BufWriter, Write};
 
@@ -40,7 +45,6 @@ const CITIES: &[(&str, f64, f64)] = &[
 ];
 
 struct Profile {
-    document_id: String,
     user_id: String,
     name: String,
     age: u8,
@@ -61,7 +65,6 @@ struct Profile {
 }
 
 struct Preferences {
-    document_id: String,
     user_id: String,
     preferred_genders: String,
     min_age: u16,
@@ -77,43 +80,27 @@ struct Preferences {
     notifications_enabled: bool,
 }
 
-// Simple random number generator using system time
-fn get_seed() -> u64 {
-    use std::time::{SystemTime, UNIX_EPOCH};
-    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64
-}
-
-fn rand_range(min: f64, max: f64) -> f64 {
-    let seed = get_seed();
-    let normalized = (seed as f64) / (u64::MAX as f64);
-    min + normalized * (max - min)
+fn rand_range(rng: &mut StdRng, min: f64, max: f64) -> f64 {
+    rng.gen_range(min..max)
 }
 
-fn rand_int(max: usize) -> usize {
-    (get_seed() % max as u64) as usize
+fn rand_int(rng: &mut StdRng, max: usize) -> usize {
+    rng.gen_range(0..max)
 }
 
-fn rand_choice_str_slice<'a>(options: &'a [&'a str]) -> &'a str {
-    &options[rand_int(options.len())]
+fn rand_choice_str_slice<'a>(rng: &mut StdRng, options: &'a [&'a str]) -> &'a str {
+    options.choose(rng).unwrap()
 }
 
-fn rand_choice_city(options: &[( &'static str, f64, f64)]) -> (&'static str, f64, f64) {
-    let idx = rand_int(options.len());
-    options[idx]
+fn rand_choice_city(rng: &mut StdRng, options: &[(&'static str, f64, f64)]) -> (&'static str, f64, f64) {
+    *options.choose(rng).unwrap()
 }
 
-fn rand_choices_str(options: &[&str], count: usize) -> Vec<String> {
-    let mut result = Vec::new();
-    let mut used = std::collections::HashSet::new();
-    let mut attempts = 0;
-    while result.len() < count.min(options.len()) && attempts < 100 {
-        let idx = rand_int(options.len());
-        if used.insert(idx) {
-            result.push(options[idx].to_string());
-        }
-        attempts += 1;
-    }
-    result
+fn rand_choices_str(rng: &mut StdRng, options: &[&str], count: usize) -> Vec<String> {
+    options
+        .choose_multiple(rng, count.min(options.len()))
+        .map(|s| s.to_string())
+        .collect()
 }
 
 fn format_timestamp() -> String {
@@ -132,6 +119,8 @@ fn escape_csv(s: &str) -> String {
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let num_profiles = 1000;
+    let seed = std::env::var("SEED").ok().and_then(|seed| seed.parse().ok());
+    let mut rng = rng::seeded_or_entropy(seed);
 
     println!("Generating {} test profiles...", num_profiles);
 
@@ -139,30 +128,28 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let mut preferences = Vec::new();
 
     for user_num in 0..num_profiles {
-        std::thread::sleep(std::time::Duration::from_millis(1)); // Seed variation
-
         let user_id = format!("test_user_{:04}", user_num);
-        let age = 18 + rand_int(72); // 18-90
+        let age = 18 + rand_int(&mut rng, 72); // 18-90
 
         // Pick a city with some random offset
-        let (city_name, base_lat, base_lon) = rand_choice_city(CITIES);
-        let lat = base_lat + rand_range(-0.1, 0.1);
-        let lon = base_lon + rand_range(-0.1, 0.1);
+        let (city_name, base_lat, base_lon) = rand_choice_city(&mut rng, CITIES);
+        let lat = base_lat + rand_range(&mut rng, -0.1, 0.1);
+        let lon = base_lon + rand_range(&mut rng, -0.1, 0.1);
 
-        let gender = rand_choice_str_slice(GENDERS);
-        let hair_color = rand_choice_str_slice(HAIR_COLORS);
-        let height_cm = 150 + rand_int(70); // 150-220 cm
+        let gender = rand_choice_str_slice(&mut rng, GENDERS);
+        let hair_color = rand_choice_str_slice(&mut rng, HAIR_COLORS);
+        let height_cm = 150 + rand_int(&mut rng, 70); // 150-220 cm
 
         // Generate some sports preferences (1-5 sports)
-        let sports_preferences: Vec<String> = rand_choices_str(SPORTS, 1 + rand_int(5));
+        let sport_count = 1 + rand_int(&mut rng, 5);
+        let sports_preferences: Vec<String> = rand_choices_str(&mut rng, SPORTS, sport_count);
 
         let timestamp = format_timestamp();
-        let is_verified = rand_int(10) > 7; // 30% verified
+        let is_verified = rand_int(&mut rng, 10) > 7; // 30% verified
 
         let profile = Profile {
-            document_id: format!("test_profile_{:04}", user_num),
             user_id: user_id.clone(),
-            name: format!("{} {:?}", rand_choice_str_slice(NAMES), user_num),
+            name: format!("{} {:?}", rand_choice_str_slice(&mut rng, NAMES), user_num),
             age: age as u8,
             height_cm: height_cm as u16,
             hair_color: hair_color.to_string(),
@@ -182,7 +169,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         profiles.push(profile);
 
         // Create preferences - realistic based on their own profile
-        let preferred_genders: Vec<String> = if rand_int(3) > 0 {
+        let preferred_genders: Vec<String> = if rand_int(&mut rng, 3) > 0 {
             match gender {
                 "male" => vec!["female", "non_binary"],
                 "female" => vec!["male", "non_binary"],
@@ -201,12 +188,13 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         let min_height_cm = (height_cm as i16 - 10).max(140) as u16;
         let max_height_cm = (height_cm as i16 + 20).min(230) as u16;
 
-        let preferred_hair_colors: Vec<String> = rand_choices_str(HAIR_COLORS, 2 + rand_int(4));
-        let preferred_sports: Vec<String> = rand_choices_str(SPORTS, 3 + rand_int(6));
-        let max_distance_km = 25 + rand_int(175); // 25-200 km
+        let hair_color_count = 2 + rand_int(&mut rng, 4);
+        let preferred_hair_colors: Vec<String> = rand_choices_str(&mut rng, HAIR_COLORS, hair_color_count);
+        let preferred_sport_count = 3 + rand_int(&mut rng, 6);
+        let preferred_sports: Vec<String> = rand_choices_str(&mut rng, SPORTS, preferred_sport_count);
+        let max_distance_km = 25 + rand_int(&mut rng, 175); // 25-200 km
 
         let prefs = Preferences {
-            document_id: format!("test_prefs_{:04}", user_num),
             user_id,
             preferred_genders: format!("[\"{}\"]", preferred_genders.join("\",\"")),
             min_age,
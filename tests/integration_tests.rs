@@ -1,7 +1,7 @@
 // Integration tests for Lume Algo
 
 use lume_algo::core::{Matcher, distance::{haversine_distance, calculate_bounding_box}};
-use lume_algo::models::{UserProfile, UserPreferences, ScoringWeights};
+use lume_algo::models::{UserProfile, UserPreferences, SCHEMA_VERSION};
 use chrono::Utc;
 
 fn create_test_profile(
@@ -15,18 +15,34 @@ fn create_test_profile(
         user_id: id.to_string(),
         name: format!("User {}", id),
         age,
-        height_cm: 170,
+        height_cm: Some(170),
         hair_color: "brown".to_string(),
         gender: gender.to_string(),
         latitude: lat,
         longitude: lon,
-        is_verified: true,
+        is_verified: Some(true),
+        verification: None,
         is_active: true,
-        is_timeout: false,
+        is_timeout: Some(false),
         image_file_ids: vec![],
         description: None,
         sports_preferences: vec!["tennis".to_string()],
-        created_at: Utc::now(),
+        interests: vec![],
+        created_at: Some(Utc::now()),
+        timezone: None,
+        is_premium: None,
+        is_snoozed: None,
+        tenant_id: None,
+        market: None,
+        city: None,
+        tier: None,
+        embedding: None,
+        interested_in: vec![],
+        languages: vec![],
+        relationship_goal: None,
+        attributes: Default::default(),
+        updated_at: None,
+        schema_version: SCHEMA_VERSION,
     }
 }
 
@@ -40,9 +56,19 @@ fn create_test_preferences(lat: f64, lon: f64) -> UserPreferences {
         max_height_cm: 180,
         preferred_hair_colors: vec![],
         preferred_sports: vec!["tennis".to_string()],
+        preferred_interests: vec![],
         max_distance_km: 50,
         latitude: lat,
         longitude: lon,
+        require_photos: None,
+        embedding: None,
+        preferred_languages: vec![],
+        require_shared_language: false,
+        verified_only: false,
+        relationship_goal: None,
+        attributes: Default::default(),
+        updated_at: None,
+        schema_version: SCHEMA_VERSION,
     }
 }
 
@@ -122,6 +148,8 @@ fn test_bounding_box_filtering() {
 
     let distance_to_inside = haversine_distance(center_lat, center_lon, inside_lat, inside_lon);
     assert!(distance_to_inside < radius_km, "Test point should be within radius");
+    assert!(inside_lat > bbox.min_lat && inside_lat < bbox.max_lat);
+    assert!(inside_lon > bbox.min_lon && inside_lon < bbox.max_lon);
 
     // Points far outside
     let far_lat = 50.0;
@@ -146,7 +174,7 @@ fn test_score_range() {
 
     for m in &result.matches {
         assert!(
-            m.match_score >= 0.0 && m.match_score <= 100.0,
+            (0.0..=100.0).contains(&m.match_score),
             "Score {} is out of range [0, 100]",
             m.match_score
         );
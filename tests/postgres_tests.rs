@@ -0,0 +1,230 @@
+// PostgreSQL tests for PostgresClient
+//
+// Uses the database in LUME_TEST_DATABASE_URL (e.g. the docker-compose
+// postgres service) or starts a container through testcontainers. Each
+// test migrates a fresh schema and drops it afterwards. Without either,
+// the tests are skipped.
+
+use chrono::Utc;
+use lume_algo::services::{EventType, PostgresClient};
+use lume_algo::testing::containers::{self, TestContainer};
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+use sqlx::PgPool;
+use std::str::FromStr;
+
+struct TestDatabase {
+    client: PostgresClient,
+    /// Connection to the test schema, for checking tables directly
+    pool: PgPool,
+    server: TestContainer,
+    schema: String,
+}
+
+impl TestDatabase {
+    async fn connect() -> Option<Self> {
+        let server = containers::postgres().await?;
+
+        let schema = format!("test_{}", uuid::Uuid::new_v4().simple());
+        let client = PostgresClient::with_schema(&server.url, 4, 0, Some(&schema)).await.expect("Failed to migrate the test schema");
+        let options = PgConnectOptions::from_str(&server.url).unwrap().options([("search_path", schema.as_str())]);
+        let pool = PgPoolOptions::new().max_connections(1).connect_with(options).await.unwrap();
+
+        Some(Self { client, pool, server, schema })
+    }
+
+    async fn count(&self, query: &str) -> i64 {
+        sqlx::query_scalar(query).fetch_one(&self.pool).await.unwrap()
+    }
+
+    async fn drop_schema(self) {
+        self.pool.close().await;
+        let pool = PgPool::connect(&self.server.url).await.unwrap();
+        sqlx::query(&format!("DROP SCHEMA {} CASCADE", self.schema)).execute(&pool).await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_seen_profile_flow() {
+    let Some(db) = TestDatabase::connect().await else {
+        return;
+    };
+
+    db.client.record_event("alice", "beth", EventType::Viewed, Utc::now()).await.unwrap();
+    db.client.record_event("alice", "carla", EventType::Passed, Utc::now()).await.unwrap();
+    // A later event replaces the earlier one for the same pair
+    db.client.record_event("alice", "beth", EventType::Liked, Utc::now()).await.unwrap();
+
+    let mut seen = db.client.get_seen_profiles("alice").await.unwrap();
+    seen.sort();
+    assert_eq!(seen, vec!["beth", "carla"]);
+    assert_eq!(db.client.get_seen_stats("alice").await.unwrap().liked, 1);
+    assert!(db.client.get_seen_profiles("beth").await.unwrap().is_empty());
+
+    assert!(db.client.remove_seen("alice", "carla").await.unwrap());
+    assert_eq!(db.client.get_seen_profiles("alice").await.unwrap(), vec!["beth"]);
+    assert_eq!(db.client.clear_seen_profiles("alice").await.unwrap(), 1);
+    assert!(db.client.get_seen_profiles("alice").await.unwrap().is_empty());
+
+    db.drop_schema().await;
+}
+
+#[tokio::test]
+async fn test_record_event_detects_mutual_matches() {
+    let Some(db) = TestDatabase::connect().await else {
+        return;
+    };
+
+    let recorded = db.client.record_event("alice", "beth", EventType::Liked, Utc::now()).await.unwrap();
+    assert!(!recorded.mutual_match);
+    let recorded = db.client.record_event("alice", "carla", EventType::Passed, Utc::now()).await.unwrap();
+    assert!(!recorded.mutual_match);
+
+    let recorded = db.client.record_event("beth", "alice", EventType::Liked, Utc::now()).await.unwrap();
+    assert!(recorded.mutual_match);
+
+    assert_eq!(db.client.get_seen_stats("alice").await.unwrap().matched, 1);
+    assert_eq!(db.client.get_seen_stats("beth").await.unwrap().matched, 1);
+    // Three events plus a `matched` entry for each side
+    assert_eq!(db.count("SELECT COUNT(*) FROM match_event_history").await, 5);
+    assert_eq!(db.count("SELECT COUNT(*) FROM match_event_history WHERE event_type = 'matched'").await, 2);
+    assert_eq!(db.count("SELECT COUNT(*) FROM mutual_matches WHERE user_a = 'alice' AND user_b = 'beth'").await, 1);
+
+    // Carla liking back a pass is no match
+    let recorded = db.client.record_event("carla", "alice", EventType::Liked, Utc::now()).await.unwrap();
+    assert!(!recorded.mutual_match);
+
+    db.drop_schema().await;
+}
+
+#[tokio::test]
+async fn test_like_answered_after_a_view_is_a_match() {
+    let Some(db) = TestDatabase::connect().await else {
+        return;
+    };
+
+    db.client.record_event("alice", "beth", EventType::Liked, Utc::now()).await.unwrap();
+    // Alice's seen record is no longer `liked`, her like still stands
+    db.client.record_event("alice", "beth", EventType::Viewed, Utc::now()).await.unwrap();
+    let recorded = db.client.record_event("beth", "alice", EventType::Liked, Utc::now()).await.unwrap();
+    assert!(recorded.mutual_match);
+
+    assert_eq!(db.client.get_seen_stats("alice").await.unwrap().matched, 1);
+    assert_eq!(db.client.get_seen_stats("beth").await.unwrap().matched, 1);
+    assert_eq!(db.count("SELECT COUNT(*) FROM mutual_matches WHERE user_a = 'alice' AND user_b = 'beth'").await, 1);
+
+    // Liking again doesn't match the pair twice
+    let recorded = db.client.record_event("alice", "beth", EventType::Liked, Utc::now()).await.unwrap();
+    assert!(!recorded.mutual_match);
+    assert_eq!(db.count("SELECT COUNT(*) FROM match_event_history WHERE event_type = 'matched'").await, 2);
+
+    db.drop_schema().await;
+}
+
+#[tokio::test]
+async fn test_likes_received_survive_later_events() {
+    let Some(db) = TestDatabase::connect().await else {
+        return;
+    };
+
+    db.client.record_event("alice", "beth", EventType::Liked, Utc::now()).await.unwrap();
+    // Alice's seen record moves on, the like stays with Beth
+    db.client.record_event("alice", "beth", EventType::Viewed, Utc::now()).await.unwrap();
+    let likes = db.client.get_likes_received("beth", 10, 0).await.unwrap();
+    assert_eq!(likes.len(), 1);
+    assert_eq!(likes[0].user_id, "alice");
+
+    db.client.record_event("beth", "alice", EventType::Passed, Utc::now()).await.unwrap();
+    assert_eq!(db.client.count_likes_received("beth").await.unwrap(), 0);
+
+    db.drop_schema().await;
+}
+
+#[tokio::test]
+async fn test_record_event_rolls_back_on_failure() {
+    let Some(db) = TestDatabase::connect().await else {
+        return;
+    };
+
+    // Longer than seen_profiles.user_id allows: the history insert
+    // succeeds, the seen upsert after it fails
+    let too_long = "x".repeat(300);
+    let result = db.client.record_event(&too_long, "beth", EventType::Liked, Utc::now()).await;
+    assert!(result.is_err());
+
+    assert_eq!(db.count("SELECT COUNT(*) FROM match_event_history").await, 0);
+    assert_eq!(db.count("SELECT COUNT(*) FROM seen_profiles").await, 0);
+
+    db.drop_schema().await;
+}
+
+#[tokio::test]
+async fn test_crossing_likes_match_once() {
+    let Some(db) = TestDatabase::connect().await else {
+        return;
+    };
+
+    for round in 0..10 {
+        let (a, b) = (format!("a{}", round), format!("b{}", round));
+        let (ab, ba) = tokio::join!(
+            db.client.record_event(&a, &b, EventType::Liked, Utc::now()),
+            db.client.record_event(&b, &a, EventType::Liked, Utc::now()),
+        );
+        assert!(ab.unwrap().mutual_match ^ ba.unwrap().mutual_match, "round {}", round);
+    }
+
+    assert_eq!(db.count("SELECT COUNT(*) FROM seen_profiles WHERE event_type = 'matched'").await, 20);
+
+    db.drop_schema().await;
+}
+
+// Queries are built at runtime rather than with sqlx's checked macros, so
+// this runs every read mapped through FromRow against the migrated schema:
+// a renamed or retyped column fails here instead of in production
+#[tokio::test]
+async fn test_row_mappings_match_the_schema() {
+    let Some(db) = TestDatabase::connect().await else {
+        return;
+    };
+
+    let now = Utc::now();
+    let hour_ago = now - chrono::Duration::hours(1);
+    db.client.record_event("alice", "beth", EventType::Viewed, now).await.unwrap();
+    db.client.record_event("carla", "alice", EventType::Liked, now).await.unwrap();
+    db.client.record_event("alice", "carla", EventType::Liked, now).await.unwrap();
+
+    assert_eq!(db.client.get_seen_since(hour_ago, 10).await.unwrap().len(), 3);
+    assert_eq!(db.client.get_seen_profiles_paginated("alice", 10, 0).await.unwrap().len(), 2);
+    assert_eq!(db.client.get_seen_stats("alice").await.unwrap().total_seen, 2);
+    db.client.get_likes_received("alice", 10, 0).await.unwrap();
+    db.client.count_likes_received("alice").await.unwrap();
+    assert_eq!(db.client.count_likes_since("alice", hour_ago).await.unwrap(), 1);
+    db.client.get_viewers("beth", hour_ago, 10, 0).await.unwrap();
+    db.client.count_viewers("beth", hour_ago).await.unwrap();
+    db.client.score_outcomes(hour_ago, 10).await.unwrap();
+    db.client.get_stale_matches("alice", now).await.unwrap();
+    db.client.get_profile_sync_state().await.unwrap();
+    db.client.lease_profile_sync(60).await.unwrap();
+
+    db.drop_schema().await;
+}
+
+#[tokio::test]
+async fn test_revert_latest_migration() {
+    let Some(db) = TestDatabase::connect().await else {
+        return;
+    };
+
+    let status = db.client.migration_status().await.unwrap();
+    assert!(status.iter().all(|migration| migration.installed_on.is_some()));
+    let latest = status.last().unwrap().version;
+
+    // Only the latest migration can be reverted
+    assert!(db.client.revert_migration(latest - 1).await.is_err());
+    db.client.revert_migration(latest).await.unwrap();
+
+    let status = db.client.migration_status().await.unwrap();
+    assert!(status.last().unwrap().installed_on.is_none());
+    assert!(status[..status.len() - 1].iter().all(|migration| migration.installed_on.is_some()));
+
+    db.drop_schema().await;
+}
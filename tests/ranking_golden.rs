@@ -0,0 +1,101 @@
+// Golden-file regression tests for ranking
+//
+// tests/golden/ranking_input.json holds a frozen set of 200 synthetic
+// candidates and a few searchers; tests/golden/ranking_expected.json the
+// top 20 the default matcher returns for each. Any change to filters or
+// scoring that moves a result fails here. If the change is intended,
+// regenerate the expected file and review its diff with the change:
+//
+//     LUME_UPDATE_GOLDEN=1 cargo test --test ranking_golden
+
+use lume_algo::core::Matcher;
+use lume_algo::models::{UserPreferences, UserProfile};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+const LIMIT: usize = 20;
+
+/// Scores are compared with this tolerance, so float noise across
+/// platforms doesn't fail the suite; the order must match exactly
+const SCORE_TOLERANCE: f64 = 1e-3;
+
+#[derive(Deserialize)]
+struct GoldenInput {
+    candidates: Vec<UserProfile>,
+    cases: Vec<GoldenCase>,
+}
+
+#[derive(Deserialize)]
+struct GoldenCase {
+    name: String,
+    preferences: UserPreferences,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RankedMatch {
+    #[serde(rename = "userId")]
+    user_id: String,
+    score: f64,
+}
+
+fn golden_path(file: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(file)
+}
+
+fn rank(input: &GoldenInput) -> BTreeMap<String, Vec<RankedMatch>> {
+    let matcher = Matcher::default();
+    input
+        .cases
+        .iter()
+        .map(|case| {
+            let result = matcher.find_matches(&case.preferences, input.candidates.clone(), LIMIT);
+            let ranked = result
+                .matches
+                .into_iter()
+                .map(|m| RankedMatch { user_id: m.user_id, score: (m.match_score * 10_000.0).round() / 10_000.0 })
+                .collect();
+            (case.name.clone(), ranked)
+        })
+        .collect()
+}
+
+#[test]
+fn test_ranking_matches_golden_file() {
+    let input: GoldenInput = serde_json::from_str(&std::fs::read_to_string(golden_path("ranking_input.json")).unwrap()).unwrap();
+    assert_eq!(input.candidates.len(), 200);
+    let actual = rank(&input);
+
+    let expected_path = golden_path("ranking_expected.json");
+    if std::env::var("LUME_UPDATE_GOLDEN").is_ok() {
+        std::fs::write(&expected_path, serde_json::to_string_pretty(&actual).unwrap() + "\n").unwrap();
+        eprintln!("Wrote {}", expected_path.display());
+        return;
+    }
+
+    let expected: BTreeMap<String, Vec<RankedMatch>> = serde_json::from_str(&std::fs::read_to_string(&expected_path).unwrap()).unwrap();
+    assert_eq!(actual.keys().collect::<Vec<_>>(), expected.keys().collect::<Vec<_>>(), "cases differ from the golden file");
+
+    let mut failures = Vec::new();
+    for (name, expected) in &expected {
+        let actual = &actual[name];
+        let actual_ids: Vec<&str> = actual.iter().map(|m| m.user_id.as_str()).collect();
+        let expected_ids: Vec<&str> = expected.iter().map(|m| m.user_id.as_str()).collect();
+        if actual_ids != expected_ids {
+            failures.push(format!("{}: ranked\n    {:?}\n  expected\n    {:?}", name, actual_ids, expected_ids));
+            continue;
+        }
+
+        for (actual, expected) in actual.iter().zip(expected) {
+            if (actual.score - expected.score).abs() > SCORE_TOLERANCE {
+                failures.push(format!("{}: {} scored {} instead of {}", name, actual.user_id, actual.score, expected.score));
+            }
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "Ranking differs from tests/golden/ranking_expected.json (rerun with LUME_UPDATE_GOLDEN=1 if intended):\n{}",
+        failures.join("\n")
+    );
+}
@@ -0,0 +1,179 @@
+// Property tests for the scoring invariants
+//
+// Guards the shape of the formula rather than exact numbers, so weight and
+// curve changes stay free as long as they keep these properties.
+
+use chrono::Utc;
+use lume_algo::core::scoring::{
+    calculate_age_score, calculate_distance_score, calculate_height_score, calculate_match_score, calculate_match_score_at_distance,
+    calculate_score_breakdown,
+};
+use lume_algo::models::{ScoringWeights, UserPreferences, UserProfile, Verification, SCHEMA_VERSION};
+use proptest::prelude::*;
+
+const SPORTS: &[&str] = &["tennis", "running", "climbing", "yoga", "cycling"];
+
+fn profile(age: u8, height_cm: Option<u16>, verification: Verification, latitude: f64, longitude: f64, sports: Vec<String>) -> UserProfile {
+    UserProfile {
+        user_id: "candidate".to_string(),
+        name: "Candidate".to_string(),
+        age,
+        height_cm,
+        hair_color: "brown".to_string(),
+        gender: "female".to_string(),
+        latitude,
+        longitude,
+        is_verified: None,
+        verification: Some(verification),
+        is_active: true,
+        is_timeout: Some(false),
+        image_file_ids: vec![],
+        description: None,
+        sports_preferences: sports,
+        interests: vec![],
+        created_at: Some(Utc::now()),
+        timezone: None,
+        is_premium: None,
+        is_snoozed: None,
+        tenant_id: None,
+        market: None,
+        city: None,
+        tier: None,
+        embedding: None,
+        interested_in: vec![],
+        languages: vec![],
+        relationship_goal: None,
+        attributes: Default::default(),
+        updated_at: None,
+        schema_version: SCHEMA_VERSION,
+    }
+}
+
+fn verification() -> impl Strategy<Value = Verification> {
+    prop_oneof![Just(Verification::Unverified), Just(Verification::Photo), Just(Verification::Id)]
+}
+
+fn sports() -> impl Strategy<Value = Vec<String>> {
+    proptest::sample::subsequence(SPORTS, 0..=SPORTS.len()).prop_map(|sports| sports.iter().map(|sport| sport.to_string()).collect())
+}
+
+fn arb_profile() -> impl Strategy<Value = UserProfile> {
+    (any::<u8>(), proptest::option::of(any::<u16>()), verification(), -90.0..=90.0f64, -180.0..=180.0f64, sports())
+        .prop_map(|(age, height_cm, verification, latitude, longitude, sports)| profile(age, height_cm, verification, latitude, longitude, sports))
+}
+
+fn arb_preferences() -> impl Strategy<Value = UserPreferences> {
+    (any::<(u8, u8)>(), any::<(u16, u16)>(), any::<u16>(), -90.0..=90.0f64, -180.0..=180.0f64, sports()).prop_map(
+        |((min_age, max_age), (min_height_cm, max_height_cm), max_distance_km, latitude, longitude, preferred_sports)| UserPreferences {
+            user_id: "viewer".to_string(),
+            preferred_genders: vec![],
+            min_age,
+            max_age,
+            min_height_cm,
+            max_height_cm,
+            preferred_hair_colors: vec![],
+            preferred_sports,
+            preferred_interests: vec![],
+            max_distance_km,
+            latitude,
+            longitude,
+            require_photos: None,
+            embedding: None,
+            preferred_languages: vec![],
+            require_shared_language: false,
+            verified_only: false,
+            relationship_goal: None,
+            attributes: Default::default(),
+            updated_at: None,
+            schema_version: SCHEMA_VERSION,
+        },
+    )
+}
+
+/// Any non-negative weights for the deterministic factors. Activity stays
+/// off: it depends on the clock, so two calls could differ.
+fn arb_weights() -> impl Strategy<Value = ScoringWeights> {
+    (0.0..=1.0f64, 0.0..=1.0f64, 0.0..=1.0f64, 0.0..=1.0f64, 0.0..=1.0f64).prop_map(|(distance, age, sports, verified, height)| {
+        ScoringWeights { distance, age, sports, verified, height, ..ScoringWeights::default() }
+    })
+}
+
+proptest! {
+    #[test]
+    fn prop_score_is_bounded(profile in arb_profile(), preferences in arb_preferences()) {
+        let (score, _) = calculate_match_score(&profile, &preferences, &ScoringWeights::default());
+        prop_assert!((0.0..=100.0).contains(&score), "score {}", score);
+    }
+
+    #[test]
+    fn prop_factors_stay_within_their_weight(
+        profile in arb_profile(),
+        preferences in arb_preferences(),
+        weights in arb_weights(),
+        distance_km in 0.0..20_000.0f64,
+    ) {
+        let (breakdown, _) = calculate_score_breakdown(&profile, &preferences, &weights, distance_km);
+        // Without a height to score, the other weights are scaled up to fill its share
+        let weights = weights.for_profile(&profile, &preferences);
+        for (points, weight) in [
+            (breakdown.distance, weights.distance),
+            (breakdown.age, weights.age),
+            (breakdown.sports, weights.sports),
+            (breakdown.verified, weights.verified),
+            (breakdown.height, weights.height),
+        ] {
+            prop_assert!(points >= 0.0 && points <= weight * 100.0 + 1e-9, "{} points for weight {}", points, weight);
+        }
+    }
+
+    #[test]
+    fn prop_verification_never_lowers_the_score(
+        profile in arb_profile(),
+        preferences in arb_preferences(),
+        weights in arb_weights(),
+        distance_km in 0.0..20_000.0f64,
+    ) {
+        let score = |verification| {
+            let profile = UserProfile { verification: Some(verification), ..profile.clone() };
+            calculate_match_score_at_distance(&profile, &preferences, &weights, distance_km).0
+        };
+        let (unverified, photo, id) = (score(Verification::Unverified), score(Verification::Photo), score(Verification::Id));
+        prop_assert!(unverified <= photo && photo <= id, "{} / {} / {}", unverified, photo, id);
+    }
+
+    #[test]
+    fn prop_age_score_is_symmetric_around_the_middle((min_age, max_age, offset) in (any::<u8>(), any::<u8>())
+        .prop_flat_map(|(a, b)| (Just(a.min(b)), Just(a.max(b)), 0..=a.abs_diff(b))))
+    {
+        let low = calculate_age_score(min_age + offset, min_age, max_age);
+        let high = calculate_age_score(max_age - offset, min_age, max_age);
+        prop_assert!((low - high).abs() < 1e-9, "{} vs {}", low, high);
+        prop_assert!((0.0..=1.0).contains(&low));
+    }
+
+    #[test]
+    fn prop_height_score_is_symmetric_around_the_middle((min_height, max_height, offset) in (any::<u16>(), any::<u16>())
+        .prop_flat_map(|(a, b)| (Just(a.min(b)), Just(a.max(b)), 0..=a.abs_diff(b))))
+    {
+        let low = calculate_height_score(min_height + offset, min_height, max_height);
+        let high = calculate_height_score(max_height - offset, min_height, max_height);
+        prop_assert!((low - high).abs() < 1e-9, "{} vs {}", low, high);
+        prop_assert!((0.0..=1.0).contains(&low));
+    }
+
+    #[test]
+    fn prop_score_decreases_with_distance(
+        profile in arb_profile(),
+        preferences in arb_preferences(),
+        weights in arb_weights(),
+        (near, far) in (0.0..20_000.0f64, 0.0..20_000.0f64).prop_map(|(a, b)| (a.min(b), a.max(b))),
+    ) {
+        let near_factor = calculate_distance_score(near, preferences.max_distance_km);
+        let far_factor = calculate_distance_score(far, preferences.max_distance_km);
+        prop_assert!(near_factor >= far_factor && (0.0..=1.0).contains(&near_factor));
+
+        let (near_score, _) = calculate_match_score_at_distance(&profile, &preferences, &weights, near);
+        let (far_score, _) = calculate_match_score_at_distance(&profile, &preferences, &weights, far);
+        prop_assert!(near_score >= far_score, "{} km: {}, {} km: {}", near, near_score, far, far_score);
+    }
+}
@@ -5,8 +5,9 @@ use lume_algo::core::{
     filters::{matches_demographics, calculate_preference_score},
     scoring::calculate_match_score,
 };
-use lume_algo::models::{UserProfile, UserPreferences, ScoringWeights};
+use lume_algo::models::{UserProfile, UserPreferences, ScoringWeights, FindMatchesRequest, ScoreCandidatesRequest, Tier, SCHEMA_VERSION};
 use chrono::Utc;
+use validator::Validate;
 
 #[test]
 fn test_haversine_distance_zero() {
@@ -63,18 +64,34 @@ fn test_demographics_match_pass() {
         user_id: "test".to_string(),
         name: "Test".to_string(),
         age: 25,
-        height_cm: 170,
+        height_cm: Some(170),
         hair_color: "brown".to_string(),
         gender: "female".to_string(),
         latitude: 40.7128,
         longitude: -74.0060,
-        is_verified: true,
+        is_verified: Some(true),
+        verification: None,
         is_active: true,
-        is_timeout: false,
+        is_timeout: Some(false),
         image_file_ids: vec![],
         description: None,
         sports_preferences: vec![],
-        created_at: Utc::now(),
+        interests: vec![],
+        created_at: Some(Utc::now()),
+        timezone: None,
+        is_premium: None,
+        is_snoozed: None,
+        tenant_id: None,
+        market: None,
+        city: None,
+        tier: None,
+        embedding: None,
+        interested_in: vec![],
+        languages: vec![],
+        relationship_goal: None,
+        attributes: Default::default(),
+        updated_at: None,
+        schema_version: SCHEMA_VERSION,
     };
 
     let preferences = UserPreferences {
@@ -86,9 +103,19 @@ fn test_demographics_match_pass() {
         max_height_cm: 180,
         preferred_hair_colors: vec![],
         preferred_sports: vec![],
+        preferred_interests: vec![],
         max_distance_km: 50,
         latitude: 40.7128,
         longitude: -74.0060,
+        require_photos: None,
+        embedding: None,
+        preferred_languages: vec![],
+        require_shared_language: false,
+        verified_only: false,
+        relationship_goal: None,
+        attributes: Default::default(),
+        updated_at: None,
+        schema_version: SCHEMA_VERSION,
     };
 
     assert!(matches_demographics(&profile, &preferences));
@@ -100,18 +127,34 @@ fn test_demographics_fail_inactive() {
         user_id: "test".to_string(),
         name: "Test".to_string(),
         age: 25,
-        height_cm: 170,
+        height_cm: Some(170),
         hair_color: "brown".to_string(),
         gender: "female".to_string(),
         latitude: 40.7128,
         longitude: -74.0060,
-        is_verified: true,
+        is_verified: Some(true),
+        verification: None,
         is_active: false, // Inactive
-        is_timeout: false,
+        is_timeout: Some(false),
         image_file_ids: vec![],
         description: None,
         sports_preferences: vec![],
-        created_at: Utc::now(),
+        interests: vec![],
+        created_at: Some(Utc::now()),
+        timezone: None,
+        is_premium: None,
+        is_snoozed: None,
+        tenant_id: None,
+        market: None,
+        city: None,
+        tier: None,
+        embedding: None,
+        interested_in: vec![],
+        languages: vec![],
+        relationship_goal: None,
+        attributes: Default::default(),
+        updated_at: None,
+        schema_version: SCHEMA_VERSION,
     };
 
     let preferences = UserPreferences {
@@ -123,9 +166,19 @@ fn test_demographics_fail_inactive() {
         max_height_cm: 180,
         preferred_hair_colors: vec![],
         preferred_sports: vec![],
+        preferred_interests: vec![],
         max_distance_km: 50,
         latitude: 40.7128,
         longitude: -74.0060,
+        require_photos: None,
+        embedding: None,
+        preferred_languages: vec![],
+        require_shared_language: false,
+        verified_only: false,
+        relationship_goal: None,
+        attributes: Default::default(),
+        updated_at: None,
+        schema_version: SCHEMA_VERSION,
     };
 
     assert!(!matches_demographics(&profile, &preferences));
@@ -137,18 +190,34 @@ fn test_demographics_fail_age() {
         user_id: "test".to_string(),
         name: "Test".to_string(),
         age: 40, // Too old
-        height_cm: 170,
+        height_cm: Some(170),
         hair_color: "brown".to_string(),
         gender: "female".to_string(),
         latitude: 40.7128,
         longitude: -74.0060,
-        is_verified: true,
+        is_verified: Some(true),
+        verification: None,
         is_active: true,
-        is_timeout: false,
+        is_timeout: Some(false),
         image_file_ids: vec![],
         description: None,
         sports_preferences: vec![],
-        created_at: Utc::now(),
+        interests: vec![],
+        created_at: Some(Utc::now()),
+        timezone: None,
+        is_premium: None,
+        is_snoozed: None,
+        tenant_id: None,
+        market: None,
+        city: None,
+        tier: None,
+        embedding: None,
+        interested_in: vec![],
+        languages: vec![],
+        relationship_goal: None,
+        attributes: Default::default(),
+        updated_at: None,
+        schema_version: SCHEMA_VERSION,
     };
 
     let preferences = UserPreferences {
@@ -160,9 +229,19 @@ fn test_demographics_fail_age() {
         max_height_cm: 180,
         preferred_hair_colors: vec![],
         preferred_sports: vec![],
+        preferred_interests: vec![],
         max_distance_km: 50,
         latitude: 40.7128,
         longitude: -74.0060,
+        require_photos: None,
+        embedding: None,
+        preferred_languages: vec![],
+        require_shared_language: false,
+        verified_only: false,
+        relationship_goal: None,
+        attributes: Default::default(),
+        updated_at: None,
+        schema_version: SCHEMA_VERSION,
     };
 
     assert!(!matches_demographics(&profile, &preferences));
@@ -174,18 +253,34 @@ fn test_preference_score_with_shared_sports() {
         user_id: "test".to_string(),
         name: "Test".to_string(),
         age: 25,
-        height_cm: 170,
+        height_cm: Some(170),
         hair_color: "blonde".to_string(),
         gender: "female".to_string(),
         latitude: 40.7128,
         longitude: -74.0060,
-        is_verified: true,
+        is_verified: Some(true),
+        verification: None,
         is_active: true,
-        is_timeout: false,
+        is_timeout: Some(false),
         image_file_ids: vec![],
         description: None,
         sports_preferences: vec!["tennis".to_string(), "swimming".to_string()],
-        created_at: Utc::now(),
+        interests: vec![],
+        created_at: Some(Utc::now()),
+        timezone: None,
+        is_premium: None,
+        is_snoozed: None,
+        tenant_id: None,
+        market: None,
+        city: None,
+        tier: None,
+        embedding: None,
+        interested_in: vec![],
+        languages: vec![],
+        relationship_goal: None,
+        attributes: Default::default(),
+        updated_at: None,
+        schema_version: SCHEMA_VERSION,
     };
 
     let preferences = UserPreferences {
@@ -197,9 +292,19 @@ fn test_preference_score_with_shared_sports() {
         max_height_cm: 180,
         preferred_hair_colors: vec!["blonde".to_string()],
         preferred_sports: vec!["tennis".to_string(), "basketball".to_string()],
+        preferred_interests: vec![],
         max_distance_km: 50,
         latitude: 40.7128,
         longitude: -74.0060,
+        require_photos: None,
+        embedding: None,
+        preferred_languages: vec![],
+        require_shared_language: false,
+        verified_only: false,
+        relationship_goal: None,
+        attributes: Default::default(),
+        updated_at: None,
+        schema_version: SCHEMA_VERSION,
     };
 
     let (score, shared) = calculate_preference_score(&profile, &preferences);
@@ -214,18 +319,34 @@ fn test_match_score_within_valid_range() {
         user_id: "test".to_string(),
         name: "Test".to_string(),
         age: 25,
-        height_cm: 170,
+        height_cm: Some(170),
         hair_color: "brown".to_string(),
         gender: "female".to_string(),
         latitude: 40.7128,
         longitude: -74.0060,
-        is_verified: true,
+        is_verified: Some(true),
+        verification: None,
         is_active: true,
-        is_timeout: false,
+        is_timeout: Some(false),
         image_file_ids: vec![],
         description: None,
         sports_preferences: vec!["tennis".to_string()],
-        created_at: Utc::now(),
+        interests: vec![],
+        created_at: Some(Utc::now()),
+        timezone: None,
+        is_premium: None,
+        is_snoozed: None,
+        tenant_id: None,
+        market: None,
+        city: None,
+        tier: None,
+        embedding: None,
+        interested_in: vec![],
+        languages: vec![],
+        relationship_goal: None,
+        attributes: Default::default(),
+        updated_at: None,
+        schema_version: SCHEMA_VERSION,
     };
 
     let preferences = UserPreferences {
@@ -237,15 +358,25 @@ fn test_match_score_within_valid_range() {
         max_height_cm: 180,
         preferred_hair_colors: vec![],
         preferred_sports: vec!["tennis".to_string()],
+        preferred_interests: vec![],
         max_distance_km: 50,
         latitude: 40.7128,
         longitude: -74.0060,
+        require_photos: None,
+        embedding: None,
+        preferred_languages: vec![],
+        require_shared_language: false,
+        verified_only: false,
+        relationship_goal: None,
+        attributes: Default::default(),
+        updated_at: None,
+        schema_version: SCHEMA_VERSION,
     };
 
     let weights = ScoringWeights::default();
     let (score, _) = calculate_match_score(&profile, &preferences, &weights);
 
-    assert!(score >= 0.0 && score <= 100.0, "Score should be in valid range");
+    assert!((0.0..=100.0).contains(&score), "Score should be in valid range");
 }
 
 #[test]
@@ -254,36 +385,68 @@ fn test_verified_user_scores_higher() {
         user_id: "test1".to_string(),
         name: "Test".to_string(),
         age: 25,
-        height_cm: 170,
+        height_cm: Some(170),
         hair_color: "brown".to_string(),
         gender: "female".to_string(),
         latitude: 40.7128,
         longitude: -74.0060,
-        is_verified: true,
+        is_verified: Some(true),
+        verification: None,
         is_active: true,
-        is_timeout: false,
+        is_timeout: Some(false),
         image_file_ids: vec![],
         description: None,
         sports_preferences: vec![],
-        created_at: Utc::now(),
+        interests: vec![],
+        created_at: Some(Utc::now()),
+        timezone: None,
+        is_premium: None,
+        is_snoozed: None,
+        tenant_id: None,
+        market: None,
+        city: None,
+        tier: None,
+        embedding: None,
+        interested_in: vec![],
+        languages: vec![],
+        relationship_goal: None,
+        attributes: Default::default(),
+        updated_at: None,
+        schema_version: SCHEMA_VERSION,
     };
 
     let unverified_profile = UserProfile {
         user_id: "test2".to_string(),
         name: "Test".to_string(),
         age: 25,
-        height_cm: 170,
+        height_cm: Some(170),
         hair_color: "brown".to_string(),
         gender: "female".to_string(),
         latitude: 40.7128,
         longitude: -74.0060,
-        is_verified: false,
+        is_verified: Some(false),
+        verification: None,
         is_active: true,
-        is_timeout: false,
+        is_timeout: Some(false),
         image_file_ids: vec![],
         description: None,
         sports_preferences: vec![],
-        created_at: Utc::now(),
+        interests: vec![],
+        created_at: Some(Utc::now()),
+        timezone: None,
+        is_premium: None,
+        is_snoozed: None,
+        tenant_id: None,
+        market: None,
+        city: None,
+        tier: None,
+        embedding: None,
+        interested_in: vec![],
+        languages: vec![],
+        relationship_goal: None,
+        attributes: Default::default(),
+        updated_at: None,
+        schema_version: SCHEMA_VERSION,
     };
 
     let preferences = UserPreferences {
@@ -295,9 +458,19 @@ fn test_verified_user_scores_higher() {
         max_height_cm: 180,
         preferred_hair_colors: vec![],
         preferred_sports: vec![],
+        preferred_interests: vec![],
         max_distance_km: 50,
         latitude: 40.7128,
         longitude: -74.0060,
+        require_photos: None,
+        embedding: None,
+        preferred_languages: vec![],
+        require_shared_language: false,
+        verified_only: false,
+        relationship_goal: None,
+        attributes: Default::default(),
+        updated_at: None,
+        schema_version: SCHEMA_VERSION,
     };
 
     let weights = ScoringWeights::default();
@@ -309,3 +482,197 @@ fn test_verified_user_scores_higher() {
         "Verified users should score higher"
     );
 }
+
+#[test]
+fn test_find_request_override_location() {
+    let req: FindMatchesRequest = serde_json::from_str(
+        r#"{"userId": "user_1", "overrideLocation": {"lat": 48.8566, "lon": 2.3522}}"#,
+    )
+    .unwrap();
+    assert!(req.validate().is_ok());
+    assert_eq!(req.override_location.unwrap().lat, 48.8566);
+
+    let invalid: FindMatchesRequest = serde_json::from_str(
+        r#"{"userId": "user_1", "overrideLocation": {"lat": 123.0, "lon": 2.0}}"#,
+    )
+    .unwrap();
+    assert!(invalid.validate().is_err());
+}
+
+#[test]
+fn test_find_request_weight_overrides() {
+    let req: FindMatchesRequest = serde_json::from_str(
+        r#"{"userId": "user_1", "weights": {"distance": 0.0, "sports": 0.6}}"#,
+    )
+    .unwrap();
+    assert!(req.validate().is_ok());
+
+    let weights = req.weights.unwrap().apply(ScoringWeights::default());
+    assert_eq!(weights.distance, 0.0);
+    assert_eq!(weights.sports, 0.6);
+    assert_eq!(weights.age, ScoringWeights::default().age);
+
+    for body in [
+        r#"{"userId": "user_1", "weights": {"age": -0.1}}"#,
+        r#"{"userId": "user_1", "weights": {"height": 5.0}}"#,
+    ] {
+        let invalid: FindMatchesRequest = serde_json::from_str(body).unwrap();
+        assert!(invalid.validate().is_err());
+    }
+}
+
+#[test]
+fn test_default_preferences_for_profile() {
+    let mut profile = UserProfile {
+        user_id: "user_1".to_string(),
+        name: "Test".to_string(),
+        age: 20,
+        height_cm: Some(180),
+        hair_color: "brown".to_string(),
+        gender: "male".to_string(),
+        latitude: 40.7128,
+        longitude: -74.0060,
+        is_verified: None,
+        verification: None,
+        is_active: true,
+        is_timeout: None,
+        image_file_ids: vec![],
+        description: None,
+        sports_preferences: vec!["tennis".to_string()],
+        interests: vec![],
+        created_at: None,
+        timezone: None,
+        is_premium: None,
+        is_snoozed: None,
+        tenant_id: None,
+        market: None,
+        city: None,
+        tier: None,
+        embedding: None,
+        interested_in: vec![],
+        languages: vec![],
+        relationship_goal: None,
+        attributes: Default::default(),
+        updated_at: None,
+        schema_version: SCHEMA_VERSION,
+    };
+    let preferences = UserPreferences::defaults_for(&profile, 5, 80);
+
+    assert_eq!(preferences.user_id, "user_1");
+    assert_eq!(preferences.min_age, 18);
+    assert_eq!(preferences.max_age, 25);
+    assert!(preferences.preferred_genders.is_empty());
+    assert_eq!(preferences.max_distance_km, 80);
+    assert!(preferences.has_location());
+
+    profile.interested_in = vec!["female".to_string()];
+    profile.languages = vec!["de".to_string(), "en".to_string()];
+    profile.age = 40;
+    let preferences = UserPreferences::defaults_for(&profile, 5, 80);
+    assert_eq!((preferences.min_age, preferences.max_age), (35, 45));
+    assert_eq!(preferences.preferred_genders, vec!["female".to_string()]);
+    assert_eq!(preferences.preferred_languages, profile.languages);
+    assert!(!preferences.require_shared_language);
+}
+
+#[test]
+fn test_language_fields() {
+    let profile: UserProfile = serde_json::from_str(
+        r#"{"userId": "user_1", "name": "Test", "age": 27, "hairColor": "brown", "gender": "female",
+            "latitude": 40.72, "longitude": -74.0, "languages": ["de", "en"]}"#,
+    )
+    .unwrap();
+    assert_eq!(profile.languages, vec!["de", "en"]);
+
+    let preferences: UserPreferences = serde_json::from_str(
+        r#"{"userId": "user_1", "preferredGenders": [], "minAge": 21, "maxAge": 35,
+            "minHeightCm": 150, "maxHeightCm": 200, "preferredHairColors": [], "preferredSports": [],
+            "maxDistanceKm": 50, "preferredLanguages": ["fr"], "requireSharedLanguage": true}"#,
+    )
+    .unwrap();
+    assert_eq!(preferences.preferred_languages, vec!["fr"]);
+    assert!(preferences.require_shared_language);
+    assert!(!profile.speaks_any(&preferences.preferred_languages));
+}
+
+#[test]
+fn test_profile_schema_versions() {
+    let base = r#""userId": "user_1", "name": "Test", "age": 27, "hairColor": "brown", "gender": "female",
+        "latitude": 40.72, "longitude": -74.0"#;
+
+    // v1 (no schemaVersion): an unknown height was stored as 0
+    let v1: UserProfile = serde_json::from_str(&format!(r#"{{{}, "heightCm": 0}}"#, base)).unwrap();
+    assert_eq!(v1.height_cm, None);
+    assert_eq!(v1.schema_version, SCHEMA_VERSION);
+
+    let v2: UserProfile =
+        serde_json::from_str(&format!(r#"{{{}, "schemaVersion": 2, "heightCm": null, "interests": ["jazz"]}}"#, base)).unwrap();
+    assert_eq!(v2.height_cm, None);
+    assert_eq!(v2.interests, vec!["jazz"]);
+
+    // Written as the current version, and read back unchanged
+    let json = serde_json::to_value(&v1).unwrap();
+    assert_eq!(json["schemaVersion"], SCHEMA_VERSION);
+    let reread: UserProfile = serde_json::from_value(json).unwrap();
+    assert_eq!(reread.height_cm, None);
+
+    let future = serde_json::from_str::<UserProfile>(&format!(r#"{{{}, "schemaVersion": 3}}"#, base));
+    assert!(future.unwrap_err().to_string().contains("unsupported schemaVersion 3"));
+}
+
+#[test]
+fn test_preferences_schema_versions() {
+    let base = r#""userId": "user_1", "preferredGenders": [], "minAge": 21, "maxAge": 35,
+        "preferredHairColors": [], "preferredSports": [], "maxDistanceKm": 50"#;
+
+    // v1: "no maximum" was stored as 0
+    let v1: UserPreferences = serde_json::from_str(&format!(r#"{{{}, "minHeightCm": 150, "maxHeightCm": 0}}"#, base)).unwrap();
+    assert_eq!((v1.min_height_cm, v1.max_height_cm), (150, u16::MAX));
+
+    // v2: heights may be left out entirely
+    let v2: UserPreferences =
+        serde_json::from_str(&format!(r#"{{{}, "schemaVersion": 2, "preferredInterests": ["jazz"]}}"#, base)).unwrap();
+    assert_eq!((v2.min_height_cm, v2.max_height_cm), (0, u16::MAX));
+    assert_eq!(v2.preferred_interests, vec!["jazz"]);
+
+    assert!(serde_json::from_str::<UserPreferences>(&format!(r#"{{{}, "schemaVersion": 9}}"#, base)).is_err());
+}
+
+#[test]
+fn test_score_request_candidate_bounds() {
+    let preferences = r#"{"userId": "user_1", "preferredGenders": [], "minAge": 21, "maxAge": 35,
+        "minHeightCm": 150, "maxHeightCm": 200, "preferredHairColors": [], "preferredSports": [],
+        "maxDistanceKm": 50, "latitude": 40.7128, "longitude": -74.0060}"#;
+    let candidate = r#"{"userId": "user_2", "name": "Test", "age": 27, "heightCm": 170,
+        "hairColor": "brown", "gender": "female", "latitude": 40.72, "longitude": -74.0}"#;
+
+    let body = |count: usize| {
+        let candidates = vec![candidate; count].join(",");
+        format!(r#"{{"preferences": {}, "candidates": [{}]}}"#, preferences, candidates)
+    };
+
+    let req: ScoreCandidatesRequest = serde_json::from_str(&body(3)).unwrap();
+    assert!(req.validate().is_ok());
+    assert_eq!(req.candidates.len(), 3);
+
+    let empty: ScoreCandidatesRequest = serde_json::from_str(&body(0)).unwrap();
+    assert!(empty.validate().is_err());
+
+    let oversized: ScoreCandidatesRequest = serde_json::from_str(&body(1001)).unwrap();
+    assert!(oversized.validate().is_err());
+}
+
+#[test]
+fn test_profile_tier() {
+    let base = r#""userId": "user_1", "name": "Test", "age": 27, "heightCm": 170,
+        "hairColor": "brown", "gender": "female", "latitude": 40.72, "longitude": -74.0"#;
+    let parse = |extra: &str| -> UserProfile { serde_json::from_str(&format!("{{{}{}}}", base, extra)).unwrap() };
+
+    assert_eq!(parse("").tier(), Tier::Free);
+    assert_eq!(parse(r#", "tier": "plus""#).tier(), Tier::Plus);
+
+    // The legacy flag only applies when no tier is set
+    assert_eq!(parse(r#", "isPremium": true"#).tier(), Tier::Premium);
+    assert_eq!(parse(r#", "isPremium": true, "tier": "free""#).tier(), Tier::Free);
+    assert!(Tier::Premium > Tier::Plus);
+}
@@ -0,0 +1,112 @@
+//! WebAssembly bindings for on-device pre-ranking
+//!
+//! Runs the service's [`Matcher`] over candidates the mobile client has
+//! cached, through the same pipeline the service runs. Only the weights
+//! and minimum score are configurable; everything else `Settings::matcher`
+//! sets (distance formula, exploration, tier boosts, freshness, relationship
+//! goals, attribute scorers, shuffle bands, geofences, calibration) keeps
+//! the library default. The offline order therefore matches the online one
+//! only where the server runs those defaults too.
+//!
+//! Profiles and preferences use the same JSON shape as the API and the
+//! Appwrite documents (`userId`, `heightCm`, `sportsPreferences`, ...).
+//!
+//! ```js
+//! import init, { Ranker } from "lume-algo-wasm";
+//!
+//! await init();
+//! const ranker = new Ranker({ weights: { distance: 0.4 }, minScore: 10 });
+//! const matches = ranker.rank(preferences, cachedProfiles, 20, seenIds);
+//! ```
+
+use lume_algo::core::{Matcher, MatchOptions};
+use lume_algo::models::{ScoringWeights, UserPreferences, UserProfile, WeightsOverride};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use wasm_bindgen::prelude::*;
+
+/// Matcher settings to mirror the server's; unset ones keep the library defaults
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RankerOptions {
+    #[serde(default)]
+    weights: Option<WeightsOverride>,
+    #[serde(default)]
+    min_score: Option<f64>,
+}
+
+/// The matching pipeline with fixed weights and minimum score, and library
+/// defaults for every other matcher setting
+#[wasm_bindgen]
+pub struct Ranker {
+    matcher: Matcher,
+}
+
+#[wasm_bindgen]
+impl Ranker {
+    /// `options`: `{ weights?: { distance?, age?, ... }, minScore? }`
+    #[wasm_bindgen(constructor)]
+    pub fn new(options: JsValue) -> Result<Ranker, JsError> {
+        let options: RankerOptions = from_js_or_default(options)?;
+        let weights = options.weights.unwrap_or_default().apply(ScoringWeights::default());
+
+        let mut builder = Matcher::builder().weights(weights);
+        if let Some(min_score) = options.min_score {
+            builder = builder.min_score(min_score);
+        }
+
+        Ok(Ranker { matcher: builder.build() })
+    }
+
+    /// Rank `candidates` for the searcher's `preferences`, best first
+    ///
+    /// Returns up to `limit` scored matches, skipping `excludeUserIds`
+    /// (e.g. profiles already swiped on this device).
+    pub fn rank(&self, preferences: JsValue, candidates: JsValue, limit: usize, exclude_user_ids: JsValue) -> Result<JsValue, JsError> {
+        let preferences: UserPreferences = from_js(preferences)?;
+        let candidates: Vec<UserProfile> = from_js(candidates)?;
+        let exclude_user_ids: Vec<String> = from_js_or_default(exclude_user_ids)?;
+
+        let options = MatchOptions {
+            limit: Some(limit),
+            exclude_user_ids: exclude_user_ids.into_iter().collect::<HashSet<_>>(),
+            ..Default::default()
+        };
+        let result = self.matcher.find_matches_with(&preferences, candidates, &options);
+
+        to_js(&result.matches)
+    }
+}
+
+/// Match score (0-100) of one candidate with the default weights
+#[wasm_bindgen(js_name = scoreCandidate)]
+pub fn score_candidate(profile: JsValue, preferences: JsValue) -> Result<f64, JsError> {
+    let profile: UserProfile = from_js(profile)?;
+    let preferences: UserPreferences = from_js(preferences)?;
+    Ok(lume_algo::core::calculate_match_score(&profile, &preferences, &ScoringWeights::default()).0)
+}
+
+/// Great-circle distance in kilometers
+#[wasm_bindgen(js_name = haversineDistance)]
+pub fn haversine_distance(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    lume_algo::haversine_distance(lat1, lon1, lat2, lon2)
+}
+
+fn from_js<T: DeserializeOwned>(value: JsValue) -> Result<T, JsError> {
+    serde_wasm_bindgen::from_value(value).map_err(|e| JsError::new(&e.to_string()))
+}
+
+fn from_js_or_default<T: DeserializeOwned + Default>(value: JsValue) -> Result<T, JsError> {
+    if value.is_undefined() || value.is_null() {
+        return Ok(T::default());
+    }
+    from_js(value)
+}
+
+/// Plain JS objects and `null` for unset fields, like the API's JSON
+fn to_js<T: Serialize>(value: &T) -> Result<JsValue, JsError> {
+    value
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .map_err(|e| JsError::new(&e.to_string()))
+}
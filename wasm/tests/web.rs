@@ -0,0 +1,82 @@
+//! Run with `wasm-pack test --node wasm`
+
+use lume_algo_wasm::{haversine_distance, score_candidate, Ranker};
+use serde::Serialize;
+use serde_json::{json, Value};
+use wasm_bindgen::JsValue;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+fn js(value: Value) -> JsValue {
+    value.serialize(&serde_wasm_bindgen::Serializer::json_compatible()).unwrap()
+}
+
+fn profile(user_id: &str, gender: &str, age: u8) -> Value {
+    json!({
+        "userId": user_id,
+        "name": user_id,
+        "age": age,
+        "heightCm": 170,
+        "hairColor": "brown",
+        "gender": gender,
+        "latitude": 52.52,
+        "longitude": 13.405,
+        "sportsPreferences": ["tennis"],
+    })
+}
+
+fn preferences() -> Value {
+    json!({
+        "userId": "alice",
+        "preferredGenders": ["female"],
+        "minAge": 25,
+        "maxAge": 35,
+        "preferredHairColors": [],
+        "preferredSports": ["tennis"],
+        "maxDistanceKm": 50,
+        "latitude": 52.52,
+        "longitude": 13.405,
+    })
+}
+
+fn candidates() -> JsValue {
+    js(json!([profile("beth", "female", 28), profile("cara", "female", 31), profile("dave", "male", 30)]))
+}
+
+fn user_ids(matches: JsValue) -> Vec<String> {
+    let matches: Vec<Value> = serde_wasm_bindgen::from_value(matches).unwrap();
+    matches.iter().map(|m| m["userId"].as_str().unwrap().to_string()).collect()
+}
+
+#[wasm_bindgen_test]
+fn test_rank_filters_and_excludes() {
+    let ranker = Ranker::new(JsValue::UNDEFINED).unwrap();
+
+    let mut ranked = user_ids(ranker.rank(js(preferences()), candidates(), 20, JsValue::NULL).unwrap());
+    ranked.sort();
+    assert_eq!(ranked, vec!["beth", "cara"]);
+
+    let ranked = ranker.rank(js(preferences()), candidates(), 20, js(json!(["cara"]))).unwrap();
+    assert_eq!(user_ids(ranked), vec!["beth"]);
+
+    let ranked = ranker.rank(js(preferences()), candidates(), 1, JsValue::NULL).unwrap();
+    assert_eq!(user_ids(ranked).len(), 1);
+}
+
+#[wasm_bindgen_test]
+fn test_options() {
+    let strict = Ranker::new(js(json!({ "weights": { "distance": 0.5 }, "minScore": 101 }))).unwrap();
+    assert!(user_ids(strict.rank(js(preferences()), candidates(), 20, JsValue::NULL).unwrap()).is_empty());
+
+    assert!(Ranker::new(js(json!({ "minScore": "high" }))).is_err());
+    assert!(Ranker::new(JsValue::UNDEFINED).unwrap().rank(js(json!({})), candidates(), 20, JsValue::NULL).is_err());
+}
+
+#[wasm_bindgen_test]
+fn test_free_functions() {
+    let score = score_candidate(js(profile("beth", "female", 28)), js(preferences())).unwrap();
+    assert!(score > 0.0 && score <= 100.0);
+
+    // Berlin to Munich
+    let km = haversine_distance(52.52, 13.405, 48.1351, 11.582);
+    assert!((km - 504.0).abs() < 5.0, "{}", km);
+}